@@ -0,0 +1,120 @@
+//! 静态分发与动态分发的对比: `cargo bench --bench dispatch`
+//!
+//! 对同一串随机排列的 `Duck`/`Pig` 调用 `fly()`, 分别使用:
+//! 单态化的泛型函数、`&dyn Fly`、`Vec<Box<dyn Fly>>`、枚举分发和函数指针表
+
+use std::time::Duration;
+
+use rust_programming_of_zhd::bench::{black_box, measure, report};
+use rust_programming_of_zhd::ch01::dispatch::Animal;
+use rust_programming_of_zhd::ch01::{fly_dyn, fly_static, Duck, Fly, Pig};
+
+const CALLS: usize = 10_000_000;
+
+/// 可复现的伪随机序列(xorshift), `true` 表示 Duck
+fn kinds(len: usize) -> Vec<bool> {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state & 1 == 1
+        })
+        .collect()
+}
+
+fn duck_fly() -> bool {
+    Duck.fly()
+}
+
+fn pig_fly() -> bool {
+    Pig.fly()
+}
+
+fn main() {
+    let min_time = Duration::from_secs(1);
+    let kinds = kinds(CALLS);
+    let items = CALLS as u64;
+
+    let duck = Duck;
+    let pig = Pig;
+    let refs: Vec<&dyn Fly> = kinds
+        .iter()
+        .map(|&k| {
+            if k {
+                &duck as &dyn Fly
+            } else {
+                &pig as &dyn Fly
+            }
+        })
+        .collect();
+    let boxes: Vec<Box<dyn Fly>> = kinds
+        .iter()
+        .map(|&k| {
+            if k {
+                Box::new(Duck) as Box<dyn Fly>
+            } else {
+                Box::new(Pig)
+            }
+        })
+        .collect();
+    let enums: Vec<Animal> = kinds
+        .iter()
+        .map(|&k| {
+            if k {
+                Animal::Duck(Duck)
+            } else {
+                Animal::Pig(Pig)
+            }
+        })
+        .collect();
+    let table: [fn() -> bool; 2] = [pig_fly, duck_fly];
+
+    let results = vec![
+        measure("generic fly_static::<T>", items, min_time, || {
+            let mut flying = 0;
+            for &k in black_box(&kinds) {
+                let f = if k { fly_static(Duck) } else { fly_static(Pig) };
+                flying += f as u64;
+            }
+            black_box(flying);
+            0
+        }),
+        measure("fly_dyn(&dyn Fly)", items, min_time, || {
+            let mut flying = 0;
+            for s in black_box(&refs) {
+                flying += fly_dyn(*s) as u64;
+            }
+            black_box(flying);
+            0
+        }),
+        measure("Vec<Box<dyn Fly>>", items, min_time, || {
+            let mut flying = 0;
+            for s in black_box(&boxes) {
+                flying += s.fly() as u64;
+            }
+            black_box(flying);
+            0
+        }),
+        measure("enum dispatch (dispatch::Animal)", items, min_time, || {
+            let mut flying = 0;
+            for a in black_box(&enums) {
+                flying += a.fly() as u64;
+            }
+            black_box(flying);
+            0
+        }),
+        measure("fn pointer table", items, min_time, || {
+            let table = black_box(&table);
+            let mut flying = 0;
+            for &k in black_box(&kinds) {
+                flying += table[k as usize]() as u64;
+            }
+            black_box(flying);
+            0
+        }),
+    ];
+    println!("{} calls of fly() per run", CALLS);
+    print!("{}", report(&results));
+}
@@ -0,0 +1,40 @@
+//! FizzBuzz 吞吐量基准: `cargo bench --bench fizzbuzz`
+
+use std::io::Write;
+use std::time::Duration;
+
+use rust_programming_of_zhd::bench::{measure, report, Counter};
+use rust_programming_of_zhd::ch02::fizzbuzz::ParallelFizzBuzz;
+use rust_programming_of_zhd::ch02::function::fizz_buzz;
+
+const N: u64 = 10_000_000;
+
+fn main() {
+    let min_time = Duration::from_secs(2);
+    let mut results = Vec::new();
+
+    results.push(measure("fizz_buzz + to_string", N, min_time, || {
+        let mut out = Counter(0);
+        for n in 1..=N as i32 {
+            writeln!(out, "{}", fizz_buzz(n)).unwrap();
+        }
+        out.0
+    }));
+
+    results.push(measure("ParallelFizzBuzz, 1 thread", N, min_time, || {
+        ParallelFizzBuzz::new()
+            .threads(1)
+            .write_to(1..=N, &mut Counter(0))
+            .unwrap()
+    }));
+
+    let generator = ParallelFizzBuzz::new();
+    results.push(measure(
+        "ParallelFizzBuzz, all threads",
+        N,
+        min_time,
+        || generator.write_to(1..=N, &mut Counter(0)).unwrap(),
+    ));
+
+    print!("{}", report(&results));
+}
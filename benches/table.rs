@@ -0,0 +1,47 @@
+//! 预先渲染的 FizzBuzz 与 `control_flow` 中运行时循环的对比: `cargo bench --bench table`
+
+use std::io::{self, Write};
+use std::time::Duration;
+
+use rust_programming_of_zhd::bench::{measure, report, Counter};
+use rust_programming_of_zhd::ch02::control_flow::{
+    for_fizzbuzz_to, loop_fizzbuzz_to, while_fizzbuzz_to,
+};
+use rust_programming_of_zhd::ch02::fizzbuzz::table::{write_fizzbuzz_100, TOKENS_100};
+
+/// 每轮输出 `1..=100` 的次数
+const ROUNDS: u64 = 10_000;
+
+fn rounds<F>(mut f: F) -> u64
+where
+    F: FnMut(&mut Counter) -> io::Result<()>,
+{
+    let mut out = Counter(0);
+    for _ in 0..ROUNDS {
+        f(&mut out).unwrap();
+    }
+    out.0
+}
+
+fn main() {
+    let min_time = Duration::from_secs(2);
+    let items = ROUNDS * 100;
+    let results = vec![
+        measure("while_fizzbuzz_to", items, min_time, || {
+            rounds(while_fizzbuzz_to)
+        }),
+        measure("loop_fizzbuzz_to", items, min_time, || {
+            rounds(loop_fizzbuzz_to)
+        }),
+        measure("for_fizzbuzz_to", items, min_time, || {
+            rounds(for_fizzbuzz_to)
+        }),
+        measure("TOKENS_100 + Display", items, min_time, || {
+            rounds(|out| TOKENS_100.iter().try_for_each(|t| writeln!(out, "{}", t)))
+        }),
+        measure("FIZZBUZZ_100 (write_all)", items, min_time, || {
+            rounds(write_fizzbuzz_100)
+        }),
+    ];
+    print!("{}", report(&results));
+}
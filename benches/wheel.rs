@@ -0,0 +1,54 @@
+//! 取模与滚动计数器两种 FizzBuzz 实现的对比: `cargo bench --bench wheel`
+
+use std::time::Duration;
+
+use rust_programming_of_zhd::bench::{black_box, measure, report};
+use rust_programming_of_zhd::ch02::fizzbuzz::wheel::Wheel;
+use rust_programming_of_zhd::ch02::function::fizz_buzz;
+
+const N: i32 = 5_000_000;
+
+fn main() {
+    let min_time = Duration::from_secs(2);
+    let results = vec![
+        measure("fizz_buzz (% 15, % 3, % 5)", N as u64, min_time, || {
+            let mut len = 0;
+            for n in 1..=N {
+                len += black_box(fizz_buzz(n)).len();
+            }
+            len as u64
+        }),
+        measure("Wheel::classic (counters)", N as u64, min_time, || {
+            let mut len = 0;
+            for word in Wheel::classic(1).take(N as usize) {
+                len += black_box(word).len();
+            }
+            len as u64
+        }),
+    ];
+    println!("== 含字符串分配");
+    print!("{}", report(&results));
+
+    let results = vec![
+        measure("n % 3 == 0, n % 5 == 0", N as u64, min_time, || {
+            let mut hits = 0;
+            for n in 1..=black_box(N) {
+                let mask = (n % 3 == 0) as u64 | ((n % 5 == 0) as u64) << 1;
+                hits += black_box(mask).count_ones();
+            }
+            black_box(hits);
+            0
+        }),
+        measure("Wheel::next_mask", N as u64, min_time, || {
+            let mut wheel = Wheel::classic(1);
+            let mut hits = 0;
+            for _ in 0..black_box(N) {
+                hits += black_box(wheel.next_mask().unwrap()).count_ones();
+            }
+            black_box(hits);
+            0
+        }),
+    ];
+    println!("\n== 只做分类");
+    print!("{}", report(&results));
+}
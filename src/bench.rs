@@ -0,0 +1,152 @@
+//! 基准测试工具
+//!
+//! `benches/` 下的基准测试都使用 `harness = false`, 在 stable 上通过 `cargo bench` 运行,
+//! 计时和报表由这里统一提供, 不依赖 nightly 的 `test::Bencher`
+
+use std::fmt;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+pub use std::hint::black_box;
+
+/// 一次基准测试的结果
+#[derive(Debug, Clone)]
+pub struct Measurement {
+    pub name: String,
+    /// 被测函数的调用次数
+    pub runs: u32,
+    /// 所有调用的总耗时
+    pub elapsed: Duration,
+    /// 每次调用处理的元素个数(例如函数调用次数、生成的数的个数)
+    pub items: u64,
+    /// 每次调用产出的字节数, 没有意义时为 0
+    pub bytes: u64,
+}
+
+impl Measurement {
+    /// 单次调用的平均耗时
+    pub fn per_run(&self) -> Duration {
+        self.elapsed / self.runs
+    }
+
+    /// 每个元素的平均耗时, 单位纳秒
+    pub fn ns_per_item(&self) -> f64 {
+        self.per_run().as_secs_f64() * 1e9 / self.items as f64
+    }
+
+    /// 吞吐量, 单位 GB/s
+    pub fn gb_per_sec(&self) -> f64 {
+        self.bytes as f64 / self.per_run().as_secs_f64() / 1e9
+    }
+}
+
+/// # 计时
+///
+/// 先预热一次, 然后反复调用 `f`, 直到总耗时超过 `min_time`
+/// `f` 返回本次调用产出的字节数
+///
+/// Basic usage:
+///
+/// ```
+/// use std::time::Duration;
+/// use rust_programming_of_zhd::bench::{measure, report};
+/// let m = measure("sum", 1000, Duration::from_millis(1), || {
+///     let total: u64 = (0..1000u64).sum();
+///     assert_eq!(total, 499500);
+///     0
+/// });
+/// assert!(m.runs >= 1);
+/// assert!(report(&[m]).contains("sum"));
+/// ```
+pub fn measure<F>(name: &str, items: u64, min_time: Duration, mut f: F) -> Measurement
+where
+    F: FnMut() -> u64,
+{
+    let bytes = black_box(f());
+    let mut runs = 0;
+    let start = Instant::now();
+    loop {
+        black_box(f());
+        runs += 1;
+        if start.elapsed() >= min_time {
+            break;
+        }
+    }
+    Measurement {
+        name: name.to_string(),
+        runs,
+        elapsed: start.elapsed(),
+        items,
+        bytes,
+    }
+}
+
+/// 把若干结果排成表格, 并以第一项为基准给出相对速度
+pub fn report(measurements: &[Measurement]) -> String {
+    let baseline = measurements.first().map(Measurement::per_run);
+    let width = measurements
+        .iter()
+        .map(|m| m.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut table = format!(
+        "{:<width$}  {:>12}  {:>10}  {:>9}  {:>8}\n",
+        "benchmark",
+        "time/run",
+        "ns/item",
+        "GB/s",
+        "speedup",
+        width = width
+    );
+    for m in measurements {
+        let speedup = baseline.map_or(1.0, |b| b.as_secs_f64() / m.per_run().as_secs_f64());
+        let throughput = if m.bytes == 0 {
+            "-".to_string()
+        } else {
+            format!("{:.3}", m.gb_per_sec())
+        };
+        table.push_str(&format!(
+            "{:<width$}  {:>12}  {:>10.3}  {:>9}  {:>7.2}x\n",
+            m.name,
+            format!("{:.3?}", m.per_run()),
+            m.ns_per_item(),
+            throughput,
+            speedup,
+            width = width
+        ));
+    }
+    table
+}
+
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", report(std::slice::from_ref(self)))
+    }
+}
+
+/// # 只统计字节数的 Writer
+///
+/// 输出型的基准测试写入它, 避免把终端或文件的开销计入结果
+///
+/// Basic usage:
+///
+/// ```
+/// use std::io::Write;
+/// use rust_programming_of_zhd::bench::Counter;
+/// let mut out = Counter(0);
+/// writeln!(out, "fizzbuzz").unwrap();
+/// assert_eq!(out.0, 9);
+/// ```
+#[derive(Debug, Default)]
+pub struct Counter(pub u64);
+
+impl Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
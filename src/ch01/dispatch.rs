@@ -0,0 +1,105 @@
+//! # 枚举分发
+//!
+//! 除了 `fly_static` 的静态分发和 `fly_dyn` 的动态分发, 还有第三种选择:
+//! 把所有可能的类型收拢到一个封闭的枚举里, 由 `match` 完成分发
+//!
+//! 与 trait 对象相比, 枚举不需要堆分配和虚表, 调用可以被内联;
+//! 代价是类型集合在编译期就固定了, 新增类型需要修改枚举.
+//! [`fly_enum!`](crate::fly_enum) 宏根据类型列表生成枚举、`Fly` 实现和 `From` 转换
+
+use super::zoo::{Eagle, Penguin};
+use super::{Duck, Pig};
+
+/// # 生成分发枚举
+///
+/// 为列出的每个类型生成一个同名的变体(也可以写成 `Variant(Type)`),
+/// 并为枚举实现 `Fly` 和每个类型到枚举的 `From`
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch01::{fly_dyn, Duck, Fly, Pig};
+/// use rust_programming_of_zhd::fly_enum;
+///
+/// struct Plane;
+/// impl Fly for Plane {
+///     fn fly(&self) -> bool {
+///         true
+///     }
+/// }
+///
+/// fly_enum! {
+///     enum Flyer { Duck, Pig, Aircraft(Plane) }
+/// }
+///
+/// let flyers: Vec<Flyer> = vec![Duck.into(), Pig.into(), Plane.into()];
+/// let results: Vec<bool> = flyers.iter().map(|f| f.fly()).collect();
+/// assert_eq!(results, [true, false, true]);
+/// // 枚举本身也实现了 Fly, 同样可以当作 trait 对象使用
+/// assert!(fly_dyn(&flyers[0]));
+/// ```
+#[macro_export]
+macro_rules! fly_enum {
+    ($(#[$meta:meta])* $vis:vis enum $name:ident { $($variant:ident $(($ty:ty))?),+ $(,)? }) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($variant($crate::fly_enum!(@type $variant $($ty)?))),+
+        }
+
+        impl $crate::ch01::Fly for $name {
+            fn fly(&self) -> bool {
+                match self {
+                    $($name::$variant(inner) => $crate::ch01::Fly::fly(inner)),+
+                }
+            }
+        }
+
+        $(
+            impl From<$crate::fly_enum!(@type $variant $($ty)?)> for $name {
+                fn from(inner: $crate::fly_enum!(@type $variant $($ty)?)) -> Self {
+                    $name::$variant(inner)
+                }
+            }
+        )+
+    };
+    (@type $variant:ident) => { $variant };
+    (@type $variant:ident $ty:ty) => { $ty };
+}
+
+fly_enum! {
+    /// # 动物枚举
+    ///
+    /// 封闭的动物集合, 通过 `match` 实现 `Fly`
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use rust_programming_of_zhd::ch01::dispatch::Animal;
+    /// use rust_programming_of_zhd::ch01::zoo::{Eagle, Penguin};
+    /// use rust_programming_of_zhd::ch01::{fly_static, Duck, Fly, Pig};
+    ///
+    /// let animals = [
+    ///     Animal::from(Duck),
+    ///     Animal::from(Pig),
+    ///     Animal::from(Eagle),
+    ///     Animal::from(Penguin),
+    /// ];
+    /// let flying: Vec<bool> = animals.iter().map(Fly::fly).collect();
+    /// assert_eq!(flying, [true, false, true, false]);
+    /// assert_eq!(animals[2].name(), "Eagle");
+    /// // 枚举也是一个普通的 Fly 类型, 可以用于静态分发
+    /// assert!(fly_static(Animal::Duck(Duck)));
+    /// ```
+    pub enum Animal { Duck, Pig, Eagle, Penguin }
+}
+
+impl Animal {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Animal::Duck(_) => "Duck",
+            Animal::Pig(_) => "Pig",
+            Animal::Eagle(_) => "Eagle",
+            Animal::Penguin(_) => "Penguin",
+        }
+    }
+}
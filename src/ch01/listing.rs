@@ -0,0 +1,22 @@
+//! 第一章的代码清单
+
+use crate::example::Example;
+
+pub const EXAMPLES: &[Example] = &[Example {
+    chapter: 1,
+    listing: 1,
+    title: "零成本抽象: 静态分发与动态分发",
+    run: listing_1_1,
+}];
+
+pub fn listing_1_1() {
+    use crate::ch01::{fly_dyn, fly_static, title, Duck, Fly, Pig};
+    title();
+    let d = Duck;
+    let p = Pig;
+    println!("d fly: {}", d.fly());
+    println!("p fly: {}", p.fly());
+
+    println!("d static: {}", fly_static::<Duck>(d));
+    println!("p dyn: {}", fly_dyn(&p));
+}
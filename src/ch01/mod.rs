@@ -105,3 +105,9 @@ pub fn fly_static<T: Fly>(s: T) -> bool {
 pub fn fly_dyn(s: &dyn Fly) -> bool {
     s.fly()
 }
+
+pub mod dispatch;
+pub mod listing;
+pub mod registry;
+pub mod vtable;
+pub mod zoo;
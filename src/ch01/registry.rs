@@ -0,0 +1,152 @@
+//! # Fly 的运行时注册表
+//!
+//! `Duck`、`Pig` 等 `Fly` 的实现者在编译期就确定了.
+//! [`FlyRegistry`] 以名字为键登记工厂函数, 于是可以在运行时根据字符串
+//! (例如命令行参数或配置文件)构造出 `Box<dyn Fly>`, 这就是常见的工厂/注册表模式
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+use super::zoo::{Eagle, Penguin};
+use super::{Duck, Fly, Pig};
+
+/// 工厂函数: 每次调用构造一个新的 `Fly` 实现者
+pub type Factory = Box<dyn Fn() -> Box<dyn Fly>>;
+
+/// 带名字的实例
+pub type Named = (String, Box<dyn Fly>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryError {
+    /// 没有以该名字登记的工厂
+    Unknown { name: String, known: Vec<String> },
+    /// 该名字已经被登记
+    Duplicate(String),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegistryError::Unknown { name, known } => write!(
+                f,
+                "unknown animal `{}`, registered animals: {}",
+                name,
+                known.join(", ")
+            ),
+            RegistryError::Duplicate(name) => write!(f, "animal `{}` is already registered", name),
+        }
+    }
+}
+
+impl Error for RegistryError {}
+
+/// # 注册表
+///
+/// 名字不区分大小写
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch01::registry::{FlyRegistry, RegistryError};
+/// use rust_programming_of_zhd::ch01::Fly;
+///
+/// struct Bat;
+/// impl Fly for Bat {
+///     fn fly(&self) -> bool {
+///         true
+///     }
+/// }
+///
+/// let mut registry = FlyRegistry::with_defaults();
+/// registry.register("bat", || Box::new(Bat)).unwrap();
+/// assert!(registry.register("Duck", || Box::new(Bat)).is_err());
+///
+/// assert!(registry.create("bat").unwrap().fly());
+/// assert!(!registry.create("PIG").unwrap().fly());
+/// assert!(matches!(registry.create("dragon"), Err(RegistryError::Unknown { .. })));
+/// assert_eq!(registry.names(), ["bat", "duck", "eagle", "penguin", "pig"]);
+/// ```
+#[derive(Default)]
+pub struct FlyRegistry {
+    factories: BTreeMap<String, Factory>,
+}
+
+impl FlyRegistry {
+    /// 空的注册表
+    pub fn new() -> Self {
+        FlyRegistry::default()
+    }
+
+    /// 登记了 `duck`、`pig`、`eagle`、`penguin` 的注册表
+    pub fn with_defaults() -> Self {
+        let mut registry = FlyRegistry::new();
+        let defaults = registry
+            .register("duck", || Box::new(Duck))
+            .and_then(|_| registry.register("pig", || Box::new(Pig)))
+            .and_then(|_| registry.register("eagle", || Box::new(Eagle)))
+            .and_then(|_| registry.register("penguin", || Box::new(Penguin)));
+        defaults.expect("default names are unique");
+        registry
+    }
+
+    /// 登记工厂, 名字已存在时返回错误
+    pub fn register<F>(&mut self, name: &str, factory: F) -> Result<(), RegistryError>
+    where
+        F: Fn() -> Box<dyn Fly> + 'static,
+    {
+        let key = name.to_lowercase();
+        if self.factories.contains_key(&key) {
+            return Err(RegistryError::Duplicate(key));
+        }
+        self.factories.insert(key, Box::new(factory));
+        Ok(())
+    }
+
+    /// 根据名字构造一个新的实例
+    pub fn create(&self, name: &str) -> Result<Box<dyn Fly>, RegistryError> {
+        match self.factories.get(&name.to_lowercase()) {
+            Some(factory) => Ok(factory()),
+            None => Err(RegistryError::Unknown {
+                name: name.to_string(),
+                known: self.names(),
+            }),
+        }
+    }
+
+    /// 已登记的名字, 按字母序排列
+    pub fn names(&self) -> Vec<String> {
+        self.factories.keys().cloned().collect()
+    }
+
+    /// # 从配置文本构造
+    ///
+    /// 配置中的名字以空白分隔, `#` 之后直到行尾为注释
+    ///
+    /// ```
+    /// use rust_programming_of_zhd::ch01::registry::FlyRegistry;
+    /// let config = "# 今天的动物\nduck pig\neagle  # 鹰\n";
+    /// let animals = FlyRegistry::with_defaults().from_config(config).unwrap();
+    /// let report: Vec<(String, bool)> = animals.iter().map(|(n, a)| (n.clone(), a.fly())).collect();
+    /// assert_eq!(
+    ///     report,
+    ///     [("duck".to_string(), true), ("pig".to_string(), false), ("eagle".to_string(), true)]
+    /// );
+    /// ```
+    pub fn from_config(&self, config: &str) -> Result<Vec<Named>, RegistryError> {
+        config
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or(""))
+            .flat_map(str::split_whitespace)
+            .map(|name| self.create(name).map(|animal| (name.to_string(), animal)))
+            .collect()
+    }
+}
+
+impl fmt::Debug for FlyRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FlyRegistry")
+            .field("names", &self.names())
+            .finish()
+    }
+}
@@ -0,0 +1,179 @@
+//! # trait 对象的内存布局
+//!
+//! `&dyn Fly` 是一个胖指针(Fat Pointer): 一个指向数据的指针, 加上一个指向虚表(vtable)的指针.
+//! 虚表由编译器为每个 `(具体类型, trait)` 组合生成, 目前 rustc 的布局是:
+//!
+//! ```text
+//! [0] drop_in_place::<T>  (没有析构逻辑时为空指针)
+//! [1] size_of::<T>()
+//! [2] align_of::<T>()
+//! [3] <T as Fly>::fly     (之后依次是 trait 的其他方法)
+//! ```
+//!
+//! 数据部分的大小与对齐可以通过稳定、安全的 `size_of_val`/`align_of_val` 得到;
+//! 虚表本身的布局则是编译器的实现细节, 读取它必须使用 `unsafe`.
+//! [`inspect`] 只在能够用安全手段交叉验证时(胖指针的数据部分等于原地址,
+//! 虚表中的大小和对齐与 `size_of`/`align_of` 一致)才报告虚表内容, 否则给出 `None`
+
+use std::any;
+use std::fmt;
+use std::mem;
+
+use super::Fly;
+
+/// 虚表的前几项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vtable {
+    pub address: usize,
+    /// `drop_in_place::<T>` 的地址, 类型没有析构逻辑时为 `None`
+    pub drop_in_place: Option<usize>,
+    pub size: usize,
+    pub align: usize,
+    /// 方法槽, `Fly` 只有一个方法 `fly`
+    pub methods: Vec<usize>,
+}
+
+/// # 布局报告
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub type_name: &'static str,
+    /// `size_of::<T>()` / `align_of::<T>()`
+    pub size: usize,
+    pub align: usize,
+    /// 通过 `&dyn Fly` 得到的 `size_of_val` / `align_of_val`
+    pub dyn_size: usize,
+    pub dyn_align: usize,
+    pub needs_drop: bool,
+    /// `size_of::<&T>()`
+    pub thin_pointer: usize,
+    /// `size_of::<&dyn Fly>()`
+    pub fat_pointer: usize,
+    pub vtable: Option<Vtable>,
+}
+
+/// # 检查 `T` 作为 `&dyn Fly` 时的布局
+///
+/// Basic usage:
+///
+/// ```
+/// use std::mem::size_of;
+/// use rust_programming_of_zhd::ch01::dispatch::Animal;
+/// use rust_programming_of_zhd::ch01::vtable::inspect;
+/// use rust_programming_of_zhd::ch01::Duck;
+///
+/// let duck = inspect(&Duck);
+/// assert_eq!(duck.size, 0);
+/// assert_eq!(duck.fat_pointer, 2 * size_of::<usize>());
+/// assert_eq!(duck.thin_pointer, size_of::<usize>());
+/// if let Some(vtable) = &duck.vtable {
+///     assert_eq!((vtable.size, vtable.align), (0, 1));
+///     assert_eq!(vtable.methods.len(), 1);
+/// }
+///
+/// let animal = inspect(&Animal::from(Duck));
+/// assert_eq!((animal.dyn_size, animal.dyn_align), (1, 1));
+/// println!("{}", animal);
+///
+/// // 拥有堆内存的类型需要析构, 虚表的第一项不为空
+/// use rust_programming_of_zhd::ch01::Fly;
+/// struct Kite(String);
+/// impl Fly for Kite {
+///     fn fly(&self) -> bool {
+///         !self.0.is_empty()
+///     }
+/// }
+/// let kite = inspect(&Kite("red".to_string()));
+/// assert!(kite.needs_drop);
+/// assert_eq!(kite.dyn_size, size_of::<String>());
+/// if let Some(vtable) = &kite.vtable {
+///     assert!(vtable.drop_in_place.is_some());
+/// }
+/// ```
+pub fn inspect<T: Fly>(value: &T) -> Report {
+    let object: &dyn Fly = value;
+    Report {
+        type_name: any::type_name::<T>(),
+        size: mem::size_of::<T>(),
+        align: mem::align_of::<T>(),
+        dyn_size: mem::size_of_val(object),
+        dyn_align: mem::align_of_val(object),
+        needs_drop: mem::needs_drop::<T>(),
+        thin_pointer: mem::size_of::<&T>(),
+        fat_pointer: mem::size_of::<&dyn Fly>(),
+        vtable: read_vtable(value),
+    }
+}
+
+fn read_vtable<T: Fly>(value: &T) -> Option<Vtable> {
+    let object: &dyn Fly = value;
+    if mem::size_of::<&dyn Fly>() != mem::size_of::<[usize; 2]>() {
+        return None;
+    }
+    // SAFETY: 两者大小相同, 且 `[usize; 2]` 对任意位模式都是合法值
+    let words: [usize; 2] = unsafe { mem::transmute::<&dyn Fly, [usize; 2]>(object) };
+    let data = value as *const T as usize;
+    // 胖指针中哪一个字是数据指针并没有保证, 先用已知的数据地址确认
+    let vtable = match words {
+        [d, v] if d == data && d != v => v,
+        [v, d] if d == data && d != v => v,
+        _ => return None,
+    };
+    let entry = |i: usize| {
+        // SAFETY: 虚表至少包含 drop/size/align 三项和 trait 的全部方法,
+        // 读取前四个字不会越界; 读取结果只用于展示, 不会被调用或解引用
+        unsafe { *(vtable as *const usize).add(i) }
+    };
+    let (drop_in_place, size, align) = (entry(0), entry(1), entry(2));
+    if size != mem::size_of::<T>() || align != mem::align_of::<T>() {
+        return None;
+    }
+    Some(Vtable {
+        address: vtable,
+        drop_in_place: if drop_in_place == 0 {
+            None
+        } else {
+            Some(drop_in_place)
+        },
+        size,
+        align,
+        methods: vec![entry(3)],
+    })
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} as &dyn Fly", self.type_name)?;
+        writeln!(
+            f,
+            "  size_of::<T>() = {}, align_of::<T>() = {}",
+            self.size, self.align
+        )?;
+        writeln!(
+            f,
+            "  size_of_val(&dyn Fly) = {}, align_of_val(&dyn Fly) = {}",
+            self.dyn_size, self.dyn_align
+        )?;
+        writeln!(f, "  needs_drop::<T>() = {}", self.needs_drop)?;
+        writeln!(
+            f,
+            "  &T: {} bytes (thin), &dyn Fly: {} bytes (data + vtable)",
+            self.thin_pointer, self.fat_pointer
+        )?;
+        match &self.vtable {
+            None => writeln!(f, "  vtable: layout not recognised on this compiler"),
+            Some(vtable) => {
+                writeln!(f, "  vtable @ {:#x}", vtable.address)?;
+                match vtable.drop_in_place {
+                    Some(addr) => writeln!(f, "    [0] drop_in_place = {:#x}", addr)?,
+                    None => writeln!(f, "    [0] drop_in_place = null (no drop glue)")?,
+                }
+                writeln!(f, "    [1] size          = {}", vtable.size)?;
+                writeln!(f, "    [2] align         = {}", vtable.align)?;
+                for (i, method) in vtable.methods.iter().enumerate() {
+                    writeln!(f, "    [{}] fly           = {:#x}", i + 3, method)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,401 @@
+//! # 动物园: 基于 trait 的能力模型
+//!
+//! 在 `Fly` 的基础上再加入 `Swim`、`Walk`、`Speak` 三种能力.
+//! 每种动物通过 [`Animal`] trait 声明自己实现了哪些能力 trait,
+//! 这样就可以把不同的动物以 `Box<dyn Animal>` 的形式放进同一个 [`Zoo`],
+//! 再按能力筛选, 并以对应的 trait 对象(例如 `&dyn Fly`)来使用它们
+
+use std::fmt;
+
+use super::{Duck, Fly, Pig};
+
+pub trait Swim {
+    fn swim(&self) -> bool;
+}
+
+pub trait Walk {
+    fn walk(&self) -> bool;
+}
+
+pub trait Speak {
+    fn speak(&self) -> &'static str;
+}
+
+/// 能力, 与 trait 一一对应
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    Fly,
+    Swim,
+    Walk,
+    Speak,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 4] = [
+        Capability::Fly,
+        Capability::Swim,
+        Capability::Walk,
+        Capability::Speak,
+    ];
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Capability::Fly => "Fly",
+            Capability::Swim => "Swim",
+            Capability::Walk => "Walk",
+            Capability::Speak => "Speak",
+        };
+        f.pad(name)
+    }
+}
+
+/// # 能力集合
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch01::zoo::{Capabilities, Capability};
+/// let caps = Capabilities::empty().with(Capability::Fly).with(Capability::Speak);
+/// assert!(caps.contains(Capability::Fly));
+/// assert!(!caps.contains(Capability::Swim));
+/// assert_eq!(caps.to_string(), "Fly + Speak");
+/// assert_eq!(Capabilities::empty().to_string(), "-");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Capabilities(u8);
+
+impl Capabilities {
+    pub fn empty() -> Self {
+        Capabilities(0)
+    }
+
+    pub fn with(self, capability: Capability) -> Self {
+        Capabilities(self.0 | capability.bit())
+    }
+
+    pub fn contains(self, capability: Capability) -> bool {
+        self.0 & capability.bit() != 0
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = Capability> {
+        Capability::ALL
+            .iter()
+            .copied()
+            .filter(move |c| self.contains(*c))
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<String> = self.iter().map(|c| c.to_string()).collect();
+        if names.is_empty() {
+            write!(f, "-")
+        } else {
+            write!(f, "{}", names.join(" + "))
+        }
+    }
+}
+
+/// # 动物
+///
+/// 每个 `as_xxx` 方法在类型实现了对应的 trait 时返回该 trait 对象, 否则返回 `None`
+/// `capabilities` 根据这些方法得出该类型实现了哪些能力 trait
+///
+/// 一般不需要手写实现, 使用 [`animal!`](crate::animal) 宏声明即可
+pub trait Animal {
+    fn name(&self) -> &'static str;
+
+    fn as_fly(&self) -> Option<&dyn Fly> {
+        None
+    }
+
+    fn as_swim(&self) -> Option<&dyn Swim> {
+        None
+    }
+
+    fn as_walk(&self) -> Option<&dyn Walk> {
+        None
+    }
+
+    fn as_speak(&self) -> Option<&dyn Speak> {
+        None
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        let mut caps = Capabilities::empty();
+        if self.as_fly().is_some() {
+            caps = caps.with(Capability::Fly);
+        }
+        if self.as_swim().is_some() {
+            caps = caps.with(Capability::Swim);
+        }
+        if self.as_walk().is_some() {
+            caps = caps.with(Capability::Walk);
+        }
+        if self.as_speak().is_some() {
+            caps = caps.with(Capability::Speak);
+        }
+        caps
+    }
+}
+
+/// # 声明动物及其能力
+///
+/// `animal!(Duck: Fly, Swim);` 为 `Duck` 实现 [`Animal`](crate::ch01::zoo::Animal),
+/// 并让 `as_fly`、`as_swim` 返回 `Some(self)`. 列出的 trait 必须已经为该类型实现
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::animal;
+/// use rust_programming_of_zhd::ch01::zoo::{Animal, Capability, Swim};
+///
+/// struct Frog;
+/// impl Swim for Frog {
+///     fn swim(&self) -> bool {
+///         true
+///     }
+/// }
+/// animal!(Frog: Swim);
+///
+/// assert_eq!(Frog.name(), "Frog");
+/// assert!(Frog.capabilities().contains(Capability::Swim));
+/// assert!(Frog.as_fly().is_none());
+/// ```
+#[macro_export]
+macro_rules! animal {
+    ($ty:ident : $($cap:ident),* $(,)?) => {
+        impl $crate::ch01::zoo::Animal for $ty {
+            fn name(&self) -> &'static str {
+                stringify!($ty)
+            }
+            $($crate::animal!(@as $cap);)*
+        }
+    };
+    (@as Fly) => {
+        fn as_fly(&self) -> Option<&dyn $crate::ch01::Fly> {
+            Some(self)
+        }
+    };
+    (@as Swim) => {
+        fn as_swim(&self) -> Option<&dyn $crate::ch01::zoo::Swim> {
+            Some(self)
+        }
+    };
+    (@as Walk) => {
+        fn as_walk(&self) -> Option<&dyn $crate::ch01::zoo::Walk> {
+            Some(self)
+        }
+    };
+    (@as Speak) => {
+        fn as_speak(&self) -> Option<&dyn $crate::ch01::zoo::Speak> {
+            Some(self)
+        }
+    };
+}
+
+pub struct Eagle;
+pub struct Fish;
+pub struct Dog;
+pub struct Penguin;
+
+impl Swim for Duck {
+    fn swim(&self) -> bool {
+        true
+    }
+}
+
+impl Walk for Duck {
+    fn walk(&self) -> bool {
+        true
+    }
+}
+
+impl Speak for Duck {
+    fn speak(&self) -> &'static str {
+        "quack"
+    }
+}
+
+impl Walk for Pig {
+    fn walk(&self) -> bool {
+        true
+    }
+}
+
+impl Speak for Pig {
+    fn speak(&self) -> &'static str {
+        "oink"
+    }
+}
+
+impl Fly for Eagle {
+    fn fly(&self) -> bool {
+        true
+    }
+}
+
+impl Walk for Eagle {
+    fn walk(&self) -> bool {
+        true
+    }
+}
+
+impl Speak for Eagle {
+    fn speak(&self) -> &'static str {
+        "screech"
+    }
+}
+
+impl Swim for Fish {
+    fn swim(&self) -> bool {
+        true
+    }
+}
+
+impl Swim for Dog {
+    fn swim(&self) -> bool {
+        true
+    }
+}
+
+impl Walk for Dog {
+    fn walk(&self) -> bool {
+        true
+    }
+}
+
+impl Speak for Dog {
+    fn speak(&self) -> &'static str {
+        "woof"
+    }
+}
+
+impl Fly for Penguin {
+    fn fly(&self) -> bool {
+        false
+    }
+}
+
+impl Swim for Penguin {
+    fn swim(&self) -> bool {
+        true
+    }
+}
+
+impl Walk for Penguin {
+    fn walk(&self) -> bool {
+        true
+    }
+}
+
+impl Speak for Penguin {
+    fn speak(&self) -> &'static str {
+        "honk"
+    }
+}
+
+animal!(Duck: Fly, Swim, Walk, Speak);
+animal!(Pig: Fly, Walk, Speak);
+animal!(Eagle: Fly, Walk, Speak);
+animal!(Fish: Swim);
+animal!(Dog: Swim, Walk, Speak);
+animal!(Penguin: Fly, Swim, Walk, Speak);
+
+/// # 动物园
+///
+/// 以 `Box<dyn Animal>` 保存各种动物, 可以按能力筛选
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch01::zoo::{Capability, Dog, Eagle, Fish, Zoo};
+/// use rust_programming_of_zhd::ch01::{Duck, Pig};
+///
+/// let zoo = Zoo::new()
+///     .with(Duck)
+///     .with(Pig)
+///     .with(Eagle)
+///     .with(Fish)
+///     .with(Dog);
+///
+/// let swimmers: Vec<&str> = zoo.filter(Capability::Swim).map(|a| a.name()).collect();
+/// assert_eq!(swimmers, ["Duck", "Fish", "Dog"]);
+///
+/// // Pig 实现了 Fly, 但 fly() 返回 false
+/// let flying: Vec<bool> = zoo.flyers().map(|f| f.fly()).collect();
+/// assert_eq!(flying, [true, false, true]);
+///
+/// let sounds: Vec<&str> = zoo.speakers().map(|s| s.speak()).collect();
+/// assert_eq!(sounds, ["quack", "oink", "screech", "woof"]);
+///
+/// assert_eq!(zoo.to_string().lines().next(), Some("Duck: Fly + Swim + Walk + Speak"));
+/// ```
+#[derive(Default)]
+pub struct Zoo {
+    animals: Vec<Box<dyn Animal>>,
+}
+
+impl Zoo {
+    pub fn new() -> Self {
+        Zoo::default()
+    }
+
+    pub fn with<A: Animal + 'static>(mut self, animal: A) -> Self {
+        self.push(Box::new(animal));
+        self
+    }
+
+    pub fn push(&mut self, animal: Box<dyn Animal>) {
+        self.animals.push(animal);
+    }
+
+    pub fn len(&self) -> usize {
+        self.animals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.animals.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Animal> {
+        self.animals.iter().map(|a| a.as_ref())
+    }
+
+    /// 具备某种能力的动物
+    pub fn filter(&self, capability: Capability) -> impl Iterator<Item = &dyn Animal> {
+        self.iter()
+            .filter(move |a| a.capabilities().contains(capability))
+    }
+
+    pub fn flyers(&self) -> impl Iterator<Item = &dyn Fly> {
+        self.iter().filter_map(|a| a.as_fly())
+    }
+
+    pub fn swimmers(&self) -> impl Iterator<Item = &dyn Swim> {
+        self.iter().filter_map(|a| a.as_swim())
+    }
+
+    pub fn walkers(&self) -> impl Iterator<Item = &dyn Walk> {
+        self.iter().filter_map(|a| a.as_walk())
+    }
+
+    pub fn speakers(&self) -> impl Iterator<Item = &dyn Speak> {
+        self.iter().filter_map(|a| a.as_speak())
+    }
+}
+
+impl fmt::Display for Zoo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for animal in self.iter() {
+            writeln!(f, "{}: {}", animal.name(), animal.capabilities())?;
+        }
+        Ok(())
+    }
+}
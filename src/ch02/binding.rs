@@ -1,3 +1,5 @@
+use std::io::{self, Write};
+
 /// # 临时值
 ///
 /// 位置表达式和值表达式
@@ -36,9 +38,12 @@
 /// 其余表达式都属于值上下文
 /// 值表达式不能出现在位置上下文中
 ///
+/// [`lang::place`](crate::lang::place) 模块可以为任意代码片段标注上述分类,
+/// 例如 `rust-programming-of-zhd place 'let x = &temp(); temp() = *x;'`
+///
 /// Basic usage:
 ///
-/// ```
+/// ```compile_fail,E0070
 /// pub fn temp() -> i32 {
 ///     return 1;
 /// }
@@ -66,6 +71,13 @@ pub fn temp() -> i32 {
 /// }
 /// immutable_and_mutable();
 /// ```
+///
+/// 对不可变绑定再次赋值, 无法通过编译:
+///
+/// ```compile_fail,E0384
+/// let a = 1;
+/// a = 2; // error[E0384]: cannot assign twice to immutable variable `a`
+/// ```
 pub fn immutable_and_mutable() {
     let a = 1;
     // a = 2;
@@ -84,9 +96,12 @@ pub fn immutable_and_mutable() {
 /// 在 Rust 中称为移动(Move)语义, 那种不转移的情况实际上
 /// 是一种复制(Copy)语义
 ///
+/// [`lang::ownership`](crate::lang::ownership) 模块可以逐步模拟任意代码片段中的移动与复制,
+/// 例如 `rust-programming-of-zhd ownership < tests/compile-fail/ownership.rs`
+///
 /// Basic usage:
 ///
-/// ```
+/// ```compile_fail,E0382
 /// pub fn ownership(){
 ///     let place1 = "hello";
 ///     //  ^^ 位置表达式 ^^  值表达式
@@ -102,21 +117,39 @@ pub fn immutable_and_mutable() {
 /// ownership();
 /// ```
 pub fn ownership() {
+    ownership_to(&mut io::stdout()).expect("failed to write to stdout")
+}
+
+/// # 所有权: 输出到任意 Writer
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::binding::ownership_to;
+/// let mut out = Vec::new();
+/// ownership_to(&mut out).unwrap();
+/// assert_eq!(out, b"\"hello\"\n\"hello\"\n\"hello\"\n");
+/// ```
+pub fn ownership_to<W: Write>(out: &mut W) -> io::Result<()> {
     let place1 = "hello";
     let place2 = "hello".to_string();
     let other = place1;
-    println!("{:?}", other);
-    println!("{:?}", place1);
+    writeln!(out, "{:?}", other)?;
+    writeln!(out, "{:?}", place1)?;
     let other = place2;
-    println!("{:?}", other);
-    // println!("{:?}", place2);
+    writeln!(out, "{:?}", other)?;
+    // writeln!(out, "{:?}", place2)?;
     // ^ Err : place2 value used here after move
+    Ok(())
 }
 /// # 引用
 ///
 /// Rust 提供引用操作符(&), 可以直接获取表达式的存储单元地址, 即内存位置
 /// 可以通过该内存位置对存储进行读取
 ///
+/// [`lang::borrowck`](crate::lang::borrowck) 模块可以在词法作用域与 NLL 两种模式下检查借用冲突,
+/// 例如 `rust-programming-of-zhd borrowck < tests/compile-fail/reference.rs`
+///
 /// Basic usage:
 ///
 /// ```
@@ -134,16 +167,36 @@ pub fn ownership() {
 /// reference();
 /// ```
 pub fn reference() {
+    reference_to(&mut io::stdout()).expect("failed to write to stdout")
+}
+
+/// # 引用: 输出到任意 Writer
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::binding::reference_to;
+/// let mut out = Vec::new();
+/// reference_to(&mut out).unwrap();
+/// let out = String::from_utf8(out).unwrap();
+/// let mut lines = out.lines();
+/// // &a 与 b 指向同一个内存地址
+/// let addrs: Vec<&str> = lines.next().unwrap().split(' ').collect();
+/// assert_eq!(addrs[0], addrs[1]);
+/// assert_eq!(lines.next(), Some("[1, 2, 3, 4]"));
+/// ```
+pub fn reference_to<W: Write>(out: &mut W) -> io::Result<()> {
     let a = [1, 2, 3];
     let b = &a;
-    println!("{:p} {:p}", &a, b); // 打印指针(内存地址)
-                                  // 要获取可变引用, 必须先声明可变绑定
+    writeln!(out, "{:p} {:p}", &a, b)?; // 打印指针(内存地址)
+                                        // 要获取可变引用, 必须先声明可变绑定
     let mut c = vec![1, 2, 3];
     let d = &mut c;
     d.push(4);
-    println!("{:?}", d);
+    writeln!(out, "{:?}", d)?;
     let e = &42;
     assert_eq!(42, *e);
+    Ok(())
 }
 
 /// # 引用2
@@ -153,12 +206,31 @@ pub fn reference() {
 ///
 /// 值表达式在位置上下文中求值时会被创建临时值
 /// println!("{:p}", &42);
+///
+/// [`lang::mir`](crate::lang::mir) 模块可以把任意代码片段降级成下面这种 MIR 风格的代码,
+/// 例如 `rust-programming-of-zhd mir 'let x = &42;'`
 pub fn reference2() {
+    reference2_to(&mut io::stdout()).expect("failed to write to stdout")
+}
+
+/// # 引用2: 输出到任意 Writer
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::binding::reference2_to;
+/// let mut out = Vec::new();
+/// reference2_to(&mut out).unwrap();
+/// let out = String::from_utf8(out).unwrap();
+/// assert!(out.starts_with("42 42\n0x"));
+/// ```
+pub fn reference2_to<W: Write>(out: &mut W) -> io::Result<()> {
     let mut _0: &i32;
     let mut _1: i32;
     _1 = 42i32;
     _0 = &_1;
-    println!("{} {}", _0, _1);
+    writeln!(out, "{} {}", _0, _1)?;
     // 值表达式在位置上下文中求值时会被创建临时值
-    println!("{:p}", &42);
+    writeln!(out, "{:p}", &42)?;
+    Ok(())
 }
@@ -1,3 +1,5 @@
+use std::io::{self, Write};
+
 /// # if表达式
 ///
 /// Basic usage:
@@ -43,22 +45,49 @@ pub fn if_expr() {
 /// while_fizzbuzz();
 /// ```
 pub fn while_fizzbuzz() {
+    while_fizzbuzz_to(&mut io::stdout()).expect("failed to write to stdout")
+}
+
+/// # while表达式: 输出到任意 Writer
+///
+/// 与 `while_fizzbuzz` 相同, 只是把结果写入 `out`, 方便对输出做断言
+/// 三种循环写法的输出完全一致
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::control_flow::*;
+/// let mut by_while = Vec::new();
+/// let mut by_loop = Vec::new();
+/// let mut by_for = Vec::new();
+/// while_fizzbuzz_to(&mut by_while).unwrap();
+/// loop_fizzbuzz_to(&mut by_loop).unwrap();
+/// for_fizzbuzz_to(&mut by_for).unwrap();
+/// assert_eq!(by_while, by_loop);
+/// assert_eq!(by_while, by_for);
+///
+/// let text = String::from_utf8(by_while).unwrap();
+/// assert_eq!(text.lines().count(), 100);
+/// assert_eq!(text.lines().last(), Some("buzz"));
+/// ```
+pub fn while_fizzbuzz_to<W: Write>(out: &mut W) -> io::Result<()> {
     let mut n = 1;
     while n < 101 {
         if n % 15 == 0 {
-            println!("fizzbuzz");
+            writeln!(out, "fizzbuzz")?;
         } else if n % 3 == 0 {
-            println!("fizz");
+            writeln!(out, "fizz")?;
         } else if n % 5 == 0 {
-            println!("buzz");
+            writeln!(out, "buzz")?;
         } else {
-            println!("{}", n);
+            writeln!(out, "{}", n)?;
         }
         n += 1;
     }
+    Ok(())
 }
 
-/// # while表达式
+/// # loop表达式
 ///
 /// Basic usage:
 ///
@@ -66,7 +95,7 @@ pub fn while_fizzbuzz() {
 /// fn loop_fizzbuzz() {
 ///     let mut n = 1;
 ///     loop {
-///         if n > 101 { break; }
+///         if n >= 101 { break; }
 ///         if n % 15 == 0 {
 ///             println!("fizzbuzz");
 ///         } else if n % 3 == 0 {
@@ -82,22 +111,28 @@ pub fn while_fizzbuzz() {
 /// loop_fizzbuzz();
 /// ```
 pub fn loop_fizzbuzz() {
+    loop_fizzbuzz_to(&mut io::stdout()).expect("failed to write to stdout")
+}
+
+/// # loop表达式: 输出到任意 Writer
+pub fn loop_fizzbuzz_to<W: Write>(out: &mut W) -> io::Result<()> {
     let mut n = 1;
     loop {
         if n >= 101 {
             break;
         }
         if n % 15 == 0 {
-            println!("fizzbuzz");
+            writeln!(out, "fizzbuzz")?;
         } else if n % 3 == 0 {
-            println!("fizz");
+            writeln!(out, "fizz")?;
         } else if n % 5 == 0 {
-            println!("buzz");
+            writeln!(out, "buzz")?;
         } else {
-            println!("{}", n);
+            writeln!(out, "{}", n)?;
         }
         n += 1;
     }
+    Ok(())
 }
 
 /// # for表达式
@@ -121,26 +156,34 @@ pub fn loop_fizzbuzz() {
 /// for_fizzbuzz();
 /// ```
 pub fn for_fizzbuzz() {
+    for_fizzbuzz_to(&mut io::stdout()).expect("failed to write to stdout")
+}
+
+/// # for表达式: 输出到任意 Writer
+pub fn for_fizzbuzz_to<W: Write>(out: &mut W) -> io::Result<()> {
     for n in 1..101 {
         if n % 15 == 0 {
-            println!("fizzbuzz");
+            writeln!(out, "fizzbuzz")?;
         } else if n % 3 == 0 {
-            println!("fizz");
+            writeln!(out, "fizz")?;
         } else if n % 5 == 0 {
-            println!("buzz");
+            writeln!(out, "buzz")?;
         } else {
-            println!("{}", n);
+            writeln!(out, "{}", n)?;
         }
     }
+    Ok(())
 }
 
 /// # while true
 ///
 /// 当需要使用无限循环的时候, 请务必使用 loop 循环, 避免使用 while true 循环
 ///
+/// [`lang::typeck`](crate::lang::typeck) 按同样的规则给循环定型, 可以用 `typeck` 子命令查看这个清单为什么报告 E0308
+///
 /// Basic usage:
 ///
-/// ```
+/// ```compile_fail,E0308
 /// fn while_true(x: i32) -> i32 {
 ///     while true {  // error[E0308]: mismatched types，expected type `i32` found type `()`
 ///         return x+1;
@@ -169,9 +212,12 @@ pub fn while_true(x: i32) -> i32 {
 
 /// # if true
 ///
+/// 新版的 rustc 对只有一个分支的 if 报告 E0317(`if` may be missing an `else` clause),
+/// 原因与 while true 相同: 缺少 else 分支的 if 表达式类型为单元值
+///
 /// Basic usage:
 ///
-/// ```
+/// ```compile_fail,E0317
 /// fn if_true(x: i32) -> i32 {
 ///     if true {  // error[E0308]: mismatched types，expected type `i32` found type `()`
 ///         return x+1;
@@ -209,7 +255,7 @@ pub fn if_true(x: i32) -> i32 {
 ///     let number = 42;
 ///     match number {
 ///         0 => println!("Origin"),  // 匹配数字
-///         1...3 => println!("All"), // 匹配范围
+///         1..=3 => println!("All"), // 匹配范围
 ///         5 | 7 | 13 => println!("Bad Luck"), // 匹配相同的分支
 ///         n @ 42 => println!("Answer is {}", n), // 使用@可以创建绑定n，分支右侧表达式中可用
 ///         _ => println!("Common"),  // 下划线为通用匹配
@@ -256,7 +302,7 @@ pub fn match_bool() {
 /// Basic usage:
 ///
 /// Rust 还提供了 if let 和 while let 表达式, 分别用来在某些场合替代 match 表达式
-/// 
+///
 /// ```
 /// fn if_let_bool() {
 ///     let boolean = true;
@@ -291,10 +337,28 @@ pub fn if_let_bool() {
 /// while_let_pop();
 /// ```
 pub fn while_let_pop() {
+    while_let_pop_to(&mut io::stdout()).expect("failed to write to stdout")
+}
+
+/// # while let: 输出到任意 Writer
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::control_flow::{loop_match_pop_to, while_let_pop_to};
+/// let mut by_while_let = Vec::new();
+/// let mut by_loop_match = Vec::new();
+/// while_let_pop_to(&mut by_while_let).unwrap();
+/// loop_match_pop_to(&mut by_loop_match).unwrap();
+/// assert_eq!(by_while_let, b"5\n4\n3\n2\n1\n");
+/// assert_eq!(by_while_let, by_loop_match);
+/// ```
+pub fn while_let_pop_to<W: Write>(out: &mut W) -> io::Result<()> {
     let mut v = vec![1, 2, 3, 4, 5];
     while let Some(x) = v.pop() {
-        println!("{}", x);
+        writeln!(out, "{}", x)?;
     }
+    Ok(())
 }
 
 // # while match
@@ -314,11 +378,17 @@ pub fn while_let_pop() {
 /// loop_match_pop();
 /// ```
 pub fn loop_match_pop() {
+    loop_match_pop_to(&mut io::stdout()).expect("failed to write to stdout")
+}
+
+/// # while match: 输出到任意 Writer
+pub fn loop_match_pop_to<W: Write>(out: &mut W) -> io::Result<()> {
     let mut v = vec![1, 2, 3, 4, 5];
     loop {
         match v.pop() {
-            Some(x) => println!("{}", x),
+            Some(x) => writeln!(out, "{}", x)?,
             None => break,
         }
     }
+    Ok(())
 }
@@ -0,0 +1,165 @@
+//! # 编译期生成的查找表
+//!
+//! [`function::init_len`](super::function::init_len) 说明了 `const fn` 可以用在数组长度这样的常量上下文中.
+//! 这里的 `const fn` 在编译期把整张表算好, 运行时只是读取静态数据;
+//! 如果计算溢出或越界, 错误在编译期就会报告出来, 而不是等到运行时才 panic
+//!
+//! [`fizzbuzz::table`](super::fizzbuzz::table) 用同样的方法生成完整的 FizzBuzz 记号表和预先渲染好的输出
+//!
+//! [`lang::consteval`](crate::lang::consteval) 用一个解释器模拟同样的求值过程, 并解释哪些操作不能在常量上下文中使用
+
+use super::function::init_len;
+
+/// FizzBuzz 的周期: 3 与 5 的最小公倍数
+pub const FIZZBUZZ_PERIOD: usize = 15;
+
+/// # FizzBuzz 周期表
+///
+/// 第 `i` 项是余数为 `i` 的数对应的单词, 输出数字本身时为 `None`
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::ctfe::{fizzbuzz_words, FIZZBUZZ_WORDS};
+///
+/// assert_eq!(FIZZBUZZ_WORDS[0], Some("fizzbuzz"));
+/// assert_eq!(FIZZBUZZ_WORDS[3], Some("fizz"));
+/// assert_eq!(FIZZBUZZ_WORDS[10], Some("buzz"));
+/// assert_eq!(FIZZBUZZ_WORDS[7], None);
+/// // 同一个 const fn 也可以在运行时调用
+/// assert_eq!(fizzbuzz_words(), FIZZBUZZ_WORDS);
+/// ```
+pub const fn fizzbuzz_words() -> [Option<&'static str>; FIZZBUZZ_PERIOD] {
+    let mut table = [None; FIZZBUZZ_PERIOD];
+    let mut i = 0;
+    // const fn 中不能使用 for 循环, 只能用 while 或 loop
+    while i < FIZZBUZZ_PERIOD {
+        table[i] = match (i % 3, i % 5) {
+            (0, 0) => Some("fizzbuzz"),
+            (0, _) => Some("fizz"),
+            (_, 0) => Some("buzz"),
+            _ => None,
+        };
+        i += 1;
+    }
+    table
+}
+
+/// 编译期生成的 FizzBuzz 周期表
+pub const FIZZBUZZ_WORDS: [Option<&str>; FIZZBUZZ_PERIOD] = fizzbuzz_words();
+
+/// # 查表得到 FizzBuzz 单词
+///
+/// 只做一次取模, 不再分别判断 3 与 5
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::ctfe::fizzbuzz_word;
+/// use rust_programming_of_zhd::ch02::function::fizz_buzz;
+///
+/// for n in 1..=100 {
+///     let expected = fizz_buzz(n as i32);
+///     assert_eq!(fizzbuzz_word(n).map_or(n.to_string(), str::to_string), expected);
+/// }
+/// // 常量上下文中同样可用
+/// const FIFTEEN: Option<&str> = fizzbuzz_word(15);
+/// assert_eq!(FIFTEEN, Some("fizzbuzz"));
+/// ```
+pub const fn fizzbuzz_word(n: u64) -> Option<&'static str> {
+    FIZZBUZZ_WORDS[(n % FIZZBUZZ_PERIOD as u64) as usize]
+}
+
+/// # 阶乘表
+///
+/// 第 `i` 项是 `i!`. `N` 超过 21 时 `21!` 溢出 `u64`, 编译期求值失败(E0080)
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::ctfe::{factorials, FACTORIALS};
+///
+/// assert_eq!(FACTORIALS[0], 1);
+/// assert_eq!(FACTORIALS[5], 120);
+/// assert_eq!(FACTORIALS[20], 2_432_902_008_176_640_000);
+/// const SMALL: [u64; 4] = factorials();
+/// assert_eq!(SMALL, [1, 1, 2, 6]);
+/// ```
+///
+/// ```compile_fail,E0080
+/// use rust_programming_of_zhd::ch02::ctfe::factorials;
+///
+/// const TOO_MANY: [u64; 22] = factorials();
+/// ```
+pub const fn factorials<const N: usize>() -> [u64; N] {
+    let mut table = [1; N];
+    let mut i = 1;
+    while i < N {
+        table[i] = table[i - 1] * i as u64;
+        i += 1;
+    }
+    table
+}
+
+/// `0!` 到 `20!`, `u64` 能表示的全部阶乘
+pub const FACTORIALS: [u64; 21] = factorials();
+
+/// # 查表计算阶乘
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::ctfe::factorial;
+///
+/// assert_eq!(factorial(10), Some(3_628_800));
+/// assert_eq!(factorial(21), None);
+/// ```
+pub const fn factorial(n: usize) -> Option<u64> {
+    if n < FACTORIALS.len() {
+        Some(FACTORIALS[n])
+    } else {
+        None
+    }
+}
+
+/// # 斐波那契表
+///
+/// 第 `i` 项是第 `i` 个斐波那契数, 从 `0, 1` 开始
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::ctfe::{fibonacci, FIBONACCI};
+///
+/// const FIRST: [u64; 10] = fibonacci();
+/// assert_eq!(FIRST, [0, 1, 1, 2, 3, 5, 8, 13, 21, 34]);
+/// assert_eq!(FIBONACCI[93], 12_200_160_415_121_876_738);
+/// ```
+pub const fn fibonacci<const N: usize>() -> [u64; N] {
+    let mut table = [0; N];
+    let mut i = 1;
+    while i < N {
+        table[i] = if i == 1 {
+            1
+        } else {
+            table[i - 1] + table[i - 2]
+        };
+        i += 1;
+    }
+    table
+}
+
+/// 前 94 个斐波那契数, 第 94 个会溢出 `u64`
+pub const FIBONACCI: [u64; 94] = fibonacci();
+
+/// # 由 `init_len` 决定长度的缓冲区
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::ctfe::INIT_BUFFER;
+/// use rust_programming_of_zhd::ch02::function::init_len;
+///
+/// assert_eq!(INIT_BUFFER.len(), init_len());
+/// ```
+pub const INIT_BUFFER: [u8; init_len()] = [0; init_len()];
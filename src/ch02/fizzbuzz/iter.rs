@@ -0,0 +1,175 @@
+//! # 惰性 FizzBuzz 迭代器
+//!
+//! `for_fizzbuzz` 等清单只能处理 `1..101`, 并且立即打印.
+//! [`fizzbuzz`] 接受任意整数类型的任意范围, 按需产出 [`FizzBuzzToken`],
+//! 支持 `rev`、`step_by`(以 O(1) 的 `nth` 实现跳跃), 可以处理上百亿的范围
+
+use std::convert::TryFrom;
+use std::iter::FusedIterator;
+use std::ops::{Bound, RangeBounds};
+
+use super::token::{FizzBuzzToken, Integer};
+
+/// # 任意范围上的 FizzBuzz
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::fizzbuzz::{fizzbuzz, FizzBuzzToken};
+/// use rust_programming_of_zhd::ch02::function::fizz_buzz;
+///
+/// // 与 for_fizzbuzz 相同的 1..101
+/// let words: Vec<String> = fizzbuzz(1..101).map(|t| t.to_string()).collect();
+/// let expected: Vec<String> = (1..101).map(fizz_buzz).collect();
+/// assert_eq!(words, expected);
+///
+/// // 负数与倒序
+/// let down: Vec<String> = fizzbuzz(-5i64..=-1).rev().map(|t| t.to_string()).collect();
+/// assert_eq!(down, ["-1", "-2", "fizz", "-4", "buzz"]);
+///
+/// // step_by 与 ExactSizeIterator
+/// let iter = fizzbuzz(0u8..=255);
+/// assert_eq!(iter.len(), 256);
+/// let fives: Vec<_> = fizzbuzz(5u32..=30).step_by(5).collect();
+/// assert_eq!(fives.len(), 6);
+/// assert!(fives.iter().all(|t| !t.is_number()));
+///
+/// // 超大范围: 不会真的遍历
+/// let mut huge = fizzbuzz(..=u128::MAX);
+/// assert_eq!(huge.nth(15), Some(FizzBuzzToken::FizzBuzz));
+/// assert_eq!(huge.next_back(), Some(FizzBuzzToken::FizzBuzz)); // 2^128 - 1 能被 15 整除
+/// assert_eq!(fizzbuzz(1i64..=10_000_000_000).size_hint().0, 10_000_000_000);
+/// ```
+pub fn fizzbuzz<T, R>(range: R) -> FizzBuzzIter<T>
+where
+    T: Integer,
+    R: RangeBounds<T>,
+{
+    FizzBuzzIter::new(range)
+}
+
+/// [`fizzbuzz`] 返回的迭代器, 内部以闭区间 `[front, back]` 表示剩余的范围
+#[derive(Debug, Clone)]
+pub struct FizzBuzzIter<T> {
+    front: T,
+    back: T,
+    exhausted: bool,
+}
+
+impl<T: Integer> FizzBuzzIter<T> {
+    pub fn new<R: RangeBounds<T>>(range: R) -> Self {
+        let mut exhausted = false;
+        let front = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) if start == T::MAX => {
+                exhausted = true;
+                start
+            }
+            Bound::Excluded(&start) => start.add(1),
+            Bound::Unbounded => T::MIN,
+        };
+        let back = match range.end_bound() {
+            Bound::Included(&end) => end,
+            Bound::Excluded(&end) if end == T::MIN => {
+                exhausted = true;
+                end
+            }
+            Bound::Excluded(&end) => end.sub(1),
+            Bound::Unbounded => T::MAX,
+        };
+        FizzBuzzIter {
+            front,
+            back,
+            exhausted: exhausted || front > back,
+        }
+    }
+
+    /// 剩余元素个数减一; 迭代器已耗尽时为 `None`
+    fn remaining_span(&self) -> Option<u128> {
+        if self.exhausted {
+            None
+        } else {
+            Some(T::span(self.front, self.back))
+        }
+    }
+}
+
+impl<T: Integer> Iterator for FizzBuzzIter<T> {
+    type Item = FizzBuzzToken<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.nth(0)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        let span = self.remaining_span()?;
+        if n as u128 > span {
+            self.exhausted = true;
+            return None;
+        }
+        let current = self.front.add(n as u128);
+        if n as u128 == span {
+            self.exhausted = true;
+        } else {
+            self.front = current.add(1);
+        }
+        Some(FizzBuzzToken::of(current))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.remaining_span() {
+            None => (0, Some(0)),
+            Some(span) => match span.checked_add(1).map(usize::try_from) {
+                Some(Ok(len)) => (len, Some(len)),
+                _ => (usize::MAX, None),
+            },
+        }
+    }
+
+    fn count(self) -> usize {
+        match self.size_hint() {
+            (len, Some(_)) => len,
+            // 长度超出 `usize`, 与 `Iterator::count` 一样在溢出时 panic, 而不是返回截断的值
+            _ => panic!("attempt to count FizzBuzzIter with overflow"),
+        }
+    }
+
+    fn last(mut self) -> Option<Self::Item> {
+        self.next_back()
+    }
+}
+
+impl<T: Integer> DoubleEndedIterator for FizzBuzzIter<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.nth_back(0)
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+        let span = self.remaining_span()?;
+        if n as u128 > span {
+            self.exhausted = true;
+            return None;
+        }
+        let current = self.back.sub(n as u128);
+        if n as u128 == span {
+            self.exhausted = true;
+        } else {
+            self.back = current.sub(1);
+        }
+        Some(FizzBuzzToken::of(current))
+    }
+}
+
+impl<T: Integer> FusedIterator for FizzBuzzIter<T> {}
+
+// 与标准库的 Range 一样, 只有长度一定能放进 usize 的类型才实现 ExactSizeIterator
+macro_rules! impl_exact_size {
+    ($($t:ty),*) => {
+        $(impl ExactSizeIterator for FizzBuzzIter<$t> {})*
+    };
+}
+
+impl_exact_size!(i8, u8, i16, u16);
+// `i32::MIN..=i32::MAX` 有 2^32 项, 在 32 位平台上放不进 usize
+#[cfg(target_pointer_width = "64")]
+impl_exact_size!(i32, u32);
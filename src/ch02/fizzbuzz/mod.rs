@@ -0,0 +1,26 @@
+//! # FizzBuzz
+//!
+//! 在 `function::fizz_buzz` 和 `control_flow` 中的几个 FizzBuzz 清单基础上,
+//! 提供可以复用的 FizzBuzz 工具
+//!
+//! - [`FizzBuzz`]: 可配置的规则引擎, 支持任意的除数规则和谓词规则
+//! - [`fizzbuzz`]: 任意整数范围上的惰性迭代器, 产出 [`FizzBuzzToken`]
+//! - [`ParallelFizzBuzz`]: 多线程分块生成, 用作吞吐量基准
+//! - [`render`] 模块: 纯文本、CSV、JSON、JSON Lines、Markdown 等输出格式
+//! - [`wheel`] 模块: 用滚动计数器代替取模运算的实现
+//! - [`strategy`] 模块: while/loop/for/递归/迭代器链五种循环策略共用同一个循环体
+//! - [`table`] 模块: 编译期生成的记号表和预先渲染好的输出
+
+pub mod iter;
+pub mod parallel;
+pub mod render;
+pub mod rules;
+pub mod strategy;
+pub mod table;
+pub mod token;
+pub mod wheel;
+
+pub use self::iter::{fizzbuzz, FizzBuzzIter};
+pub use self::parallel::ParallelFizzBuzz;
+pub use self::rules::{FizzBuzz, Mode};
+pub use self::token::{FizzBuzzToken, Integer};
@@ -0,0 +1,245 @@
+//! # 并行分块的 FizzBuzz 生成器
+//!
+//! `fizz_buzz` 对每个数都调用一次 `to_string()`, 吞吐量受限于内存分配.
+//! [`ParallelFizzBuzz`] 把范围切成固定大小的块, 交给若干个工作线程,
+//! 每个线程把结果直接格式化进可复用的字节缓冲区,
+//! 写出线程再按块的顺序把缓冲区写入 `Write`
+//!
+//! 第 `k` 块总是由第 `k % threads` 个线程生成, 写出线程按轮转的方式依次从
+//! 各个线程的有界通道中取块, 因此不需要额外的重排序, 内存占用也是有界的
+
+use std::cmp;
+use std::convert::TryFrom;
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+use std::sync::mpsc;
+use std::thread;
+
+const WORDS: [&[u8]; 15] = [
+    b"fizzbuzz\n",
+    b"",
+    b"",
+    b"fizz\n",
+    b"",
+    b"buzz\n",
+    b"fizz\n",
+    b"",
+    b"",
+    b"fizz\n",
+    b"buzz\n",
+    b"",
+    b"fizz\n",
+    b"",
+    b"",
+];
+
+/// # 格式化一段范围
+///
+/// 把 `range` 中每个数的 FizzBuzz 结果追加到 `buf`, 每项一行, 不做任何堆分配(除了 `buf` 扩容)
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::fizzbuzz::parallel::format_range;
+/// let mut buf = Vec::new();
+/// format_range(9..=16, &mut buf);
+/// assert_eq!(buf, b"fizz\nbuzz\n11\nfizz\n13\n14\nfizzbuzz\n16\n");
+///
+/// buf.clear();
+/// format_range(97..=101, &mut buf);
+/// assert_eq!(buf, b"97\n98\nfizz\nbuzz\n101\n");
+///
+/// buf.clear();
+/// format_range(u64::MAX - 1..=u64::MAX, &mut buf);
+/// assert_eq!(buf, b"18446744073709551614\nfizzbuzz\n");
+/// ```
+pub fn format_range(range: RangeInclusive<u64>, buf: &mut Vec<u8>) {
+    let (start, end) = range.into_inner();
+    if start > end {
+        return;
+    }
+    let mut n = start;
+    let mut decimal = Decimal::new(start);
+    let mut phase = (start % 15) as usize;
+    loop {
+        let word = WORDS[phase];
+        if word.is_empty() {
+            buf.extend_from_slice(decimal.as_bytes());
+            buf.push(b'\n');
+        } else {
+            buf.extend_from_slice(word);
+        }
+        if n == end {
+            break;
+        }
+        n += 1;
+        decimal.increment();
+        phase = if phase == 14 { 0 } else { phase + 1 };
+    }
+}
+
+/// 以 ASCII 形式保存的十进制计数器, 自增时只处理进位, 不做除法
+struct Decimal {
+    digits: [u8; 20],
+    start: usize,
+}
+
+impl Decimal {
+    fn new(mut n: u64) -> Self {
+        let mut digits = [b'0'; 20];
+        let mut start = digits.len();
+        loop {
+            start -= 1;
+            digits[start] = b'0' + (n % 10) as u8;
+            n /= 10;
+            if n == 0 {
+                break;
+            }
+        }
+        Decimal { digits, start }
+    }
+
+    fn increment(&mut self) {
+        let mut i = self.digits.len() - 1;
+        loop {
+            if self.digits[i] != b'9' {
+                self.digits[i] += 1;
+                return;
+            }
+            self.digits[i] = b'0';
+            if i == self.start {
+                self.start -= 1;
+                self.digits[self.start] = b'1';
+                return;
+            }
+            i -= 1;
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.digits[self.start..]
+    }
+}
+
+/// # 并行 FizzBuzz 生成器
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::fizzbuzz::parallel::ParallelFizzBuzz;
+/// use rust_programming_of_zhd::ch02::function::fizz_buzz;
+///
+/// let mut out = Vec::new();
+/// let written = ParallelFizzBuzz::new()
+///     .threads(4)
+///     .chunk_size(7)
+///     .write_to(1..=100, &mut out)
+///     .unwrap();
+/// assert_eq!(written, out.len() as u64);
+///
+/// let expected: String = (1..=100).map(|n| fizz_buzz(n) + "\n").collect();
+/// assert_eq!(String::from_utf8(out).unwrap(), expected);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParallelFizzBuzz {
+    threads: usize,
+    chunk_size: u64,
+}
+
+impl ParallelFizzBuzz {
+    /// 默认使用全部可用的 CPU, 每块 64K 个数
+    pub fn new() -> Self {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        ParallelFizzBuzz {
+            threads,
+            chunk_size: 1 << 16,
+        }
+    }
+
+    /// 工作线程数, 至少为 1
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = cmp::max(threads, 1);
+        self
+    }
+
+    /// 每块包含的数的个数, 至少为 1
+    pub fn chunk_size(mut self, chunk_size: u64) -> Self {
+        self.chunk_size = cmp::max(chunk_size, 1);
+        self
+    }
+
+    /// 把 `range` 的 FizzBuzz 结果按顺序写入 `out`, 返回写入的字节数
+    pub fn write_to<W: Write>(&self, range: RangeInclusive<u64>, out: &mut W) -> io::Result<u64> {
+        let (start, end) = range.into_inner();
+        if start > end {
+            return Ok(0);
+        }
+        let chunk_size = self.chunk_size;
+        // 最后一块的序号. 块数 `last + 1` 在整个 u64 范围且每块一个数时会溢出, 所以只保存序号
+        let last = (end - start) / chunk_size;
+        let chunk_range = move |k: u64| {
+            let lo = start + k * chunk_size;
+            let hi = if end - lo < chunk_size - 1 {
+                end
+            } else {
+                lo + (chunk_size - 1)
+            };
+            lo..=hi
+        };
+        // 预估每个数平均 10 个字节, 避免缓冲区反复扩容
+        let capacity = usize::try_from(chunk_size.saturating_mul(10)).unwrap_or(usize::MAX);
+        let threads = cmp::min(self.threads as u64, last.saturating_add(1)) as usize;
+
+        if threads == 1 {
+            let mut buf = Vec::with_capacity(capacity);
+            let mut written = 0;
+            for k in 0..=last {
+                buf.clear();
+                format_range(chunk_range(k), &mut buf);
+                out.write_all(&buf)?;
+                written += buf.len() as u64;
+            }
+            return Ok(written);
+        }
+
+        thread::scope(|scope| {
+            let mut filled = Vec::with_capacity(threads);
+            let mut recycle = Vec::with_capacity(threads);
+            for worker in 0..threads {
+                let (filled_tx, filled_rx) = mpsc::sync_channel::<Vec<u8>>(2);
+                let (recycle_tx, recycle_rx) = mpsc::channel::<Vec<u8>>();
+                scope.spawn(move || {
+                    for k in (worker as u64..=last).step_by(threads) {
+                        let mut buf = recycle_rx
+                            .try_recv()
+                            .unwrap_or_else(|_| Vec::with_capacity(capacity));
+                        buf.clear();
+                        format_range(chunk_range(k), &mut buf);
+                        // 写出线程出错提前退出时, 通道已关闭, 工作线程随之结束
+                        if filled_tx.send(buf).is_err() {
+                            return;
+                        }
+                    }
+                });
+                filled.push(filled_rx);
+                recycle.push(recycle_tx);
+            }
+
+            let mut written = 0;
+            for k in 0..=last {
+                let worker = (k % threads as u64) as usize;
+                let buf = filled[worker].recv().expect("FizzBuzz worker panicked");
+                out.write_all(&buf)?;
+                written += buf.len() as u64;
+                let _ = recycle[worker].send(buf);
+            }
+            Ok(written)
+        })
+    }
+}
+
+impl Default for ParallelFizzBuzz {
+    fn default() -> Self {
+        ParallelFizzBuzz::new()
+    }
+}
@@ -0,0 +1,263 @@
+//! # FizzBuzz 输出格式
+//!
+//! `control_flow` 中的循环只能输出按行分隔的文本,
+//! 这里把"如何输出"抽象成 [`Renderer`], 提供纯文本、CSV、JSON、JSON Lines 和 Markdown 表格五种实现
+
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use super::token::{FizzBuzzToken, Integer};
+
+/// # 渲染器
+///
+/// 依次调用 `header`、每一项的 `row` 和 `footer`
+pub trait Renderer {
+    fn header(&self, _out: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// 输出第 `index` 项(从 0 开始), `n` 是原始的数, `token` 是 FizzBuzz 结果
+    fn row(
+        &self,
+        out: &mut dyn Write,
+        index: usize,
+        n: &dyn Display,
+        token: &dyn Display,
+    ) -> io::Result<()>;
+
+    /// `count` 为已输出的项数
+    fn footer(&self, _out: &mut dyn Write, _count: usize) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 每行一项, 与 `for_fizzbuzz` 的输出相同
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Plain;
+
+/// `n,token` 两列, 带表头
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Csv;
+
+/// 一个 JSON 数组, 每项为 `{"n": 1, "token": "1"}`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json;
+
+/// 每行一个 JSON 对象
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonLines;
+
+/// Markdown 表格
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Markdown;
+
+impl Renderer for Plain {
+    fn row(
+        &self,
+        out: &mut dyn Write,
+        _: usize,
+        _: &dyn Display,
+        token: &dyn Display,
+    ) -> io::Result<()> {
+        writeln!(out, "{}", token)
+    }
+}
+
+impl Renderer for Csv {
+    fn header(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "n,token")
+    }
+
+    fn row(
+        &self,
+        out: &mut dyn Write,
+        _: usize,
+        n: &dyn Display,
+        token: &dyn Display,
+    ) -> io::Result<()> {
+        writeln!(out, "{},{}", n, token)
+    }
+}
+
+impl Renderer for Json {
+    fn header(&self, out: &mut dyn Write) -> io::Result<()> {
+        write!(out, "[")
+    }
+
+    fn row(
+        &self,
+        out: &mut dyn Write,
+        index: usize,
+        n: &dyn Display,
+        token: &dyn Display,
+    ) -> io::Result<()> {
+        let separator = if index == 0 { "" } else { "," };
+        write!(
+            out,
+            "{}\n  {{\"n\": {}, \"token\": \"{}\"}}",
+            separator, n, token
+        )
+    }
+
+    fn footer(&self, out: &mut dyn Write, count: usize) -> io::Result<()> {
+        if count == 0 {
+            writeln!(out, "]")
+        } else {
+            writeln!(out, "\n]")
+        }
+    }
+}
+
+impl Renderer for JsonLines {
+    fn row(
+        &self,
+        out: &mut dyn Write,
+        _: usize,
+        n: &dyn Display,
+        token: &dyn Display,
+    ) -> io::Result<()> {
+        writeln!(out, "{{\"n\":{},\"token\":\"{}\"}}", n, token)
+    }
+}
+
+impl Renderer for Markdown {
+    fn header(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "| n | token |")?;
+        writeln!(out, "|--:|:------|")
+    }
+
+    fn row(
+        &self,
+        out: &mut dyn Write,
+        _: usize,
+        n: &dyn Display,
+        token: &dyn Display,
+    ) -> io::Result<()> {
+        writeln!(out, "| {} | {} |", n, token)
+    }
+}
+
+/// # 输出格式
+///
+/// 可以从命令行参数解析, 例如 `--format csv`
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::fizzbuzz::render::{render, Format};
+///
+/// let mut out = Vec::new();
+/// render(13..=15, "csv".parse::<Format>().unwrap().renderer(), &mut out).unwrap();
+/// assert_eq!(out, b"n,token\n13,13\n14,14\n15,fizzbuzz\n");
+///
+/// let mut out = Vec::new();
+/// render(1..=3, Format::Json.renderer(), &mut out).unwrap();
+/// assert_eq!(
+///     String::from_utf8(out).unwrap(),
+///     "[\n  {\"n\": 1, \"token\": \"1\"},\n  {\"n\": 2, \"token\": \"2\"},\n  {\"n\": 3, \"token\": \"fizz\"}\n]\n"
+/// );
+///
+/// let mut out = Vec::new();
+/// render(4..=5, Format::JsonLines.renderer(), &mut out).unwrap();
+/// assert_eq!(out, b"{\"n\":4,\"token\":\"4\"}\n{\"n\":5,\"token\":\"buzz\"}\n");
+///
+/// let mut out = Vec::new();
+/// render(5..=6, Format::Markdown.renderer(), &mut out).unwrap();
+/// assert_eq!(out, b"| n | token |\n|--:|:------|\n| 5 | buzz |\n| 6 | fizz |\n");
+///
+/// assert!("yaml".parse::<Format>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Plain,
+    Csv,
+    Json,
+    JsonLines,
+    Markdown,
+}
+
+impl Format {
+    pub const ALL: [Format; 5] = [
+        Format::Plain,
+        Format::Csv,
+        Format::Json,
+        Format::JsonLines,
+        Format::Markdown,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Plain => "plain",
+            Format::Csv => "csv",
+            Format::Json => "json",
+            Format::JsonLines => "jsonl",
+            Format::Markdown => "markdown",
+        }
+    }
+
+    pub fn renderer(self) -> &'static dyn Renderer {
+        match self {
+            Format::Plain => &Plain,
+            Format::Csv => &Csv,
+            Format::Json => &Json,
+            Format::JsonLines => &JsonLines,
+            Format::Markdown => &Markdown,
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseFormatError(String);
+
+impl fmt::Display for ParseFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&str> = Format::ALL.iter().map(|f| f.name()).collect();
+        write!(
+            f,
+            "unknown format `{}`, expected one of: {}",
+            self.0,
+            names.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for ParseFormatError {}
+
+impl FromStr for Format {
+    type Err = ParseFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" | "text" => Ok(Format::Plain),
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            "jsonl" | "jsonlines" | "ndjson" => Ok(Format::JsonLines),
+            "markdown" | "md" => Ok(Format::Markdown),
+            _ => Err(ParseFormatError(s.to_string())),
+        }
+    }
+}
+
+/// 用 `renderer` 输出 `numbers` 的 FizzBuzz 序列, 返回输出的项数
+pub fn render<T, I, W>(numbers: I, renderer: &dyn Renderer, out: &mut W) -> io::Result<usize>
+where
+    T: Integer,
+    I: IntoIterator<Item = T>,
+    W: Write,
+{
+    renderer.header(out)?;
+    let mut count = 0;
+    for n in numbers {
+        renderer.row(out, count, &n, &FizzBuzzToken::of(n))?;
+        count += 1;
+    }
+    renderer.footer(out, count)?;
+    Ok(count)
+}
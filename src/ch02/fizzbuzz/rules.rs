@@ -0,0 +1,202 @@
+//! # 可配置的 FizzBuzz 规则引擎
+//!
+//! 书中的 `fizz_buzz` 把 3/5/15 三条规则写死在了代码里,
+//! [`FizzBuzz`] 则允许调用者登记任意的规则, 经典预设 [`FizzBuzz::classic`] 与 `fizz_buzz` 的输出完全一致:
+//!
+//! - 除数规则: `n` 能被某个数整除时输出对应的单词
+//! - 谓词规则: 任意 `Fn(i64) -> bool` 为真时输出对应的单词, 例如"包含数字 7"
+//!
+//! 规则按登记的顺序依次检查, 所有命中的单词用分隔符连接起来(默认不分隔);
+//! 也可以切换为只取第一个命中的规则. 没有任何规则命中时, 交给兜底的格式化函数
+
+use std::fmt;
+
+enum Matcher {
+    Divisor(i64),
+    Predicate(Box<dyn Fn(i64) -> bool>),
+}
+
+impl Matcher {
+    fn matches(&self, n: i64) -> bool {
+        match self {
+            // `i64::MIN % -1` 会溢出, 回绕取余给出数学上正确的 0
+            Matcher::Divisor(d) => n.wrapping_rem(*d) == 0,
+            Matcher::Predicate(p) => p(n),
+        }
+    }
+}
+
+struct Rule {
+    word: String,
+    matcher: Matcher,
+}
+
+/// 多条规则同时命中时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// 按登记顺序把所有命中的单词连接起来, 例如 15 => "fizzbuzz"
+    Concat,
+    /// 只取第一条命中的规则, 后登记的规则优先级更低
+    FirstMatch,
+}
+
+/// # FizzBuzz 规则引擎
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::fizzbuzz::FizzBuzz;
+/// use rust_programming_of_zhd::ch02::function::fizz_buzz;
+///
+/// // 经典规则与 fizz_buzz 完全一致
+/// let classic = FizzBuzz::classic();
+/// for n in -100..=100 {
+///     assert_eq!(classic.apply(n as i64), fizz_buzz(n));
+/// }
+///
+/// // Fizz-Buzz-Woof: 用 "-" 连接所有命中的单词
+/// let woof = FizzBuzz::new()
+///     .divisor(3, "Fizz")
+///     .divisor(5, "Buzz")
+///     .divisor(7, "Woof")
+///     .separator("-");
+/// assert_eq!(woof.apply(105), "Fizz-Buzz-Woof");
+/// assert_eq!(woof.apply(21), "Fizz-Woof");
+/// assert_eq!(woof.apply(11), "11");
+///
+/// // Bazz: 包含数字 7 的数输出 Bazz, 且优先于其他规则
+/// let bazz = FizzBuzz::new()
+///     .predicate("Bazz", |n| n.to_string().contains('7'))
+///     .divisor(15, "FizzBuzz")
+///     .divisor(3, "Fizz")
+///     .divisor(5, "Buzz")
+///     .first_match();
+/// assert_eq!(bazz.apply(27), "Bazz");
+/// assert_eq!(bazz.apply(30), "FizzBuzz");
+/// assert_eq!(bazz.apply(9), "Fizz");
+///
+/// // 兜底格式化: 没有规则命中时的输出
+/// let padded = FizzBuzz::classic().fallback(|n| format!("{:03}", n));
+/// assert_eq!(padded.apply(7), "007");
+/// ```
+pub struct FizzBuzz {
+    rules: Vec<Rule>,
+    mode: Mode,
+    separator: String,
+    fallback: Box<dyn Fn(i64) -> String>,
+}
+
+impl FizzBuzz {
+    /// 没有任何规则的引擎, 所有数都原样输出
+    pub fn new() -> Self {
+        FizzBuzz {
+            rules: Vec::new(),
+            mode: Mode::Concat,
+            separator: String::new(),
+            fallback: Box::new(|n| n.to_string()),
+        }
+    }
+
+    /// 经典的 FizzBuzz: 3 => fizz, 5 => buzz, 15 => fizzbuzz
+    pub fn classic() -> Self {
+        FizzBuzz::new().divisor(3, "fizz").divisor(5, "buzz")
+    }
+
+    /// 登记除数规则
+    ///
+    /// # Panics
+    ///
+    /// `divisor` 为 0 时 panic
+    pub fn divisor(mut self, divisor: i64, word: &str) -> Self {
+        assert!(divisor != 0, "FizzBuzz divisor must not be zero");
+        self.rules.push(Rule {
+            word: word.to_string(),
+            matcher: Matcher::Divisor(divisor),
+        });
+        self
+    }
+
+    /// 登记谓词规则
+    pub fn predicate<F>(mut self, word: &str, predicate: F) -> Self
+    where
+        F: Fn(i64) -> bool + 'static,
+    {
+        self.rules.push(Rule {
+            word: word.to_string(),
+            matcher: Matcher::Predicate(Box::new(predicate)),
+        });
+        self
+    }
+
+    /// 多条规则命中时, 单词之间的分隔符
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_string();
+        self
+    }
+
+    /// 只取第一条命中的规则
+    pub fn first_match(mut self) -> Self {
+        self.mode = Mode::FirstMatch;
+        self
+    }
+
+    /// 没有规则命中时使用的格式化函数, 默认为 `n.to_string()`
+    pub fn fallback<F>(mut self, fallback: F) -> Self
+    where
+        F: Fn(i64) -> String + 'static,
+    {
+        self.fallback = Box::new(fallback);
+        self
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// 计算 `n` 对应的输出
+    pub fn apply(&self, n: i64) -> String {
+        let mut words = self
+            .rules
+            .iter()
+            .filter(|rule| rule.matcher.matches(n))
+            .map(|rule| rule.word.as_str());
+        match self.mode {
+            Mode::FirstMatch => match words.next() {
+                Some(word) => word.to_string(),
+                None => (self.fallback)(n),
+            },
+            Mode::Concat => {
+                let words: Vec<&str> = words.collect();
+                if words.is_empty() {
+                    (self.fallback)(n)
+                } else {
+                    words.join(&self.separator)
+                }
+            }
+        }
+    }
+}
+
+impl Default for FizzBuzz {
+    fn default() -> Self {
+        FizzBuzz::new()
+    }
+}
+
+impl fmt::Debug for FizzBuzz {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rules: Vec<String> = self
+            .rules
+            .iter()
+            .map(|rule| match rule.matcher {
+                Matcher::Divisor(d) => format!("{} => {:?}", d, rule.word),
+                Matcher::Predicate(_) => format!("<predicate> => {:?}", rule.word),
+            })
+            .collect();
+        f.debug_struct("FizzBuzz")
+            .field("rules", &rules)
+            .field("mode", &self.mode)
+            .field("separator", &self.separator)
+            .finish()
+    }
+}
@@ -0,0 +1,196 @@
+//! # 循环策略
+//!
+//! `while_fizzbuzz`、`loop_fizzbuzz` 和 `for_fizzbuzz` 三个清单重复了同一段分支判断,
+//! 区别只在于循环的写法. [`LoopStrategy`] 把"怎么循环"和"循环体做什么"分开:
+//! 各个策略只负责按顺序把范围内的每个数交给同一个循环体
+//!
+//! 除了清单里的三种写法, 还提供了递归和迭代器链两种写法, 语义完全一致
+
+use std::io::{self, Write};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// 循环体, 返回 `Err` 时循环提前结束
+pub type Body<'a> = dyn FnMut(i32) -> io::Result<()> + 'a;
+
+/// # 循环策略
+///
+/// 按升序对 `range` 中的每个数调用一次 `body`, `body` 出错时立即返回该错误
+pub trait LoopStrategy {
+    fn name(&self) -> &'static str;
+
+    fn drive(&self, range: Range<i32>, body: &mut Body) -> io::Result<()>;
+}
+
+/// `while n < end { ...; n += 1; }`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct While;
+
+/// `loop { if n >= end { break; } ...; n += 1; }`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Loop;
+
+/// `for n in range { ... }`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct For;
+
+/// 把范围一分为二分别递归, 递归深度只有 O(log n), 不会栈溢出
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Recursive;
+
+/// `range.map(..).try_for_each(..)`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IteratorChain;
+
+impl LoopStrategy for While {
+    fn name(&self) -> &'static str {
+        "while"
+    }
+
+    fn drive(&self, range: Range<i32>, body: &mut Body) -> io::Result<()> {
+        let mut n = range.start;
+        while n < range.end {
+            body(n)?;
+            n += 1;
+        }
+        Ok(())
+    }
+}
+
+impl LoopStrategy for Loop {
+    fn name(&self) -> &'static str {
+        "loop"
+    }
+
+    fn drive(&self, range: Range<i32>, body: &mut Body) -> io::Result<()> {
+        let mut n = range.start;
+        loop {
+            if n >= range.end {
+                break;
+            }
+            body(n)?;
+            n += 1;
+        }
+        Ok(())
+    }
+}
+
+impl LoopStrategy for For {
+    fn name(&self) -> &'static str {
+        "for"
+    }
+
+    fn drive(&self, range: Range<i32>, body: &mut Body) -> io::Result<()> {
+        for n in range {
+            body(n)?;
+        }
+        Ok(())
+    }
+}
+
+impl LoopStrategy for Recursive {
+    fn name(&self) -> &'static str {
+        "recursive"
+    }
+
+    fn drive(&self, range: Range<i32>, body: &mut Body) -> io::Result<()> {
+        let len = i64::from(range.end) - i64::from(range.start);
+        match len {
+            len if len <= 0 => Ok(()),
+            1 => body(range.start),
+            len => {
+                let mid = range.start + (len / 2) as i32;
+                self.drive(range.start..mid, body)?;
+                self.drive(mid..range.end, body)
+            }
+        }
+    }
+}
+
+impl LoopStrategy for IteratorChain {
+    fn name(&self) -> &'static str {
+        "iterator"
+    }
+
+    fn drive(&self, range: Range<i32>, body: &mut Body) -> io::Result<()> {
+        range.into_iter().try_for_each(body)
+    }
+}
+
+/// 全部五种策略
+pub fn strategies() -> [&'static dyn LoopStrategy; 5] {
+    [&While, &Loop, &For, &Recursive, &IteratorChain]
+}
+
+/// 三个 FizzBuzz 清单共用的循环体
+pub fn fizzbuzz_line<W: Write>(out: &mut W, n: i32) -> io::Result<()> {
+    if n % 15 == 0 {
+        writeln!(out, "fizzbuzz")
+    } else if n % 3 == 0 {
+        writeln!(out, "fizz")
+    } else if n % 5 == 0 {
+        writeln!(out, "buzz")
+    } else {
+        writeln!(out, "{}", n)
+    }
+}
+
+/// # 用指定的策略运行 FizzBuzz
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::control_flow::for_fizzbuzz_to;
+/// use rust_programming_of_zhd::ch02::fizzbuzz::strategy::{run, strategies};
+///
+/// let mut listing = Vec::new();
+/// for_fizzbuzz_to(&mut listing).unwrap();
+/// for strategy in strategies().iter() {
+///     let mut out = Vec::new();
+///     run(*strategy, 1..101, &mut out).unwrap();
+///     assert_eq!(out, listing, "{} differs", strategy.name());
+/// }
+/// ```
+pub fn run<W: Write>(
+    strategy: &dyn LoopStrategy,
+    range: Range<i32>,
+    out: &mut W,
+) -> io::Result<()> {
+    strategy.drive(range, &mut |n| fizzbuzz_line(out, n))
+}
+
+/// 一次计时的结果
+#[derive(Debug, Clone)]
+pub struct Timing {
+    pub name: &'static str,
+    pub elapsed: Duration,
+    pub output: Vec<u8>,
+}
+
+/// # 比较所有策略
+///
+/// 依次用每种策略运行一遍, 记录耗时与输出
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::fizzbuzz::strategy::compare;
+/// let timings = compare(-50..50);
+/// assert_eq!(timings.len(), 5);
+/// assert!(timings.iter().all(|t| t.output == timings[0].output));
+/// ```
+pub fn compare(range: Range<i32>) -> Vec<Timing> {
+    strategies()
+        .iter()
+        .map(|strategy| {
+            let mut output = Vec::new();
+            let start = Instant::now();
+            run(*strategy, range.clone(), &mut output).expect("writing to Vec never fails");
+            Timing {
+                name: strategy.name(),
+                elapsed: start.elapsed(),
+                output,
+            }
+        })
+        .collect()
+}
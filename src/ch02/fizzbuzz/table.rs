@@ -0,0 +1,185 @@
+//! # 编译期 FizzBuzz 表
+//!
+//! [`ctfe`](crate::ch02::ctfe) 中的周期表只保存单词, 这里更进一步:
+//! [`table`] 在编译期生成任意长度的 [`FizzBuzzToken`] 表,
+//! [`FIZZBUZZ_100`] 则把 `1..=100` 的输出整个渲染成字节, 打印时只需一次 `write_all`,
+//! 运行时不做任何取模或格式化
+
+use std::io::{self, Write};
+
+use super::FizzBuzzToken;
+use crate::ch02::ctfe::{fizzbuzz_word, FIZZBUZZ_PERIOD};
+
+/// # 编译期生成 FizzBuzz 记号表
+///
+/// 第 `i` 项是 `i + 1` 对应的记号. [`FizzBuzzToken::of`] 要经过 [`Integer`](super::Integer) trait,
+/// 不能在 `const fn` 中调用, 所以这里只对 `u64` 取一次模, 按周期内的位置给出记号
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::fizzbuzz::table::table;
+/// use rust_programming_of_zhd::ch02::fizzbuzz::FizzBuzzToken;
+///
+/// const FIRST: [FizzBuzzToken<u64>; 5] = table();
+/// assert_eq!(
+///     FIRST,
+///     [
+///         FizzBuzzToken::Number(1),
+///         FizzBuzzToken::Number(2),
+///         FizzBuzzToken::Fizz,
+///         FizzBuzzToken::Number(4),
+///         FizzBuzzToken::Buzz,
+///     ]
+/// );
+/// assert_eq!(table::<0>(), []);
+/// ```
+pub const fn table<const N: usize>() -> [FizzBuzzToken<u64>; N] {
+    let mut table = [FizzBuzzToken::FizzBuzz; N];
+    let mut i = 0;
+    while i < N {
+        let n = i as u64 + 1;
+        table[i] = match n % FIZZBUZZ_PERIOD as u64 {
+            0 => FizzBuzzToken::FizzBuzz,
+            3 | 6 | 9 | 12 => FizzBuzzToken::Fizz,
+            5 | 10 => FizzBuzzToken::Buzz,
+            _ => FizzBuzzToken::Number(n),
+        };
+        i += 1;
+    }
+    table
+}
+
+/// `1..=100` 的记号表
+pub const TOKENS_100: [FizzBuzzToken<u64>; 100] = table();
+
+/// 十进制位数
+const fn digits(mut n: u64) -> usize {
+    let mut len = 1;
+    while n >= 10 {
+        n /= 10;
+        len += 1;
+    }
+    len
+}
+
+/// # 渲染 `1..=n` 所需的字节数
+///
+/// 每一项之后跟一个换行符, 与 `control_flow` 中的 `writeln!` 输出一致
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::fizzbuzz::table::rendered_len;
+///
+/// assert_eq!(rendered_len(0), 0);
+/// // "1\n2\nfizz\n4\nbuzz\n"
+/// assert_eq!(rendered_len(5), 16);
+/// ```
+pub const fn rendered_len(n: u64) -> usize {
+    let mut len = 0;
+    let mut i = 1;
+    while i <= n {
+        len += match fizzbuzz_word(i) {
+            Some(word) => word.len(),
+            None => digits(i),
+        } + 1;
+        i += 1;
+    }
+    len
+}
+
+/// # 编译期渲染 `1..=n` 的输出
+///
+/// `LEN` 必须等于 [`rendered_len(n)`](rendered_len), 否则在常量上下文中求值失败(E0080)
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::fizzbuzz::table::{render, rendered_len};
+///
+/// const FIFTEEN: [u8; rendered_len(15)] = render(15);
+/// assert!(FIFTEEN.starts_with(b"1\n2\nfizz\n4\nbuzz\n"));
+/// assert!(FIFTEEN.ends_with(b"14\nfizzbuzz\n"));
+/// ```
+///
+/// ```compile_fail,E0080
+/// use rust_programming_of_zhd::ch02::fizzbuzz::table::render;
+///
+/// const SHORT: [u8; 4] = render(15);
+/// ```
+pub const fn render<const LEN: usize>(n: u64) -> [u8; LEN] {
+    let mut buf = [0; LEN];
+    let mut pos = 0;
+    let mut i = 1;
+    while i <= n {
+        if let Some(word) = fizzbuzz_word(i) {
+            let word = word.as_bytes();
+            let mut k = 0;
+            while k < word.len() {
+                buf[pos] = word[k];
+                pos += 1;
+                k += 1;
+            }
+        } else {
+            // 从最低位开始, 倒着写入数字
+            let len = digits(i);
+            let mut rest = i;
+            let mut k = len;
+            while k > 0 {
+                k -= 1;
+                buf[pos + k] = b'0' + (rest % 10) as u8;
+                rest /= 10;
+            }
+            pos += len;
+        }
+        buf[pos] = b'\n';
+        pos += 1;
+        i += 1;
+    }
+    assert!(pos == LEN, "LEN must equal rendered_len(n)");
+    buf
+}
+
+/// [`FIZZBUZZ_100`] 的长度
+pub const FIZZBUZZ_100_LEN: usize = rendered_len(100);
+
+const RENDERED_100: [u8; FIZZBUZZ_100_LEN] = render(100);
+
+/// # 预先渲染好的 `1..=100` FizzBuzz 输出
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::control_flow::while_fizzbuzz_to;
+/// use rust_programming_of_zhd::ch02::fizzbuzz::table::FIZZBUZZ_100;
+///
+/// let mut expected = Vec::new();
+/// while_fizzbuzz_to(&mut expected).unwrap();
+/// assert_eq!(&FIZZBUZZ_100[..], &expected[..]);
+/// ```
+pub static FIZZBUZZ_100: [u8; FIZZBUZZ_100_LEN] = RENDERED_100;
+
+/// [`FIZZBUZZ_100`] 的文本形式, UTF-8 校验同样在编译期完成
+pub const FIZZBUZZ_100_TEXT: &str = match std::str::from_utf8(&RENDERED_100) {
+    Ok(text) => text,
+    Err(_) => panic!("rendered FizzBuzz is not UTF-8"),
+};
+
+/// # 输出预先渲染好的 `1..=100`
+///
+/// 与 `control_flow` 中的 `while_fizzbuzz_to` 等函数输出相同, 但只调用一次 `write_all`
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::fizzbuzz::table::{write_fizzbuzz_100, FIZZBUZZ_100_TEXT};
+///
+/// let mut out = Vec::new();
+/// write_fizzbuzz_100(&mut out).unwrap();
+/// assert_eq!(String::from_utf8(out).unwrap(), FIZZBUZZ_100_TEXT);
+/// assert_eq!(FIZZBUZZ_100_TEXT.lines().nth(14), Some("fizzbuzz"));
+/// ```
+pub fn write_fizzbuzz_100<W: Write>(out: &mut W) -> io::Result<()> {
+    out.write_all(&FIZZBUZZ_100)
+}
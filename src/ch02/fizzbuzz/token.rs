@@ -0,0 +1,123 @@
+//! # FizzBuzz 记号
+//!
+//! 经典 FizzBuzz 的每一项要么是 `fizz`、`buzz`、`fizzbuzz` 三个单词之一,
+//! 要么是数字本身. [`FizzBuzzToken`] 只保存这四种情况, 不分配 `String`,
+//! 需要文本时通过 `Display` 格式化即可
+
+use std::fmt;
+
+/// # FizzBuzz 记号
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::fizzbuzz::FizzBuzzToken;
+/// assert_eq!(FizzBuzzToken::of(15u8), FizzBuzzToken::FizzBuzz);
+/// assert_eq!(FizzBuzzToken::of(-9i64), FizzBuzzToken::Fizz);
+/// assert_eq!(FizzBuzzToken::of(10u128), FizzBuzzToken::Buzz);
+/// assert_eq!(FizzBuzzToken::of(7i32), FizzBuzzToken::Number(7));
+/// assert_eq!(FizzBuzzToken::of(7i32).to_string(), "7");
+/// assert_eq!(FizzBuzzToken::<i32>::Fizz.as_str(), Some("fizz"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FizzBuzzToken<T> {
+    Number(T),
+    Fizz,
+    Buzz,
+    FizzBuzz,
+}
+
+impl<T: Integer> FizzBuzzToken<T> {
+    /// 按经典规则对 `n` 分类
+    pub fn of(n: T) -> Self {
+        match (n.is_multiple_of_3(), n.is_multiple_of_5()) {
+            (true, true) => FizzBuzzToken::FizzBuzz,
+            (true, false) => FizzBuzzToken::Fizz,
+            (false, true) => FizzBuzzToken::Buzz,
+            (false, false) => FizzBuzzToken::Number(n),
+        }
+    }
+}
+
+impl<T> FizzBuzzToken<T> {
+    /// 单词记号对应的静态字符串, 数字记号返回 `None`
+    pub fn as_str(&self) -> Option<&'static str> {
+        match self {
+            FizzBuzzToken::Number(_) => None,
+            FizzBuzzToken::Fizz => Some("fizz"),
+            FizzBuzzToken::Buzz => Some("buzz"),
+            FizzBuzzToken::FizzBuzz => Some("fizzbuzz"),
+        }
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self, FizzBuzzToken::Number(_))
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for FizzBuzzToken<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FizzBuzzToken::Number(n) => n.fmt(f),
+            FizzBuzzToken::Fizz => f.pad("fizz"),
+            FizzBuzzToken::Buzz => f.pad("buzz"),
+            FizzBuzzToken::FizzBuzz => f.pad("fizzbuzz"),
+        }
+    }
+}
+
+/// # 原生整数
+///
+/// FizzBuzz 需要的整数操作. 为所有原生整数类型实现, 包括 `i128`/`u128`
+///
+/// `span`/`add`/`sub` 都以同宽度的无符号数做回绕运算,
+/// 因此对有符号数也能正确计算任意两个值之间的距离
+pub trait Integer: Copy + Ord + fmt::Display + fmt::Debug {
+    const MIN: Self;
+    const MAX: Self;
+
+    fn is_multiple_of_3(self) -> bool;
+    fn is_multiple_of_5(self) -> bool;
+    /// `hi - lo`, 要求 `lo <= hi`
+    fn span(lo: Self, hi: Self) -> u128;
+    /// `self + k`, 调用者保证不越界
+    fn add(self, k: u128) -> Self;
+    /// `self - k`, 调用者保证不越界
+    fn sub(self, k: u128) -> Self;
+}
+
+macro_rules! impl_integer {
+    ($($t:ty => $u:ty),*) => {
+        $(
+            impl Integer for $t {
+                const MIN: Self = <$t>::MIN;
+                const MAX: Self = <$t>::MAX;
+
+                fn is_multiple_of_3(self) -> bool {
+                    self % 3 == 0
+                }
+
+                fn is_multiple_of_5(self) -> bool {
+                    self % 5 == 0
+                }
+
+                fn span(lo: Self, hi: Self) -> u128 {
+                    (hi as $u).wrapping_sub(lo as $u) as u128
+                }
+
+                fn add(self, k: u128) -> Self {
+                    (self as $u).wrapping_add(k as $u) as $t
+                }
+
+                fn sub(self, k: u128) -> Self {
+                    (self as $u).wrapping_sub(k as $u) as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_integer! {
+    i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize,
+    u8 => u8, u16 => u16, u32 => u32, u64 => u64, u128 => u128, usize => usize
+}
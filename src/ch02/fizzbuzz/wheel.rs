@@ -0,0 +1,150 @@
+//! # 不用取模运算的 FizzBuzz
+//!
+//! `fizz_buzz` 对每个数都要计算 `% 15`、`% 3`、`% 5`.
+//! 换一个角度看, "能被 3 整除"其实是一个周期为 3 的循环: 每数三个数命中一次.
+//! [`CyclicRule`] 为每条规则维护一个滚动计数器, 每前进一个数, 计数器加一,
+//! 到达周期时归零并命中. 只有在构造时需要一次性地算出起点所处的相位
+//!
+//! 这一技巧不局限于 3 和 5, 任意一组周期性的规则都可以使用
+
+use std::borrow::Cow;
+
+/// # 循环规则
+///
+/// 周期为 `period` 的规则, 命中时输出 `word`. 字段是私有的, 只能通过 [`CyclicRule::new`] 构造,
+/// 因此周期不可能为 0
+///
+/// ```compile_fail,E0451
+/// use rust_programming_of_zhd::ch02::fizzbuzz::wheel::CyclicRule;
+///
+/// let never = CyclicRule { period: 0, word: "never" };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CyclicRule {
+    period: u32,
+    word: &'static str,
+}
+
+impl CyclicRule {
+    /// # Panics
+    ///
+    /// `period` 为 0 时 panic
+    pub const fn new(period: u32, word: &'static str) -> Self {
+        assert!(period != 0, "period must not be zero");
+        CyclicRule { period, word }
+    }
+
+    /// 周期, 总是大于 0
+    pub const fn period(&self) -> u32 {
+        self.period
+    }
+
+    /// 命中时输出的单词
+    pub const fn word(&self) -> &'static str {
+        self.word
+    }
+}
+
+/// 经典的 FizzBuzz 规则
+pub const CLASSIC: [CyclicRule; 2] = [CyclicRule::new(3, "fizz"), CyclicRule::new(5, "buzz")];
+
+/// # 滚动计数器
+///
+/// 从 `start` 开始依次产出每个数的结果, 命中多条规则时按顺序连接单词
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::fizzbuzz::wheel::{CyclicRule, Wheel};
+/// use rust_programming_of_zhd::ch02::function::fizz_buzz;
+///
+/// let classic: Vec<String> = Wheel::classic(-20).take(200).map(|w| w.into_owned()).collect();
+/// let expected: Vec<String> = (-20..180).map(fizz_buzz).collect();
+/// assert_eq!(classic, expected);
+///
+/// // 同样的技巧用于其他除数
+/// let rules = [CyclicRule::new(2, "Even"), CyclicRule::new(7, "Woof")];
+/// let words: Vec<_> = Wheel::new(&rules, 12).take(3).collect();
+/// assert_eq!(words, ["Even", "13", "EvenWoof"]);
+/// assert_eq!(rules[1].period(), 7);
+/// assert_eq!(rules[1].word(), "Woof");
+/// ```
+///
+/// 规则数 `N` 是常量泛型参数, 计数器保存在栈上的数组里, 编译器可以把循环完全展开
+#[derive(Debug, Clone)]
+pub struct Wheel<'a, const N: usize> {
+    rules: &'a [CyclicRule; N],
+    /// 每条规则的计数器, 为 0 表示当前的数命中该规则
+    counters: [u32; N],
+    next: Option<i64>,
+}
+
+impl Wheel<'static, 2> {
+    /// 使用经典规则, 输出与 `fizz_buzz` 相同
+    pub fn classic(start: i64) -> Self {
+        Wheel::new(&CLASSIC, start)
+    }
+}
+
+impl<'a, const N: usize> Wheel<'a, N> {
+    /// # Panics
+    ///
+    /// 规则超过 64 条时 panic
+    pub fn new(rules: &'a [CyclicRule; N], start: i64) -> Self {
+        assert!(N <= 64, "at most 64 cyclic rules are supported");
+        // 仅在构造时计算一次相位
+        let mut counters = [0; N];
+        for (counter, rule) in counters.iter_mut().zip(rules) {
+            *counter = start.rem_euclid(i64::from(rule.period)) as u32;
+        }
+        Wheel {
+            rules,
+            counters,
+            next: Some(start),
+        }
+    }
+
+    /// 前进一个数, 返回该数命中的规则(第 `i` 位表示第 `i` 条规则), 不做任何分配
+    ///
+    /// ```
+    /// use rust_programming_of_zhd::ch02::fizzbuzz::wheel::Wheel;
+    /// let mut wheel = Wheel::classic(14);
+    /// assert_eq!(wheel.next_mask(), Some(0b00)); // 14
+    /// assert_eq!(wheel.next_mask(), Some(0b11)); // 15: fizz + buzz
+    /// assert_eq!(wheel.next_mask(), Some(0b00)); // 16
+    /// ```
+    pub fn next_mask(&mut self) -> Option<u64> {
+        let n = self.next?;
+        self.next = n.checked_add(1);
+        let mut mask = 0;
+        for (i, (rule, counter)) in self.rules.iter().zip(self.counters.iter_mut()).enumerate() {
+            if *counter == 0 {
+                mask |= 1 << i;
+            }
+            *counter += 1;
+            if *counter == rule.period {
+                *counter = 0;
+            }
+        }
+        Some(mask)
+    }
+}
+
+impl<'a, const N: usize> Iterator for Wheel<'a, N> {
+    type Item = Cow<'static, str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let n = self.next?;
+        let mask = self.next_mask()?;
+        let mut word: Option<Cow<'static, str>> = None;
+        for (i, rule) in self.rules.iter().enumerate() {
+            if mask & (1 << i) != 0 {
+                word = Some(match word {
+                    None => Cow::Borrowed(rule.word),
+                    Some(prefix) => Cow::Owned(prefix.into_owned() + rule.word),
+                });
+            }
+        }
+        Some(word.unwrap_or_else(|| Cow::Owned(n.to_string())))
+    }
+}
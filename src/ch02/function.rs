@@ -1,3 +1,5 @@
+use super::ops::BinaryOp;
+
 /// # 函数定义
 ///
 /// Basic usage:
@@ -78,8 +80,10 @@ pub fn lexical_scope() {
 /// Basic usage:
 ///
 /// ```
-/// pub fn math(op: fn(i32, i32) -> i32, a: i32, b: i32) -> i32{
-///     op(a, b)
+/// use rust_programming_of_zhd::ch02::ops::BinaryOp;
+///
+/// pub fn math<T, O: BinaryOp<T>>(op: O, a: T, b: T) -> O::Output {
+///     op.apply(a, b)
 /// }
 /// fn sum(a: i32, b: i32) -> i32 {
 ///     a + b
@@ -93,8 +97,21 @@ pub fn lexical_scope() {
 /// assert_eq!(math(sum, a, b), 5);
 /// assert_eq!(math(product, a, b), 6);
 /// ```
-pub fn math(op: fn(i32, i32) -> i32, a: i32, b: i32) -> i32 {
-    op(a, b)
+///
+/// 这里的 `math` 接受任何 [`BinaryOp`], 函数指针、闭包以及 [`ops`](super::ops) 中带溢出检查的运算都可以传入:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::function::{math, product, sum};
+/// use rust_programming_of_zhd::ch02::ops::{checked_sum, saturating_product, ArithmeticError, Op};
+///
+/// assert_eq!(math(sum, 2, 3), 5);
+/// assert_eq!(math(product, 2, 3), 6);
+/// assert_eq!(math(|a: f64, b: f64| a.hypot(b), 3.0, 4.0), 5.0);
+/// assert_eq!(math(checked_sum, 250u8, 6), Err(ArithmeticError::Overflow(Op::Add)));
+/// assert_eq!(math(saturating_product, i64::MAX, 2), Ok(i64::MAX));
+/// ```
+pub fn math<T, O: BinaryOp<T>>(op: O, a: T, b: T) -> O::Output {
+    op.apply(a, b)
 }
 
 pub fn sum(a: i32, b: i32) -> i32 {
@@ -136,6 +153,9 @@ pub fn true_maker() -> fn() -> bool {
 /// 枚举、只包含单行代码的块表达式、范围等
 /// Rust 想要拥有完善的 CTFE 支持, 还有很多工作要做
 ///
+/// [`lang::consteval`](crate::lang::consteval) 模拟了常量检查与编译期求值的过程,
+/// [`ctfe`](super::ctfe) 中是用 `const fn` 在编译期生成的查找表
+///
 /// Basic usage:
 ///
 /// ```
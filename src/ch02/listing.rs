@@ -0,0 +1,239 @@
+//! 第二章的代码清单
+
+use crate::example::Example;
+
+pub const EXAMPLES: &[Example] = &[
+    Example {
+        chapter: 2,
+        listing: 2,
+        title: "一切皆表达式",
+        run: listing_2_2,
+    },
+    Example {
+        chapter: 2,
+        listing: 3,
+        title: "临时值: 位置表达式和值表达式",
+        run: listing_2_3,
+    },
+    Example {
+        chapter: 2,
+        listing: 4,
+        title: "不变与可变",
+        run: listing_2_4,
+    },
+    Example {
+        chapter: 2,
+        listing: 5,
+        title: "所有权",
+        run: listing_2_5,
+    },
+    Example {
+        chapter: 2,
+        listing: 6,
+        title: "引用",
+        run: listing_2_6,
+    },
+    Example {
+        chapter: 2,
+        listing: 8,
+        title: "函数定义: FizzBuzz",
+        run: listing_2_8,
+    },
+    Example {
+        chapter: 2,
+        listing: 9,
+        title: "词法作用域",
+        run: listing_2_9,
+    },
+    Example {
+        chapter: 2,
+        listing: 10,
+        title: "函数指针: 函数作为参数",
+        run: listing_2_10,
+    },
+    Example {
+        chapter: 2,
+        listing: 11,
+        title: "函数指针: 函数作为返回值",
+        run: listing_2_11,
+    },
+    Example {
+        chapter: 2,
+        listing: 12,
+        title: "CTFE: const fn",
+        run: listing_2_12,
+    },
+    Example {
+        chapter: 2,
+        listing: 13,
+        title: "闭包",
+        run: listing_2_13,
+    },
+    Example {
+        chapter: 2,
+        listing: 14,
+        title: "闭包: 作为参数",
+        run: listing_2_14,
+    },
+    Example {
+        chapter: 2,
+        listing: 15,
+        title: "闭包: 作为返回值",
+        run: listing_2_15,
+    },
+    Example {
+        chapter: 2,
+        listing: 16,
+        title: "if 表达式",
+        run: listing_2_16,
+    },
+    Example {
+        chapter: 2,
+        listing: 17,
+        title: "while/loop/for 表达式: FizzBuzz",
+        run: listing_2_17,
+    },
+    Example {
+        chapter: 2,
+        listing: 19,
+        title: "while true 与 if true",
+        run: listing_2_19,
+    },
+    Example {
+        chapter: 2,
+        listing: 20,
+        title: "match 匹配",
+        run: listing_2_20,
+    },
+    Example {
+        chapter: 2,
+        listing: 21,
+        title: "match/if let/while let",
+        run: listing_2_21,
+    },
+];
+
+pub fn listing_2_2() {
+    use crate::ch02::answer;
+    answer();
+}
+
+pub fn listing_2_3() {
+    use crate::ch02::binding::temp;
+    let x = &temp();
+    println!("{}", x);
+    // temp() = *x;
+    // ^ error[E0070]: invalid left- hand side expression
+}
+
+pub fn listing_2_4() {
+    use crate::ch02::binding::immutable_and_mutable;
+    immutable_and_mutable();
+}
+
+pub fn listing_2_5() {
+    use crate::ch02::binding::ownership;
+    ownership();
+}
+
+pub fn listing_2_6() {
+    use crate::ch02::binding::reference;
+    reference();
+    use crate::ch02::binding::reference2;
+    reference2();
+}
+
+pub fn listing_2_8() {
+    use crate::ch02::function::fizz_buzz;
+    assert_eq!(fizz_buzz(15), "fizzbuzz".to_string());
+    assert_eq!(fizz_buzz(3), "fizz".to_string());
+    assert_eq!(fizz_buzz(5), "buzz".to_string());
+    assert_eq!(fizz_buzz(13), "13".to_string());
+}
+
+pub fn listing_2_9() {
+    use crate::ch02::function::lexical_scope;
+    lexical_scope();
+}
+
+pub fn listing_2_10() {
+    use crate::ch02::function::{math, product, sum};
+    let a = 2;
+    let b = 3;
+    println!("{} == {}", math(sum, a, b), 5);
+    println!("{} == {}", math(product, a, b), 6);
+}
+
+pub fn listing_2_11() {
+    use crate::ch02::function::true_maker;
+    println!("true_maker: {}", true_maker()());
+}
+
+pub fn listing_2_12() {
+    use crate::ch02::function::init_len;
+    // 通过［O; N] 这种形式来初始化初始值为0、长度为N的数组
+    // Rust 中固定长度的数组必须在编译期就知道长度, 否则会编译出错
+    // 以函数 init_len 必须在编译期求值. 这就是 CTFE 的能力
+    let arr = [0; init_len()];
+    println!("{:?}", arr);
+}
+
+pub fn listing_2_13() {
+    use crate::ch02::function::closure;
+    closure();
+}
+
+pub fn listing_2_14() {
+    use crate::ch02::function::closure_math;
+    let a = 2;
+    let b = 3;
+    println!("{} == {}", closure_math(|| a + b), 5);
+    println!("{} == {}", closure_math(|| a * b), 6);
+}
+
+pub fn listing_2_15() {
+    use crate::ch02::function::{two_times, two_times_dyn, two_times_impl};
+    println!("{} == {}", two_times()(2), 4);
+    println!("{} == {}", two_times_dyn()(2), 4);
+    println!("{} == {}", two_times_impl()(2), 4);
+}
+
+pub fn listing_2_16() {
+    use crate::ch02::control_flow::if_expr;
+    if_expr()
+}
+
+pub fn listing_2_17() {
+    use crate::ch02::control_flow::for_fizzbuzz;
+    use crate::ch02::control_flow::loop_fizzbuzz;
+    use crate::ch02::control_flow::while_fizzbuzz;
+    while_fizzbuzz();
+    println!("------------------");
+    loop_fizzbuzz();
+    println!("------------------");
+    for_fizzbuzz();
+}
+
+pub fn listing_2_19() {
+    use crate::ch02::control_flow::if_true;
+    use crate::ch02::control_flow::while_true;
+    println!("while_true {}", while_true(12));
+    println!("if_true {}", if_true(12));
+}
+
+pub fn listing_2_20() {
+    use crate::ch02::control_flow::match_expr;
+    match_expr(43);
+}
+
+pub fn listing_2_21() {
+    use crate::ch02::control_flow::if_let_bool;
+    use crate::ch02::control_flow::loop_match_pop;
+    use crate::ch02::control_flow::match_bool;
+    use crate::ch02::control_flow::while_let_pop;
+
+    match_bool();
+    if_let_bool();
+    while_let_pop();
+    loop_match_pop();
+}
@@ -22,6 +22,7 @@ pub fn title() {
 /// 一切皆表达式
 /// 表达式: 求值表达式, 返回`求值结果`
 /// 语句: 带分号的特殊表达式, 返回`()`
+/// 可以用 [`lang::interp`](crate::lang::interp) 或 `repl` 子命令逐步查看每个表达式求出的值
 /// ```
 /// // extern crate std;      // 声明语句
 /// // use std::prelude::v1::*;  // 声明语句
@@ -48,6 +49,18 @@ pub fn sum(a: i32, b: i32) -> i32 {
     a + b
 }
 
+#[allow(unused_assignments, clippy::just_underscores_and_digits)]
 pub mod binding;
+#[allow(
+    unused_parens,
+    clippy::never_loop,
+    clippy::redundant_pattern_matching,
+    clippy::while_let_loop
+)]
 pub mod control_flow;
+pub mod ctfe;
+pub mod fizzbuzz;
+#[allow(bare_trait_objects)]
 pub mod function;
+pub mod listing;
+pub mod ops;
@@ -0,0 +1,413 @@
+//! # 算术运算工具
+//!
+//! [`function::math`](super::function::math) 最初只接受 `fn(i32, i32) -> i32`.
+//! 这里把运算本身当作值来传递:
+//!
+//! - [`BinaryOp`] trait: 二元运算, 函数指针与闭包都自动实现了它
+//! - [`Number`] trait: 为所有原生整数和浮点数实现的检查/回绕/饱和运算
+//! - [`Arithmetic`] 结构体: 把 [`Op`] 与 [`Overflow`] 组合成一个可以传递的运算值
+//! - `checked_sum`、`wrapping_product` 等函数: 签名统一为 [`OpFn`],
+//!   可以直接放进数组或作为函数指针传递
+//!
+//! 溢出和除以零都以 [`ArithmeticError`] 返回, 不会 panic
+
+use std::error::Error;
+use std::fmt;
+
+/// # 二元运算
+///
+/// 任何 `Fn(T, T) -> R` 都是二元运算, 因此函数名、函数指针和闭包都可以直接使用
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::ops::{checked_sum, BinaryOp};
+///
+/// fn apply<O: BinaryOp<u8>>(op: O, a: u8, b: u8) -> O::Output {
+///     op.apply(a, b)
+/// }
+///
+/// let max: fn(u8, u8) -> u8 = std::cmp::max;
+/// assert_eq!(apply(max, 2, 3), 3);
+/// assert_eq!(apply(|a: u8, b: u8| a.min(b), 2, 3), 2);
+/// assert!(apply(checked_sum, 200, 100).is_err());
+/// ```
+pub trait BinaryOp<T> {
+    type Output;
+
+    fn apply(&self, lhs: T, rhs: T) -> Self::Output;
+}
+
+impl<T, R, F> BinaryOp<T> for F
+where
+    F: Fn(T, T) -> R,
+{
+    type Output = R;
+
+    fn apply(&self, lhs: T, rhs: T) -> R {
+        self(lhs, rhs)
+    }
+}
+
+/// 算术运算符
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl Op {
+    pub const ALL: [Op; 5] = [Op::Add, Op::Sub, Op::Mul, Op::Div, Op::Rem];
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Op::Add => "+",
+            Op::Sub => "-",
+            Op::Mul => "*",
+            Op::Div => "/",
+            Op::Rem => "%",
+        }
+    }
+
+    /// 与 rustc 的 panic 信息一致的动词
+    fn verb(self) -> &'static str {
+        match self {
+            Op::Add => "add",
+            Op::Sub => "subtract",
+            Op::Mul => "multiply",
+            Op::Div => "divide",
+            Op::Rem => "calculate the remainder",
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.symbol())
+    }
+}
+
+/// 溢出时的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Overflow {
+    /// 溢出时返回错误
+    Checked,
+    /// 整数按位回绕; 浮点数按 IEEE 754 计算, 允许得到无穷大
+    Wrapping,
+    /// 结果限制在类型的最小值与最大值之间
+    Saturating,
+}
+
+/// # 算术错误
+///
+/// `Display` 与 rustc 在 debug 模式下的 panic 信息一致
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::ops::{ArithmeticError, Op};
+///
+/// assert_eq!(ArithmeticError::Overflow(Op::Add).to_string(), "attempt to add with overflow");
+/// assert_eq!(
+///     ArithmeticError::DivideByZero(Op::Rem).to_string(),
+///     "attempt to calculate the remainder with a divisor of zero"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArithmeticError {
+    /// 结果超出类型的表示范围, 浮点数则是有限的操作数得到了无穷大
+    Overflow(Op),
+    /// 除数为零
+    DivideByZero(Op),
+    /// 不是 NaN 的操作数得到了 NaN, 例如 `inf - inf`
+    NotANumber(Op),
+}
+
+impl fmt::Display for ArithmeticError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArithmeticError::Overflow(op) => write!(f, "attempt to {} with overflow", op.verb()),
+            ArithmeticError::DivideByZero(Op::Rem) => {
+                write!(
+                    f,
+                    "attempt to calculate the remainder with a divisor of zero"
+                )
+            }
+            ArithmeticError::DivideByZero(_) => write!(f, "attempt to divide by zero"),
+            ArithmeticError::NotANumber(op) => write!(f, "attempt to {} produced NaN", op.verb()),
+        }
+    }
+}
+
+impl Error for ArithmeticError {}
+
+/// # 原生数值
+///
+/// 为所有原生整数和浮点数实现. 三个方法只在无法给出结果时返回错误:
+/// `checked` 在溢出或除以零时出错, `wrapping` 和 `saturating` 只在除以零时出错.
+/// 与标准库一致, 有符号整数的 `MIN % -1` 在 `checked` 中也视为溢出
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::ops::{ArithmeticError, Number, Op};
+///
+/// assert_eq!(100u8.checked(Op::Add, 100), Ok(200));
+/// assert_eq!(200u8.checked(Op::Add, 100), Err(ArithmeticError::Overflow(Op::Add)));
+/// assert_eq!(200u8.wrapping(Op::Add, 100), Ok(44));
+/// assert_eq!(200u8.saturating(Op::Add, 100), Ok(255));
+/// assert_eq!(i8::MIN.saturating(Op::Div, -1), Ok(i8::MAX));
+/// assert_eq!(7i32.wrapping(Op::Rem, 0), Err(ArithmeticError::DivideByZero(Op::Rem)));
+///
+/// assert_eq!(f64::MAX.checked(Op::Mul, 2.0), Err(ArithmeticError::Overflow(Op::Mul)));
+/// assert_eq!(f64::MAX.wrapping(Op::Mul, 2.0), Ok(f64::INFINITY));
+/// assert_eq!(f64::MAX.saturating(Op::Mul, 2.0), Ok(f64::MAX));
+/// assert_eq!(1.0f32.checked(Op::Div, 0.0), Err(ArithmeticError::DivideByZero(Op::Div)));
+/// ```
+pub trait Number: Copy + PartialOrd + fmt::Debug + fmt::Display {
+    const ZERO: Self;
+    const ONE: Self;
+    const MIN: Self;
+    const MAX: Self;
+
+    fn checked(self, op: Op, rhs: Self) -> Result<Self, ArithmeticError>;
+    fn wrapping(self, op: Op, rhs: Self) -> Result<Self, ArithmeticError>;
+    fn saturating(self, op: Op, rhs: Self) -> Result<Self, ArithmeticError>;
+
+    /// 按指定的溢出处理方式计算
+    fn compute(self, op: Op, overflow: Overflow, rhs: Self) -> Result<Self, ArithmeticError> {
+        match overflow {
+            Overflow::Checked => self.checked(op, rhs),
+            Overflow::Wrapping => self.wrapping(op, rhs),
+            Overflow::Saturating => self.saturating(op, rhs),
+        }
+    }
+}
+
+macro_rules! impl_integer {
+    ($($t:ty),*) => {
+        $(
+            impl Number for $t {
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+                const MIN: Self = <$t>::MIN;
+                const MAX: Self = <$t>::MAX;
+
+                fn checked(self, op: Op, rhs: Self) -> Result<Self, ArithmeticError> {
+                    let result = match op {
+                        Op::Add => self.checked_add(rhs),
+                        Op::Sub => self.checked_sub(rhs),
+                        Op::Mul => self.checked_mul(rhs),
+                        Op::Div | Op::Rem if rhs == 0 => {
+                            return Err(ArithmeticError::DivideByZero(op))
+                        }
+                        Op::Div => self.checked_div(rhs),
+                        Op::Rem => self.checked_rem(rhs),
+                    };
+                    result.ok_or(ArithmeticError::Overflow(op))
+                }
+
+                fn wrapping(self, op: Op, rhs: Self) -> Result<Self, ArithmeticError> {
+                    Ok(match op {
+                        Op::Add => self.wrapping_add(rhs),
+                        Op::Sub => self.wrapping_sub(rhs),
+                        Op::Mul => self.wrapping_mul(rhs),
+                        Op::Div | Op::Rem if rhs == 0 => {
+                            return Err(ArithmeticError::DivideByZero(op))
+                        }
+                        Op::Div => self.wrapping_div(rhs),
+                        Op::Rem => self.wrapping_rem(rhs),
+                    })
+                }
+
+                fn saturating(self, op: Op, rhs: Self) -> Result<Self, ArithmeticError> {
+                    Ok(match op {
+                        Op::Add => self.saturating_add(rhs),
+                        Op::Sub => self.saturating_sub(rhs),
+                        Op::Mul => self.saturating_mul(rhs),
+                        Op::Div | Op::Rem if rhs == 0 => {
+                            return Err(ArithmeticError::DivideByZero(op))
+                        }
+                        Op::Div => self.saturating_div(rhs),
+                        // `MIN % -1` 的数学结果是 0, 回绕运算恰好给出 0
+                        Op::Rem => self.wrapping_rem(rhs),
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+macro_rules! impl_float {
+    ($($t:ty),*) => {
+        $(
+            impl Number for $t {
+                const ZERO: Self = 0.0;
+                const ONE: Self = 1.0;
+                const MIN: Self = <$t>::MIN;
+                const MAX: Self = <$t>::MAX;
+
+                fn checked(self, op: Op, rhs: Self) -> Result<Self, ArithmeticError> {
+                    let result = self.wrapping(op, rhs)?;
+                    if matches!(op, Op::Div | Op::Rem) && rhs == 0.0 {
+                        Err(ArithmeticError::DivideByZero(op))
+                    } else if result.is_nan() && !self.is_nan() && !rhs.is_nan() {
+                        Err(ArithmeticError::NotANumber(op))
+                    } else if result.is_infinite() && self.is_finite() && rhs.is_finite() {
+                        Err(ArithmeticError::Overflow(op))
+                    } else {
+                        Ok(result)
+                    }
+                }
+
+                fn wrapping(self, op: Op, rhs: Self) -> Result<Self, ArithmeticError> {
+                    Ok(match op {
+                        Op::Add => self + rhs,
+                        Op::Sub => self - rhs,
+                        Op::Mul => self * rhs,
+                        Op::Div => self / rhs,
+                        Op::Rem => self % rhs,
+                    })
+                }
+
+                fn saturating(self, op: Op, rhs: Self) -> Result<Self, ArithmeticError> {
+                    if matches!(op, Op::Div | Op::Rem) && rhs == 0.0 {
+                        return Err(ArithmeticError::DivideByZero(op));
+                    }
+                    let result = self.wrapping(op, rhs)?;
+                    // NaN 原样返回, 无穷大限制为最大或最小的有限值
+                    Ok(if result.is_nan() {
+                        result
+                    } else {
+                        result.clamp(<$t>::MIN, <$t>::MAX)
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_float!(f32, f64);
+
+/// # 可传递的算术运算
+///
+/// 把运算符与溢出处理方式组合成一个值, 它实现了 [`BinaryOp`]
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::function::math;
+/// use rust_programming_of_zhd::ch02::ops::{Arithmetic, ArithmeticError, Op};
+///
+/// let ops = [Arithmetic::checked(Op::Mul), Arithmetic::saturating(Op::Mul)];
+/// let results: Vec<_> = ops.iter().map(|&op| math(op, 16i8, 8)).collect();
+/// assert_eq!(results, [Err(ArithmeticError::Overflow(Op::Mul)), Ok(127)]);
+/// assert_eq!(math(Arithmetic::wrapping(Op::Sub), 0u32, 1), Ok(u32::MAX));
+/// assert_eq!(Arithmetic::checked(Op::Add).to_string(), "checked +");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Arithmetic {
+    pub op: Op,
+    pub overflow: Overflow,
+}
+
+impl Arithmetic {
+    pub fn new(op: Op, overflow: Overflow) -> Self {
+        Arithmetic { op, overflow }
+    }
+
+    pub fn checked(op: Op) -> Self {
+        Arithmetic::new(op, Overflow::Checked)
+    }
+
+    pub fn wrapping(op: Op) -> Self {
+        Arithmetic::new(op, Overflow::Wrapping)
+    }
+
+    pub fn saturating(op: Op) -> Self {
+        Arithmetic::new(op, Overflow::Saturating)
+    }
+}
+
+impl<T: Number> BinaryOp<T> for Arithmetic {
+    type Output = Result<T, ArithmeticError>;
+
+    fn apply(&self, lhs: T, rhs: T) -> Self::Output {
+        lhs.compute(self.op, self.overflow, rhs)
+    }
+}
+
+impl fmt::Display for Arithmetic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let overflow = match self.overflow {
+            Overflow::Checked => "checked",
+            Overflow::Wrapping => "wrapping",
+            Overflow::Saturating => "saturating",
+        };
+        write!(f, "{} {}", overflow, self.op)
+    }
+}
+
+/// 下面这些运算函数共同的函数指针类型
+pub type OpFn<T> = fn(T, T) -> Result<T, ArithmeticError>;
+
+macro_rules! binary_fns {
+    ($($name:ident => $op:ident, $overflow:ident, $doc:literal;)*) => {
+        $(
+            #[doc = $doc]
+            pub fn $name<T: Number>(a: T, b: T) -> Result<T, ArithmeticError> {
+                a.compute(Op::$op, Overflow::$overflow, b)
+            }
+        )*
+    };
+}
+
+binary_fns! {
+    checked_sum => Add, Checked, "检查溢出的加法";
+    wrapping_sum => Add, Wrapping, "回绕的加法";
+    saturating_sum => Add, Saturating, "饱和的加法";
+    checked_difference => Sub, Checked, "检查溢出的减法";
+    wrapping_difference => Sub, Wrapping, "回绕的减法";
+    saturating_difference => Sub, Saturating, "饱和的减法";
+    checked_product => Mul, Checked, "检查溢出的乘法";
+    wrapping_product => Mul, Wrapping, "回绕的乘法";
+    saturating_product => Mul, Saturating, "饱和的乘法";
+    checked_quotient => Div, Checked, "检查溢出与除以零的除法";
+    wrapping_quotient => Div, Wrapping, "回绕的除法, 除以零时返回错误";
+    saturating_quotient => Div, Saturating, "饱和的除法, 除以零时返回错误";
+    checked_remainder => Rem, Checked, "检查溢出与除以零的取余";
+    wrapping_remainder => Rem, Wrapping, "回绕的取余, 除以零时返回错误";
+    saturating_remainder => Rem, Saturating, "饱和的取余, 除以零时返回错误";
+}
+
+/// # 依次应用二元运算
+///
+/// 遇到第一个错误就停止, 与 `Iterator::try_fold` 相同
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::ch02::ops::{
+///     checked_product, checked_sum, saturating_product, try_reduce, ArithmeticError, Op,
+/// };
+///
+/// assert_eq!(try_reduce(checked_sum, 0u8, vec![1, 2, 3]), Ok(6));
+/// assert_eq!(
+///     try_reduce(checked_product, 1u8, 1..=6),
+///     Err(ArithmeticError::Overflow(Op::Mul))
+/// );
+/// assert_eq!(try_reduce(saturating_product, 1u8, 1..=6), Ok(u8::MAX));
+/// assert_eq!(try_reduce(checked_sum, 0.0, vec![0.5, 0.25]), Ok(0.75));
+/// ```
+pub fn try_reduce<T, O, I>(op: O, init: T, items: I) -> Result<T, ArithmeticError>
+where
+    O: BinaryOp<T, Output = Result<T, ArithmeticError>>,
+    I: IntoIterator<Item = T>,
+{
+    items.into_iter().try_fold(init, |acc, x| op.apply(acc, x))
+}
@@ -0,0 +1,152 @@
+//! 编译失败测试
+//!
+//! 一些清单的文档专门展示无法通过编译的代码. `tests/compile-fail/` 中的每个 `.rs` 文件
+//! 都是这样一个用例, 同名的 `.expected` 文件记录了它应当产生的错误(错误码和错误信息).
+//! 运行器直接调用 `rustc`(可以通过环境变量 `RUSTC` 指定), 不需要联网,
+//! 设置环境变量 `BLESS=1` 时用实际的错误更新 `.expected`
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::snapshot::diff;
+
+/// # 提取错误
+///
+/// 从 rustc 的输出中提取形如 `error[E0070]: ...` 的行, 忽略警告和总结行
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::compile_fail::extract_errors;
+/// let stderr = "\
+/// warning: unused variable: `x`
+/// error[E0070]: invalid left-hand side of assignment
+///  --> temp.rs:9:12
+/// error: aborting due to 1 previous error
+/// ";
+/// assert_eq!(extract_errors(stderr), ["error[E0070]: invalid left-hand side of assignment"]);
+/// ```
+pub fn extract_errors(stderr: &str) -> Vec<String> {
+    stderr
+        .lines()
+        .filter(|line| line.starts_with("error"))
+        .filter(|line| !line.starts_with("error: aborting due to"))
+        .map(str::to_string)
+        .collect()
+}
+
+/// 单个用例的结果
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// 编译失败, 且错误与预期一致
+    Pass,
+    /// 编译成功了
+    Compiled,
+    /// 错误与预期不一致
+    Mismatch { expected: String, actual: String },
+    /// 没有 `.expected` 文件
+    Missing,
+    /// 使用 `BLESS=1` 更新了 `.expected`
+    Blessed,
+}
+
+impl Outcome {
+    pub fn is_ok(&self) -> bool {
+        match self {
+            Outcome::Pass | Outcome::Blessed => true,
+            Outcome::Compiled | Outcome::Mismatch { .. } | Outcome::Missing => false,
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Pass => write!(f, "ok"),
+            Outcome::Blessed => write!(f, "blessed"),
+            Outcome::Compiled => write!(f, "compiled successfully, expected an error"),
+            Outcome::Missing => write!(f, "missing .expected file (run with BLESS=1)"),
+            Outcome::Mismatch { expected, actual } => {
+                writeln!(f, "errors differ from .expected")?;
+                write!(f, "{}", diff(expected, actual))
+            }
+        }
+    }
+}
+
+/// 编译失败测试的运行器
+#[derive(Debug, Clone)]
+pub struct Runner {
+    rustc: PathBuf,
+    out_dir: PathBuf,
+    bless: bool,
+}
+
+impl Runner {
+    /// 根据环境变量 `RUSTC` 和 `BLESS` 创建
+    pub fn from_env() -> Self {
+        Runner {
+            rustc: env::var_os("RUSTC").map_or_else(|| PathBuf::from("rustc"), PathBuf::from),
+            out_dir: env::temp_dir().join(format!("compile-fail-{}", std::process::id())),
+            bless: env::var_os("BLESS").is_some_and(|v| v != "0"),
+        }
+    }
+
+    /// 编译单个文件, 只生成元数据, 因此不需要 `main` 函数
+    pub fn check(&self, case: &Path) -> io::Result<Outcome> {
+        fs::create_dir_all(&self.out_dir)?;
+        let output = Command::new(&self.rustc)
+            .args([
+                "--edition",
+                "2018",
+                "--crate-type",
+                "lib",
+                "--emit",
+                "metadata",
+            ])
+            .args(["--color", "never", "--cap-lints", "allow"])
+            .arg("--out-dir")
+            .arg(&self.out_dir)
+            .arg(case)
+            .output()?;
+        if output.status.success() {
+            return Ok(Outcome::Compiled);
+        }
+        let mut actual = extract_errors(&String::from_utf8_lossy(&output.stderr)).join("\n");
+        actual.push('\n');
+
+        let expected_path = case.with_extension("expected");
+        if self.bless {
+            fs::write(&expected_path, actual)?;
+            return Ok(Outcome::Blessed);
+        }
+        match fs::read_to_string(&expected_path) {
+            Ok(expected) if expected == actual => Ok(Outcome::Pass),
+            Ok(expected) => Ok(Outcome::Mismatch { expected, actual }),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Outcome::Missing),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 运行目录下的全部 `.rs` 用例, 按文件名排序
+    pub fn run_dir(&self, dir: &Path) -> io::Result<Vec<(String, Outcome)>> {
+        let mut cases: Vec<PathBuf> = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<_>>()?;
+        cases.retain(|path| path.extension().is_some_and(|ext| ext == "rs"));
+        cases.sort();
+        let results = cases
+            .iter()
+            .map(|case| {
+                let name = case.file_name().unwrap().to_string_lossy().into_owned();
+                self.check(case).map(|outcome| (name, outcome))
+            })
+            .collect();
+        let _ = fs::remove_dir_all(&self.out_dir);
+        results
+    }
+}
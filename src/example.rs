@@ -0,0 +1,132 @@
+//! 示例注册表
+//!
+//! 每一章把自己的代码清单(Listing)登记到 `EXAMPLES` 中,
+//! 二进制程序通过这里的注册表按章节或清单编号挑选要运行的示例
+
+use std::fmt;
+use std::str::FromStr;
+
+/// # 代码清单
+///
+/// 一个可以单独运行的示例: 所在章节、清单编号、标题以及入口函数
+#[derive(Clone, Copy)]
+pub struct Example {
+    pub chapter: u32,
+    pub listing: u32,
+    pub title: &'static str,
+    pub run: fn(),
+}
+
+impl Example {
+    /// 清单编号, 形如 `2.17`
+    pub fn id(&self) -> String {
+        format!("{}.{}", self.chapter, self.listing)
+    }
+}
+
+impl fmt::Debug for Example {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Example")
+            .field("chapter", &self.chapter)
+            .field("listing", &self.listing)
+            .field("title", &self.title)
+            .finish()
+    }
+}
+
+/// # 所有章节的示例
+///
+/// 按章节、清单编号的顺序返回
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::example::registry;
+/// let examples = registry();
+/// assert_eq!(examples[0].id(), "1.1");
+/// assert!(examples.iter().any(|e| e.id() == "2.17"));
+/// ```
+pub fn registry() -> Vec<&'static Example> {
+    let mut examples: Vec<&'static Example> = crate::ch01::listing::EXAMPLES
+        .iter()
+        .chain(crate::ch02::listing::EXAMPLES.iter())
+        .collect();
+    examples.sort_by_key(|e| (e.chapter, e.listing));
+    examples
+}
+
+/// # 示例选择器
+///
+/// 支持三种写法:
+/// 1. `--all`: 全部示例
+/// 2. `ch02`: 某一章的全部示例
+/// 3. `2.17`: 某一个清单
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::example::Selector;
+/// assert_eq!("--all".parse::<Selector>(), Ok(Selector::All));
+/// assert_eq!("ch02".parse::<Selector>(), Ok(Selector::Chapter(2)));
+/// assert_eq!("2.17".parse::<Selector>(), Ok(Selector::Listing(2, 17)));
+/// assert!("2.x".parse::<Selector>().is_err());
+///
+/// let selected = Selector::Listing(2, 17).select();
+/// assert_eq!(selected.len(), 1);
+/// assert!(Selector::Chapter(2).select().iter().all(|e| e.chapter == 2));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selector {
+    All,
+    Chapter(u32),
+    Listing(u32, u32),
+}
+
+impl Selector {
+    pub fn matches(&self, example: &Example) -> bool {
+        match *self {
+            Selector::All => true,
+            Selector::Chapter(chapter) => example.chapter == chapter,
+            Selector::Listing(chapter, listing) => {
+                example.chapter == chapter && example.listing == listing
+            }
+        }
+    }
+
+    pub fn select(&self) -> Vec<&'static Example> {
+        registry().into_iter().filter(|e| self.matches(e)).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseSelectorError(String);
+
+impl fmt::Display for ParseSelectorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid example selector `{}`, expected `--all`, `chNN` or `N.M`",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseSelectorError {}
+
+impl FromStr for Selector {
+    type Err = ParseSelectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseSelectorError(s.to_string());
+        if s == "--all" || s == "all" {
+            return Ok(Selector::All);
+        }
+        if let Some(chapter) = s.strip_prefix("ch") {
+            return chapter.parse().map(Selector::Chapter).map_err(|_| err());
+        }
+        let mut parts = s.splitn(2, '.');
+        let chapter = parts.next().and_then(|c| c.parse().ok()).ok_or_else(err)?;
+        let listing = parts.next().and_then(|l| l.parse().ok()).ok_or_else(err)?;
+        Ok(Selector::Listing(chapter, listing))
+    }
+}
@@ -0,0 +1,759 @@
+//! # 语法树
+//!
+//! 教学用 Rust 子集的抽象语法树. 覆盖书中前两章出现的大部分语法:
+//! 字面量、路径、一元与二元运算、引用与解引用、赋值、字段与索引、函数与方法调用、宏调用、
+//! 元组与数组、块、`if`/`if let`、`while`/`while let`、`loop`、`for`、`match`、区间以及 `as` 转换
+//!
+//! 每个表达式、语句和模式都记录了起始位置, 各个分析器用它来报告诊断信息.
+//! 语法树实现了 `Display`, 输出规范化后的源码
+
+use std::fmt;
+
+use super::lexer::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lit {
+    /// 整数字面量, 负号作为一元运算单独表示
+    Int {
+        value: u128,
+        suffix: Option<String>,
+    },
+    Float {
+        value: f64,
+        suffix: Option<String>,
+    },
+    Bool(bool),
+    Str(String),
+    Char(char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnOp {
+    /// `-x`
+    Neg,
+    /// `!x`
+    Not,
+    /// `*x`
+    Deref,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    And,
+    Or,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl BinOp {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Rem => "%",
+            BinOp::And => "&&",
+            BinOp::Or => "||",
+            BinOp::BitAnd => "&",
+            BinOp::BitOr => "|",
+            BinOp::BitXor => "^",
+            BinOp::Shl => "<<",
+            BinOp::Shr => ">>",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+        }
+    }
+
+    /// 比较运算符, 它们会隐式借用两侧的操作数
+    pub fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge
+        )
+    }
+
+    /// 短路求值的逻辑运算符
+    pub fn is_lazy(self) -> bool {
+        matches!(self, BinOp::And | BinOp::Or)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Lit(Lit),
+    /// `x`、`None`、`std::i32::MAX`
+    Path(Vec<String>),
+    /// `(expr)`, 保留括号是为了区分 "位置表达式组合"
+    Paren(Box<Expr>),
+    Unary(UnOp, Box<Expr>),
+    /// `&expr` 或 `&mut expr`
+    Ref {
+        mutable: bool,
+        expr: Box<Expr>,
+    },
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+    /// `place = value`
+    Assign(Box<Expr>, Box<Expr>),
+    /// `place += value` 等复合赋值
+    AssignOp(BinOp, Box<Expr>, Box<Expr>),
+    /// `expr.field`, 元组字段的名字是数字
+    Field(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Call(Box<Expr>, Vec<Expr>),
+    MethodCall {
+        receiver: Box<Expr>,
+        method: String,
+        args: Vec<Expr>,
+    },
+    /// `name!(args)`, 参数按逗号分隔的表达式解析; `vec![..]` 的唯一参数是一个数组表达式
+    Macro {
+        name: String,
+        args: Vec<Expr>,
+    },
+    Tuple(Vec<Expr>),
+    Array(Vec<Expr>),
+    /// `[value; len]`
+    Repeat(Box<Expr>, Box<Expr>),
+    /// `let pat = expr`, 只出现在 `if` 与 `while` 的条件中
+    Let(Box<Pat>, Box<Expr>),
+    Block(Block),
+    If {
+        cond: Box<Expr>,
+        then: Block,
+        /// 另一个 `Block` 或 `If` 表达式
+        els: Option<Box<Expr>>,
+    },
+    While {
+        cond: Box<Expr>,
+        body: Block,
+    },
+    Loop(Block),
+    For {
+        pat: Box<Pat>,
+        iter: Box<Expr>,
+        body: Block,
+    },
+    Match {
+        scrutinee: Box<Expr>,
+        arms: Vec<Arm>,
+    },
+    Break(Option<Box<Expr>>),
+    Continue,
+    Return(Option<Box<Expr>>),
+    Range {
+        start: Option<Box<Expr>>,
+        end: Option<Box<Expr>>,
+        inclusive: bool,
+    },
+    Cast(Box<Expr>, Type),
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Expr { kind, span }
+    }
+
+    /// 是否为块状表达式, 它们作为语句时不需要分号
+    pub fn is_block_like(&self) -> bool {
+        matches!(
+            self.kind,
+            ExprKind::Block(_)
+                | ExprKind::If { .. }
+                | ExprKind::While { .. }
+                | ExprKind::Loop(_)
+                | ExprKind::For { .. }
+                | ExprKind::Match { .. }
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arm {
+    pub pat: Pat,
+    pub guard: Option<Expr>,
+    pub body: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub span: Span,
+    /// 右花括号的位置, 块中的绑定在这里离开作用域
+    pub end: Span,
+}
+
+impl Block {
+    /// 块的值: 最后一条不带分号的表达式语句
+    pub fn tail(&self) -> Option<&Expr> {
+        match self.stmts.last() {
+            Some(Stmt {
+                kind: StmtKind::Expr(expr),
+                ..
+            }) => Some(expr),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind {
+    Let {
+        pat: Pat,
+        ty: Option<Type>,
+        init: Option<Expr>,
+    },
+    /// 不带分号的表达式, 位于块的末尾时就是块的值
+    Expr(Expr),
+    /// 以分号结尾的表达式, 值被丢弃, 语句本身的类型是 `()`
+    Semi(Expr),
+    Item(Item),
+    /// `use x;` 伪语句: 使用一次 `x` 的值但不移动它, 供所有权和借用分析使用
+    Use(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pat {
+    pub kind: PatKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatKind {
+    /// `_`
+    Wild,
+    /// `x`、`mut x`、`ref x`、`ref mut x`、`n @ 42`
+    Binding {
+        name: String,
+        by_ref: bool,
+        mutable: bool,
+        sub: Option<Box<Pat>>,
+    },
+    /// 字面量或者负数字面量
+    Lit(Expr),
+    /// `1..=3`、`'a'..='z'`
+    Range {
+        start: Box<Expr>,
+        end: Box<Expr>,
+        inclusive: bool,
+    },
+    Tuple(Vec<Pat>),
+    /// `None`、`Ordering::Less` 等, 大写开头或多段的路径
+    Path(Vec<String>),
+    /// `Some(x)`
+    TupleStruct(Vec<String>, Vec<Pat>),
+    /// `&pat`、`&mut pat`
+    Ref {
+        mutable: bool,
+        pat: Box<Pat>,
+    },
+    /// `a | b`
+    Or(Vec<Pat>),
+}
+
+impl Pat {
+    /// 模式中按顺序出现的全部绑定
+    pub fn bindings(&self) -> Vec<&Pat> {
+        let mut out = Vec::new();
+        self.collect_bindings(&mut out);
+        out
+    }
+
+    fn collect_bindings<'a>(&'a self, out: &mut Vec<&'a Pat>) {
+        match &self.kind {
+            PatKind::Binding { sub, .. } => {
+                out.push(self);
+                if let Some(sub) = sub {
+                    sub.collect_bindings(out);
+                }
+            }
+            PatKind::Tuple(pats) | PatKind::TupleStruct(_, pats) => {
+                for pat in pats {
+                    pat.collect_bindings(out);
+                }
+            }
+            // 或模式的每个分支绑定相同的名字, 取第一个即可
+            PatKind::Or(pats) => {
+                if let Some(pat) = pats.first() {
+                    pat.collect_bindings(out);
+                }
+            }
+            PatKind::Ref { pat, .. } => pat.collect_bindings(out),
+            PatKind::Wild | PatKind::Lit(_) | PatKind::Range { .. } | PatKind::Path(_) => {}
+        }
+    }
+
+    /// 是否包含 `ref` 绑定
+    pub fn has_ref_binding(&self) -> bool {
+        self.bindings()
+            .iter()
+            .any(|p| matches!(p.kind, PatKind::Binding { by_ref: true, .. }))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    /// `i32`、`String`、`Vec<i32>`、`std::io::Result<()>`
+    Path {
+        name: String,
+        args: Vec<Type>,
+    },
+    Ref {
+        lifetime: Option<String>,
+        mutable: bool,
+        inner: Box<Type>,
+    },
+    /// `()` 是零元组
+    Tuple(Vec<Type>),
+    Array(Box<Type>, Box<Expr>),
+    Slice(Box<Type>),
+    /// `fn(i32, i32) -> i32`
+    Fn(Vec<Type>, Box<Type>),
+    /// `dyn Trait` 或 `impl Trait`
+    Trait {
+        dynamic: bool,
+        bound: Box<Type>,
+    },
+    /// `!`
+    Never,
+    /// `_`
+    Infer,
+}
+
+impl Type {
+    pub fn unit() -> Self {
+        Type::Tuple(Vec::new())
+    }
+
+    pub fn named(name: &str) -> Self {
+        Type::Path {
+            name: name.to_string(),
+            args: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub pat: Pat,
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FnDecl {
+    pub name: String,
+    pub is_const: bool,
+    pub params: Vec<Param>,
+    /// 省略返回类型时为 `()`
+    pub ret: Type,
+    /// 返回类型的位置, 省略返回类型时为 `None`
+    pub ret_span: Option<Span>,
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Fn(FnDecl),
+    /// `const NAME: Type = expr;`
+    Const {
+        name: String,
+        ty: Type,
+        expr: Expr,
+        span: Span,
+    },
+    /// `static NAME: Type = expr;`
+    Static {
+        name: String,
+        mutable: bool,
+        ty: Type,
+        expr: Expr,
+        span: Span,
+    },
+}
+
+impl Item {
+    pub fn name(&self) -> &str {
+        match self {
+            Item::Fn(decl) => &decl.name,
+            Item::Const { name, .. } | Item::Static { name, .. } => name,
+        }
+    }
+}
+
+fn comma_separated<T: fmt::Display>(f: &mut fmt::Formatter, items: &[T]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Lit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Lit::Int { value, suffix } => write!(f, "{}{}", value, suffix.as_deref().unwrap_or("")),
+            Lit::Float { value, suffix } => {
+                write!(f, "{:?}{}", value, suffix.as_deref().unwrap_or(""))
+            }
+            Lit::Bool(b) => write!(f, "{}", b),
+            Lit::Str(s) => write!(f, "{:?}", s),
+            Lit::Char(c) => write!(f, "{:?}", c),
+        }
+    }
+}
+
+impl fmt::Display for UnOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            UnOp::Neg => "-",
+            UnOp::Not => "!",
+            UnOp::Deref => "*",
+        })
+    }
+}
+
+impl fmt::Display for BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ExprKind::Lit(lit) => write!(f, "{}", lit),
+            ExprKind::Path(segments) => f.write_str(&segments.join("::")),
+            ExprKind::Paren(expr) => write!(f, "({})", expr),
+            ExprKind::Unary(op, expr) => write!(f, "{}{}", op, expr),
+            ExprKind::Ref { mutable, expr } => {
+                write!(f, "&{}{}", if *mutable { "mut " } else { "" }, expr)
+            }
+            ExprKind::Binary(op, lhs, rhs) => write!(f, "{} {} {}", lhs, op, rhs),
+            ExprKind::Assign(lhs, rhs) => write!(f, "{} = {}", lhs, rhs),
+            ExprKind::AssignOp(op, lhs, rhs) => write!(f, "{} {}= {}", lhs, op, rhs),
+            ExprKind::Field(expr, field) => write!(f, "{}.{}", expr, field),
+            ExprKind::Index(expr, index) => write!(f, "{}[{}]", expr, index),
+            ExprKind::Call(callee, args) => {
+                write!(f, "{}(", callee)?;
+                comma_separated(f, args)?;
+                f.write_str(")")
+            }
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+            } => {
+                write!(f, "{}.{}(", receiver, method)?;
+                comma_separated(f, args)?;
+                f.write_str(")")
+            }
+            ExprKind::Macro { name, args } => match (name.as_str(), args.as_slice()) {
+                ("vec", [array]) => write!(f, "vec!{}", array),
+                _ => {
+                    write!(f, "{}!(", name)?;
+                    comma_separated(f, args)?;
+                    f.write_str(")")
+                }
+            },
+            ExprKind::Tuple(items) => {
+                f.write_str("(")?;
+                comma_separated(f, items)?;
+                if items.len() == 1 {
+                    f.write_str(",")?;
+                }
+                f.write_str(")")
+            }
+            ExprKind::Array(items) => {
+                f.write_str("[")?;
+                comma_separated(f, items)?;
+                f.write_str("]")
+            }
+            ExprKind::Repeat(value, len) => write!(f, "[{}; {}]", value, len),
+            ExprKind::Let(pat, expr) => write!(f, "let {} = {}", pat, expr),
+            ExprKind::Block(block) => write!(f, "{}", block),
+            ExprKind::If { cond, then, els } => {
+                write!(f, "if {} {}", cond, then)?;
+                match els {
+                    Some(els) => write!(f, " else {}", els),
+                    None => Ok(()),
+                }
+            }
+            ExprKind::While { cond, body } => write!(f, "while {} {}", cond, body),
+            ExprKind::Loop(body) => write!(f, "loop {}", body),
+            ExprKind::For { pat, iter, body } => write!(f, "for {} in {} {}", pat, iter, body),
+            ExprKind::Match { scrutinee, arms } => {
+                write!(f, "match {} {{ ", scrutinee)?;
+                for arm in arms {
+                    write!(f, "{}", arm.pat)?;
+                    if let Some(guard) = &arm.guard {
+                        write!(f, " if {}", guard)?;
+                    }
+                    write!(f, " => {}, ", arm.body)?;
+                }
+                f.write_str("}")
+            }
+            ExprKind::Break(value) => match value {
+                Some(value) => write!(f, "break {}", value),
+                None => f.write_str("break"),
+            },
+            ExprKind::Continue => f.write_str("continue"),
+            ExprKind::Return(value) => match value {
+                Some(value) => write!(f, "return {}", value),
+                None => f.write_str("return"),
+            },
+            ExprKind::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                if let Some(start) = start {
+                    write!(f, "{}", start)?;
+                }
+                f.write_str(if *inclusive { "..=" } else { ".." })?;
+                if let Some(end) = end {
+                    write!(f, "{}", end)?;
+                }
+                Ok(())
+            }
+            ExprKind::Cast(expr, ty) => write!(f, "{} as {}", expr, ty),
+        }
+    }
+}
+
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.stmts.is_empty() {
+            return f.write_str("{}");
+        }
+        f.write_str("{ ")?;
+        for stmt in &self.stmts {
+            write!(f, "{} ", stmt)?;
+        }
+        f.write_str("}")
+    }
+}
+
+impl fmt::Display for Stmt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            StmtKind::Let { pat, ty, init } => {
+                write!(f, "let {}", pat)?;
+                if let Some(ty) = ty {
+                    write!(f, ": {}", ty)?;
+                }
+                if let Some(init) = init {
+                    write!(f, " = {}", init)?;
+                }
+                f.write_str(";")
+            }
+            StmtKind::Expr(expr) => write!(f, "{}", expr),
+            StmtKind::Semi(expr) => write!(f, "{};", expr),
+            StmtKind::Item(item) => write!(f, "{}", item),
+            StmtKind::Use(expr) => write!(f, "use {};", expr),
+        }
+    }
+}
+
+impl fmt::Display for Pat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            PatKind::Wild => f.write_str("_"),
+            PatKind::Binding {
+                name,
+                by_ref,
+                mutable,
+                sub,
+            } => {
+                if *by_ref {
+                    f.write_str("ref ")?;
+                }
+                if *mutable {
+                    f.write_str("mut ")?;
+                }
+                f.write_str(name)?;
+                match sub {
+                    Some(sub) => write!(f, " @ {}", sub),
+                    None => Ok(()),
+                }
+            }
+            PatKind::Lit(expr) => write!(f, "{}", expr),
+            PatKind::Range {
+                start,
+                end,
+                inclusive,
+            } => write!(
+                f,
+                "{}{}{}",
+                start,
+                if *inclusive { "..=" } else { ".." },
+                end
+            ),
+            PatKind::Tuple(pats) => {
+                f.write_str("(")?;
+                comma_separated(f, pats)?;
+                if pats.len() == 1 {
+                    f.write_str(",")?;
+                }
+                f.write_str(")")
+            }
+            PatKind::Path(path) => f.write_str(&path.join("::")),
+            PatKind::TupleStruct(path, pats) => {
+                write!(f, "{}(", path.join("::"))?;
+                comma_separated(f, pats)?;
+                f.write_str(")")
+            }
+            PatKind::Ref { mutable, pat } => {
+                write!(f, "&{}{}", if *mutable { "mut " } else { "" }, pat)
+            }
+            PatKind::Or(pats) => {
+                for (i, pat) in pats.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" | ")?;
+                    }
+                    write!(f, "{}", pat)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Path { name, args } => {
+                f.write_str(name)?;
+                if !args.is_empty() {
+                    f.write_str("<")?;
+                    comma_separated(f, args)?;
+                    f.write_str(">")?;
+                }
+                Ok(())
+            }
+            Type::Ref {
+                lifetime,
+                mutable,
+                inner,
+            } => {
+                f.write_str("&")?;
+                if let Some(lifetime) = lifetime {
+                    write!(f, "{} ", lifetime)?;
+                }
+                if *mutable {
+                    f.write_str("mut ")?;
+                }
+                write!(f, "{}", inner)
+            }
+            Type::Tuple(items) => {
+                f.write_str("(")?;
+                comma_separated(f, items)?;
+                if items.len() == 1 {
+                    f.write_str(",")?;
+                }
+                f.write_str(")")
+            }
+            Type::Array(elem, len) => write!(f, "[{}; {}]", elem, len),
+            Type::Slice(elem) => write!(f, "[{}]", elem),
+            Type::Fn(params, ret) => {
+                f.write_str("fn(")?;
+                comma_separated(f, params)?;
+                f.write_str(")")?;
+                if **ret != Type::unit() {
+                    write!(f, " -> {}", ret)?;
+                }
+                Ok(())
+            }
+            Type::Trait { dynamic, bound } => {
+                f.write_str(if *dynamic { "dyn " } else { "impl " })?;
+                match &**bound {
+                    // 闭包 trait 的语法糖
+                    Type::Fn(params, ret) => {
+                        f.write_str("Fn(")?;
+                        comma_separated(f, params)?;
+                        f.write_str(")")?;
+                        if **ret != Type::unit() {
+                            write!(f, " -> {}", ret)?;
+                        }
+                        Ok(())
+                    }
+                    bound => write!(f, "{}", bound),
+                }
+            }
+            Type::Never => f.write_str("!"),
+            Type::Infer => f.write_str("_"),
+        }
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.pat, self.ty)
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Item::Fn(decl) => {
+                if decl.is_const {
+                    f.write_str("const ")?;
+                }
+                write!(f, "fn {}(", decl.name)?;
+                comma_separated(f, &decl.params)?;
+                f.write_str(")")?;
+                if decl.ret != Type::unit() {
+                    write!(f, " -> {}", decl.ret)?;
+                }
+                write!(f, " {}", decl.body)
+            }
+            Item::Const { name, ty, expr, .. } => write!(f, "const {}: {} = {};", name, ty, expr),
+            Item::Static {
+                name,
+                mutable,
+                ty,
+                expr,
+                ..
+            } => write!(
+                f,
+                "static {}{}: {} = {};",
+                if *mutable { "mut " } else { "" },
+                name,
+                ty,
+                expr
+            ),
+        }
+    }
+}
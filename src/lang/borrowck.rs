@@ -0,0 +1,1162 @@
+//! # 借用检查
+//!
+//! 对 [`ch02::binding::reference`](crate::ch02::binding::reference) 中 `&` 与 `&mut` 的借用做一个
+//! 简化的借用检查: 同一时刻, 一个位置要么只有若干个共享借用, 要么只有一个可变借用(aliasing XOR mutability)
+//!
+//! 借用的存活范围有两种算法, 可以切换对比:
+//!
+//! - [`Mode::Lexical`] 模式: 借用一直持续到持有它的绑定离开作用域(Rust 2018 之前的行为)
+//! - [`Mode::NonLexical`] 模式: 借用只持续到持有它的绑定最后一次被使用, 或者被重新赋值为止(NLL)
+//!
+//! 此外还模拟了再借用(`&mut *r` 以及把引用赋给另一个绑定)、方法调用的两阶段借用(`v.push(v.len())`),
+//! 以及被借用的值先于引用离开作用域的情况(E0597). `use x;` 是一条伪语句, 表示使用一次 `x`
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::ast::{BinOp, Block, Expr, ExprKind, Item, Lit, Pat, Stmt, StmtKind, Type, UnOp};
+use super::diagnostic::Diagnostic;
+use super::lexer::Span;
+use super::ownership::is_copy;
+use super::parser::{parse_stmts, ParseError};
+
+/// 会修改接收者的方法, 调用时需要可变借用接收者
+const MUTATING_METHODS: [&str; 10] = [
+    "push", "push_str", "pop", "insert", "remove", "clear", "truncate", "sort", "extend", "append",
+];
+
+/// 返回值借用了接收者的方法, 例如 `let first = v.first();`
+const BORROWING_METHODS: [&str; 14] = [
+    "iter",
+    "iter_mut",
+    "first",
+    "last",
+    "get",
+    "get_mut",
+    "as_str",
+    "as_slice",
+    "as_mut_slice",
+    "as_ref",
+    "as_mut",
+    "chars",
+    "trim",
+    "lines",
+];
+
+/// 会借用参数的格式化宏
+const FORMAT_MACROS: [&str; 11] = [
+    "println",
+    "print",
+    "eprintln",
+    "eprint",
+    "format",
+    "write",
+    "writeln",
+    "assert",
+    "assert_eq",
+    "assert_ne",
+    "panic",
+];
+
+/// 借用的存活范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// 词法作用域: 借用持续到持有者离开作用域
+    Lexical,
+    /// 非词法作用域(NLL): 借用持续到持有者最后一次被使用
+    NonLexical,
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Mode::Lexical => f.write_str("lexical"),
+            Mode::NonLexical => f.write_str("NLL"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Proj {
+    Deref,
+    Field(String),
+    Index,
+}
+
+/// 被访问的位置: 本地变量加上解引用、字段与索引
+#[derive(Debug, Clone, PartialEq)]
+struct Place {
+    /// 本地变量的声明位置
+    root: Span,
+    name: String,
+    projs: Vec<Proj>,
+}
+
+impl Place {
+    /// 两个位置是否可能指向同一块内存: 一个是另一个的前缀, 不同的索引视为重叠
+    fn overlaps(&self, other: &Place) -> bool {
+        self.root == other.root && self.projs.iter().zip(&other.projs).all(|(a, b)| a == b)
+    }
+
+    fn deref(&self) -> Place {
+        let mut place = self.clone();
+        place.projs.push(Proj::Deref);
+        place
+    }
+}
+
+impl fmt::Display for Place {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut text = self.name.clone();
+        for proj in &self.projs {
+            text = match proj {
+                Proj::Deref => format!("*{}", text),
+                Proj::Field(field) if text.starts_with('*') => format!("({}).{}", text, field),
+                Proj::Field(field) => format!("{}.{}", text, field),
+                Proj::Index => format!("{}[_]", text),
+            };
+        }
+        f.write_str(&text)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    /// 读取或复制
+    Read,
+    Move,
+    Write,
+    Borrow {
+        mutable: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Loan {
+    id: usize,
+    place: Place,
+    mutable: bool,
+    span: Span,
+    /// 持有这个借用的绑定(以声明位置区分); 临时借用没有持有者
+    holders: Vec<Span>,
+    temporary: bool,
+}
+
+impl Loan {
+    fn kind(&self) -> &'static str {
+        if self.mutable {
+            "mutable"
+        } else {
+            "immutable"
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Local {
+    name: String,
+    decl: Span,
+    is_ref: bool,
+    copy: bool,
+}
+
+/// 检查过程中创建的一个借用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Borrow {
+    pub span: Span,
+    /// 被借用的位置, 例如 `c` 或 `*r`
+    pub place: String,
+    pub mutable: bool,
+    /// 持有借用的绑定, 临时借用为 `None`
+    pub holder: Option<String>,
+    /// 借用在哪里结束: NLL 中是持有者最后一次使用的位置, 词法作用域中是持有者所在块的结尾
+    pub until: Option<Span>,
+}
+
+impl fmt::Display for Borrow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mutable = if self.mutable { "mut " } else { "" };
+        write!(
+            f,
+            "{:>5}  &{}{}",
+            self.span.to_string(),
+            mutable,
+            self.place
+        )?;
+        match (&self.holder, self.until) {
+            (Some(holder), Some(until)) => write!(f, " -> {}, 直到 {}", holder, until),
+            (Some(holder), None) => write!(f, " -> {}, 没有被使用", holder),
+            (None, _) => f.write_str(" (临时)"),
+        }
+    }
+}
+
+/// 对绑定整体的一次赋值 `x = rhs`
+#[derive(Debug, Clone)]
+struct Overwrite {
+    /// 开始求值右侧之前的程序点
+    start: usize,
+    /// 写入发生的程序点, 右侧对旧值的使用都在它之前
+    end: usize,
+    /// 赋值所在的分支
+    regions: Vec<usize>,
+}
+
+struct Checker {
+    mode: Mode,
+    /// 第一遍只记录每个绑定在哪里被使用和覆盖, 第二遍才检查冲突
+    checking: bool,
+    /// 程序点: 每次访问加一, 两遍遍历得到相同的编号
+    point: usize,
+    locals: Vec<Local>,
+    /// 每个作用域开始时 `locals` 的长度, 以及作用域结束的位置
+    scopes: Vec<(usize, Span)>,
+    uses: HashMap<Span, Vec<(usize, Span)>>,
+    overwrites: HashMap<Span, Vec<Overwrite>>,
+    /// `break`、`continue` 与 `return` 所在的程序点
+    jumps: Vec<usize>,
+    /// 当前所在的条件分支与循环体, 以进入时的程序点区分
+    regions: Vec<usize>,
+    loans: Vec<Loan>,
+    next_loan: usize,
+    /// 正在求值的表达式产生的借用由谁持有
+    escape: Option<Span>,
+    /// 持有者的名字, 持有者还没有被声明时(`let r = &a;`)用于输出
+    pending: Option<String>,
+    borrows: Vec<Borrow>,
+    diagnostics: Vec<Diagnostic>,
+    second_iteration: bool,
+}
+
+impl Checker {
+    fn new(mode: Mode) -> Self {
+        Checker {
+            mode,
+            checking: false,
+            point: 0,
+            locals: Vec::new(),
+            scopes: Vec::new(),
+            uses: HashMap::new(),
+            overwrites: HashMap::new(),
+            jumps: Vec::new(),
+            regions: Vec::new(),
+            loans: Vec::new(),
+            next_loan: 0,
+            escape: None,
+            pending: None,
+            borrows: Vec::new(),
+            diagnostics: Vec::new(),
+            second_iteration: false,
+        }
+    }
+
+    /// 开始第二遍遍历
+    fn restart(&mut self) {
+        self.checking = true;
+        self.point = 0;
+        self.locals.clear();
+        self.scopes.clear();
+        self.loans.clear();
+        self.next_loan = 0;
+    }
+
+    fn local(&self, name: &str) -> Option<&Local> {
+        self.locals.iter().rev().find(|l| l.name == name)
+    }
+
+    fn place_of(&self, expr: &Expr) -> Option<Place> {
+        match &expr.kind {
+            ExprKind::Path(path) if path.len() == 1 => self.local(&path[0]).map(|local| Place {
+                root: local.decl,
+                name: local.name.clone(),
+                projs: Vec::new(),
+            }),
+            ExprKind::Paren(inner) => self.place_of(inner),
+            ExprKind::Unary(UnOp::Deref, inner) => self.place_of(inner).map(|p| p.deref()),
+            ExprKind::Field(base, field) => self.place_of(base).map(|mut p| {
+                p.projs.push(Proj::Field(field.clone()));
+                p
+            }),
+            ExprKind::Index(base, _) => self.place_of(base).map(|mut p| {
+                p.projs.push(Proj::Index);
+                p
+            }),
+            _ => None,
+        }
+    }
+
+    fn is_ref(&self, place: &Place) -> bool {
+        place.projs.is_empty()
+            && self
+                .locals
+                .iter()
+                .rev()
+                .find(|l| l.decl == place.root)
+                .is_some_and(|l| l.is_ref)
+    }
+
+    /// 位置中的索引表达式本身也要求值
+    fn index_operands(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Paren(inner) | ExprKind::Unary(_, inner) | ExprKind::Field(inner, _) => {
+                self.index_operands(inner)
+            }
+            ExprKind::Index(base, index) => {
+                self.index_operands(base);
+                self.nested(|c| c.expr(index));
+            }
+            _ => {}
+        }
+    }
+
+    // ---------------------------------------------------------------- 借用的存活范围
+
+    /// 程序点 `after` 之后一定会执行到的覆盖: 与 `after` 在同一个分支或外层分支中, 且两者之间没有跳转
+    fn overwritten(&self, holder: Span, after: usize) -> impl Iterator<Item = &Overwrite> {
+        self.overwrites
+            .get(&holder)
+            .into_iter()
+            .flatten()
+            .filter(move |w| {
+                w.start >= after
+                    && self.regions.starts_with(&w.regions)
+                    && !self.jumps.iter().any(|j| (after..=w.start).contains(j))
+            })
+    }
+
+    /// NLL: 持有者在程序点 `after` 之后、被整体覆盖之前的最后一次使用.
+    /// 覆盖之后的使用读到的是新的值, 与旧值持有的借用无关
+    fn live_use(&self, holder: Span, after: usize) -> Option<(usize, Span)> {
+        let killed = self.overwritten(holder, after).map(|w| w.end).min();
+        self.uses
+            .get(&holder)
+            .into_iter()
+            .flatten()
+            .filter(|(point, _)| *point >= after && killed.is_none_or(|end| *point < end))
+            .max()
+            .copied()
+    }
+
+    fn holder_live(&self, holder: Span, after: usize) -> bool {
+        match self.mode {
+            Mode::Lexical => true,
+            Mode::NonLexical => self.live_use(holder, after).is_some(),
+        }
+    }
+
+    fn is_live(&self, loan: &Loan) -> bool {
+        loan.temporary
+            || loan
+                .holders
+                .iter()
+                .any(|h| self.holder_live(*h, self.point))
+    }
+
+    /// 借用在哪里结束
+    fn until(&self, holders: &[Span]) -> Option<Span> {
+        match self.mode {
+            Mode::Lexical => holders.iter().filter_map(|h| self.scope_end(*h)).max(),
+            Mode::NonLexical => holders
+                .iter()
+                .filter_map(|h| self.live_use(*h, self.point))
+                .max()
+                .map(|(_, span)| span),
+        }
+    }
+
+    fn scope_end(&self, holder: Span) -> Option<Span> {
+        match self.locals.iter().rposition(|l| l.decl == holder) {
+            Some(index) => self
+                .scopes
+                .iter()
+                .rev()
+                .find(|(start, _)| *start <= index)
+                .map(|(_, end)| *end),
+            // 持有者还没有被声明, 它将属于当前作用域
+            None => self.scopes.last().map(|(_, end)| *end),
+        }
+    }
+
+    /// 冲突诊断中指出借用后来在哪里还会被用到
+    fn later_use(&self, loan: &Loan, diagnostic: Diagnostic, what: &str) -> Diagnostic {
+        if loan.temporary {
+            return diagnostic;
+        }
+        match (self.mode, self.until(&loan.holders)) {
+            (Mode::Lexical, Some(end)) => diagnostic.with_note(end, &format!("{} ends here", what)),
+            (Mode::NonLexical, Some(at)) => {
+                diagnostic.with_note(at, &format!("{} later used here", what))
+            }
+            (_, None) => diagnostic,
+        }
+    }
+
+    fn report(&mut self, diagnostic: Diagnostic) {
+        let duplicate = self
+            .diagnostics
+            .iter()
+            .any(|d| d.code == diagnostic.code && d.span == diagnostic.span);
+        if !duplicate {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    // ---------------------------------------------------------------- 访问
+
+    fn access(&mut self, place: &Place, span: Span, access: Access) {
+        self.point += 1;
+        if !self.checking {
+            // 给本地变量整体赋值不算使用它
+            if access != Access::Write || !place.projs.is_empty() {
+                self.uses
+                    .entry(place.root)
+                    .or_default()
+                    .push((self.point, span));
+            }
+            return;
+        }
+        let conflict = self
+            .loans
+            .iter()
+            .find(|loan| loan.place.overlaps(place) && self.is_live(loan))
+            .cloned();
+        if let Some(loan) = conflict {
+            self.conflict(&loan, place, span, access);
+        }
+        if let Access::Borrow { mutable } = access {
+            self.create_loan(place.clone(), mutable, span);
+        }
+    }
+
+    fn conflict(&mut self, loan: &Loan, place: &Place, span: Span, access: Access) {
+        let borrowed = format!("`{}` is borrowed here", loan.place);
+        let diagnostic = match access {
+            Access::Read if loan.mutable => Diagnostic::error(
+                "E0503",
+                span,
+                format!("cannot use `{}` because it was mutably borrowed", place),
+            )
+            .with_label(&format!("use of borrowed `{}`", loan.place))
+            .with_note(loan.span, &borrowed),
+            Access::Read => return,
+            Access::Move => Diagnostic::error(
+                "E0505",
+                span,
+                format!("cannot move out of `{}` because it is borrowed", place),
+            )
+            .with_label(&format!("move out of `{}` occurs here", place))
+            .with_note(
+                loan.span,
+                &format!("borrow of `{}` occurs here", loan.place),
+            ),
+            Access::Write => Diagnostic::error(
+                "E0506",
+                span,
+                format!("cannot assign to `{}` because it is borrowed", place),
+            )
+            .with_label(&format!(
+                "`{}` is assigned to here but it was already borrowed",
+                place
+            ))
+            .with_note(loan.span, &borrowed),
+            Access::Borrow { mutable: true } if loan.mutable => {
+                let diagnostic = Diagnostic::error(
+                    "E0499",
+                    span,
+                    format!(
+                        "cannot borrow `{}` as mutable more than once at a time",
+                        place
+                    ),
+                )
+                .with_label("second mutable borrow occurs here")
+                .with_note(loan.span, "first mutable borrow occurs here");
+                let diagnostic = self.later_use(loan, diagnostic, "first borrow");
+                self.report(diagnostic);
+                return;
+            }
+            Access::Borrow { mutable: false } if !loan.mutable => return,
+            Access::Borrow { mutable } => {
+                let (kind, other) = if mutable {
+                    ("mutable", "immutable")
+                } else {
+                    ("immutable", "mutable")
+                };
+                let diagnostic = Diagnostic::error(
+                    "E0502",
+                    span,
+                    format!(
+                        "cannot borrow `{}` as {} because it is also borrowed as {}",
+                        place, kind, other
+                    ),
+                )
+                .with_label(&format!("{} borrow occurs here", kind))
+                .with_note(loan.span, &format!("{} borrow occurs here", other));
+                let what = format!("{} borrow", loan.kind());
+                let diagnostic = self.later_use(loan, diagnostic, &what);
+                self.report(diagnostic);
+                return;
+            }
+        };
+        let diagnostic = self.later_use(loan, diagnostic, "borrow");
+        self.report(diagnostic);
+    }
+
+    fn create_loan(&mut self, place: Place, mutable: bool, span: Span) {
+        let holders: Vec<Span> = self.escape.into_iter().collect();
+        if !self.second_iteration {
+            let holder = holders.first().and_then(|h| {
+                self.locals
+                    .iter()
+                    .rev()
+                    .find(|l| l.decl == *h)
+                    .map(|l| l.name.clone())
+                    .or_else(|| self.pending.clone())
+            });
+            self.borrows.push(Borrow {
+                span,
+                place: place.to_string(),
+                mutable,
+                holder,
+                until: self.until(&holders),
+            });
+        }
+        self.loans.push(Loan {
+            id: self.next_loan,
+            place,
+            mutable,
+            span,
+            temporary: holders.is_empty(),
+            holders,
+        });
+        self.next_loan += 1;
+    }
+
+    /// 再借用: 通过引用 `r` 得到的新引用同样持有 `r` 的借用
+    fn derive(&mut self, place: &Place) {
+        if let (true, Some(holder)) = (self.checking, self.escape) {
+            for loan in &mut self.loans {
+                if loan.holders.contains(&place.root) && !loan.holders.contains(&holder) {
+                    loan.holders.push(holder);
+                }
+            }
+        }
+    }
+
+    /// 在不把借用交给持有者的上下文中求值, 例如函数参数
+    fn nested<F: FnOnce(&mut Self)>(&mut self, f: F) {
+        let escape = self.escape.take();
+        f(self);
+        self.escape = escape;
+    }
+
+    /// 求值结束后, 其中产生的临时借用随之结束
+    fn temporaries<F: FnOnce(&mut Self)>(&mut self, f: F) {
+        let mark = self.next_loan;
+        f(self);
+        self.loans.retain(|l| !l.temporary || l.id < mark);
+    }
+
+    fn with_escape<F: FnOnce(&mut Self)>(&mut self, holder: Option<Span>, f: F) {
+        let escape = std::mem::replace(&mut self.escape, holder);
+        f(self);
+        self.escape = escape;
+    }
+
+    // ---------------------------------------------------------------- 作用域
+
+    fn push_scope(&mut self, end: Span) {
+        self.scopes.push((self.locals.len(), end));
+    }
+
+    fn pop_scope(&mut self) {
+        let (start, end) = match self.scopes.pop() {
+            Some(scope) => scope,
+            None => return,
+        };
+        let dying: Vec<Local> = self.locals.split_off(start);
+        if !self.checking {
+            return;
+        }
+        let dies = |span: &Span| dying.iter().any(|l| l.decl == *span);
+        for local in &dying {
+            // 通过引用再借用的是它指向的值(`&mut *r`), 引用本身离开作用域不影响这个借用
+            let reborrow =
+                |place: &Place| local.is_ref && place.projs.first() == Some(&Proj::Deref);
+            let escaped = self.loans.iter().find(|loan| {
+                loan.place.root == local.decl
+                    && !reborrow(&loan.place)
+                    && loan
+                        .holders
+                        .iter()
+                        .any(|h| !dies(h) && self.holder_live(*h, self.point + 1))
+            });
+            if let Some(loan) = escaped {
+                let mut diagnostic = Diagnostic::error(
+                    "E0597",
+                    loan.span,
+                    format!("`{}` does not live long enough", local.name),
+                )
+                .with_label("borrowed value does not live long enough")
+                .with_note(
+                    end,
+                    &format!("`{}` dropped here while still borrowed", local.name),
+                );
+                if self.mode == Mode::NonLexical {
+                    let holders: Vec<Span> =
+                        loan.holders.iter().copied().filter(|h| !dies(h)).collect();
+                    if let Some(at) = self.until(&holders) {
+                        diagnostic = diagnostic.with_note(at, "borrow later used here");
+                    }
+                }
+                self.report(diagnostic);
+            }
+        }
+        self.loans.retain(|loan| !dies(&loan.place.root));
+        for loan in &mut self.loans {
+            loan.holders.retain(|h| !dies(h));
+        }
+        self.loans
+            .retain(|loan| loan.temporary || !loan.holders.is_empty());
+    }
+
+    fn declare(&mut self, pat: &Pat, is_ref: bool, copy: bool) {
+        for binding in pat.bindings() {
+            if let super::ast::PatKind::Binding { name, .. } = &binding.kind {
+                self.locals.push(Local {
+                    name: name.clone(),
+                    decl: binding.span,
+                    is_ref,
+                    copy,
+                });
+            }
+        }
+    }
+
+    /// 表达式的值是否是引用, 以及是否可以复制
+    fn value_kind(&self, expr: &Expr) -> (bool, bool) {
+        match &expr.kind {
+            ExprKind::Lit(Lit::Str(_)) => (true, true),
+            ExprKind::Lit(_) | ExprKind::Cast(..) => (false, true),
+            ExprKind::Ref { mutable, .. } => (true, !mutable),
+            ExprKind::Paren(inner) => self.value_kind(inner),
+            ExprKind::Unary(UnOp::Deref, _) => (false, false),
+            ExprKind::Unary(_, inner) => self.value_kind(inner),
+            ExprKind::Binary(op, lhs, _) if !op.is_comparison() => self.value_kind(lhs),
+            ExprKind::Binary(..) => (false, true),
+            ExprKind::Path(path) if path.len() == 1 => self
+                .local(&path[0])
+                .map_or((false, false), |l| (l.is_ref, l.copy)),
+            ExprKind::MethodCall { method, .. } => match method.as_str() {
+                "len" | "is_empty" | "contains" => (false, true),
+                m if BORROWING_METHODS.contains(&m) => (true, !m.ends_with("_mut")),
+                _ => (false, false),
+            },
+            ExprKind::Block(block) => block.tail().map_or((false, true), |t| self.value_kind(t)),
+            _ => (false, false),
+        }
+    }
+
+    // ---------------------------------------------------------------- 语句
+
+    fn block(&mut self, block: &Block) {
+        self.push_scope(block.end);
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+        self.pop_scope();
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Let { pat, ty, init } => {
+                let holder = pat.bindings().first().map(|b| b.span);
+                if let Some(init) = init {
+                    self.pending = binding_name(pat);
+                    self.temporaries(|c| c.with_escape(holder, |c| c.expr(init)));
+                    self.pending = None;
+                }
+                let (is_ref, copy) = match (ty, init) {
+                    (Some(ty), _) => (matches!(ty, Type::Ref { .. }), is_copy(ty)),
+                    (None, Some(init)) => self.value_kind(init),
+                    (None, None) => (false, false),
+                };
+                self.declare(pat, is_ref, copy);
+            }
+            StmtKind::Expr(expr) | StmtKind::Semi(expr) => {
+                self.temporaries(|c| c.with_escape(None, |c| c.expr(expr)))
+            }
+            StmtKind::Use(expr) => self.temporaries(|c| match c.place_of(expr) {
+                Some(place) => {
+                    c.index_operands(expr);
+                    c.access(&place, expr.span, Access::Read);
+                }
+                None => c.expr(expr),
+            }),
+            StmtKind::Item(Item::Fn(decl)) => {
+                // 函数体不能访问外部的绑定
+                let locals = std::mem::take(&mut self.locals);
+                let scopes = std::mem::take(&mut self.scopes);
+                let loans = std::mem::take(&mut self.loans);
+                self.push_scope(decl.body.end);
+                for param in &decl.params {
+                    let is_ref = matches!(param.ty, Type::Ref { .. });
+                    self.declare(&param.pat, is_ref, is_copy(&param.ty));
+                }
+                self.block(&decl.body);
+                self.pop_scope();
+                self.locals = locals;
+                self.scopes = scopes;
+                self.loans = loans;
+            }
+            StmtKind::Item(_) => {}
+        }
+    }
+
+    // ---------------------------------------------------------------- 表达式
+
+    /// 在值上下文中求值
+    fn expr(&mut self, expr: &Expr) {
+        if let Some(place) = self.place_of(expr) {
+            self.index_operands(expr);
+            let copy = !place.projs.is_empty()
+                || self
+                    .locals
+                    .iter()
+                    .rev()
+                    .find(|l| l.decl == place.root)
+                    .is_some_and(|l| l.copy);
+            let access = if copy { Access::Read } else { Access::Move };
+            self.access(&place, expr.span, access);
+            self.derive(&place);
+            return;
+        }
+        match &expr.kind {
+            ExprKind::Lit(_) | ExprKind::Path(_) => {}
+            ExprKind::Continue => self.jump(),
+            ExprKind::Paren(inner) => self.expr(inner),
+            ExprKind::Ref {
+                mutable,
+                expr: inner,
+            } => self.borrow(inner, *mutable, expr.span),
+            ExprKind::Unary(_, inner) | ExprKind::Cast(inner, _) => self.nested(|c| c.expr(inner)),
+            ExprKind::Field(base, _) | ExprKind::Index(base, _) => self.expr(base),
+            ExprKind::Binary(op, lhs, rhs) => self.nested(|c| {
+                if op.is_comparison() {
+                    c.borrow(lhs, false, lhs.span);
+                    c.borrow(rhs, false, rhs.span);
+                } else if matches!(op, BinOp::And | BinOp::Or) {
+                    // 短路求值: 右侧不一定会执行
+                    c.expr(lhs);
+                    c.branch(|c| c.expr(rhs));
+                } else {
+                    c.expr(lhs);
+                    c.expr(rhs);
+                }
+            }),
+            ExprKind::Assign(lhs, rhs) => self.assign(lhs, rhs),
+            ExprKind::AssignOp(_, lhs, rhs) => {
+                self.nested(|c| c.expr(rhs));
+                match self.place_of(lhs) {
+                    Some(place) => {
+                        self.index_operands(lhs);
+                        self.access(&place, lhs.span, Access::Read);
+                        self.access(&place, lhs.span, Access::Write);
+                    }
+                    None => self.expr(lhs),
+                }
+            }
+            ExprKind::Call(callee, args) => self.nested(|c| {
+                c.temporaries(|c| {
+                    c.expr(callee);
+                    for arg in args {
+                        c.expr(arg);
+                    }
+                })
+            }),
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+            } => self.method_call(receiver, method, args),
+            ExprKind::Macro { name, args } => self.nested(|c| {
+                c.temporaries(|c| {
+                    for arg in args {
+                        if FORMAT_MACROS.contains(&name.as_str()) {
+                            c.borrow(arg, false, arg.span);
+                        } else {
+                            c.expr(arg);
+                        }
+                    }
+                })
+            }),
+            ExprKind::Tuple(items) | ExprKind::Array(items) => {
+                for item in items {
+                    self.expr(item);
+                }
+            }
+            ExprKind::Repeat(item, _) => self.expr(item),
+            ExprKind::Let(pat, scrutinee) => {
+                let holder = pat.bindings().first().map(|b| b.span);
+                self.with_escape(holder, |c| c.expr(scrutinee));
+                let (is_ref, copy) = self.value_kind(scrutinee);
+                self.declare(pat, is_ref, copy);
+            }
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::If { cond, then, els } => {
+                self.push_scope(then.end);
+                self.nested(|c| c.expr(cond));
+                let before = self.loans.clone();
+                self.branch(|c| c.block(then));
+                let after_then = std::mem::replace(&mut self.loans, before);
+                if let Some(els) = els {
+                    self.branch(|c| c.expr(els));
+                }
+                self.merge(after_then);
+                self.pop_scope();
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                self.nested(|c| c.expr(scrutinee));
+                let before = self.loans.clone();
+                let mut merged = Vec::new();
+                for arm in arms {
+                    self.loans = before.clone();
+                    self.branch(|c| {
+                        c.push_scope(arm.body.span);
+                        c.declare(&arm.pat, false, false);
+                        if let Some(guard) = &arm.guard {
+                            c.nested(|c| c.expr(guard));
+                        }
+                        c.expr(&arm.body);
+                        c.pop_scope();
+                    });
+                    self.merge(std::mem::take(&mut merged));
+                    merged = self.loans.clone();
+                }
+            }
+            ExprKind::While { cond, body } => self.repeat(|c| {
+                c.push_scope(body.end);
+                c.nested(|c| c.temporaries(|c| c.expr(cond)));
+                c.block(body);
+                c.pop_scope();
+            }),
+            ExprKind::Loop(body) => self.repeat(|c| c.block(body)),
+            ExprKind::For { pat, iter, body } => {
+                // 迭代器是一个隐藏的临时值, 它持有的借用贯穿整个循环
+                let iterator = iter.span;
+                self.pending = Some("for".to_string());
+                self.with_escape(Some(iterator), |c| c.expr(iter));
+                self.pending = None;
+                self.repeat(|c| {
+                    c.push_scope(body.end);
+                    c.declare(pat, false, true);
+                    c.block(body);
+                    c.pop_scope();
+                });
+                self.point += 1;
+                if self.checking {
+                    for loan in &mut self.loans {
+                        loan.holders.retain(|h| *h != iterator);
+                    }
+                    self.loans
+                        .retain(|loan| loan.temporary || !loan.holders.is_empty());
+                } else {
+                    self.uses
+                        .entry(iterator)
+                        .or_default()
+                        .push((self.point, iter.span));
+                }
+            }
+            ExprKind::Break(value) | ExprKind::Return(value) => {
+                if let Some(value) = value {
+                    self.nested(|c| c.expr(value));
+                }
+                self.jump();
+            }
+            ExprKind::Range { start, end, .. } => {
+                for bound in start.iter().chain(end) {
+                    self.expr(bound);
+                }
+            }
+        }
+    }
+
+    /// `&expr` 或 `&mut expr`
+    fn borrow(&mut self, target: &Expr, mutable: bool, span: Span) {
+        match self.place_of(target) {
+            Some(place) => {
+                self.index_operands(target);
+                self.access(&place, span, Access::Borrow { mutable });
+                self.derive(&place);
+            }
+            // 借用值表达式时会创建临时值
+            None => self.expr(target),
+        }
+    }
+
+    fn assign(&mut self, lhs: &Expr, rhs: &Expr) {
+        let place = self.place_of(lhs);
+        let holder = place
+            .as_ref()
+            .filter(|p| p.projs.is_empty())
+            .map(|p| p.root);
+        // 旧值持有的借用. 右侧的再借用(`s = &mut *r`)会让 `s` 成为更早的借用的持有者, 不能一并结束
+        let old: Vec<usize> = self
+            .loans
+            .iter()
+            .filter(|l| holder.is_some_and(|h| l.holders.contains(&h)))
+            .map(|l| l.id)
+            .collect();
+        let start = self.point;
+        self.pending = place.as_ref().map(|p| p.name.clone());
+        self.with_escape(holder, |c| c.expr(rhs));
+        self.pending = None;
+        match place {
+            Some(place) => {
+                self.index_operands(lhs);
+                if let (Some(holder), Mode::NonLexical, true) = (holder, self.mode, self.checking) {
+                    // NLL: 重新赋值之后, 旧值持有的借用随之结束
+                    for loan in self.loans.iter_mut().filter(|l| old.contains(&l.id)) {
+                        loan.holders.retain(|h| *h != holder);
+                    }
+                    self.loans
+                        .retain(|loan| loan.temporary || !loan.holders.is_empty());
+                }
+                if holder.is_some() {
+                    let (is_ref, copy) = self.value_kind(rhs);
+                    if let Some(local) = self
+                        .locals
+                        .iter_mut()
+                        .rev()
+                        .find(|l| Some(l.decl) == holder)
+                    {
+                        local.is_ref |= is_ref;
+                        local.copy = copy;
+                    }
+                }
+                self.access(&place, lhs.span, Access::Write);
+                if let (Some(holder), false) = (holder, self.checking) {
+                    let overwrite = Overwrite {
+                        start,
+                        end: self.point,
+                        regions: self.regions.clone(),
+                    };
+                    self.overwrites.entry(holder).or_default().push(overwrite);
+                }
+            }
+            None => self.expr(lhs),
+        }
+    }
+
+    fn method_call(&mut self, receiver: &Expr, method: &str, args: &[Expr]) {
+        let mutable = MUTATING_METHODS.contains(&method) || method.ends_with("_mut");
+        let holder = if BORROWING_METHODS.contains(&method) {
+            self.escape
+        } else {
+            None
+        };
+        let place = self.place_of(receiver).map(|place| {
+            // 自动引用: 接收者本身是引用时, 借用的是它指向的值
+            if self.is_ref(&place) {
+                place.deref()
+            } else {
+                place
+            }
+        });
+        let receive = |c: &mut Self| match &place {
+            Some(place) => {
+                c.index_operands(receiver);
+                c.with_escape(holder, |c| {
+                    c.access(place, receiver.span, Access::Borrow { mutable });
+                    c.derive(place);
+                });
+            }
+            None => c.nested(|c| c.expr(receiver)),
+        };
+        let arguments = |c: &mut Self| {
+            c.nested(|c| {
+                for arg in args {
+                    c.expr(arg);
+                }
+            })
+        };
+        self.temporaries(|c| match c.mode {
+            // 两阶段借用: 先求值参数, 再激活对接收者的可变借用
+            Mode::NonLexical => {
+                arguments(c);
+                receive(c);
+            }
+            Mode::Lexical => {
+                receive(c);
+                arguments(c);
+            }
+        });
+    }
+
+    /// 合并两个分支之后的借用
+    fn merge(&mut self, other: Vec<Loan>) {
+        for loan in other {
+            if !self.loans.iter().any(|l| l.id == loan.id) {
+                self.loans.push(loan);
+            }
+        }
+    }
+
+    /// 在条件分支中求值, 其中的赋值不一定会执行
+    fn branch<F: FnOnce(&mut Self)>(&mut self, f: F) {
+        self.regions.push(self.point);
+        f(self);
+        self.regions.pop();
+    }
+
+    fn jump(&mut self) {
+        if !self.checking {
+            self.jumps.push(self.point);
+        }
+    }
+
+    /// 循环体: 第一遍延长循环外绑定的使用范围, 第二遍把循环体执行两次
+    fn repeat<F: Fn(&mut Self)>(&mut self, body: F) {
+        let start = self.point;
+        let outer = self.locals.len();
+        self.regions.push(start);
+        body(self);
+        if !self.checking {
+            let end = self.point;
+            for local in &self.locals[..outer] {
+                // 下一轮迭代最先到达的是循环体中的第一次使用
+                let first = self.uses.get(&local.decl).and_then(|uses| {
+                    uses.iter()
+                        .filter(|(point, _)| *point > start)
+                        .min()
+                        .copied()
+                });
+                let (first, span) = match first {
+                    Some(first) => first,
+                    None => continue,
+                };
+                // 下一轮迭代在使用之前一定会先覆盖它时, 本轮的值不会被带到下一轮
+                let overwritten = self.overwritten(local.decl, start).any(|w| w.end < first);
+                if !overwritten {
+                    let uses = self.uses.entry(local.decl).or_default();
+                    uses.push((end, span));
+                }
+            }
+            self.regions.pop();
+            return;
+        }
+        let nested = self.second_iteration;
+        let end = self.point;
+        self.point = start;
+        self.second_iteration = true;
+        body(self);
+        self.second_iteration = nested;
+        self.point = end;
+        self.regions.pop();
+    }
+}
+
+fn binding_name(pat: &Pat) -> Option<String> {
+    match &pat.kind {
+        super::ast::PatKind::Binding { name, .. } => Some(name.clone()),
+        _ => None,
+    }
+}
+
+/// 一次借用检查的结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct BorrowCheck {
+    pub mode: Mode,
+    pub borrows: Vec<Borrow>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl BorrowCheck {
+    /// 是否通过了借用检查
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// 先列出每个借用及其结束位置, 再按 rustc 的格式输出诊断信息
+    pub fn render(&self, src: &str) -> String {
+        let mut out: String = self.borrows.iter().map(|b| format!("{}\n", b)).collect();
+        for diagnostic in &self.diagnostics {
+            out.push('\n');
+            out.push_str(&diagnostic.render(src));
+        }
+        out
+    }
+}
+
+impl fmt::Display for BorrowCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for borrow in &self.borrows {
+            writeln!(f, "{}", borrow)?;
+        }
+        for diagnostic in &self.diagnostics {
+            writeln!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+/// 片段最后一个字符之后的位置, 顶层绑定在这里离开作用域
+fn end_of(src: &str) -> Span {
+    let line = src.lines().count().max(1);
+    let col = src.lines().last().map_or(0, |l| l.chars().count()) + 1;
+    Span::new(line, col)
+}
+
+/// # 借用检查
+///
+/// 同一段代码在词法作用域下被拒绝, 在 NLL 下被接受: `r` 在 `c.push(4)` 之前就不再被使用了
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::lang::borrowck::{check, Mode};
+///
+/// let src = "let mut c = vec![1, 2, 3]; let r = &mut c; r.push(4); c.push(5);";
+/// let lexical = check(src, Mode::Lexical).unwrap();
+/// assert_eq!(lexical.to_string(), "\
+/// \x201:36  &mut c -> r, 直到 1:65
+/// \x201:44  &mut *r (临时)
+/// \x201:55  &mut c (临时)
+/// error[E0499]: 1:55: cannot borrow `c` as mutable more than once at a time
+///   1:36: first mutable borrow occurs here
+///   1:65: first borrow ends here
+/// ");
+///
+/// let nll = check(src, Mode::NonLexical).unwrap();
+/// assert!(nll.is_ok());
+/// assert_eq!(nll.borrows[0].to_string(), " 1:36  &mut c -> r, 直到 1:44");
+///
+/// // 两阶段借用: 先求值参数 `c.len()`, 再可变借用 `c`
+/// let src = "let mut c = vec![1]; c.push(c.len());";
+/// assert_eq!(check(src, Mode::Lexical).unwrap().diagnostics[0].code, Some("E0502"));
+/// assert!(check(src, Mode::NonLexical).unwrap().is_ok());
+///
+/// // 引用比被借用的值活得更久
+/// let src = "let r; { let x = 5; r = &x; } use r;";
+/// let nll = check(src, Mode::NonLexical).unwrap();
+/// assert_eq!(nll.diagnostics[0].code, Some("E0597"));
+/// ```
+pub fn check(src: &str, mode: Mode) -> Result<BorrowCheck, ParseError> {
+    let stmts = parse_stmts(src)?;
+    let end = end_of(src);
+    let mut checker = Checker::new(mode);
+    for pass in 0..2 {
+        if pass == 1 {
+            checker.restart();
+        }
+        checker.push_scope(end);
+        for stmt in &stmts {
+            checker.stmt(stmt);
+        }
+        checker.pop_scope();
+    }
+    Ok(BorrowCheck {
+        mode,
+        borrows: checker.borrows,
+        diagnostics: checker.diagnostics,
+    })
+}
@@ -0,0 +1,1822 @@
+//! # 编译期求值
+//!
+//! [`function::init_len`](crate::ch02::function::init_len) 的文档说 CTFE 由 miri 执行,
+//! 只有常量表达式才能在编译期求值. 这里用一个小解释器模拟这个过程:
+//!
+//! 1. 常量检查: 检查 `const`/`static` 的初始化表达式、`const fn` 的函数体以及数组长度 `[x; N]`,
+//!    拒绝调用普通函数(E0015)、在常量中分配堆内存(E0010)、使用运行时的变量(E0435)等操作
+//! 2. 求值: 按需求出每个常量的值, 整数运算按声明的类型检查溢出, 除以零与越界访问同样报告 E0080
+//!
+//! 支持字面量、元组、数组、算术与比较运算、`if`、`match`、`while`、`loop`、局部变量以及对 `const fn` 的调用.
+//! 顶层的 `let` 语句是运行时代码, 其中只有数组长度处于常量上下文中.
+//! 整数统一用 `i128` 表示, 其中 `u128` 保存的是按位相同的 `i128`, 运算时按声明的类型的宽度进行. 引用被当作它所指向的值
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+use super::ast::{BinOp, Block, Expr, ExprKind, FnDecl, Item, Lit, Pat, PatKind, Stmt, StmtKind};
+use super::ast::{Type, UnOp};
+use super::diagnostic::Diagnostic;
+use super::lexer::Span;
+use super::parser::{parse_stmts, ParseError};
+
+/// 一次求值最多执行的步数, 相当于 rustc 的 `long_running_const_eval`
+const STEP_LIMIT: usize = 1_000_000;
+
+/// 常量函数调用的最大嵌套层数
+const CALL_DEPTH_LIMIT: usize = 128;
+
+/// `[x; N]` 的最大长度, 防止一次分配耗尽内存
+const ARRAY_LEN_LIMIT: usize = 1 << 20;
+
+const INTEGERS: [&str; 12] = [
+    "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize",
+];
+
+/// 可以在常量上下文中调用的方法
+const CONST_METHODS: [&str; 12] = [
+    "len",
+    "is_empty",
+    "pow",
+    "abs",
+    "wrapping_add",
+    "wrapping_sub",
+    "wrapping_mul",
+    "saturating_add",
+    "saturating_sub",
+    "count_ones",
+    "leading_zeros",
+    "trailing_zeros",
+];
+
+/// 可以在常量上下文中调用的关联函数
+const CONST_PATHS: [&str; 2] = ["String::new", "Vec::new"];
+
+/// 常量的值
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Unit,
+    Bool(bool),
+    /// 整数及其类型, 没有后缀也无法从上下文得到类型的字面量为 `None`.
+    /// `u128` 的值按位保存为 `i128`, 例如 `u128::MAX` 保存为 `-1`
+    Int(i128, Option<&'static str>),
+    Float(f64),
+    Char(char),
+    Str(String),
+    Tuple(Vec<Value>),
+    Array(Vec<Value>),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |values: &[Value]| {
+            values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            Value::Unit => f.write_str("()"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(i, Some("u128")) => write!(f, "{}", *i as u128),
+            Value::Int(i, _) => write!(f, "{}", i),
+            Value::Float(x) => write!(f, "{:?}", x),
+            Value::Char(c) => write!(f, "{:?}", c),
+            Value::Str(s) => write!(f, "{:?}", s),
+            Value::Tuple(values) if values.len() == 1 => write!(f, "({},)", values[0]),
+            Value::Tuple(values) => write!(f, "({})", list(values)),
+            Value::Array(values) => write!(f, "[{}]", list(values)),
+        }
+    }
+}
+
+/// 一个求出了值的常量
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluated {
+    pub span: Span,
+    /// 常量的名字; 数组长度记为 `[_; 表达式]`
+    pub name: String,
+    pub ty: Type,
+    pub value: Value,
+}
+
+impl fmt::Display for Evaluated {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:>5}  {}: {} = {}",
+            self.span.to_string(),
+            self.name,
+            self.ty,
+            self.value
+        )
+    }
+}
+
+/// 一次编译期求值的结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstEval {
+    pub values: Vec<Evaluated>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ConstEval {
+    /// 是否所有常量都求值成功
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// 按名字查找常量的值
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values
+            .iter()
+            .find(|v| v.name == name)
+            .map(|v| &v.value)
+    }
+
+    /// 先列出求出的值, 再按 rustc 的格式输出诊断信息
+    pub fn render(&self, src: &str) -> String {
+        let mut out: String = self.values.iter().map(|v| format!("{}\n", v)).collect();
+        for diagnostic in &self.diagnostics {
+            out.push('\n');
+            out.push_str(&diagnostic.render(src));
+        }
+        out
+    }
+}
+
+impl fmt::Display for ConstEval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for value in &self.values {
+            writeln!(f, "{}", value)?;
+        }
+        for diagnostic in &self.diagnostics {
+            writeln!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------- 常量检查
+
+/// 常量上下文的种类, 用于错误信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    Const,
+    Static,
+    ConstFn,
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Context::Const => "constants",
+            Context::Static => "statics",
+            Context::ConstFn => "constant functions",
+        })
+    }
+}
+
+struct ConstChecker<'a> {
+    context: Context,
+    fns: &'a HashMap<String, FnDecl>,
+    /// 常量与静态变量的名字
+    globals: &'a HashMap<String, Global>,
+    /// 运行时的局部变量, 在常量中使用时报告 E0435
+    runtime: &'a [String],
+    /// 常量上下文中自己声明的局部变量与参数
+    locals: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl ConstChecker<'_> {
+    fn non_const_call(&mut self, span: Span, what: String) {
+        let note = format!(
+            "calls in {} are limited to constant functions, tuple structs and tuple variants",
+            self.context
+        );
+        self.diagnostics.push(
+            Diagnostic::error("E0015", span, format!("{} in {}", what, self.context))
+                .with_label(&note),
+        );
+    }
+
+    fn block(&mut self, block: &Block) {
+        let scope = self.locals.len();
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+        self.locals.truncate(scope);
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Let { pat, init, .. } => {
+                if let Some(init) = init {
+                    self.expr(init);
+                }
+                self.bind(pat);
+            }
+            StmtKind::Expr(expr) | StmtKind::Semi(expr) | StmtKind::Use(expr) => self.expr(expr),
+            StmtKind::Item(_) => {}
+        }
+    }
+
+    fn bind(&mut self, pat: &Pat) {
+        for binding in pat.bindings() {
+            if let PatKind::Binding { name, .. } = &binding.kind {
+                self.locals.push(name.clone());
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        let span = expr.span;
+        match &expr.kind {
+            ExprKind::Path(path) => {
+                let name = path.join("::");
+                if self.locals.contains(&name)
+                    || self.globals.contains_key(&name)
+                    || limit(&name).is_some()
+                {
+                    return;
+                }
+                if self.runtime.contains(&name) {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            "E0435",
+                            span,
+                            "attempt to use a non-constant value in a constant".to_string(),
+                        )
+                        .with_label("non-constant value"),
+                    );
+                } else if !self.fns.contains_key(&name) {
+                    self.diagnostics.push(
+                        Diagnostic::error(
+                            "E0425",
+                            span,
+                            format!("cannot find value `{}` in this scope", name),
+                        )
+                        .with_label("not found in this scope"),
+                    );
+                }
+            }
+            ExprKind::Call(callee, args) => {
+                for arg in args {
+                    self.expr(arg);
+                }
+                let name = match &callee.kind {
+                    ExprKind::Path(path) => path.join("::"),
+                    _ => return self.expr(callee),
+                };
+                match self.fns.get(&name) {
+                    Some(decl) if decl.is_const => {}
+                    Some(_) => self
+                        .non_const_call(span, format!("cannot call non-const function `{}`", name)),
+                    None if CONST_PATHS.contains(&name.as_str()) => {}
+                    None if name.contains("::") => self.non_const_call(
+                        span,
+                        format!("cannot call non-const associated function `{}`", name),
+                    ),
+                    None => self.diagnostics.push(
+                        Diagnostic::error(
+                            "E0425",
+                            callee.span,
+                            format!("cannot find function `{}` in this scope", name),
+                        )
+                        .with_label("not found in this scope"),
+                    ),
+                }
+            }
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+            } => {
+                self.expr(receiver);
+                for arg in args {
+                    self.expr(arg);
+                }
+                if !CONST_METHODS.contains(&method.as_str()) {
+                    self.non_const_call(span, format!("cannot call non-const method `{}`", method));
+                }
+            }
+            ExprKind::Macro { name, args } => {
+                for arg in args {
+                    self.expr(arg);
+                }
+                match name.as_str() {
+                    "vec" => self.diagnostics.push(
+                        Diagnostic::error(
+                            "E0010",
+                            span,
+                            format!("allocations are not allowed in {}", self.context),
+                        )
+                        .with_label("allocation not allowed"),
+                    ),
+                    "assert" | "panic" | "unreachable" | "todo" => {}
+                    _ => self.non_const_call(
+                        span,
+                        format!("cannot call non-const formatting macro `{}!`", name),
+                    ),
+                }
+            }
+            ExprKind::For { pat, iter, body } => {
+                self.expr(iter);
+                let scope = self.locals.len();
+                self.bind(pat);
+                self.block(body);
+                self.locals.truncate(scope);
+                self.non_const_call(span, format!("cannot use `for` loop on `{}`", iter));
+            }
+            ExprKind::Block(block) | ExprKind::Loop(block) => self.block(block),
+            ExprKind::If { cond, then, els } => {
+                let scope = self.locals.len();
+                self.expr(cond);
+                self.block(then);
+                self.locals.truncate(scope);
+                if let Some(els) = els {
+                    self.expr(els);
+                }
+            }
+            ExprKind::While { cond, body } => {
+                let scope = self.locals.len();
+                self.expr(cond);
+                self.block(body);
+                self.locals.truncate(scope);
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                self.expr(scrutinee);
+                for arm in arms {
+                    let scope = self.locals.len();
+                    self.bind(&arm.pat);
+                    if let Some(guard) = &arm.guard {
+                        self.expr(guard);
+                    }
+                    self.expr(&arm.body);
+                    self.locals.truncate(scope);
+                }
+            }
+            ExprKind::Let(pat, scrutinee) => {
+                self.expr(scrutinee);
+                self.bind(pat);
+            }
+            ExprKind::Lit(_) | ExprKind::Continue => {}
+            ExprKind::Paren(inner)
+            | ExprKind::Unary(_, inner)
+            | ExprKind::Ref { expr: inner, .. }
+            | ExprKind::Field(inner, _)
+            | ExprKind::Cast(inner, _) => self.expr(inner),
+            ExprKind::Binary(_, lhs, rhs)
+            | ExprKind::Assign(lhs, rhs)
+            | ExprKind::AssignOp(_, lhs, rhs)
+            | ExprKind::Index(lhs, rhs)
+            | ExprKind::Repeat(lhs, rhs) => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            ExprKind::Tuple(items) | ExprKind::Array(items) => {
+                for item in items {
+                    self.expr(item);
+                }
+            }
+            ExprKind::Break(value) | ExprKind::Return(value) => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+            }
+            ExprKind::Range { start, end, .. } => {
+                for bound in start.iter().chain(end) {
+                    self.expr(bound);
+                }
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------- 求值
+
+#[derive(Debug, Clone)]
+struct Global {
+    ty: Type,
+    expr: Expr,
+    span: Span,
+    context: Context,
+}
+
+#[derive(Debug, Clone)]
+enum State {
+    Evaluating,
+    Done(Value),
+    /// 求值失败, 错误已经报告过了
+    Failed,
+}
+
+/// 非正常的控制流; 错误为 `None` 时表示依赖的常量已经报告过错误
+enum Flow {
+    Break(Value),
+    Continue,
+    Return(Value),
+    Error(Option<Diagnostic>),
+}
+
+type Eval<T> = Result<T, Flow>;
+
+struct Evaluator<'a> {
+    fns: &'a HashMap<String, FnDecl>,
+    globals: &'a HashMap<String, Global>,
+    /// 常量检查没有通过的常量函数
+    rejected: &'a [String],
+    states: HashMap<String, State>,
+    scopes: Vec<Vec<(String, Value)>>,
+    /// 正在求值的常量, 用于错误信息
+    current: String,
+    /// 调用栈: 被调用的函数名与调用位置
+    calls: Vec<(String, Span)>,
+    budget: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+fn fail(diagnostic: Diagnostic) -> Flow {
+    Flow::Error(Some(diagnostic))
+}
+
+impl Evaluator<'_> {
+    /// E0080: 求值失败, 标注出错的位置以及经过的常量函数调用
+    fn failure(&self, span: Span, message: String) -> Flow {
+        let label = format!("evaluation of `{}` failed here", self.current);
+        let mut diagnostic = Diagnostic::error("E0080", span, message).with_label(&label);
+        // 递归调用只标注一次
+        for (name, call) in self.calls.iter().rev() {
+            let note = format!("inside `{}`", name);
+            if !diagnostic
+                .notes
+                .iter()
+                .any(|(s, n)| s == call && *n == note)
+            {
+                diagnostic = diagnostic.with_note(*call, &note);
+            }
+        }
+        fail(diagnostic)
+    }
+
+    /// 求出全局常量的值, 第一次使用时才求值
+    fn global(&mut self, name: &str, span: Span) -> Eval<Value> {
+        match self.states.get(name) {
+            Some(State::Done(value)) => return Ok(value.clone()),
+            Some(State::Failed) => return Err(Flow::Error(None)),
+            Some(State::Evaluating) => {
+                return Err(fail(
+                    Diagnostic::error(
+                        "E0391",
+                        span,
+                        format!("cycle detected when const-evaluating `{}`", name),
+                    )
+                    .with_label("...which requires const-evaluating itself"),
+                ))
+            }
+            None => {}
+        }
+        let global = self.globals[name].clone();
+        self.states.insert(name.to_string(), State::Evaluating);
+        let outer_scopes = std::mem::replace(&mut self.scopes, vec![Vec::new()]);
+        let outer_calls = std::mem::take(&mut self.calls);
+        let outer = std::mem::replace(&mut self.current, name.to_string());
+        let result = self
+            .expr(&global.expr, &global.ty)
+            .and_then(|value| self.fit(value, &global.ty, global.expr.span));
+        self.current = outer;
+        self.calls = outer_calls;
+        self.scopes = outer_scopes;
+        match result {
+            Ok(value) | Err(Flow::Return(value)) => {
+                self.states
+                    .insert(name.to_string(), State::Done(value.clone()));
+                Ok(value)
+            }
+            Err(Flow::Error(diagnostic)) => {
+                self.states.insert(name.to_string(), State::Failed);
+                if let Some(diagnostic) = diagnostic {
+                    self.diagnostics.push(diagnostic);
+                }
+                Err(Flow::Error(None))
+            }
+            Err(Flow::Break(_)) | Err(Flow::Continue) => {
+                self.states.insert(name.to_string(), State::Failed);
+                Err(Flow::Error(None))
+            }
+        }
+    }
+
+    /// 把值放进类型为 `ty` 的位置: 无类型的整数字面量得到类型, 并检查是否超出范围
+    fn fit(&self, value: Value, ty: &Type, span: Span) -> Eval<Value> {
+        Ok(match (value, ty) {
+            (Value::Int(i, None), ty) => match integer(ty) {
+                Some(name) if !in_range(i, name) => {
+                    return Err(self.failure(span, format!("literal out of range for `{}`", name)))
+                }
+                Some(name) => Value::Int(i, Some(name)),
+                None => Value::Int(i, None),
+            },
+            (Value::Array(values), Type::Array(elem, _))
+            | (Value::Array(values), Type::Slice(elem)) => Value::Array(
+                values
+                    .into_iter()
+                    .map(|v| self.fit(v, elem, span))
+                    .collect::<Eval<_>>()?,
+            ),
+            (Value::Tuple(values), Type::Tuple(tys)) if values.len() == tys.len() => Value::Tuple(
+                values
+                    .into_iter()
+                    .zip(tys)
+                    .map(|(v, ty)| self.fit(v, ty, span))
+                    .collect::<Eval<_>>()?,
+            ),
+            (value, Type::Ref { inner, .. }) => self.fit(value, inner, span)?,
+            (value, _) => value,
+        })
+    }
+
+    fn lookup(&mut self, name: &str) -> Option<&mut Value> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value)
+    }
+
+    fn bind(&mut self, pat: &Pat, value: &Value) -> Eval<bool> {
+        let mut bindings = Vec::new();
+        if !self.matches(pat, value, &mut bindings)? {
+            return Ok(false);
+        }
+        self.scopes.last_mut().unwrap().extend(bindings);
+        Ok(true)
+    }
+
+    fn matches(
+        &mut self,
+        pat: &Pat,
+        value: &Value,
+        bindings: &mut Vec<(String, Value)>,
+    ) -> Eval<bool> {
+        Ok(match (&pat.kind, value) {
+            (PatKind::Wild, _) => true,
+            (PatKind::Binding { name, sub, .. }, _) => {
+                if let Some(sub) = sub {
+                    if !self.matches(sub, value, bindings)? {
+                        return Ok(false);
+                    }
+                }
+                bindings.push((name.clone(), value.clone()));
+                true
+            }
+            (PatKind::Lit(lit), _) => same(&self.expr(lit, &Type::Infer)?, value),
+            (
+                PatKind::Range {
+                    start,
+                    end,
+                    inclusive,
+                },
+                _,
+            ) => {
+                let start = self.expr(start, &Type::Infer)?;
+                let end = self.expr(end, &Type::Infer)?;
+                let above = order(value, &start).is_some_and(|o| o.is_ge());
+                let below =
+                    order(value, &end).is_some_and(|o| o.is_lt() || (*inclusive && o.is_eq()));
+                above && below
+            }
+            (PatKind::Tuple(pats), Value::Tuple(values)) if pats.len() == values.len() => {
+                for (pat, value) in pats.iter().zip(values) {
+                    if !self.matches(pat, value, bindings)? {
+                        return Ok(false);
+                    }
+                }
+                true
+            }
+            (PatKind::Ref { pat, .. }, _) => self.matches(pat, value, bindings)?,
+            (PatKind::Or(alternatives), _) => {
+                for alternative in alternatives {
+                    let mut inner = Vec::new();
+                    if self.matches(alternative, value, &mut inner)? {
+                        bindings.extend(inner);
+                        return Ok(true);
+                    }
+                }
+                false
+            }
+            (PatKind::Path(path), _) if path.len() == 1 && self.globals.contains_key(&path[0]) => {
+                same(&self.global(&path[0], pat.span)?, value)
+            }
+            _ => return Err(self.failure(pat.span, format!("不支持模式 `{}`", pat))),
+        })
+    }
+
+    fn block(&mut self, block: &Block, hint: &Type) -> Eval<Value> {
+        self.scopes.push(Vec::new());
+        let result = self.stmts(&block.stmts, hint);
+        self.scopes.pop();
+        result
+    }
+
+    fn stmts(&mut self, stmts: &[Stmt], hint: &Type) -> Eval<Value> {
+        for (i, stmt) in stmts.iter().enumerate() {
+            match &stmt.kind {
+                StmtKind::Let { pat, ty, init } => {
+                    let ty = ty.clone().unwrap_or(Type::Infer);
+                    let value = match init {
+                        Some(init) => {
+                            let value = self.expr(init, &ty)?;
+                            self.fit(value, &ty, init.span)?
+                        }
+                        // 常量检查已经保证了使用前一定赋过值, 这里用 () 占位
+                        None => Value::Unit,
+                    };
+                    if !self.bind(pat, &value)? {
+                        return Err(self.failure(
+                            pat.span,
+                            format!("refutable pattern `{}` in local binding", pat),
+                        ));
+                    }
+                }
+                StmtKind::Expr(expr) if i + 1 == stmts.len() => return self.expr(expr, hint),
+                StmtKind::Expr(expr) | StmtKind::Semi(expr) | StmtKind::Use(expr) => {
+                    self.expr(expr, &Type::Infer)?;
+                }
+                StmtKind::Item(_) => {}
+            }
+        }
+        Ok(Value::Unit)
+    }
+
+    /// 消耗一步. 空的 `loop {}` 不求值任何表达式, 所以循环每执行一次也要消耗一步
+    fn tick(&mut self, span: Span) -> Eval<()> {
+        if self.budget == 0 {
+            return Err(self.failure(
+                span,
+                format!("constant evaluation exceeded {} steps", STEP_LIMIT),
+            ));
+        }
+        self.budget -= 1;
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &Expr, hint: &Type) -> Eval<Value> {
+        self.tick(expr.span)?;
+        let span = expr.span;
+        match &expr.kind {
+            ExprKind::Lit(lit) => self.literal(lit, hint, span),
+            ExprKind::Path(path) => {
+                let name = path.join("::");
+                if let Some(value) = self.lookup(&name) {
+                    return Ok(value.clone());
+                }
+                if self.globals.contains_key(&name) {
+                    return self.global(&name, span);
+                }
+                if let Some(value) = limit(&name) {
+                    return Ok(value);
+                }
+                // 常量检查已经报告过未定义的名字
+                Err(Flow::Error(None))
+            }
+            ExprKind::Paren(inner) => self.expr(inner, hint),
+            // `-128i8` 中的 128 本身超出了 i8 的范围, 需要连同负号一起检查
+            ExprKind::Unary(UnOp::Neg, inner)
+                if matches!(inner.kind, ExprKind::Lit(Lit::Int { .. })) =>
+            {
+                let (value, suffix) = match &inner.kind {
+                    ExprKind::Lit(Lit::Int { value, suffix }) => (*value, suffix),
+                    _ => unreachable!(),
+                };
+                let ty = match suffix {
+                    Some(suffix) => integer(&Type::named(suffix)),
+                    None => integer(hint),
+                };
+                let value = i128::try_from(value)
+                    .ok()
+                    .map(|v| -v)
+                    .filter(|v| ty.is_none_or(|ty| in_range(*v, ty)));
+                match value {
+                    Some(value) => Ok(Value::Int(value, ty)),
+                    None => Err(self.failure(
+                        span,
+                        format!("literal out of range for `{}`", ty.unwrap_or("i128")),
+                    )),
+                }
+            }
+            ExprKind::Unary(op, inner) => match (op, self.expr(inner, hint)?) {
+                (UnOp::Neg, Value::Int(i, ty)) => match negate(i, ty) {
+                    Some(n) => Ok(Value::Int(n, ty)),
+                    _ => Err(self.failure(
+                        span,
+                        format!(
+                            "attempt to negate `{}`, which would overflow",
+                            operand(i, ty)
+                        ),
+                    )),
+                },
+                (UnOp::Neg, Value::Float(x)) => Ok(Value::Float(-x)),
+                (UnOp::Not, Value::Bool(b)) => Ok(Value::Bool(!b)),
+                (UnOp::Not, Value::Int(i, ty)) => Ok(Value::Int(wrap(!i, ty), ty)),
+                (UnOp::Deref, value) => Ok(value),
+                (op, value) => Err(self.failure(
+                    span,
+                    format!("cannot apply unary operator `{}` to `{}`", op, value),
+                )),
+            },
+            ExprKind::Ref { expr, .. } => {
+                let hint = match hint {
+                    Type::Ref { inner, .. } => inner,
+                    _ => &Type::Infer,
+                };
+                self.expr(expr, hint)
+            }
+            ExprKind::Binary(op, lhs, rhs) if op.is_lazy() => {
+                let left = self.truth(lhs)?;
+                let short = if *op == BinOp::And { !left } else { left };
+                if short {
+                    return Ok(Value::Bool(left));
+                }
+                Ok(Value::Bool(self.truth(rhs)?))
+            }
+            ExprKind::Binary(op, lhs, rhs) => {
+                let operand_hint = if op.is_comparison() {
+                    &Type::Infer
+                } else {
+                    hint
+                };
+                let lhs = self.expr(lhs, operand_hint)?;
+                let rhs_hint = match (&lhs, op) {
+                    (_, BinOp::Shl) | (_, BinOp::Shr) => Type::Infer,
+                    (Value::Int(_, Some(ty)), _) => Type::named(ty),
+                    _ => operand_hint.clone(),
+                };
+                let rhs = self.expr(rhs, &rhs_hint)?;
+                self.binary(*op, lhs, rhs, span)
+            }
+            ExprKind::Assign(place, value) => {
+                let current = self.expr(place, &Type::Infer)?;
+                let value = self.expr(value, &type_of(&current))?;
+                self.assign(place, value)?;
+                Ok(Value::Unit)
+            }
+            ExprKind::AssignOp(op, place, value) => {
+                let current = self.expr(place, &Type::Infer)?;
+                let value = self.expr(value, &type_of(&current))?;
+                let value = self.binary(*op, current, value, span)?;
+                self.assign(place, value)?;
+                Ok(Value::Unit)
+            }
+            ExprKind::Field(base, field) => {
+                match (self.expr(base, &Type::Infer)?, field.parse::<usize>()) {
+                    (Value::Tuple(values), Ok(i)) if i < values.len() => Ok(values[i].clone()),
+                    (value, _) => {
+                        Err(self.failure(span, format!("no field `{}` on `{}`", field, value)))
+                    }
+                }
+            }
+            ExprKind::Index(base, index) => {
+                let base = self.expr(base, &Type::Infer)?;
+                let index = self.expr(index, &Type::named("usize"))?;
+                self.index(base, index, span)
+            }
+            ExprKind::Call(callee, args) => {
+                let name = match &callee.kind {
+                    ExprKind::Path(path) => path.join("::"),
+                    _ => String::new(),
+                };
+                match name.as_str() {
+                    "String::new" => return Ok(Value::Str(String::new())),
+                    "Vec::new" => return Ok(Value::Array(Vec::new())),
+                    _ => {}
+                }
+                self.call(&name, args, span)
+            }
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+            } => {
+                let receiver = self.expr(receiver, hint)?;
+                let ty = type_of(&receiver);
+                let args = args
+                    .iter()
+                    .map(|arg| self.expr(arg, &ty))
+                    .collect::<Eval<Vec<_>>>()?;
+                self.method(receiver, method, args, span)
+            }
+            ExprKind::Macro { name, args } => match name.as_str() {
+                "assert" => match args.first().map(|a| self.truth(a)).transpose()? {
+                    Some(true) => Ok(Value::Unit),
+                    _ => Err(self.failure(
+                        span,
+                        format!(
+                            "evaluation panicked: assertion failed: {}",
+                            args.first().map_or(String::new(), |a| a.to_string())
+                        ),
+                    )),
+                },
+                "panic" | "unreachable" | "todo" => {
+                    let message = match args.first().map(|a| &a.kind) {
+                        Some(ExprKind::Lit(Lit::Str(s))) => s.clone(),
+                        _ => match name.as_str() {
+                            "unreachable" => "internal error: entered unreachable code".to_string(),
+                            "todo" => "not yet implemented".to_string(),
+                            _ => "explicit panic".to_string(),
+                        },
+                    };
+                    Err(self.failure(span, format!("evaluation panicked: {}", message)))
+                }
+                _ => Err(Flow::Error(None)),
+            },
+            ExprKind::Tuple(items) => {
+                let hints: Vec<Type> = match hint {
+                    Type::Tuple(tys) if tys.len() == items.len() => tys.clone(),
+                    _ => vec![Type::Infer; items.len()],
+                };
+                let values = items
+                    .iter()
+                    .zip(&hints)
+                    .map(|(item, hint)| self.expr(item, hint))
+                    .collect::<Eval<_>>()?;
+                Ok(Value::Tuple(values))
+            }
+            ExprKind::Array(items) => {
+                let hint = element_hint(hint);
+                let values = items
+                    .iter()
+                    .map(|item| self.expr(item, &hint))
+                    .collect::<Eval<_>>()?;
+                Ok(Value::Array(values))
+            }
+            ExprKind::Repeat(item, len) => {
+                let item = self.expr(item, &element_hint(hint))?;
+                let len_span = len.span;
+                let len = self.array_len(len)?;
+                if len > ARRAY_LEN_LIMIT {
+                    return Err(self.failure(
+                        len_span,
+                        format!(
+                            "array length {} exceeds the evaluator limit of {} elements",
+                            len, ARRAY_LEN_LIMIT
+                        ),
+                    ));
+                }
+                Ok(Value::Array(vec![item; len]))
+            }
+            ExprKind::Let(pat, scrutinee) => {
+                let value = self.expr(scrutinee, &Type::Infer)?;
+                Ok(Value::Bool(self.bind(pat, &value)?))
+            }
+            ExprKind::Block(block) => self.block(block, hint),
+            ExprKind::If { cond, then, els } => {
+                self.scopes.push(Vec::new());
+                let result = match self.truth(cond) {
+                    Ok(true) => self.block(then, hint),
+                    Ok(false) => match els {
+                        Some(els) => self.expr(els, hint),
+                        None => Ok(Value::Unit),
+                    },
+                    Err(flow) => Err(flow),
+                };
+                self.scopes.pop();
+                result
+            }
+            ExprKind::While { cond, body } => {
+                loop {
+                    self.tick(span)?;
+                    self.scopes.push(Vec::new());
+                    let result = match self.truth(cond) {
+                        Ok(true) => match self.block(body, &Type::unit()) {
+                            Ok(_) | Err(Flow::Continue) => Ok(true),
+                            Err(Flow::Break(_)) => Ok(false),
+                            Err(flow) => Err(flow),
+                        },
+                        Ok(false) => Ok(false),
+                        Err(flow) => Err(flow),
+                    };
+                    self.scopes.pop();
+                    if !result? {
+                        break;
+                    }
+                }
+                Ok(Value::Unit)
+            }
+            ExprKind::Loop(body) => loop {
+                self.tick(span)?;
+                match self.block(body, &Type::unit()) {
+                    Ok(_) | Err(Flow::Continue) => {}
+                    Err(Flow::Break(value)) => return Ok(value),
+                    Err(flow) => return Err(flow),
+                }
+            },
+            ExprKind::Match { scrutinee, arms } => {
+                let value = self.expr(scrutinee, &Type::Infer)?;
+                for arm in arms {
+                    self.scopes.push(Vec::new());
+                    let result = match self.bind(&arm.pat, &value) {
+                        Ok(true) => match &arm.guard {
+                            Some(guard) => self.truth(guard),
+                            None => Ok(true),
+                        },
+                        other => other,
+                    };
+                    let result = match result {
+                        Ok(true) => self.expr(&arm.body, hint).map(Some),
+                        Ok(false) => Ok(None),
+                        Err(flow) => Err(flow),
+                    };
+                    self.scopes.pop();
+                    if let Some(value) = result? {
+                        return Ok(value);
+                    }
+                }
+                Err(self.failure(span, format!("no match arm matches `{}`", value)))
+            }
+            ExprKind::Break(value) => {
+                let value = match value {
+                    Some(value) => self.expr(value, &Type::Infer)?,
+                    None => Value::Unit,
+                };
+                Err(Flow::Break(value))
+            }
+            ExprKind::Continue => Err(Flow::Continue),
+            ExprKind::Return(value) => {
+                let value = match value {
+                    Some(value) => self.expr(value, hint)?,
+                    None => Value::Unit,
+                };
+                Err(Flow::Return(value))
+            }
+            ExprKind::Range { .. } => Err(self.failure(span, "区间只能出现在模式中".to_string())),
+            ExprKind::Cast(inner, ty) => {
+                let value = self.expr(inner, &Type::Infer)?;
+                self.cast(value, ty, span)
+            }
+            ExprKind::For { .. } => Err(Flow::Error(None)),
+        }
+    }
+
+    fn literal(&self, lit: &Lit, hint: &Type, span: Span) -> Eval<Value> {
+        Ok(match lit {
+            Lit::Int { value, suffix } => {
+                let ty = match suffix {
+                    Some(suffix) => integer(&Type::named(suffix)),
+                    None => integer(hint),
+                };
+                let value = match ty {
+                    Some("u128") => Some(*value as i128),
+                    _ => i128::try_from(*value)
+                        .ok()
+                        .filter(|v| ty.is_none_or(|ty| in_range(*v, ty))),
+                };
+                match value {
+                    Some(value) => Value::Int(value, ty),
+                    None => {
+                        return Err(self.failure(
+                            span,
+                            format!("literal out of range for `{}`", ty.unwrap_or("i128")),
+                        ))
+                    }
+                }
+            }
+            Lit::Float { value, .. } => Value::Float(*value),
+            Lit::Bool(b) => Value::Bool(*b),
+            Lit::Char(c) => Value::Char(*c),
+            Lit::Str(s) => Value::Str(s.clone()),
+        })
+    }
+
+    fn truth(&mut self, expr: &Expr) -> Eval<bool> {
+        match self.expr(expr, &Type::named("bool"))? {
+            Value::Bool(b) => Ok(b),
+            other => Err(self.failure(expr.span, format!("expected `bool`, found `{}`", other))),
+        }
+    }
+
+    fn array_len(&mut self, len: &Expr) -> Eval<usize> {
+        match self.expr(len, &Type::named("usize"))? {
+            Value::Int(n, _) if n >= 0 => Ok(n as usize),
+            other => Err(self.failure(
+                len.span,
+                format!("array length must be a `usize`, found `{}`", other),
+            )),
+        }
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr], span: Span) -> Eval<Value> {
+        let decl = match self.fns.get(name) {
+            Some(decl) if decl.is_const && !self.rejected.iter().any(|r| r == name) => decl,
+            // 常量检查已经报告过错误
+            _ => return Err(Flow::Error(None)),
+        };
+        if args.len() != decl.params.len() {
+            return Err(fail(Diagnostic::error(
+                "E0061",
+                span,
+                format!(
+                    "this function takes {} arguments but {} arguments were supplied",
+                    decl.params.len(),
+                    args.len()
+                ),
+            )));
+        }
+        let mut frame = Vec::new();
+        for (param, arg) in decl.params.iter().zip(args) {
+            let value = self.expr(arg, &param.ty)?;
+            let value = self.fit(value, &param.ty, arg.span)?;
+            let mut bindings = Vec::new();
+            if !self.matches(&param.pat, &value, &mut bindings)? {
+                return Err(self.failure(arg.span, format!("refutable pattern `{}`", param.pat)));
+            }
+            frame.extend(bindings);
+        }
+        if self.calls.len() >= CALL_DEPTH_LIMIT {
+            return Err(self.failure(
+                span,
+                format!(
+                    "reached the configured maximum number of stack frames ({})",
+                    CALL_DEPTH_LIMIT
+                ),
+            ));
+        }
+        // 函数体看不到调用者的局部变量
+        let caller = std::mem::replace(&mut self.scopes, vec![frame]);
+        self.calls.push((name.to_string(), span));
+        let result = match self.block(&decl.body, &decl.ret) {
+            Ok(value) | Err(Flow::Return(value)) => self.fit(value, &decl.ret, span),
+            Err(Flow::Break(_)) | Err(Flow::Continue) => Err(Flow::Error(None)),
+            Err(flow) => Err(flow),
+        };
+        self.calls.pop();
+        self.scopes = caller;
+        result
+    }
+
+    fn method(&self, receiver: Value, method: &str, args: Vec<Value>, span: Span) -> Eval<Value> {
+        let usize_int = |n: usize| Value::Int(n as i128, Some("usize"));
+        let overflow = |what: &str, i: i128, ty: Option<&'static str>| {
+            self.failure(
+                span,
+                format!(
+                    "attempt to compute `{}.{}`, which would overflow",
+                    operand(i, ty),
+                    what
+                ),
+            )
+        };
+        Ok(match (receiver, method, args.as_slice()) {
+            (Value::Array(values), "len", []) => usize_int(values.len()),
+            (Value::Str(s), "len", []) => usize_int(s.len()),
+            (Value::Array(values), "is_empty", []) => Value::Bool(values.is_empty()),
+            (Value::Str(s), "is_empty", []) => Value::Bool(s.is_empty()),
+            (Value::Int(i, ty), "pow", [Value::Int(exp, _)]) => {
+                let result = u32::try_from(*exp).ok().and_then(|exp| match ty {
+                    Some("u128") => (i as u128).checked_pow(exp).map(|r| r as i128),
+                    _ => i
+                        .checked_pow(exp)
+                        .filter(|r| ty.is_none_or(|ty| in_range(*r, ty))),
+                });
+                match result {
+                    Some(result) => Value::Int(result, ty),
+                    None => return Err(overflow(&format!("pow({})", exp), i, ty)),
+                }
+            }
+            (Value::Int(i, ty), "abs", []) => match ty.filter(|ty| !in_range(-i, ty)) {
+                Some(_) => return Err(overflow("abs()", i, ty)),
+                None => Value::Int(i.abs(), ty),
+            },
+            (Value::Int(a, ty), "wrapping_add", [Value::Int(b, _)]) => {
+                Value::Int(wrap(a.wrapping_add(*b), ty), ty)
+            }
+            (Value::Int(a, ty), "wrapping_sub", [Value::Int(b, _)]) => {
+                Value::Int(wrap(a.wrapping_sub(*b), ty), ty)
+            }
+            (Value::Int(a, ty), "wrapping_mul", [Value::Int(b, _)]) => {
+                Value::Int(wrap(a.wrapping_mul(*b), ty), ty)
+            }
+            (Value::Int(a, Some("u128")), "saturating_add", [Value::Int(b, _)]) => {
+                Value::Int((a as u128).saturating_add(*b as u128) as i128, Some("u128"))
+            }
+            (Value::Int(a, Some("u128")), "saturating_sub", [Value::Int(b, _)]) => {
+                Value::Int((a as u128).saturating_sub(*b as u128) as i128, Some("u128"))
+            }
+            (Value::Int(a, ty), "saturating_add", [Value::Int(b, _)]) => {
+                Value::Int(saturate(a.saturating_add(*b), ty), ty)
+            }
+            (Value::Int(a, ty), "saturating_sub", [Value::Int(b, _)]) => {
+                Value::Int(saturate(a.saturating_sub(*b), ty), ty)
+            }
+            (Value::Int(i, ty), "count_ones" | "leading_zeros" | "trailing_zeros", []) => {
+                let bits = bits(ty.unwrap_or("i32"));
+                let raw = (i as u128) & mask(bits);
+                let n = match method {
+                    "count_ones" => raw.count_ones(),
+                    "leading_zeros" => raw.leading_zeros() - (128 - bits),
+                    _ => raw.trailing_zeros().min(bits),
+                };
+                Value::Int(n as i128, Some("u32"))
+            }
+            (receiver, _, _) => {
+                return Err(self.failure(
+                    span,
+                    format!("no method named `{}` found for `{}`", method, receiver),
+                ))
+            }
+        })
+    }
+
+    fn index(&self, base: Value, index: Value, span: Span) -> Eval<Value> {
+        match (base, index) {
+            (Value::Array(values), Value::Int(i, _)) => usize::try_from(i)
+                .ok()
+                .and_then(|i| values.get(i).cloned())
+                .ok_or_else(|| {
+                    self.failure(
+                        span,
+                        format!(
+                            "index out of bounds: the length is {} but the index is {}",
+                            values.len(),
+                            i
+                        ),
+                    )
+                }),
+            (base, index) => {
+                Err(self.failure(span, format!("cannot index `{}` with `{}`", base, index)))
+            }
+        }
+    }
+
+    fn binary(&self, op: BinOp, lhs: Value, rhs: Value, span: Span) -> Eval<Value> {
+        if op.is_comparison() {
+            let ordering = order(&lhs, &rhs);
+            let result = match op {
+                BinOp::Eq => same(&lhs, &rhs),
+                BinOp::Ne => !same(&lhs, &rhs),
+                BinOp::Lt => ordering.is_some_and(|o| o.is_lt()),
+                BinOp::Le => ordering.is_some_and(|o| o.is_le()),
+                BinOp::Gt => ordering.is_some_and(|o| o.is_gt()),
+                _ => ordering.is_some_and(|o| o.is_ge()),
+            };
+            return Ok(Value::Bool(result));
+        }
+        match (lhs, rhs) {
+            (Value::Int(a, ta), Value::Int(b, tb)) => {
+                let ty = ta.or(tb);
+                let shown = || (operand(a, ty), operand(b, ty));
+                if matches!(op, BinOp::Div | BinOp::Rem) && b == 0 {
+                    let message = if op == BinOp::Div {
+                        format!("attempt to divide `{}` by zero", shown().0)
+                    } else {
+                        format!(
+                            "attempt to calculate the remainder of `{}` with a divisor of zero",
+                            shown().0
+                        )
+                    };
+                    return Err(self.failure(span, message));
+                }
+                if let BinOp::Shl | BinOp::Shr = op {
+                    let width = bits(ta.unwrap_or("i32")) as i128;
+                    if b < 0 || b >= width {
+                        let (a, _) = shown();
+                        return Err(self.failure(
+                            span,
+                            format!(
+                                "attempt to shift {} `{}` by `{}_{}`, which would overflow",
+                                if op == BinOp::Shl { "left" } else { "right" },
+                                a,
+                                b,
+                                tb.unwrap_or("i32")
+                            ),
+                        ));
+                    }
+                    let shifted = match (op, ta) {
+                        (BinOp::Shl, _) => a << b,
+                        // `u128` 按位保存, 右移时不能带上符号位
+                        (_, Some("u128")) => ((a as u128) >> b) as i128,
+                        _ => a >> b,
+                    };
+                    return Ok(Value::Int(wrap(shifted, ta), ta));
+                }
+                match arithmetic(op, a, b, ty) {
+                    Some(result) => Ok(Value::Int(result, ty)),
+                    None => {
+                        let (a, b) = shown();
+                        // 与 rustc 一样, 取余溢出单独说明
+                        let what = if op == BinOp::Rem {
+                            "compute the remainder of"
+                        } else {
+                            "compute"
+                        };
+                        Err(self.failure(
+                            span,
+                            format!(
+                                "attempt to {} `{} {} {}`, which would overflow",
+                                what,
+                                a,
+                                op.as_str(),
+                                b
+                            ),
+                        ))
+                    }
+                }
+            }
+            (Value::Float(a), Value::Float(b)) => Ok(Value::Float(match op {
+                BinOp::Add => a + b,
+                BinOp::Sub => a - b,
+                BinOp::Mul => a * b,
+                BinOp::Div => a / b,
+                BinOp::Rem => a % b,
+                _ => {
+                    return Err(self.failure(
+                        span,
+                        format!("no implementation for `f64 {} f64`", op.as_str()),
+                    ))
+                }
+            })),
+            (Value::Bool(a), Value::Bool(b))
+                if matches!(op, BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor) =>
+            {
+                Ok(Value::Bool(match op {
+                    BinOp::BitAnd => a & b,
+                    BinOp::BitOr => a | b,
+                    _ => a ^ b,
+                }))
+            }
+            (lhs, rhs) => Err(self.failure(
+                span,
+                format!("cannot apply `{}` to `{}` and `{}`", op.as_str(), lhs, rhs),
+            )),
+        }
+    }
+
+    fn cast(&self, value: Value, ty: &Type, span: Span) -> Eval<Value> {
+        let target = integer(ty);
+        Ok(match (value, target, ty) {
+            (Value::Int(i, _), Some(name), _) => Value::Int(wrap(i, Some(name)), Some(name)),
+            (Value::Bool(b), Some(name), _) => Value::Int(b as i128, Some(name)),
+            (Value::Char(c), Some(name), _) => Value::Int(wrap(c as i128, Some(name)), Some(name)),
+            (Value::Float(x), Some("u128"), _) => Value::Int(x as u128 as i128, Some("u128")),
+            (Value::Float(x), Some(name), _) => {
+                Value::Int(saturate(x as i128, Some(name)), Some(name))
+            }
+            (Value::Int(i, Some("u128")), None, Type::Path { name, .. })
+                if name == "f32" || name == "f64" =>
+            {
+                Value::Float(i as u128 as f64)
+            }
+            (Value::Int(i, _), None, Type::Path { name, .. }) if name == "f32" || name == "f64" => {
+                Value::Float(i as f64)
+            }
+            (Value::Float(x), None, Type::Path { name, .. }) if name == "f32" || name == "f64" => {
+                Value::Float(x)
+            }
+            (Value::Int(i, Some("u8")), None, Type::Path { name, .. }) if name == "char" => {
+                Value::Char(i as u8 as char)
+            }
+            (value, _, _) => {
+                return Err(
+                    self.failure(span, format!("non-primitive cast: `{}` as `{}`", value, ty))
+                )
+            }
+        })
+    }
+
+    /// 给局部变量、数组元素或元组字段赋值
+    fn assign(&mut self, place: &Expr, value: Value) -> Eval<()> {
+        let mut path = Vec::new();
+        let name = self.place_path(place, &mut path)?;
+        let mut root = match self.lookup(&name) {
+            Some(root) => root.clone(),
+            None => return Err(Flow::Error(None)),
+        };
+        if let Err(message) = set(&mut root, &path, value) {
+            return Err(self.failure(place.span, message));
+        }
+        *self.lookup(&name).unwrap() = root;
+        Ok(())
+    }
+
+    /// 位置表达式的根变量, 以及一路上的下标
+    fn place_path(&mut self, place: &Expr, path: &mut Vec<usize>) -> Eval<String> {
+        match &place.kind {
+            ExprKind::Path(names) if names.len() == 1 => Ok(names[0].clone()),
+            ExprKind::Paren(inner) | ExprKind::Unary(UnOp::Deref, inner) => {
+                self.place_path(inner, path)
+            }
+            ExprKind::Field(base, field) => {
+                let name = self.place_path(base, path)?;
+                path.push(field.parse().unwrap_or(usize::MAX));
+                Ok(name)
+            }
+            ExprKind::Index(base, index) => {
+                let name = self.place_path(base, path)?;
+                match self.expr(index, &Type::named("usize"))? {
+                    Value::Int(i, _) => path.push(usize::try_from(i).unwrap_or(usize::MAX)),
+                    other => {
+                        return Err(
+                            self.failure(index.span, format!("cannot index with `{}`", other))
+                        )
+                    }
+                }
+                Ok(name)
+            }
+            _ => Err(self.failure(
+                place.span,
+                "invalid left-hand side of assignment".to_string(),
+            )),
+        }
+    }
+}
+
+fn set(target: &mut Value, path: &[usize], value: Value) -> Result<(), String> {
+    let (index, rest) = match path.split_first() {
+        Some(split) => split,
+        None => {
+            *target = value;
+            return Ok(());
+        }
+    };
+    match target {
+        Value::Array(values) | Value::Tuple(values) => {
+            let len = values.len();
+            match values.get_mut(*index) {
+                Some(item) => set(item, rest, value),
+                None => Err(format!(
+                    "index out of bounds: the length is {} but the index is {}",
+                    len, index
+                )),
+            }
+        }
+        other => Err(format!("cannot index into `{}`", other)),
+    }
+}
+
+/// 类型中的整数类型名
+pub(super) fn integer(ty: &Type) -> Option<&'static str> {
+    match ty {
+        Type::Path { name, args } if args.is_empty() => {
+            INTEGERS.iter().copied().find(|i| i == name)
+        }
+        _ => None,
+    }
+}
+
+pub(super) fn bits(ty: &str) -> u32 {
+    match &ty[1..] {
+        "size" => 64,
+        bits => bits.parse().unwrap_or(128),
+    }
+}
+
+fn mask(bits: u32) -> u128 {
+    if bits >= 128 {
+        u128::MAX
+    } else {
+        (1u128 << bits) - 1
+    }
+}
+
+/// 类型的取值范围, `u128` 的上界按 `i128::MAX` 计算
+pub(super) fn range(ty: &str) -> (i128, i128) {
+    let bits = bits(ty);
+    match (ty.starts_with('i'), bits) {
+        (_, 128) if ty == "u128" => (0, i128::MAX),
+        (_, 128) => (i128::MIN, i128::MAX),
+        (true, bits) => (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1),
+        (false, bits) => (0, (1i128 << bits) - 1),
+    }
+}
+
+pub(super) fn in_range(value: i128, ty: &str) -> bool {
+    let (min, max) = range(ty);
+    min <= value && value <= max
+}
+
+/// 按类型的宽度回绕, 与 `as` 转换的语义一致
+pub(super) fn wrap(value: i128, ty: Option<&str>) -> i128 {
+    let ty = match ty {
+        Some(ty) if bits(ty) < 128 => ty,
+        _ => return value,
+    };
+    let bits = bits(ty);
+    let truncated = (value as u128 & mask(bits)) as i128;
+    if ty.starts_with('i') && truncated >> (bits - 1) == 1 {
+        truncated - (1i128 << bits)
+    } else {
+        truncated
+    }
+}
+
+fn saturate(value: i128, ty: Option<&str>) -> i128 {
+    match ty {
+        Some(ty) => {
+            let (min, max) = range(ty);
+            value.clamp(min, max)
+        }
+        None => value,
+    }
+}
+
+/// 整数类型的关联常量 `i32::MIN`、`u128::MAX` 等
+fn limit(path: &str) -> Option<Value> {
+    let (ty, name) = path.split_once("::")?;
+    let ty = integer(&Type::named(ty))?;
+    let (min, max) = range(ty);
+    match name {
+        "MIN" => Some(Value::Int(min, Some(ty))),
+        "MAX" if ty == "u128" => Some(Value::Int(u128::MAX as i128, Some(ty))),
+        "MAX" => Some(Value::Int(max, Some(ty))),
+        "BITS" => Some(Value::Int(i128::from(bits(ty)), Some("u32"))),
+        _ => None,
+    }
+}
+
+/// 按类型的宽度做算术运算, 溢出时返回 `None`. 没有类型的整数只检查 `i128` 本身是否溢出
+fn arithmetic(op: BinOp, a: i128, b: i128, ty: Option<&str>) -> Option<i128> {
+    if ty == Some("u128") {
+        let (a, b) = (a as u128, b as u128);
+        let result = match op {
+            BinOp::Add => a.checked_add(b),
+            BinOp::Sub => a.checked_sub(b),
+            BinOp::Mul => a.checked_mul(b),
+            BinOp::Div => a.checked_div(b),
+            BinOp::Rem => a.checked_rem(b),
+            BinOp::BitAnd => Some(a & b),
+            BinOp::BitOr => Some(a | b),
+            BinOp::BitXor => Some(a ^ b),
+            _ => unreachable!("比较、逻辑与移位运算已经处理过了"),
+        };
+        return result.map(|r| r as i128);
+    }
+    let fits = |r: &i128| ty.is_none_or(|ty| in_range(*r, ty));
+    match op {
+        BinOp::Add => a.checked_add(b).filter(fits),
+        BinOp::Sub => a.checked_sub(b).filter(fits),
+        BinOp::Mul => a.checked_mul(b).filter(fits),
+        BinOp::Div => a.checked_div(b).filter(fits),
+        // 余数本身不会越界, 但 `i32::MIN % -1` 对应的商溢出了, rustc 同样报告溢出
+        BinOp::Rem => a.checked_div(b).filter(fits).and_then(|_| a.checked_rem(b)),
+        BinOp::BitAnd => Some(a & b),
+        BinOp::BitOr => Some(a | b),
+        BinOp::BitXor => Some(a ^ b),
+        _ => unreachable!("比较、逻辑与移位运算已经处理过了"),
+    }
+}
+
+/// 取负, 无符号数只有 0 可以取负
+fn negate(value: i128, ty: Option<&str>) -> Option<i128> {
+    match ty {
+        Some(ty) if ty.starts_with('u') => (value == 0).then_some(0),
+        _ => value
+            .checked_neg()
+            .filter(|n| ty.is_none_or(|ty| in_range(*n, ty))),
+    }
+}
+
+/// 按 rustc 的写法显示运算数, 例如 `1_i32`、`u8::MAX`
+fn operand(value: i128, ty: Option<&str>) -> String {
+    let ty = ty.unwrap_or("i32");
+    if ty == "u128" {
+        return match value as u128 {
+            u128::MAX => "u128::MAX".to_string(),
+            value => format!("{}_u128", value),
+        };
+    }
+    let (min, max) = range(ty);
+    if value == max {
+        format!("{}::MAX", ty)
+    } else if value == min && min != 0 {
+        format!("{}::MIN", ty)
+    } else {
+        format!("{}_{}", value, ty)
+    }
+}
+
+/// 值的类型, 用作后续字面量的类型提示
+fn type_of(value: &Value) -> Type {
+    match value {
+        Value::Int(_, Some(ty)) => Type::named(ty),
+        _ => Type::Infer,
+    }
+}
+
+fn element_hint(hint: &Type) -> Type {
+    match hint {
+        Type::Array(elem, _) | Type::Slice(elem) => (**elem).clone(),
+        Type::Ref { inner, .. } => element_hint(inner),
+        _ => Type::Infer,
+    }
+}
+
+/// 比较两个值, 整数不区分类型
+fn same(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Int(a, _), Value::Int(b, _)) => a == b,
+        (Value::Tuple(a), Value::Tuple(b)) | (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same(a, b))
+        }
+        (a, b) => a == b,
+    }
+}
+
+fn order(a: &Value, b: &Value) -> Option<std::cmp::Ordering> {
+    match (a, b) {
+        (Value::Int(a, Some("u128")), Value::Int(b, _))
+        | (Value::Int(a, _), Value::Int(b, Some("u128"))) => Some((*a as u128).cmp(&(*b as u128))),
+        (Value::Int(a, _), Value::Int(b, _)) => Some(a.cmp(b)),
+        (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+        (Value::Char(a), Value::Char(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+/// 运行时代码中的数组长度 `[x; N]`, 它们处于常量上下文中
+fn array_lengths<'a>(stmts: &'a [Stmt], out: &mut Vec<&'a Expr>) {
+    fn visit<'a>(expr: &'a Expr, out: &mut Vec<&'a Expr>) {
+        match &expr.kind {
+            ExprKind::Repeat(item, len) => {
+                visit(item, out);
+                out.push(len);
+            }
+            ExprKind::Block(block) | ExprKind::Loop(block) => array_lengths(&block.stmts, out),
+            ExprKind::Paren(inner)
+            | ExprKind::Unary(_, inner)
+            | ExprKind::Ref { expr: inner, .. }
+            | ExprKind::Field(inner, _)
+            | ExprKind::Cast(inner, _) => visit(inner, out),
+            ExprKind::Tuple(items) | ExprKind::Array(items) => {
+                items.iter().for_each(|item| visit(item, out))
+            }
+            ExprKind::Macro { args, .. } | ExprKind::Call(_, args) => {
+                args.iter().for_each(|arg| visit(arg, out))
+            }
+            _ => {}
+        }
+    }
+    for stmt in stmts {
+        match &stmt.kind {
+            StmtKind::Let {
+                init: Some(expr), ..
+            }
+            | StmtKind::Expr(expr)
+            | StmtKind::Semi(expr)
+            | StmtKind::Use(expr) => visit(expr, out),
+            _ => {}
+        }
+    }
+}
+
+/// # 编译期求值的沙盒
+///
+/// 先用 [`register`](Sandbox::register) 登记常量函数与常量, 它们可以在之后的每次求值中使用
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::lang::consteval::{Sandbox, Value};
+///
+/// let mut sandbox = Sandbox::new();
+/// // function::init_len
+/// sandbox.register("const fn init_len() -> usize { return 5; }").unwrap();
+/// let result = sandbox.evaluate("let buffer = [0; init_len()];").unwrap();
+/// assert_eq!(result.values[0].to_string(), " 1:18  [_; init_len()]: usize = 5");
+/// let result = sandbox.evaluate("const LEN: usize = init_len() * 2;").unwrap();
+/// assert_eq!(result.get("LEN"), Some(&Value::Int(10, Some("usize"))));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Sandbox {
+    prelude: Vec<Stmt>,
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        Sandbox::default()
+    }
+
+    /// 登记 `const fn`、`fn`、`const` 与 `static` 项, 其他语句被忽略
+    pub fn register(&mut self, src: &str) -> Result<&mut Self, ParseError> {
+        let stmts = parse_stmts(src)?;
+        self.prelude.extend(
+            stmts
+                .into_iter()
+                .filter(|stmt| matches!(stmt.kind, StmtKind::Item(_))),
+        );
+        Ok(self)
+    }
+
+    /// 常量检查并求出 `src` 中每个常量、静态变量以及数组长度的值
+    pub fn evaluate(&self, src: &str) -> Result<ConstEval, ParseError> {
+        super::with_eval_stack(|| self.run(src))
+    }
+
+    fn run(&self, src: &str) -> Result<ConstEval, ParseError> {
+        let stmts = parse_stmts(src)?;
+        let mut fns = HashMap::new();
+        let mut globals = HashMap::new();
+        let mut order = Vec::new();
+        let mut runtime = Vec::new();
+        for (stmt, registered) in self
+            .prelude
+            .iter()
+            .map(|s| (s, true))
+            .chain(stmts.iter().map(|s| (s, false)))
+        {
+            match &stmt.kind {
+                StmtKind::Item(Item::Fn(decl)) => {
+                    fns.insert(decl.name.clone(), decl.clone());
+                }
+                StmtKind::Item(Item::Const {
+                    name,
+                    ty,
+                    expr,
+                    span,
+                })
+                | StmtKind::Item(Item::Static {
+                    name,
+                    ty,
+                    expr,
+                    span,
+                    ..
+                }) => {
+                    let context = match &stmt.kind {
+                        StmtKind::Item(Item::Const { .. }) => Context::Const,
+                        _ => Context::Static,
+                    };
+                    globals.insert(
+                        name.clone(),
+                        Global {
+                            ty: ty.clone(),
+                            expr: expr.clone(),
+                            span: *span,
+                            context,
+                        },
+                    );
+                    if !registered {
+                        order.push(name.clone());
+                    }
+                }
+                StmtKind::Let { pat, .. } => {
+                    for binding in pat.bindings() {
+                        if let PatKind::Binding { name, .. } = &binding.kind {
+                            runtime.push(name.clone());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut diagnostics = Vec::new();
+        let checker = |context: Context, locals: Vec<String>| ConstChecker {
+            context,
+            fns: &fns,
+            globals: &globals,
+            runtime: &runtime,
+            locals,
+            diagnostics: Vec::new(),
+        };
+        // 常量函数体
+        let mut rejected = Vec::new();
+        let mut decls: Vec<&FnDecl> = fns.values().filter(|decl| decl.is_const).collect();
+        decls.sort_by_key(|decl| decl.span);
+        for decl in decls {
+            let params = decl
+                .params
+                .iter()
+                .flat_map(|p| p.pat.bindings())
+                .filter_map(|b| match &b.kind {
+                    PatKind::Binding { name, .. } => Some(name.clone()),
+                    _ => None,
+                })
+                .collect();
+            let mut checker = checker(Context::ConstFn, params);
+            checker.block(&decl.body);
+            if !checker.diagnostics.is_empty() {
+                rejected.push(decl.name.clone());
+            }
+            diagnostics.extend(checker.diagnostics);
+        }
+        // 常量与静态变量的初始化表达式
+        let mut checked = Vec::new();
+        for name in &order {
+            let global = &globals[name];
+            let mut checker = checker(global.context, Vec::new());
+            checker.expr(&global.expr);
+            if checker.diagnostics.is_empty() {
+                checked.push(name.clone());
+            }
+            diagnostics.extend(checker.diagnostics);
+        }
+        // 运行时代码中的数组长度
+        let mut lengths = Vec::new();
+        array_lengths(&stmts, &mut lengths);
+        let mut checked_lengths = Vec::new();
+        for len in lengths {
+            let mut checker = checker(Context::Const, Vec::new());
+            checker.expr(len);
+            if checker.diagnostics.is_empty() {
+                checked_lengths.push(len);
+            }
+            diagnostics.extend(checker.diagnostics);
+        }
+
+        let mut evaluator = Evaluator {
+            fns: &fns,
+            globals: &globals,
+            rejected: &rejected,
+            states: HashMap::new(),
+            scopes: vec![Vec::new()],
+            current: String::new(),
+            calls: Vec::new(),
+            budget: STEP_LIMIT,
+            diagnostics: Vec::new(),
+        };
+        let mut values = Vec::new();
+        for name in &checked {
+            if let Ok(value) = evaluator.global(name, globals[name].span) {
+                let global = &globals[name];
+                values.push(Evaluated {
+                    span: global.span,
+                    name: name.clone(),
+                    ty: global.ty.clone(),
+                    value,
+                });
+            }
+        }
+        let usize_ty = Type::named("usize");
+        for len in checked_lengths {
+            evaluator.current = format!("[_; {}]", len);
+            evaluator.scopes = vec![Vec::new()];
+            let result = evaluator
+                .expr(len, &usize_ty)
+                .and_then(|value| evaluator.fit(value, &usize_ty, len.span));
+            match result {
+                Ok(value) => values.push(Evaluated {
+                    span: len.span,
+                    name: format!("[_; {}]", len),
+                    ty: usize_ty.clone(),
+                    value,
+                }),
+                Err(Flow::Error(Some(diagnostic))) => evaluator.diagnostics.push(diagnostic),
+                Err(_) => {}
+            }
+        }
+        diagnostics.extend(evaluator.diagnostics);
+        Ok(ConstEval {
+            values,
+            diagnostics,
+        })
+    }
+}
+
+/// # 编译期求值
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::lang::consteval::{evaluate, Value};
+///
+/// let src = "
+/// const fn fizzbuzz_words() -> [u8; 15] {
+///     let mut table = [0; 15];
+///     let mut i = 0;
+///     while i < 15 {
+///         table[i] = match (i % 3, i % 5) { (0, 0) => 3, (0, _) => 1, (_, 0) => 2, _ => 0 };
+///         i += 1;
+///     }
+///     table
+/// }
+/// const WORDS: [u8; 15] = fizzbuzz_words();
+/// const FIFTEEN: u8 = WORDS[15 % 15];
+/// ";
+/// let result = evaluate(src).unwrap();
+/// assert!(result.is_ok());
+/// assert_eq!(result.get("WORDS").unwrap().to_string(), "[3, 0, 0, 1, 0, 2, 1, 0, 0, 1, 2, 0, 1, 0, 0]");
+/// assert_eq!(result.get("FIFTEEN"), Some(&Value::Int(3, Some("u8"))));
+///
+/// // 溢出在编译期就被发现
+/// let result = evaluate("const B: u8 = 255 + 1;").unwrap();
+/// assert_eq!(result.to_string(), "\
+/// error[E0080]: 1:15: attempt to compute `u8::MAX + 1_u8`, which would overflow\n");
+///
+/// // 常量中不能调用普通函数、分配堆内存或者使用运行时的变量
+/// let result = evaluate("fn foo() -> usize { 3 } const A: usize = foo(); const V: Vec<i32> = vec![1]; let x = 5; const Y: i32 = x;").unwrap();
+/// let codes: Vec<_> = result.diagnostics.iter().map(|d| d.code.unwrap()).collect();
+/// assert_eq!(codes, ["E0015", "E0010", "E0435"]);
+/// assert_eq!(result.diagnostics[0].message, "cannot call non-const function `foo` in constants");
+///
+/// // const fn 中不能使用 for 循环
+/// let result = evaluate("const fn sum(n: usize) -> usize { let mut s = 0; for i in 0..n { s += i; } s }").unwrap();
+/// assert_eq!(result.diagnostics[0].message, "cannot use `for` loop on `0..n` in constant functions");
+/// ```
+pub fn evaluate(src: &str) -> Result<ConstEval, ParseError> {
+    Sandbox::new().evaluate(src)
+}
@@ -0,0 +1,173 @@
+//! # 诊断信息
+//!
+//! 各个分析器共用的诊断信息, 可以按 rustc 的格式连同源码一起输出:
+//!
+//! ```text
+//! error[E0382]: use of moved value: `b`
+//!  --> 1:47
+//!   |
+//! 1 | let c = b; use b;
+//!   |         - value moved here
+//!   |                ^ value used here after move
+//! ```
+
+use std::fmt;
+
+use super::lexer::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+    Error,
+    Note,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// rustc 的错误码, 例如 `E0070`
+    pub code: Option<&'static str>,
+    pub span: Span,
+    pub message: String,
+    /// 主位置上的说明
+    pub label: Option<String>,
+    /// 其他位置上的说明, 例如 "value moved here"
+    pub notes: Vec<(Span, String)>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, span: Span, message: String) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code: Some(code),
+            span,
+            message,
+            label: None,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn note(span: Span, message: String) -> Self {
+        Diagnostic {
+            severity: Severity::Note,
+            code: None,
+            span,
+            message,
+            label: None,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    pub fn with_note(mut self, span: Span, note: &str) -> Self {
+        self.notes.push((span, note.to_string()));
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    fn header(&self) -> String {
+        match (self.severity, self.code) {
+            (Severity::Error, Some(code)) => format!("error[{}]: {}", code, self.message),
+            (Severity::Error, None) => format!("error: {}", self.message),
+            (Severity::Note, _) => format!("note: {}", self.message),
+        }
+    }
+
+    /// # 连同源码一起输出
+    ///
+    /// 标注按位置排序, 主位置用 `^` 标出, 其他位置用 `-` 标出
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use rust_programming_of_zhd::lang::diagnostic::Diagnostic;
+    /// use rust_programming_of_zhd::lang::lexer::Span;
+    ///
+    /// let src = "let c = b;\nuse b;";
+    /// let diagnostic = Diagnostic::error("E0382", Span::new(2, 5), "use of moved value: `b`".to_string())
+    ///     .with_label("value used here after move")
+    ///     .with_note(Span::new(1, 9), "value moved here");
+    /// assert_eq!(diagnostic.render(src), "\
+    /// error[E0382]: use of moved value: `b`
+    ///  --> 2:5
+    ///   |
+    /// 1 | let c = b;
+    ///   |         - value moved here
+    /// 2 | use b;
+    ///   |     ^ value used here after move
+    /// ");
+    /// ```
+    pub fn render(&self, src: &str) -> String {
+        let lines: Vec<&str> = src.lines().collect();
+        let mut labels: Vec<(Span, char, &str)> = self
+            .notes
+            .iter()
+            .map(|(span, note)| (*span, '-', note.as_str()))
+            .collect();
+        labels.push((self.span, '^', self.label.as_deref().unwrap_or("")));
+        labels.sort_by_key(|(span, marker, _)| (*span, *marker == '^'));
+        let gutter = labels
+            .iter()
+            .map(|(span, ..)| span.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let pad = " ".repeat(gutter);
+        let mut out = format!("{}\n{}--> {}\n{} |\n", self.header(), pad, self.span, pad);
+        let mut current = 0;
+        for (span, marker, text) in labels {
+            let line = match lines.get(span.line.wrapping_sub(1)) {
+                Some(line) => *line,
+                None => continue,
+            };
+            if span.line != current {
+                out.push_str(&format!(
+                    "{:>width$} | {}\n",
+                    span.line,
+                    line,
+                    width = gutter
+                ));
+                current = span.line;
+            }
+            let before: String = line.chars().take(span.col - 1).collect();
+            let indent: String = before
+                .chars()
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let word = line
+                .chars()
+                .skip(span.col - 1)
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .count()
+                .max(1);
+            let underline = marker.to_string().repeat(word);
+            let label = if text.is_empty() {
+                underline
+            } else {
+                format!("{} {}", underline, text)
+            };
+            out.push_str(&format!("{} | {}{}\n", pad, indent, label));
+        }
+        out
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.severity, self.code) {
+            (Severity::Error, Some(code)) => write!(f, "error[{}]", code)?,
+            (Severity::Error, None) => f.write_str("error")?,
+            (Severity::Note, _) => f.write_str("note")?,
+        }
+        write!(f, ": {}: {}", self.span, self.message)?;
+        for (span, note) in &self.notes {
+            write!(f, "\n  {}: {}", span, note)?;
+        }
+        Ok(())
+    }
+}
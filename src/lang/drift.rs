@@ -0,0 +1,591 @@
+//! # 文档示例与实现的偏差检查
+//!
+//! `ch01` 和 `ch02` 中的很多函数会在文档注释里把自己的实现再抄写一遍,
+//! 两份代码分开维护, 时间一长就会出现偏差(例如一边是 `1...3`, 另一边已经改成了 `1..=3`)
+//!
+//! 这里逐个找出文档示例中与被注释函数同名的 `fn`, 把两者都切分成记号,
+//! 经过规范化后按记号做最长公共子序列比较, 报告不一致的片段. 规范化会忽略:
+//!
+//! - 注释和空白
+//! - 可见性(`pub`、`pub(crate)` 等)
+//! - 紧贴在右括号之前的尾随逗号
+//! - 块末尾 `return expr;` 与 `expr` 的写法差异
+//!
+//! 有些函数只是把同名的 `xxx_to(&mut io::stdout())` 转发到标准输出, 真正的代码在 `xxx_to` 中.
+//! 这时改为拿文档示例与 `xxx_to` 的函数体比较, 并把 `writeln!(out, ...)?` 看作 `println!(...)`,
+//! 忽略末尾的 `Ok(())`
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::lexer::{tokenize, LexError, Token, TokenKind};
+
+/// 默认检查的目录, 相对于 crate 根目录
+pub const DEFAULT_DIRS: [&str; 2] = ["src/ch01", "src/ch02"];
+
+/// 一处连续的差异, `removed` 只出现在文档中, `added` 只出现在实现中
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub removed: Vec<Token>,
+    pub added: Vec<Token>,
+}
+
+/// 某个函数的文档示例与实现之间的偏差
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Drift {
+    pub file: String,
+    pub function: String,
+    /// 文档示例中 `fn` 所在的行
+    pub doc_line: usize,
+    /// 实现中 `fn` 所在的行
+    pub src_line: usize,
+    pub hunks: Vec<Hunk>,
+}
+
+fn join(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|t| t.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl Drift {
+    /// 不含行号的摘要, 用作已知偏差的基线, 这样在文件中增删无关的行不会影响比较
+    pub fn fingerprint(&self) -> String {
+        let mut out = format!("{}: fn {}\n", self.file, self.function);
+        for hunk in &self.hunks {
+            if !hunk.removed.is_empty() {
+                out.push_str(&format!("  - {}\n", join(&hunk.removed)));
+            }
+            if !hunk.added.is_empty() {
+                out.push_str(&format!("  + {}\n", join(&hunk.added)));
+            }
+        }
+        out
+    }
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: fn {} (doc line {}, src line {})",
+            self.file, self.function, self.doc_line, self.src_line
+        )?;
+        for hunk in &self.hunks {
+            if let Some(first) = hunk.removed.first() {
+                writeln!(f, "  - doc {}: {}", first.span.line, join(&hunk.removed))?;
+            }
+            if let Some(first) = hunk.added.first() {
+                writeln!(f, "  + src {}: {}", first.span.line, join(&hunk.added))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 一段文档注释中的示例代码, 以及每一行在源文件中的行号
+struct DocExample {
+    code: String,
+    lines: Vec<usize>,
+}
+
+/// 一段连续的 `///` 注释, `end` 为最后一行的行号
+struct DocBlock {
+    examples: Vec<DocExample>,
+    end: usize,
+}
+
+/// 代码块的语言标记是否表示 Rust 代码
+fn is_rust_fence(info: &str) -> bool {
+    info.split(',')
+        .map(str::trim)
+        .all(|attr| !matches!(attr, "text" | "sh" | "console" | "json" | "toml" | "ignore"))
+}
+
+fn doc_blocks(src: &str) -> Vec<DocBlock> {
+    let mut blocks = Vec::new();
+    let mut current: Option<DocBlock> = None;
+    let mut fence: Option<DocExample> = None;
+    for (index, line) in src.lines().enumerate() {
+        let number = index + 1;
+        let doc = match line.trim_start().strip_prefix("///") {
+            Some(doc) if !doc.starts_with('/') => doc.strip_prefix(' ').unwrap_or(doc),
+            _ => {
+                blocks.extend(current.take());
+                fence = None;
+                continue;
+            }
+        };
+        let block = current.get_or_insert_with(|| DocBlock {
+            examples: Vec::new(),
+            end: number,
+        });
+        block.end = number;
+        if let Some(info) = doc.trim_start().strip_prefix("```") {
+            match fence.take() {
+                Some(example) => block.examples.push(example),
+                None if is_rust_fence(info) => {
+                    fence = Some(DocExample {
+                        code: String::new(),
+                        lines: Vec::new(),
+                    })
+                }
+                // 非 Rust 代码块用一个不会被收集的占位代替, 直到遇到结束标记
+                None => {
+                    fence = Some(DocExample {
+                        code: String::new(),
+                        lines: vec![0],
+                    })
+                }
+            }
+        } else if let Some(example) = fence.as_mut() {
+            // rustdoc 会隐藏以 `# ` 开头的行, 但它们仍然是示例代码的一部分
+            let code = if doc.trim_start() == "#" {
+                ""
+            } else {
+                doc.trim_start().strip_prefix("# ").unwrap_or(doc)
+            };
+            example.code.push_str(code);
+            example.code.push('\n');
+            example.lines.push(number);
+        }
+    }
+    blocks.extend(current);
+    for block in &mut blocks {
+        block.examples.retain(|e| e.lines.first() != Some(&0));
+    }
+    blocks
+}
+
+/// 从 `tokens[start]` 处的 `fn` 开始, 返回整个函数的记号; 没有函数体时返回 `None`
+fn function_at(tokens: &[Token], start: usize) -> Option<&[Token]> {
+    let open = start
+        + tokens[start..]
+            .iter()
+            .position(|t| t.is_punct("{") || t.is_punct(";"))?;
+    if tokens[open].is_punct(";") {
+        return None;
+    }
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        if token.is_punct("{") {
+            depth += 1;
+        } else if token.is_punct("}") {
+            depth -= 1;
+            if depth == 0 {
+                return Some(&tokens[start..=i]);
+            }
+        }
+    }
+    None
+}
+
+/// 文档块之后的第一个条目如果是函数, 返回 `fn` 记号的下标
+fn documented_fn(tokens: &[Token], after_line: usize) -> Option<usize> {
+    let mut i = tokens.iter().position(|t| t.span.line > after_line)?;
+    loop {
+        let token = tokens.get(i)?;
+        if token.is_punct("#") {
+            // 跳过 `#[...]` 属性
+            let mut depth = 0;
+            for (j, t) in tokens.iter().enumerate().skip(i + 1) {
+                if t.is_punct("[") {
+                    depth += 1;
+                } else if t.is_punct("]") {
+                    depth -= 1;
+                    if depth == 0 {
+                        i = j + 1;
+                        break;
+                    }
+                }
+            }
+            if depth != 0 {
+                return None;
+            }
+        } else if token.is_ident("pub") && tokens.get(i + 1).is_some_and(|t| t.is_punct("(")) {
+            i += 1 + tokens[i + 1..].iter().position(|t| t.is_punct(")"))? + 1;
+        } else if ["pub", "const", "unsafe", "async", "extern"]
+            .iter()
+            .any(|k| token.is_ident(k))
+            || token.kind == TokenKind::Str
+        {
+            i += 1;
+        } else if token.is_ident("fn") {
+            return Some(i);
+        } else {
+            return None;
+        }
+    }
+}
+
+/// 在 `tokens` 中找到名为 `name` 的函数
+fn find_function<'a>(tokens: &'a [Token], name: &str) -> Option<&'a [Token]> {
+    let start = tokens
+        .windows(2)
+        .position(|w| w[0].is_ident("fn") && w[1].is_ident(name))?;
+    function_at(tokens, start)
+}
+
+/// 与 `open` 处的左括号配对的右括号下标
+fn closing(tokens: &[Token], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        if token.is_punct("(") || token.is_punct("[") || token.is_punct("{") {
+            depth += 1;
+        } else if is_closer(token) {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
+/// # 输出到 Writer 的实现
+///
+/// `wrapper` 的函数体调用了同一文件中的 `xxx_to` 时, 返回由 `wrapper` 的签名和 `xxx_to` 的函数体拼成的记号,
+/// 其中 `writeln!(out, ...)?` 和 `write!(out, ...)?` 改写为 `println!(...)` 和 `print!(...)`,
+/// 函数体末尾的 `Ok(())` 被去掉
+fn redirected(tokens: &[Token], name: &str, wrapper: &[Token]) -> Option<Vec<Token>> {
+    let target_name = format!("{}_to", name);
+    if !wrapper.iter().any(|t| t.is_ident(&target_name)) {
+        return None;
+    }
+    let target = find_function(tokens, &target_name)?;
+    let header = wrapper.iter().position(|t| t.is_punct("{"))?;
+    let open = target.iter().position(|t| t.is_punct("{"))?;
+    let mut body = &target[open + 1..target.len() - 1];
+    if let [rest @ .., ok, l1, l2, r2, r1] = body {
+        if ok.is_ident("Ok")
+            && l1.is_punct("(")
+            && l2.is_punct("(")
+            && r2.is_punct(")")
+            && r1.is_punct(")")
+        {
+            body = rest;
+        }
+    }
+
+    let mut out: Vec<Token> = wrapper[..=header].to_vec();
+    let mut skip_try = Vec::new();
+    let mut i = 0;
+    while i < body.len() {
+        let token = &body[i];
+        let printer = match token.text.as_str() {
+            "writeln" => Some("println"),
+            "write" => Some("print"),
+            _ => None,
+        };
+        let redirects = printer.is_some()
+            && token.kind == TokenKind::Ident
+            && body.get(i + 1).is_some_and(|t| t.is_punct("!"))
+            && body.get(i + 2).is_some_and(|t| t.is_punct("("))
+            && body.get(i + 3).is_some_and(|t| t.is_ident("out"));
+        if let (Some(printer), true) = (printer, redirects) {
+            let mut macro_name = token.clone();
+            macro_name.text = printer.to_string();
+            out.push(macro_name);
+            out.push(body[i + 1].clone());
+            out.push(body[i + 2].clone());
+            if let Some(close) = closing(body, i + 2) {
+                skip_try.push(close + 1);
+            }
+            // 跳过 `out` 以及紧随其后的逗号
+            i += 4;
+            if body.get(i).is_some_and(|t| t.is_punct(",")) {
+                i += 1;
+            }
+            continue;
+        }
+        if token.is_punct("?") && skip_try.contains(&i) {
+            i += 1;
+            continue;
+        }
+        out.push(token.clone());
+        i += 1;
+    }
+    out.push(target[target.len() - 1].clone());
+    Some(out)
+}
+
+fn is_closer(token: &Token) -> bool {
+    token.is_punct("}") || token.is_punct(")") || token.is_punct("]")
+}
+
+/// # 规范化
+///
+/// 去掉可见性和尾随逗号, 并把紧贴在 `}` 之前的 `return expr;` 改写成 `expr`
+fn normalize(tokens: &[Token]) -> Vec<Token> {
+    let mut out: Vec<Token> = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        if token.is_ident("pub") {
+            i += 1;
+            if tokens.get(i).is_some_and(|t| t.is_punct("(")) {
+                while i < tokens.len() && !tokens[i].is_punct(")") {
+                    i += 1;
+                }
+                i += 1;
+            }
+            continue;
+        }
+        if token.is_punct(",") && tokens.get(i + 1).is_some_and(is_closer) {
+            i += 1;
+            continue;
+        }
+        out.push(token.clone());
+        i += 1;
+    }
+    // 找出每个 `return` 对应的 `;`, 如果它的后面紧跟着 `}` 就把两者一起去掉
+    let mut remove = vec![false; out.len()];
+    for start in 0..out.len() {
+        if !out[start].is_ident("return") {
+            continue;
+        }
+        let mut depth = 0i32;
+        for end in start + 1..out.len() {
+            let t = &out[end];
+            if t.is_punct("(") || t.is_punct("[") || t.is_punct("{") {
+                depth += 1;
+            } else if is_closer(t) {
+                depth -= 1;
+                if depth < 0 {
+                    break;
+                }
+            } else if depth == 0 && t.is_punct(";") {
+                if out.get(end + 1).is_some_and(|t| t.is_punct("}")) {
+                    remove[start] = true;
+                    remove[end] = true;
+                }
+                break;
+            }
+        }
+    }
+    out.into_iter()
+        .zip(remove)
+        .filter(|(_, removed)| !removed)
+        .map(|(token, _)| token)
+        .collect()
+}
+
+/// 两段差异之间的公共记号不超过这个数目时合并成一段, 避免报告过于零碎
+const MERGE_GAP: usize = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+/// 基于最长公共子序列的记号级差异
+fn diff(doc: &[Token], src: &[Token]) -> Vec<Hunk> {
+    let (n, m) = (doc.len(), src.len());
+    // lcs[i][j] 为 doc[i..] 与 src[j..] 的最长公共子序列长度
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if doc[i].text == src[j].text {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut ops = Vec::with_capacity(n + m);
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && doc[i].text == src[j].text {
+            ops.push(Op::Equal(i, j));
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            ops.push(Op::Added(j));
+            j += 1;
+        } else {
+            ops.push(Op::Removed(i));
+            i += 1;
+        }
+    }
+
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let mut k = 0;
+    while k < ops.len() {
+        let run = ops[k..]
+            .iter()
+            .take_while(|op| matches!(op, Op::Equal(..)))
+            .count();
+        if run > 0 {
+            // 夹在两段差异之间的短公共片段并入前一段差异
+            let between = !hunks.is_empty() && k + run < ops.len();
+            if between && run <= MERGE_GAP {
+                let hunk = hunks.last_mut().expect("checked above");
+                for op in &ops[k..k + run] {
+                    if let Op::Equal(i, j) = *op {
+                        hunk.removed.push(doc[i].clone());
+                        hunk.added.push(src[j].clone());
+                    }
+                }
+            } else {
+                hunks.push(Hunk {
+                    removed: Vec::new(),
+                    added: Vec::new(),
+                });
+            }
+            k += run;
+            continue;
+        }
+        if hunks.is_empty() {
+            hunks.push(Hunk {
+                removed: Vec::new(),
+                added: Vec::new(),
+            });
+        }
+        let hunk = hunks.last_mut().expect("pushed above");
+        match ops[k] {
+            Op::Removed(i) => hunk.removed.push(doc[i].clone()),
+            Op::Added(j) => hunk.added.push(src[j].clone()),
+            Op::Equal(..) => unreachable!(),
+        }
+        k += 1;
+    }
+    hunks.retain(|h| !h.removed.is_empty() || !h.added.is_empty());
+    hunks
+}
+
+/// # 检查单个源文件
+///
+/// `file` 只用于报告. 文档示例无法切分成记号时直接跳过, 源文件本身无法切分时返回错误
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::lang::drift::check_source;
+///
+/// let src = r#"
+/// /// ```
+/// /// fn answer() -> i32 {
+/// ///     return 42;
+/// /// }
+/// /// assert_eq!(answer(), 42);
+/// /// ```
+/// pub fn answer() -> i32 {
+///     42 // 与文档一致
+/// }
+///
+/// /// ```
+/// /// fn range(n: i32) -> bool {
+/// ///     match n { 1...3 => true, _ => false, }
+/// /// }
+/// /// ```
+/// pub fn range(n: i32) -> bool {
+///     match n { 1..=3 => true, _ => false }
+/// }
+/// "#;
+/// let drifts = check_source("lib.rs", src).unwrap();
+/// assert_eq!(drifts.len(), 1);
+/// assert_eq!(drifts[0].function, "range");
+/// assert_eq!(
+///     drifts[0].to_string(),
+///     "lib.rs: fn range (doc line 13, src line 17)\n  - doc 14: ...\n  + src 18: ..=\n"
+/// );
+/// ```
+pub fn check_source(file: &str, src: &str) -> Result<Vec<Drift>, LexError> {
+    let tokens = tokenize(src)?;
+    let mut drifts = Vec::new();
+    for block in doc_blocks(src) {
+        let start = match documented_fn(&tokens, block.end) {
+            Some(start) => start,
+            None => continue,
+        };
+        let name = match tokens.get(start + 1) {
+            Some(name) => &name.text,
+            None => continue,
+        };
+        let implementation = match function_at(&tokens, start) {
+            Some(implementation) => implementation,
+            None => continue,
+        };
+        let redirected = redirected(&tokens, name, implementation);
+        let implementation = redirected.as_deref().unwrap_or(implementation);
+        for example in &block.examples {
+            let mut doc_tokens = match tokenize(&example.code) {
+                Ok(doc_tokens) => doc_tokens,
+                Err(_) => continue,
+            };
+            // 把示例中的行号换算成源文件中的行号
+            for token in &mut doc_tokens {
+                token.span.line = example.lines[token.span.line - 1];
+            }
+            let doc_start = doc_tokens
+                .windows(2)
+                .position(|w| w[0].is_ident("fn") && w[1].text == *name);
+            let documented = match doc_start.and_then(|i| function_at(&doc_tokens, i)) {
+                Some(documented) => documented,
+                None => continue,
+            };
+            let hunks = diff(&normalize(documented), &normalize(implementation));
+            if !hunks.is_empty() {
+                drifts.push(Drift {
+                    file: file.to_string(),
+                    function: name.clone(),
+                    doc_line: documented[0].span.line,
+                    src_line: implementation[0].span.line,
+                    hunks,
+                });
+            }
+        }
+    }
+    Ok(drifts)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<std::path::PathBuf>) -> io::Result<()> {
+    if dir.is_file() {
+        files.push(dir.to_path_buf());
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if path.extension().is_some_and(|e| e == "rs") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// # 检查若干文件或目录
+///
+/// `paths` 相对于 `root`, 目录会被递归遍历; 报告中的文件名同样相对于 `root`
+pub fn check_paths(root: &Path, paths: &[&str]) -> io::Result<Vec<Drift>> {
+    let mut files = Vec::new();
+    for path in paths {
+        collect_files(&root.join(path), &mut files)?;
+    }
+    files.sort();
+    let mut drifts = Vec::new();
+    for path in files {
+        let src = fs::read_to_string(&path)?;
+        let label = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let found = check_source(&label, &src)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}:{}", label, e)))?;
+        drifts.extend(found);
+    }
+    Ok(drifts)
+}
+
+/// 把全部偏差拼成一份报告
+pub fn report(drifts: &[Drift]) -> String {
+    drifts.iter().map(Drift::to_string).collect()
+}
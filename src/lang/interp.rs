@@ -0,0 +1,1494 @@
+//! # 表达式解释器
+//!
+//! [`ch02::answer`](crate::ch02::answer) 与 [`ch02::sum`](crate::ch02::sum) 说明了 Rust 中一切皆表达式:
+//! 块的值是最后一个不带分号的表达式的值, 带分号的表达式是语句, 它的值被丢弃, 语句本身的值是 `()`
+//!
+//! 这里是一个教学用的解释器, 支持 `let`、块、`if`/`else`、带值的 `break`、`while`、`for`、`match` 以及函数,
+//! 求值时记录每一步发生了什么, 例如块的值来自哪个表达式、`if` 走了哪个分支、`loop` 因为哪个 `break` 结束
+//!
+//! 为了简单起见, 引用被当作它所指向的值的副本, 整数统一用 `i128` 表示并记录它的类型,
+//! 算术运算按类型的取值范围检查溢出. 没有类型的整数按 rustc 的默认类型 `i32` 检查, 放不下时依次放宽到 `i64`、`i128`
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use super::ast::{BinOp, Block, Expr, ExprKind, FnDecl, Item, Lit, Pat, PatKind, Stmt, StmtKind};
+use super::ast::{Type, UnOp};
+use super::consteval::{bits, in_range, integer, range, wrap};
+use super::lexer::{tokenize, Span};
+use super::parser::{parse_stmts, ParseError};
+
+/// 一次求值最多执行的步数, 防止死循环
+const STEP_LIMIT: usize = 100_000;
+
+/// 函数调用的最大嵌套层数
+const CALL_DEPTH_LIMIT: usize = 200;
+
+/// `[x; N]` 的最大长度, 防止一次分配耗尽内存
+const ARRAY_LEN_LIMIT: i128 = 1 << 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Unit,
+    Bool(bool),
+    /// 整数及其类型, 没有后缀也无法从上下文得到类型的字面量为 `None`
+    Int(i128, Option<&'static str>),
+    Float(f64),
+    Char(char),
+    /// 字符串切片 `&str`, 例如字符串字面量
+    Str(String),
+    /// 拥有所有权的 `String`
+    String(String),
+    Tuple(Vec<Value>),
+    Array(Vec<Value>),
+    /// 半开区间 `start..end`
+    Range(i128, i128),
+    /// 函数名, 函数可以作为值传递, 例如 `math(sum, a, b)`
+    Fn(String),
+}
+
+impl Value {
+    /// 按 `{}` 格式化
+    pub fn display(&self) -> String {
+        match self {
+            Value::Str(s) | Value::String(s) => s.clone(),
+            Value::Char(c) => c.to_string(),
+            other => other.to_string(),
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Value::Unit => "()",
+            Value::Bool(_) => "bool",
+            Value::Int(..) => "integer",
+            Value::Float(_) => "float",
+            Value::Char(_) => "char",
+            Value::Str(_) => "`&str`",
+            Value::String(_) => "`String`",
+            Value::Tuple(_) => "tuple",
+            Value::Array(_) => "array",
+            Value::Range(..) => "range",
+            Value::Fn(_) => "fn",
+        }
+    }
+}
+
+/// 与 `{:?}` 的输出一致
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |values: &[Value]| {
+            values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            Value::Unit => f.write_str("()"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(i, _) => write!(f, "{}", i),
+            Value::Float(x) => write!(f, "{:?}", x),
+            Value::Char(c) => write!(f, "{:?}", c),
+            Value::Str(s) | Value::String(s) => write!(f, "{:?}", s),
+            Value::Tuple(values) if values.len() == 1 => write!(f, "({},)", values[0]),
+            Value::Tuple(values) => write!(f, "({})", list(values)),
+            Value::Array(values) => write!(f, "[{}]", list(values)),
+            Value::Range(start, end) => write!(f, "{}..{}", start, end),
+            Value::Fn(name) => write!(f, "fn {}", name),
+        }
+    }
+}
+
+/// 求值错误, 包括语法错误和运行时的 panic
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
+    }
+}
+
+impl Error for EvalError {}
+
+impl From<ParseError> for EvalError {
+    fn from(e: ParseError) -> Self {
+        EvalError {
+            message: e.message,
+            span: e.span,
+        }
+    }
+}
+
+fn error(span: Span, message: String) -> Flow {
+    Flow::Error(EvalError { message, span })
+}
+
+/// 求值过程中的一步
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    /// 嵌套深度: 每进入一个块或函数加一
+    pub depth: usize,
+    pub span: Span,
+    pub text: String,
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{:>5}  {}",
+            "  ".repeat(self.depth),
+            self.span.to_string(),
+            self.text
+        )
+    }
+}
+
+/// 一次求值的结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    pub value: Value,
+    pub steps: Vec<Step>,
+    /// `println!` 等宏输出的内容
+    pub output: String,
+}
+
+/// 非正常的控制流: `break`、`continue`、`return` 与错误
+enum Flow {
+    Break(Value),
+    Continue,
+    Return(Value),
+    Error(EvalError),
+}
+
+impl From<EvalError> for Flow {
+    fn from(e: EvalError) -> Self {
+        Flow::Error(e)
+    }
+}
+
+type Eval<T> = Result<T, Flow>;
+
+#[derive(Debug, Clone)]
+struct Binding {
+    name: String,
+    /// `let x;` 声明但尚未初始化时为 `None`
+    value: Option<Value>,
+    mutable: bool,
+}
+
+/// # 解释器会话
+///
+/// 顶层的 `let` 绑定和函数定义在多次求值之间保留, `repl` 子命令就是这样使用的
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::lang::interp::{Session, Value};
+///
+/// let mut session = Session::new();
+/// session.eval("fn sum(a: i32, b: i32) -> i32 { a + b }").unwrap();
+/// session.eval("let a = 40; let b = 2;").unwrap();
+/// assert_eq!(session.eval("sum(a, b)").unwrap().value, Value::Int(42, Some("i32")));
+/// // 带分号的表达式是语句, 值是 `()`
+/// assert_eq!(session.eval("sum(a, b);").unwrap().value, Value::Unit);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    globals: Vec<Binding>,
+    fns: HashMap<String, Arc<FnDecl>>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Session::default()
+    }
+
+    /// 求值一段代码, 代码的值是最后一个不带分号的表达式的值
+    pub fn eval(&mut self, src: &str) -> Result<Evaluation, EvalError> {
+        super::with_eval_stack(|| self.run(src))
+    }
+
+    fn run(&mut self, src: &str) -> Result<Evaluation, EvalError> {
+        let stmts = parse_stmts(src)?;
+        let mut interp = Interp {
+            scopes: vec![std::mem::take(&mut self.globals)],
+            fns: std::mem::take(&mut self.fns),
+            steps: Vec::new(),
+            output: String::new(),
+            depth: 0,
+            budget: STEP_LIMIT,
+            calls: 0,
+        };
+        let result = interp.stmts(&stmts, false);
+        self.globals = interp.scopes.swap_remove(0);
+        self.fns = interp.fns;
+        let value = match result {
+            Ok(value) | Err(Flow::Return(value)) => value,
+            Err(Flow::Break(_)) | Err(Flow::Continue) => {
+                let span = stmts.first().map_or(Span::new(1, 1), |s| s.span);
+                return Err(EvalError {
+                    message: "`break` 或 `continue` 出现在循环之外".to_string(),
+                    span,
+                });
+            }
+            Err(Flow::Error(e)) => return Err(e),
+        };
+        Ok(Evaluation {
+            value,
+            steps: interp.steps,
+            output: interp.output,
+        })
+    }
+}
+
+struct Interp {
+    scopes: Vec<Vec<Binding>>,
+    fns: HashMap<String, Arc<FnDecl>>,
+    steps: Vec<Step>,
+    output: String,
+    depth: usize,
+    budget: usize,
+    calls: usize,
+}
+
+impl Interp {
+    fn step(&mut self, span: Span, text: String) {
+        self.steps.push(Step {
+            depth: self.depth,
+            span,
+            text,
+        });
+    }
+
+    fn declare(&mut self, name: &str, value: Option<Value>, mutable: bool) {
+        self.scopes.last_mut().unwrap().push(Binding {
+            name: name.to_string(),
+            value,
+            mutable,
+        });
+    }
+
+    fn lookup(&mut self, name: &str) -> Option<&mut Binding> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|b| b.name == name)
+    }
+
+    fn scoped<T, F: FnOnce(&mut Self) -> Eval<T>>(&mut self, f: F) -> Eval<T> {
+        self.scopes.push(Vec::new());
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        self.scopes.pop();
+        result
+    }
+
+    // ---------------------------------------------------------------- 语句
+
+    fn block(&mut self, block: &Block) -> Eval<Value> {
+        self.scoped(|interp| interp.stmts(&block.stmts, true))
+    }
+
+    /// `in_block` 为 `false` 时是顶层代码, 此时结果不是块的值
+    fn stmts(&mut self, stmts: &[Stmt], in_block: bool) -> Eval<Value> {
+        for stmt in stmts {
+            if let StmtKind::Item(Item::Fn(decl)) = &stmt.kind {
+                self.fns.insert(decl.name.clone(), Arc::new(decl.clone()));
+            }
+        }
+        for (i, stmt) in stmts.iter().enumerate() {
+            let last = i + 1 == stmts.len();
+            match &stmt.kind {
+                StmtKind::Let { pat, ty, init } => match init {
+                    Some(init) => {
+                        let value = self.expr(init)?;
+                        let value = match ty {
+                            Some(ty) => fit(value, ty, init.span)?,
+                            None => value,
+                        };
+                        if !self.bind(pat, &value)? {
+                            return Err(error(
+                                pat.span,
+                                format!("模式 `{}` 不能匹配值 {}", pat, value),
+                            ));
+                        }
+                        self.step(stmt.span, format!("let {} = {}", pat, value));
+                    }
+                    None => {
+                        for binding in pat.bindings() {
+                            if let PatKind::Binding { name, mutable, .. } = &binding.kind {
+                                self.declare(name, None, *mutable);
+                            }
+                        }
+                        self.step(stmt.span, format!("let {}; 声明但没有初始化", pat));
+                    }
+                },
+                StmtKind::Expr(expr) if last => {
+                    let value = self.expr(expr)?;
+                    let text = if in_block {
+                        format!("块的值是尾表达式 `{}` 的值: {}", expr, value)
+                    } else {
+                        format!("结果是表达式 `{}` 的值: {}", expr, value)
+                    };
+                    self.step(expr.span, text);
+                    return Ok(value);
+                }
+                StmtKind::Expr(expr) => {
+                    // 块状表达式作为语句时不需要分号
+                    self.expr(expr)?;
+                }
+                StmtKind::Semi(expr) => {
+                    let value = self.expr(expr)?;
+                    if value != Value::Unit {
+                        self.step(
+                            stmt.span,
+                            format!(
+                                "`{};` 是语句, 表达式的值 {} 被丢弃, 语句的值是 ()",
+                                expr, value
+                            ),
+                        );
+                    }
+                }
+                StmtKind::Use(expr) => {
+                    let value = self.expr(expr)?;
+                    self.step(stmt.span, format!("use {}: {}", expr, value));
+                }
+                StmtKind::Item(item) => {
+                    self.step(stmt.span, format!("定义 {}", item.name()));
+                }
+            }
+        }
+        if in_block {
+            let span = stmts.last().map_or(Span::new(1, 1), |s| s.span);
+            self.step(span, "块没有尾表达式, 块的值是 ()".to_string());
+        }
+        Ok(Value::Unit)
+    }
+
+    /// 把值按模式解构到当前作用域, 不匹配时返回 `false`
+    fn bind(&mut self, pat: &Pat, value: &Value) -> Eval<bool> {
+        let mut bindings = Vec::new();
+        if !self.matches(pat, value, &mut bindings)? {
+            return Ok(false);
+        }
+        for (name, value, mutable) in bindings {
+            self.declare(&name, Some(value), mutable);
+        }
+        Ok(true)
+    }
+
+    fn matches(
+        &mut self,
+        pat: &Pat,
+        value: &Value,
+        bindings: &mut Vec<(String, Value, bool)>,
+    ) -> Eval<bool> {
+        Ok(match (&pat.kind, value) {
+            (PatKind::Wild, _) => true,
+            (
+                PatKind::Binding {
+                    name, mutable, sub, ..
+                },
+                _,
+            ) => {
+                if let Some(sub) = sub {
+                    if !self.matches(sub, value, bindings)? {
+                        return Ok(false);
+                    }
+                }
+                bindings.push((name.clone(), value.clone(), *mutable));
+                true
+            }
+            (PatKind::Lit(lit), _) => same(&self.expr(lit)?, value),
+            (
+                PatKind::Range {
+                    start,
+                    end,
+                    inclusive,
+                },
+                _,
+            ) => {
+                let start = self.expr(start)?;
+                let end = self.expr(end)?;
+                let above = compare(value, &start, pat.span)? != std::cmp::Ordering::Less;
+                let below = match compare(value, &end, pat.span)? {
+                    std::cmp::Ordering::Less => true,
+                    std::cmp::Ordering::Equal => *inclusive,
+                    std::cmp::Ordering::Greater => false,
+                };
+                above && below
+            }
+            (PatKind::Tuple(pats), Value::Tuple(values)) if pats.len() == values.len() => {
+                for (pat, value) in pats.iter().zip(values) {
+                    if !self.matches(pat, value, bindings)? {
+                        return Ok(false);
+                    }
+                }
+                true
+            }
+            (PatKind::Ref { pat, .. }, _) => self.matches(pat, value, bindings)?,
+            (PatKind::Or(alternatives), _) => {
+                for alternative in alternatives {
+                    let mut inner = Vec::new();
+                    if self.matches(alternative, value, &mut inner)? {
+                        bindings.extend(inner);
+                        return Ok(true);
+                    }
+                }
+                false
+            }
+            (PatKind::Tuple(_), _) => {
+                return Err(error(
+                    pat.span,
+                    format!("元组模式 `{}` 不能匹配 {}", pat, value.kind()),
+                ))
+            }
+            (PatKind::Path(_), _) | (PatKind::TupleStruct(..), _) => {
+                return Err(error(pat.span, format!("不支持枚举模式 `{}`", pat)))
+            }
+        })
+    }
+
+    // ---------------------------------------------------------------- 表达式
+
+    /// 消耗一步. 空的 `loop {}` 不求值任何表达式, 所以循环每执行一次也要消耗一步
+    fn tick(&mut self, span: Span) -> Eval<()> {
+        if self.budget == 0 {
+            return Err(error(
+                span,
+                format!("求值超过了 {} 步, 可能是死循环", STEP_LIMIT),
+            ));
+        }
+        self.budget -= 1;
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Eval<Value> {
+        self.tick(expr.span)?;
+        let span = expr.span;
+        match &expr.kind {
+            ExprKind::Lit(lit) => literal(lit, span),
+            ExprKind::Path(path) => {
+                let name = path.join("::");
+                let is_fn = self.fns.contains_key(&name);
+                match self.lookup(&name) {
+                    Some(Binding {
+                        value: Some(value), ..
+                    }) => Ok(value.clone()),
+                    Some(_) => Err(error(
+                        span,
+                        format!("used binding `{}` isn't initialized", name),
+                    )),
+                    None if is_fn => Ok(Value::Fn(name)),
+                    None => Err(error(
+                        span,
+                        format!("cannot find value `{}` in this scope", name),
+                    )),
+                }
+            }
+            ExprKind::Paren(inner) => self.expr(inner),
+            ExprKind::Unary(op, inner) => {
+                // 负号紧贴着的整数字面量按负数检查范围, `-128i8` 是合法的
+                if let (UnOp::Neg, ExprKind::Lit(Lit::Int { value, suffix })) =
+                    (op, &strip_parens(inner).kind)
+                {
+                    return int_literal(*value, suffix, true, span);
+                }
+                let value = self.expr(inner)?;
+                match (op, value) {
+                    (UnOp::Neg, Value::Int(i, ty)) => i
+                        .checked_neg()
+                        .filter(|n| in_range(*n, ty.unwrap_or_else(|| natural(&[i]))))
+                        .map(|n| Value::Int(n, ty))
+                        .ok_or_else(|| error(span, "attempt to negate with overflow".to_string())),
+                    (UnOp::Neg, Value::Float(x)) => Ok(Value::Float(-x)),
+                    (UnOp::Not, Value::Bool(b)) => Ok(Value::Bool(!b)),
+                    (UnOp::Not, Value::Int(i, ty)) => Ok(Value::Int(wrap(!i, ty), ty)),
+                    // 引用被当作值本身, 解引用什么也不做
+                    (UnOp::Deref, value) => Ok(value),
+                    (op, value) => Err(error(
+                        span,
+                        format!("cannot apply unary operator `{:?}` to {}", op, value.kind()),
+                    )),
+                }
+            }
+            ExprKind::Ref { expr, .. } => self.expr(expr),
+            ExprKind::Binary(op, lhs, rhs) if op.is_lazy() => {
+                let left = self.truth(lhs)?;
+                let short = match op {
+                    BinOp::And => !left,
+                    _ => left,
+                };
+                if short {
+                    self.step(
+                        span,
+                        format!("`{}` 的左侧为 {}, 短路求值, 不再计算右侧", expr, left),
+                    );
+                    return Ok(Value::Bool(left));
+                }
+                Ok(Value::Bool(self.truth(rhs)?))
+            }
+            ExprKind::Binary(op, lhs, rhs) => {
+                let lhs = self.expr(lhs)?;
+                let rhs = self.expr(rhs)?;
+                binary(*op, lhs, rhs, span)
+            }
+            ExprKind::Assign(lhs, rhs) => {
+                let value = self.expr(rhs)?;
+                self.assign(lhs, value.clone())?;
+                self.step(span, format!("{} = {}, 赋值表达式的值是 ()", lhs, value));
+                Ok(Value::Unit)
+            }
+            ExprKind::AssignOp(op, lhs, rhs) => {
+                let rhs = self.expr(rhs)?;
+                let current = self.expr(lhs)?;
+                let value = binary(*op, current, rhs, span)?;
+                self.assign(lhs, value.clone())?;
+                self.step(
+                    span,
+                    format!(
+                        "{} {}= .. 之后 {} 为 {}, 值是 ()",
+                        lhs,
+                        op.as_str(),
+                        lhs,
+                        value
+                    ),
+                );
+                Ok(Value::Unit)
+            }
+            ExprKind::Field(base, field) => match (self.expr(base)?, field.parse::<usize>()) {
+                (Value::Tuple(values), Ok(i)) if i < values.len() => Ok(values[i].clone()),
+                (value, _) => Err(error(
+                    span,
+                    format!("no field `{}` on {}", field, value.kind()),
+                )),
+            },
+            ExprKind::Index(base, index) => {
+                let base = self.expr(base)?;
+                let index = self.expr(index)?;
+                match (base, index) {
+                    (Value::Array(values), Value::Int(i, _)) => usize::try_from(i)
+                        .ok()
+                        .and_then(|i| values.get(i).cloned())
+                        .ok_or_else(|| {
+                            error(
+                                span,
+                                format!(
+                                    "index out of bounds: the len is {} but the index is {}",
+                                    values.len(),
+                                    i
+                                ),
+                            )
+                        }),
+                    (base, index) => Err(error(
+                        span,
+                        format!("cannot index {} with {}", base.kind(), index.kind()),
+                    )),
+                }
+            }
+            ExprKind::Call(callee, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.expr(arg))
+                    .collect::<Eval<Vec<_>>>()?;
+                let name = match &callee.kind {
+                    ExprKind::Path(path) => path.join("::"),
+                    _ => String::new(),
+                };
+                match (name.as_str(), self.expr(callee)) {
+                    (_, Ok(Value::Fn(name))) => self.call(&name, args, span),
+                    ("String::from", _) | ("String::new", _) => Ok(Value::String(
+                        args.first().map_or(String::new(), |arg| arg.display()),
+                    )),
+                    ("drop", _) => Ok(Value::Unit),
+                    (_, Ok(value)) => Err(error(
+                        span,
+                        format!("expected function, found {}", value.kind()),
+                    )),
+                    (_, Err(e)) => Err(e),
+                }
+            }
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+            } => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.expr(arg))
+                    .collect::<Eval<Vec<_>>>()?;
+                self.method(receiver, method, args, span)
+            }
+            ExprKind::Macro { name, args } => self.macro_call(name, args, span),
+            ExprKind::Tuple(items) => Ok(Value::Tuple(
+                items
+                    .iter()
+                    .map(|item| self.expr(item))
+                    .collect::<Eval<_>>()?,
+            )),
+            ExprKind::Array(items) => Ok(Value::Array(
+                items
+                    .iter()
+                    .map(|item| self.expr(item))
+                    .collect::<Eval<_>>()?,
+            )),
+            ExprKind::Repeat(item, len) => {
+                let item = self.expr(item)?;
+                match self.expr(len)? {
+                    Value::Int(n, _) if n > ARRAY_LEN_LIMIT => Err(error(
+                        span,
+                        format!("数组长度 {} 超过了解释器的上限 {}", n, ARRAY_LEN_LIMIT),
+                    )),
+                    Value::Int(n, _) if n >= 0 => Ok(Value::Array(vec![item; n as usize])),
+                    other => Err(error(
+                        span,
+                        format!(
+                            "array length must be a non-negative integer, found {}",
+                            other
+                        ),
+                    )),
+                }
+            }
+            ExprKind::Let(pat, scrutinee) => {
+                let value = self.expr(scrutinee)?;
+                Ok(Value::Bool(self.bind(pat, &value)?))
+            }
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::If { cond, then, els } => self.scoped(|interp| {
+                let taken = interp.truth(cond)?;
+                match (taken, els) {
+                    (true, _) => {
+                        interp.step(span, format!("条件 `{}` 为 true, 执行 if 分支", cond));
+                        interp.block(then)
+                    }
+                    (false, Some(els)) => {
+                        interp.step(span, format!("条件 `{}` 为 false, 执行 else 分支", cond));
+                        interp.expr(els)
+                    }
+                    (false, None) => {
+                        interp.step(
+                            span,
+                            format!(
+                                "条件 `{}` 为 false 且没有 else 分支, if 表达式的值是 ()",
+                                cond
+                            ),
+                        );
+                        Ok(Value::Unit)
+                    }
+                }
+            }),
+            ExprKind::While { cond, body } => {
+                let mut iterations = 0;
+                loop {
+                    self.tick(span)?;
+                    let taken = self.scoped(|interp| {
+                        if !interp.truth(cond)? {
+                            return Ok(false);
+                        }
+                        match interp.block(body) {
+                            Ok(_) | Err(Flow::Continue) => Ok(true),
+                            Err(Flow::Break(_)) => Ok(false),
+                            Err(flow) => Err(flow),
+                        }
+                    })?;
+                    if !taken {
+                        break;
+                    }
+                    iterations += 1;
+                }
+                self.step(
+                    span,
+                    format!("while 循环执行了 {} 次后结束, 值是 ()", iterations),
+                );
+                Ok(Value::Unit)
+            }
+            ExprKind::Loop(body) => loop {
+                self.tick(span)?;
+                match self.block(body) {
+                    Ok(_) | Err(Flow::Continue) => {}
+                    Err(Flow::Break(value)) => {
+                        self.step(
+                            span,
+                            format!("break {}: loop 表达式的值是 {}", value, value),
+                        );
+                        return Ok(value);
+                    }
+                    Err(flow) => return Err(flow),
+                }
+            },
+            ExprKind::For { pat, iter, body } => {
+                let items = match self.expr(iter)? {
+                    Value::Range(start, end) => (start..end).map(|i| Value::Int(i, None)).collect(),
+                    Value::Array(values) => values,
+                    Value::Str(s) | Value::String(s) => s.chars().map(Value::Char).collect(),
+                    other => {
+                        return Err(error(
+                            iter.span,
+                            format!("{} is not an iterator", other.kind()),
+                        ))
+                    }
+                };
+                let count = items.len();
+                for item in items {
+                    self.tick(span)?;
+                    let flow = self.scoped(|interp| {
+                        interp.bind(pat, &item)?;
+                        match interp.block(body) {
+                            Ok(_) | Err(Flow::Continue) => Ok(true),
+                            Err(Flow::Break(_)) => Ok(false),
+                            Err(flow) => Err(flow),
+                        }
+                    })?;
+                    if !flow {
+                        break;
+                    }
+                }
+                self.step(span, format!("for 循环遍历了 {} 个元素, 值是 ()", count));
+                Ok(Value::Unit)
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                let value = self.expr(scrutinee)?;
+                for arm in arms {
+                    let result = self.scoped(|interp| {
+                        if !interp.bind(&arm.pat, &value)? {
+                            return Ok(None);
+                        }
+                        if let Some(guard) = &arm.guard {
+                            if !interp.truth(guard)? {
+                                return Ok(None);
+                            }
+                        }
+                        interp.step(
+                            span,
+                            format!("`{}` 的值 {} 匹配分支 `{}`", scrutinee, value, arm.pat),
+                        );
+                        interp.expr(&arm.body).map(Some)
+                    })?;
+                    if let Some(result) = result {
+                        return Ok(result);
+                    }
+                }
+                Err(error(span, format!("没有分支能匹配 {}", value)))
+            }
+            ExprKind::Break(value) => {
+                let value = match value {
+                    Some(value) => self.expr(value)?,
+                    None => Value::Unit,
+                };
+                Err(Flow::Break(value))
+            }
+            ExprKind::Continue => Err(Flow::Continue),
+            ExprKind::Return(value) => {
+                let value = match value {
+                    Some(value) => self.expr(value)?,
+                    None => Value::Unit,
+                };
+                Err(Flow::Return(value))
+            }
+            ExprKind::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let bound = |interp: &mut Self, e: &Option<Box<Expr>>| match e {
+                    Some(e) => match interp.expr(e)? {
+                        Value::Int(i, _) => Ok(Some(i)),
+                        other => Err(error(
+                            e.span,
+                            format!("range bounds must be integers, found {}", other.kind()),
+                        )),
+                    },
+                    None => Ok(None),
+                };
+                let start = bound(self, start)?.unwrap_or(0);
+                let end = match bound(self, end)? {
+                    Some(end) if *inclusive => end + 1,
+                    Some(end) => end,
+                    None => return Err(error(span, "不支持没有上界的区间".to_string())),
+                };
+                Ok(Value::Range(start, end))
+            }
+            ExprKind::Cast(inner, ty) => {
+                let value = self.expr(inner)?;
+                cast(value, ty, span)
+            }
+        }
+    }
+
+    fn truth(&mut self, expr: &Expr) -> Eval<bool> {
+        match self.expr(expr)? {
+            Value::Bool(b) => Ok(b),
+            other => Err(error(
+                expr.span,
+                format!("mismatched types: expected `bool`, found {}", other.kind()),
+            )),
+        }
+    }
+
+    fn call(&mut self, name: &str, args: Vec<Value>, span: Span) -> Eval<Value> {
+        let decl = match self.fns.get(name) {
+            Some(decl) => Arc::clone(decl),
+            None => {
+                return Err(error(
+                    span,
+                    format!("cannot find function `{}` in this scope", name),
+                ))
+            }
+        };
+        if args.len() != decl.params.len() {
+            return Err(error(
+                span,
+                format!(
+                    "this function takes {} arguments but {} arguments were supplied",
+                    decl.params.len(),
+                    args.len()
+                ),
+            ));
+        }
+        if self.calls >= CALL_DEPTH_LIMIT {
+            return Err(error(
+                span,
+                format!("函数调用超过了 {} 层", CALL_DEPTH_LIMIT),
+            ));
+        }
+        let shown: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        self.step(span, format!("调用 {}({})", name, shown.join(", ")));
+        // 函数体看不到调用者的局部变量
+        let caller = std::mem::replace(&mut self.scopes, vec![Vec::new()]);
+        self.calls += 1;
+        self.depth += 1;
+        let mut result = Ok(true);
+        for (param, arg) in decl.params.iter().zip(args) {
+            result = fit(arg, &param.ty, span).and_then(|arg| self.bind(&param.pat, &arg));
+            if !matches!(result, Ok(true)) {
+                break;
+            }
+        }
+        let result = match result {
+            Ok(_) => match self.block(&decl.body) {
+                Ok(value) | Err(Flow::Return(value)) => fit(value, &decl.ret, decl.body.end),
+                Err(Flow::Break(_)) | Err(Flow::Continue) => Err(error(
+                    decl.span,
+                    "`break` 或 `continue` 出现在循环之外".to_string(),
+                )),
+                Err(flow) => Err(flow),
+            },
+            Err(flow) => Err(flow),
+        };
+        self.depth -= 1;
+        self.calls -= 1;
+        self.scopes = caller;
+        let value = result?;
+        self.step(span, format!("{} 返回 {}", name, value));
+        Ok(value)
+    }
+
+    fn method(
+        &mut self,
+        receiver: &Expr,
+        method: &str,
+        args: Vec<Value>,
+        span: Span,
+    ) -> Eval<Value> {
+        if let ("push" | "pop" | "clear" | "push_str", Some(name)) = (method, root_name(receiver)) {
+            let name = name.to_string();
+            let mut value = self.expr(receiver)?;
+            let result = match (&mut value, method, args.as_slice()) {
+                (Value::Array(values), "push", [item]) => {
+                    values.push(item.clone());
+                    Value::Unit
+                }
+                (Value::Array(values), "pop", []) => match values.pop() {
+                    Some(item) => Value::Tuple(vec![item]),
+                    None => Value::Unit,
+                },
+                (Value::Array(values), "clear", []) => {
+                    values.clear();
+                    Value::Unit
+                }
+                (Value::String(s), "push_str", [item]) => {
+                    s.push_str(&item.display());
+                    Value::Unit
+                }
+                _ => {
+                    return Err(error(
+                        span,
+                        format!("no method named `{}` found for {}", method, value.kind()),
+                    ))
+                }
+            };
+            self.assign(receiver, value)
+                .map_err(|_| error(span, format!("cannot borrow `{}` as mutable", name)))?;
+            return Ok(result);
+        }
+        let value = self.expr(receiver)?;
+        Ok(match (value, method, args.as_slice()) {
+            (Value::Array(values), "len", []) => Value::Int(values.len() as i128, Some("usize")),
+            (Value::Str(s) | Value::String(s), "len", []) => {
+                Value::Int(s.len() as i128, Some("usize"))
+            }
+            (Value::Array(values), "is_empty", []) => Value::Bool(values.is_empty()),
+            (Value::Str(s) | Value::String(s), "is_empty", []) => Value::Bool(s.is_empty()),
+            (Value::Array(values), "contains", [item]) => {
+                Value::Bool(values.iter().any(|value| same(value, item)))
+            }
+            (Value::Range(start, end), "contains", [Value::Int(i, _)]) => {
+                Value::Bool(start <= *i && *i < end)
+            }
+            (Value::Int(i, ty), "abs", []) => Value::Int(
+                i.checked_abs()
+                    .filter(|n| in_range(*n, ty.unwrap_or_else(|| natural(&[i]))))
+                    .ok_or_else(|| error(span, "attempt to negate with overflow".to_string()))?,
+                ty,
+            ),
+            (Value::Int(i, ty), "pow", [Value::Int(exp, _)]) => match u32::try_from(*exp) {
+                Ok(exp) => Value::Int(
+                    i.checked_pow(exp)
+                        .filter(|n| in_range(*n, ty.unwrap_or_else(|| natural(&[i]))))
+                        .ok_or_else(|| {
+                            error(span, "attempt to multiply with overflow".to_string())
+                        })?,
+                    ty,
+                ),
+                Err(_) => return Err(error(span, "指数必须是非负整数".to_string())),
+            },
+            (Value::Int(a, ta), "min", [Value::Int(b, tb)]) => Value::Int(a.min(*b), ta.or(*tb)),
+            (Value::Int(a, ta), "max", [Value::Int(b, tb)]) => Value::Int(a.max(*b), ta.or(*tb)),
+            (Value::Float(x), "sqrt", []) => Value::Float(x.sqrt()),
+            (value, "to_string" | "to_owned", []) => Value::String(value.display()),
+            (value, "clone", []) => value,
+            (value, _, _) => {
+                return Err(error(
+                    span,
+                    format!("no method named `{}` found for {}", method, value.kind()),
+                ))
+            }
+        })
+    }
+
+    fn macro_call(&mut self, name: &str, args: &[Expr], span: Span) -> Eval<Value> {
+        let values = args
+            .iter()
+            .map(|arg| self.expr(arg))
+            .collect::<Eval<Vec<_>>>()?;
+        match name {
+            "println" | "print" | "eprintln" | "eprint" | "format" => {
+                let text = self.format(args, &values, span)?;
+                match name {
+                    "format" => return Ok(Value::String(text)),
+                    "println" | "eprintln" => self.output.push_str(&format!("{}\n", text)),
+                    _ => self.output.push_str(&text),
+                }
+                self.step(span, format!("{}! 输出 {:?}", name, text));
+                Ok(Value::Unit)
+            }
+            "vec" => match values.into_iter().next() {
+                Some(array @ Value::Array(_)) => Ok(array),
+                _ => Ok(Value::Array(Vec::new())),
+            },
+            "assert" => match values.first() {
+                Some(Value::Bool(true)) => Ok(Value::Unit),
+                _ => Err(error(
+                    span,
+                    format!(
+                        "assertion failed: {}",
+                        args.first().map_or(String::new(), |a| a.to_string())
+                    ),
+                )),
+            },
+            "assert_eq" | "assert_ne" => match values.as_slice() {
+                [left, right, ..] if same(left, right) == (name == "assert_eq") => Ok(Value::Unit),
+                [left, right, ..] => {
+                    let op = if name == "assert_eq" { "==" } else { "!=" };
+                    Err(error(
+                        span,
+                        format!(
+                            "assertion `left {} right` failed\n  left: {}\n right: {}",
+                            op, left, right
+                        ),
+                    ))
+                }
+                _ => Err(error(span, format!("{}! 需要两个参数", name))),
+            },
+            "panic" => {
+                let text = self.format(args, &values, span)?;
+                Err(error(span, format!("panicked: {}", text)))
+            }
+            _ => Err(error(span, format!("不支持宏 `{}!`", name))),
+        }
+    }
+
+    /// 支持 `{}`、`{:?}`、`{name}` 以及 `{{`、`}}`
+    fn format(&mut self, args: &[Expr], values: &[Value], span: Span) -> Eval<String> {
+        let template = match (args.first(), values.first()) {
+            (
+                Some(Expr {
+                    kind: ExprKind::Lit(Lit::Str(_)),
+                    ..
+                }),
+                Some(Value::Str(s)),
+            ) => s.clone(),
+            _ => {
+                return Err(error(
+                    span,
+                    "格式化宏的第一个参数必须是字符串字面量".to_string(),
+                ))
+            }
+        };
+        let mut positional = values[1..].iter();
+        let mut out = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    out.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    out.push('}');
+                }
+                '{' => {
+                    let spec: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                    let (name, format) = spec.split_once(':').unwrap_or((&spec, ""));
+                    let value = if name.is_empty() {
+                        positional.next().cloned()
+                    } else {
+                        self.lookup(name).and_then(|b| b.value.clone())
+                    };
+                    let value = value.ok_or_else(|| {
+                        error(
+                            span,
+                            format!("格式字符串中的 `{{{}}}` 没有对应的参数", spec),
+                        )
+                    })?;
+                    if format.contains('?') {
+                        out.push_str(&value.to_string());
+                    } else {
+                        out.push_str(&value.display());
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+        Ok(out)
+    }
+
+    /// 给位置表达式赋值: 本地变量、数组元素或元组字段
+    fn assign(&mut self, place: &Expr, value: Value) -> Eval<()> {
+        let mut path = Vec::new();
+        let name = self.place_path(place, &mut path)?;
+        let binding = match self.lookup(&name) {
+            Some(binding) => binding,
+            None => {
+                return Err(error(
+                    place.span,
+                    format!("cannot find value `{}` in this scope", name),
+                ))
+            }
+        };
+        if binding.value.is_some() && !binding.mutable {
+            return Err(error(
+                place.span,
+                format!("cannot assign twice to immutable variable `{}`", name),
+            ));
+        }
+        if binding.value.is_none() {
+            if !path.is_empty() {
+                return Err(error(
+                    place.span,
+                    format!("used binding `{}` isn't initialized", name),
+                ));
+            }
+            binding.value = Some(value);
+            return Ok(());
+        }
+        let mut target = binding.value.as_mut().unwrap();
+        for index in path {
+            target = match target {
+                Value::Array(values) | Value::Tuple(values) => {
+                    let len = values.len();
+                    values.get_mut(index).ok_or_else(|| {
+                        error(
+                            place.span,
+                            format!(
+                                "index out of bounds: the len is {} but the index is {}",
+                                len, index
+                            ),
+                        )
+                    })?
+                }
+                other => {
+                    return Err(error(
+                        place.span,
+                        format!("cannot index into {}", other.kind()),
+                    ))
+                }
+            };
+        }
+        // 没有类型的整数赋给有类型的位置时得到这个类型, 例如 `let mut x: u8 = 0; x = 300;`
+        *target = match (&*target, value) {
+            (Value::Int(_, Some(ty)), value) => fit(value, &Type::named(ty), place.span)?,
+            (_, value) => value,
+        };
+        Ok(())
+    }
+
+    /// 位置表达式的根变量, 以及一路上的下标
+    fn place_path(&mut self, place: &Expr, path: &mut Vec<usize>) -> Eval<String> {
+        match &place.kind {
+            ExprKind::Path(names) if names.len() == 1 => Ok(names[0].clone()),
+            ExprKind::Paren(inner) | ExprKind::Unary(UnOp::Deref, inner) => {
+                self.place_path(inner, path)
+            }
+            ExprKind::Field(base, field) => {
+                let name = self.place_path(base, path)?;
+                let index = field
+                    .parse()
+                    .map_err(|_| error(place.span, format!("no field `{}`", field)))?;
+                path.push(index);
+                Ok(name)
+            }
+            ExprKind::Index(base, index) => {
+                let name = self.place_path(base, path)?;
+                match self.expr(index)? {
+                    Value::Int(i, _) if i >= 0 => path.push(i as usize),
+                    other => {
+                        return Err(error(
+                            index.span,
+                            format!("index must be a non-negative integer, found {}", other),
+                        ))
+                    }
+                }
+                Ok(name)
+            }
+            _ => Err(error(
+                place.span,
+                "invalid left-hand side of assignment".to_string(),
+            )),
+        }
+    }
+}
+
+fn root_name(expr: &Expr) -> Option<&str> {
+    match &expr.kind {
+        ExprKind::Path(names) if names.len() == 1 => Some(&names[0]),
+        ExprKind::Paren(inner)
+        | ExprKind::Unary(UnOp::Deref, inner)
+        | ExprKind::Field(inner, _)
+        | ExprKind::Index(inner, _) => root_name(inner),
+        _ => None,
+    }
+}
+
+fn strip_parens(expr: &Expr) -> &Expr {
+    match &expr.kind {
+        ExprKind::Paren(inner) => strip_parens(inner),
+        _ => expr,
+    }
+}
+
+/// 整数字面量, `negative` 表示字面量前面紧贴着负号
+fn int_literal(value: u128, suffix: &Option<String>, negative: bool, span: Span) -> Eval<Value> {
+    let ty = suffix.as_deref().and_then(|s| integer(&Type::named(s)));
+    let value = if negative {
+        0i128.checked_sub_unsigned(value)
+    } else {
+        i128::try_from(value).ok()
+    }
+    .ok_or_else(|| error(span, "integer literal is too large".to_string()))?;
+    match ty {
+        Some(ty) if !in_range(value, ty) => {
+            Err(error(span, format!("literal out of range for `{}`", ty)))
+        }
+        ty => Ok(Value::Int(value, ty)),
+    }
+}
+
+fn literal(lit: &Lit, span: Span) -> Eval<Value> {
+    Ok(match lit {
+        Lit::Int { value, suffix } => int_literal(*value, suffix, false, span)?,
+        Lit::Float { value, .. } => Value::Float(*value),
+        Lit::Bool(b) => Value::Bool(*b),
+        Lit::Str(s) => Value::Str(s.clone()),
+        Lit::Char(c) => Value::Char(*c),
+    })
+}
+
+fn compare(lhs: &Value, rhs: &Value, span: Span) -> Eval<std::cmp::Ordering> {
+    let ordering = match (lhs, rhs) {
+        (Value::Int(a, _), Value::Int(b, _)) => Some(a.cmp(b)),
+        (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+        (Value::Char(a), Value::Char(b)) => Some(a.cmp(b)),
+        (Value::Str(a) | Value::String(a), Value::Str(b) | Value::String(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    };
+    ordering.ok_or_else(|| {
+        error(
+            span,
+            format!("cannot compare {} with {}", lhs.kind(), rhs.kind()),
+        )
+    })
+}
+
+fn binary(op: BinOp, lhs: Value, rhs: Value, span: Span) -> Eval<Value> {
+    if op.is_comparison() {
+        let result = match op {
+            BinOp::Eq => same(&lhs, &rhs),
+            BinOp::Ne => !same(&lhs, &rhs),
+            _ => {
+                let ordering = compare(&lhs, &rhs, span)?;
+                match op {
+                    BinOp::Lt => ordering.is_lt(),
+                    BinOp::Le => ordering.is_le(),
+                    BinOp::Gt => ordering.is_gt(),
+                    _ => ordering.is_ge(),
+                }
+            }
+        };
+        return Ok(Value::Bool(result));
+    }
+    let overflow = |what: &str| error(span, format!("attempt to {} with overflow", what));
+    match (lhs, rhs) {
+        (Value::Int(a, ta), Value::Int(b, tb)) => {
+            // 按运算数的类型检查溢出, 例如 `i32::MIN % -1` 与 `-i64::MIN` 都会溢出
+            let ty = ta.or(tb);
+            let width = ty.unwrap_or_else(|| natural(&[a, b]));
+            let fits = |r: &i128| in_range(*r, width);
+            Ok(Value::Int(
+                match op {
+                    BinOp::Add => a
+                        .checked_add(b)
+                        .filter(fits)
+                        .ok_or_else(|| overflow("add"))?,
+                    BinOp::Sub => a
+                        .checked_sub(b)
+                        .filter(fits)
+                        .ok_or_else(|| overflow("subtract"))?,
+                    BinOp::Mul => a
+                        .checked_mul(b)
+                        .filter(fits)
+                        .ok_or_else(|| overflow("multiply"))?,
+                    BinOp::Div if b == 0 => {
+                        return Err(error(span, "attempt to divide by zero".to_string()))
+                    }
+                    BinOp::Div => a
+                        .checked_div(b)
+                        .filter(fits)
+                        .ok_or_else(|| overflow("divide"))?,
+                    BinOp::Rem if b == 0 => {
+                        return Err(error(
+                            span,
+                            "attempt to calculate the remainder with a divisor of zero".to_string(),
+                        ))
+                    }
+                    // 余数本身不会越界, 但 `MIN % -1` 对应的商溢出了
+                    BinOp::Rem if b == -1 && a == range(width).0 && a != 0 => {
+                        return Err(overflow("calculate the remainder"))
+                    }
+                    BinOp::Rem => a % b,
+                    BinOp::BitAnd => a & b,
+                    BinOp::BitOr => a | b,
+                    BinOp::BitXor => a ^ b,
+                    BinOp::Shl | BinOp::Shr if b < 0 || b >= i128::from(bits(width)) => {
+                        let what = if op == BinOp::Shl { "left" } else { "right" };
+                        return Err(overflow(&format!("shift {}", what)));
+                    }
+                    BinOp::Shl => wrap(a << b, Some(width)),
+                    BinOp::Shr => a >> b,
+                    _ => unreachable!("比较与逻辑运算已经处理过了"),
+                },
+                ty,
+            ))
+        }
+        (Value::Float(a), Value::Float(b)) => Ok(Value::Float(match op {
+            BinOp::Add => a + b,
+            BinOp::Sub => a - b,
+            BinOp::Mul => a * b,
+            BinOp::Div => a / b,
+            BinOp::Rem => a % b,
+            _ => {
+                return Err(error(
+                    span,
+                    format!("no implementation for `f64 {} f64`", op.as_str()),
+                ))
+            }
+        })),
+        (Value::Bool(a), Value::Bool(b))
+            if matches!(op, BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor) =>
+        {
+            Ok(Value::Bool(match op {
+                BinOp::BitAnd => a & b,
+                BinOp::BitOr => a | b,
+                _ => a ^ b,
+            }))
+        }
+        // 与 Rust 一样只有 `String + &str`: 左侧必须是拥有所有权的 `String`, 右侧必须是切片
+        (Value::String(a), Value::Str(b)) if op == BinOp::Add => Ok(Value::String(a + &b)),
+        (Value::String(_), Value::String(_)) if op == BinOp::Add => Err(error(
+            span,
+            "mismatched types: expected `&str`, found `String`".to_string(),
+        )),
+        (Value::Str(_), rhs @ (Value::Str(_) | Value::String(_))) if op == BinOp::Add => {
+            Err(error(span, format!("cannot add {} to `&str`", rhs.kind())))
+        }
+        (lhs, rhs) => Err(error(
+            span,
+            format!(
+                "cannot apply `{}` to {} and {}",
+                op.as_str(),
+                lhs.kind(),
+                rhs.kind()
+            ),
+        )),
+    }
+}
+
+fn cast(value: Value, ty: &Type, span: Span) -> Eval<Value> {
+    let target = match ty {
+        Type::Path { name, .. } => name.as_str(),
+        _ => "",
+    };
+    let int = integer(ty);
+    Ok(match (value, target) {
+        (Value::Int(i, _), "f32" | "f64") => Value::Float(i as f64),
+        (Value::Float(x), "f32" | "f64") => Value::Float(x),
+        (Value::Int(i, _), "char") if (0..=255).contains(&i) => Value::Char(i as u8 as char),
+        (Value::Char(c), _) if int.is_some() => Value::Int(c as i128, int),
+        (Value::Bool(b), _) if int.is_some() => Value::Int(b as i128, int),
+        (Value::Float(x), _) if int.is_some() => Value::Int(x as i128, int),
+        // 与 Rust 一样按目标宽度截断
+        (Value::Int(i, _), _) if int.is_some() => Value::Int(wrap(i, int), int),
+        (Value::Int(..), _) => return Err(error(span, format!("不支持转换为 `{}`", ty))),
+        (value, _) => {
+            return Err(error(
+                span,
+                format!("non-primitive cast: {} as `{}`", value.kind(), ty),
+            ))
+        }
+    })
+}
+
+/// 把值放进类型为 `ty` 的位置: 没有类型的整数得到类型, 并检查是否超出范围
+fn fit(value: Value, ty: &Type, span: Span) -> Eval<Value> {
+    Ok(match (value, ty) {
+        (Value::Int(i, None), ty) => match integer(ty) {
+            Some(name) if !in_range(i, name) => {
+                return Err(error(span, format!("literal out of range for `{}`", name)))
+            }
+            name => Value::Int(i, name),
+        },
+        (Value::Array(values), Type::Array(elem, _))
+        | (Value::Array(values), Type::Slice(elem)) => Value::Array(
+            values
+                .into_iter()
+                .map(|v| fit(v, elem, span))
+                .collect::<Eval<_>>()?,
+        ),
+        (Value::Tuple(values), Type::Tuple(tys)) if values.len() == tys.len() => Value::Tuple(
+            values
+                .into_iter()
+                .zip(tys)
+                .map(|(v, ty)| fit(v, ty, span))
+                .collect::<Eval<_>>()?,
+        ),
+        (value, Type::Ref { inner, .. }) => fit(value, inner, span)?,
+        (value, _) => value,
+    })
+}
+
+/// 没有类型的整数按 rustc 的默认类型 `i32` 检查, 放不下时依次放宽到 `i64`、`i128`
+fn natural(values: &[i128]) -> &'static str {
+    ["i32", "i64"]
+        .iter()
+        .copied()
+        .find(|ty| values.iter().all(|v| in_range(*v, ty)))
+        .unwrap_or("i128")
+}
+
+/// 比较两个值, 整数不区分类型
+fn same(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Int(a, _), Value::Int(b, _)) => a == b,
+        (Value::Str(a) | Value::String(a), Value::Str(b) | Value::String(b)) => a == b,
+        (Value::Tuple(a), Value::Tuple(b)) | (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same(a, b))
+        }
+        (a, b) => a == b,
+    }
+}
+
+/// # 代码是否完整
+///
+/// 括号没有闭合或字符串没有结束时返回 `false`, `repl` 据此决定是否继续读取下一行
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::lang::interp::is_complete;
+///
+/// assert!(is_complete("let a = 1;"));
+/// assert!(!is_complete("fn sum(a: i32, b: i32) -> i32 {"));
+/// assert!(!is_complete("let s = \"hello"));
+/// ```
+pub fn is_complete(src: &str) -> bool {
+    let tokens = match tokenize(src) {
+        Ok(tokens) => tokens,
+        Err(_) => return false,
+    };
+    let mut depth = 0i32;
+    for token in &tokens {
+        match token.text.as_str() {
+            "{" | "(" | "[" if token.is_punct(&token.text) => depth += 1,
+            "}" | ")" | "]" if token.is_punct(&token.text) => depth -= 1,
+            _ => {}
+        }
+    }
+    depth <= 0
+}
+
+/// # 求值一段代码
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::lang::interp::{eval, Value};
+///
+/// // ch02::answer: 块的值是尾表达式的值, 带分号的语句的值是 ()
+/// let result = eval("fn sum(a: i32, b: i32) -> i32 { a + b } let a = 40; let b = 2; sum(a, b)").unwrap();
+/// assert_eq!(result.value, Value::Int(42, Some("i32")));
+/// let steps: Vec<String> = result.steps.iter().map(|s| s.to_string()).collect();
+/// assert_eq!(steps, [
+///     "  1:1  定义 sum",
+///     " 1:41  let a = 40",
+///     " 1:53  let b = 2",
+///     " 1:64  调用 sum(40, 2)",
+///     "     1:33  块的值是尾表达式 `a + b` 的值: 42",
+///     " 1:64  sum 返回 42",
+///     " 1:64  结果是表达式 `sum(a, b)` 的值: 42",
+/// ]);
+///
+/// assert_eq!(eval("{ 1 + 1; }").unwrap().value, Value::Unit);
+/// let n = eval("let mut n = 0; let v = loop { n += 1; if n == 3 { break n * 10; } }; v");
+/// assert_eq!(n.unwrap().value, Value::Int(30, None));
+/// let grade = eval("let score = 85; match score { 90..=100 => 'A', 80..=89 => 'B', _ => 'C' }");
+/// assert_eq!(grade.unwrap().value, Value::Char('B'));
+///
+/// let result = eval("let a = 1; a = 2;");
+/// assert_eq!(result.unwrap_err().message, "cannot assign twice to immutable variable `a`");
+/// ```
+pub fn eval(src: &str) -> Result<Evaluation, EvalError> {
+    Session::new().eval(src)
+}
@@ -0,0 +1,361 @@
+//! # 词法分析
+//!
+//! 把 Rust 源码切分成记号(Token). 注释和空白会被丢弃, 每个记号都带有所在的行列位置
+//!
+//! 支持标识符、生命周期、整数与浮点数字面量(含类型后缀)、字符串(含原始字符串和字节串)、
+//! 字符以及全部的运算符和分隔符. `>>` 与 `>>=` 作为一个记号输出,
+//! 语法分析在需要单个 `>` 时(例如泛型参数的结尾)自行拆分
+
+use std::error::Error;
+use std::fmt;
+
+/// 源码中的位置, 行列号都从 1 开始
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct Span {
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Span {
+    pub fn new(line: usize, col: usize) -> Self {
+        Span { line, col }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Ident,
+    Lifetime,
+    Int,
+    Float,
+    Str,
+    Char,
+    Punct,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// 记号在源码中的原文
+    pub text: String,
+    pub span: Span,
+}
+
+impl Token {
+    /// 是否为指定的标识符或关键字
+    pub fn is_ident(&self, text: &str) -> bool {
+        self.kind == TokenKind::Ident && self.text == text
+    }
+
+    /// 是否为指定的运算符或分隔符
+    pub fn is_punct(&self, text: &str) -> bool {
+        self.kind == TokenKind::Punct && self.text == text
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
+    }
+}
+
+impl Error for LexError {}
+
+// 按长度从长到短排列, 保证最长匹配
+const PUNCTS: [&str; 51] = [
+    "<<=", ">>=", "...", "..=", "::", "->", "=>", "==", "!=", "<=", ">=", "&&", "||", "+=", "-=",
+    "*=", "/=", "%=", "^=", "&=", "|=", "<<", ">>", "..", "+", "-", "*", "/", "%", "^", "!", "&",
+    "|", "=", "<", ">", "@", ".", ",", ";", ":", "#", "$", "?", "~", "{", "}", "[", "]", "(", ")",
+];
+
+/// `s` 是否以原始字符串的开头(`"`、`#"`、`##"` ...)开始
+fn starts_raw_string(s: &str) -> bool {
+    s.trim_start_matches('#').starts_with('"')
+}
+
+struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+    line: usize,
+    col: usize,
+    /// 上一个记号是否为 `.`, 此时 `0.1` 是两次字段访问而不是浮点数
+    after_dot: bool,
+}
+
+impl<'a> Lexer<'a> {
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+
+    fn span(&self) -> Span {
+        Span::new(self.line, self.col)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+        Some(c)
+    }
+
+    fn bump_n(&mut self, n: usize) {
+        for _ in 0..n {
+            self.bump();
+        }
+    }
+
+    fn error(&self, span: Span, message: &str) -> LexError {
+        LexError {
+            message: message.to_string(),
+            span,
+        }
+    }
+
+    fn skip_trivia(&mut self) -> Result<(), LexError> {
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("//") {
+                while let Some(c) = self.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    self.bump();
+                }
+            } else if rest.starts_with("/*") {
+                let start = self.span();
+                self.bump_n(2);
+                let mut depth = 1;
+                while depth > 0 {
+                    let rest = self.rest();
+                    if rest.is_empty() {
+                        return Err(self.error(start, "unterminated block comment"));
+                    } else if rest.starts_with("/*") {
+                        depth += 1;
+                        self.bump_n(2);
+                    } else if rest.starts_with("*/") {
+                        depth -= 1;
+                        self.bump_n(2);
+                    } else {
+                        self.bump();
+                    }
+                }
+            } else if self.peek().is_some_and(char::is_whitespace) {
+                self.bump();
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn eat_while<F: Fn(char) -> bool>(&mut self, f: F) {
+        while self.peek().is_some_and(&f) {
+            self.bump();
+        }
+    }
+
+    fn quoted(&mut self, quote: char, start: Span) -> Result<(), LexError> {
+        // 已经越过了开头的引号
+        loop {
+            match self.bump() {
+                None => return Err(self.error(start, "unterminated literal")),
+                Some('\\') => {
+                    self.bump();
+                }
+                Some(c) if c == quote => return Ok(()),
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn raw_string(&mut self, start: Span) -> Result<(), LexError> {
+        // 位于 `r` 之后
+        let mut hashes = 0;
+        while self.peek() == Some('#') {
+            hashes += 1;
+            self.bump();
+        }
+        if self.bump() != Some('"') {
+            return Err(self.error(start, "invalid raw string literal"));
+        }
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        loop {
+            if self.rest().starts_with(&terminator) {
+                self.bump_n(terminator.len());
+                return Ok(());
+            }
+            if self.bump().is_none() {
+                return Err(self.error(start, "unterminated raw string literal"));
+            }
+        }
+    }
+
+    fn number(&mut self) -> TokenKind {
+        let mut kind = TokenKind::Int;
+        if self.rest().starts_with("0x")
+            || self.rest().starts_with("0o")
+            || self.rest().starts_with("0b")
+        {
+            self.bump_n(2);
+            self.eat_while(|c| c.is_ascii_hexdigit() || c == '_');
+        } else {
+            self.eat_while(|c| c.is_ascii_digit() || c == '_');
+            // `1.5` 是浮点数, 但 `1..2`、`1.foo()` 和 `x.0.1` 中的点不属于数字
+            if !self.after_dot
+                && self.peek() == Some('.')
+                && self.peek_nth(1).is_some_and(|c| c.is_ascii_digit())
+            {
+                kind = TokenKind::Float;
+                self.bump();
+                self.eat_while(|c| c.is_ascii_digit() || c == '_');
+            }
+            if matches!(self.peek(), Some('e') | Some('E'))
+                && self
+                    .peek_nth(1)
+                    .is_some_and(|c| c.is_ascii_digit() || c == '-' || c == '+')
+            {
+                kind = TokenKind::Float;
+                self.bump_n(2);
+                self.eat_while(|c| c.is_ascii_digit() || c == '_');
+            }
+        }
+        // 类型后缀, 例如 `42i32`、`1.0f64`
+        let suffix_start = self.pos;
+        self.eat_while(|c| c.is_alphanumeric() || c == '_');
+        if self.src[suffix_start..self.pos].starts_with('f') {
+            kind = TokenKind::Float;
+        }
+        kind
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, LexError> {
+        self.skip_trivia()?;
+        let start = self.pos;
+        let span = self.span();
+        let c = match self.peek() {
+            None => return Ok(None),
+            Some(c) => c,
+        };
+        let rest = self.rest();
+        let kind = if rest.starts_with('r') && starts_raw_string(&rest[1..]) {
+            self.bump();
+            self.raw_string(span)?;
+            TokenKind::Str
+        } else if rest.starts_with("br") && starts_raw_string(&rest[2..]) {
+            self.bump_n(2);
+            self.raw_string(span)?;
+            TokenKind::Str
+        } else if self.rest().starts_with("b\"") {
+            self.bump_n(2);
+            self.quoted('"', span)?;
+            TokenKind::Str
+        } else if self.rest().starts_with("b'") {
+            self.bump_n(2);
+            self.quoted('\'', span)?;
+            TokenKind::Char
+        } else if c.is_alphabetic() || c == '_' {
+            if self.rest().starts_with("r#") {
+                self.bump_n(2);
+            }
+            self.eat_while(|c| c.is_alphanumeric() || c == '_');
+            TokenKind::Ident
+        } else if c.is_ascii_digit() {
+            self.number()
+        } else if c == '"' {
+            self.bump();
+            self.quoted('"', span)?;
+            TokenKind::Str
+        } else if c == '\'' {
+            // 'a' 是字符, 'a 是生命周期
+            let is_char = matches!(
+                (self.peek_nth(1), self.peek_nth(2)),
+                (Some('\\'), _) | (Some(_), Some('\''))
+            );
+            self.bump();
+            if is_char {
+                self.quoted('\'', span)?;
+                TokenKind::Char
+            } else {
+                self.eat_while(|c| c.is_alphanumeric() || c == '_');
+                TokenKind::Lifetime
+            }
+        } else if let Some(p) = PUNCTS.iter().find(|p| self.rest().starts_with(*p)) {
+            self.bump_n(p.len());
+            TokenKind::Punct
+        } else {
+            return Err(self.error(span, &format!("unexpected character `{}`", c)));
+        };
+        let text = &self.src[start..self.pos];
+        self.after_dot = kind == TokenKind::Punct && text == ".";
+        Ok(Some(Token {
+            kind,
+            text: text.to_string(),
+            span,
+        }))
+    }
+}
+
+/// # 词法分析
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::lang::lexer::{tokenize, TokenKind};
+///
+/// let tokens = tokenize("let x = &temp(); // 注释\nx..=1.5e3 'a' 'b r#\"raw\"#").unwrap();
+/// let texts: Vec<&str> = tokens.iter().map(|t| t.text.as_str()).collect();
+/// assert_eq!(texts, ["let", "x", "=", "&", "temp", "(", ")", ";", "x", "..=", "1.5e3", "'a'", "'b", "r#\"raw\"#"]);
+/// assert_eq!(tokens[8].span.line, 2);
+/// assert_eq!(tokens[10].kind, TokenKind::Float);
+/// assert_eq!(tokens[12].kind, TokenKind::Lifetime);
+///
+/// // 区间中的点不属于数字
+/// let texts: Vec<String> = tokenize("1..3 x.0.1").unwrap().into_iter().map(|t| t.text).collect();
+/// assert_eq!(texts, ["1", "..", "3", "x", ".", "0", ".", "1"]);
+///
+/// assert!(tokenize("\"unterminated").is_err());
+/// ```
+pub fn tokenize(src: &str) -> Result<Vec<Token>, LexError> {
+    let mut lexer = Lexer {
+        src,
+        pos: 0,
+        line: 1,
+        col: 1,
+        after_dot: false,
+    };
+    let mut tokens = Vec::new();
+    while let Some(token) = lexer.next_token()? {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
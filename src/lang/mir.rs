@@ -0,0 +1,1722 @@
+//! # MIR 风格的中间表示
+//!
+//! [`ch02::binding::reference2`](crate::ch02::binding::reference2) 手写了一段类似 MIR 的代码来说明临时值:
+//!
+//! ```text
+//! let mut _0: &i32;
+//! let mut _1: i32;
+//! _1 = 42i32;
+//! _0 = &_1;
+//! ```
+//!
+//! 这里把教学用 Rust 子集降级(lowering)成同样风格的中间表示: 编号的局部变量、临时值、
+//! `StorageLive`/`StorageDead` 以及由终结语句(terminator)连接起来的基本块,
+//! 并且可以把控制流图输出为 Graphviz DOT
+//!
+//! 与 rustc 相比做了不少简化: 没有 drop 与 unwind, 宏调用保留为 `println!(..)` 这样的调用,
+//! 类型只做粗略的推断, 推断不出来的类型写作 `_`
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use super::ast::{BinOp, Block, Expr, ExprKind, FnDecl, Item, Lit, Pat, PatKind, Stmt, StmtKind};
+use super::ast::{Type, UnOp};
+use super::lexer::Span;
+use super::ownership::is_copy;
+use super::parser::{parse_stmts, ParseError};
+
+/// 会修改接收者的方法, 调用时自动可变借用接收者
+const MUTATING_METHODS: [&str; 10] = [
+    "push", "push_str", "pop", "insert", "remove", "clear", "truncate", "sort", "extend", "append",
+];
+
+/// 参数以引用方式传入的格式化宏
+const FORMAT_MACROS: [&str; 11] = [
+    "println",
+    "print",
+    "eprintln",
+    "eprint",
+    "format",
+    "write",
+    "writeln",
+    "assert",
+    "assert_eq",
+    "assert_ne",
+    "panic",
+];
+
+/// 降级失败: 语法错误, 或者使用了尚不支持的语法
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LowerError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for LowerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
+    }
+}
+
+impl Error for LowerError {}
+
+impl From<ParseError> for LowerError {
+    fn from(e: ParseError) -> Self {
+        LowerError {
+            message: e.message,
+            span: e.span,
+        }
+    }
+}
+
+type LResult<T> = Result<T, LowerError>;
+
+/// 局部变量 `_N`, `_0` 是返回值, 接下来是参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Local(pub usize);
+
+impl fmt::Display for Local {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "_{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocalDecl {
+    pub mutable: bool,
+    pub ty: Type,
+    /// 用户声明的变量名, 临时值为 `None`
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Projection {
+    Deref,
+    Field(String),
+    Index(Local),
+    /// 把枚举看作某个变体, 例如 `(_5 as Some)`
+    Downcast(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Place {
+    pub local: Local,
+    pub projection: Vec<Projection>,
+}
+
+impl Place {
+    fn project(&self, projection: Projection) -> Place {
+        let mut place = self.clone();
+        place.projection.push(projection);
+        place
+    }
+}
+
+impl From<Local> for Place {
+    fn from(local: Local) -> Self {
+        Place {
+            local,
+            projection: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for Place {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut text = self.local.to_string();
+        for projection in &self.projection {
+            text = match projection {
+                Projection::Deref => format!("(*{})", text),
+                Projection::Field(field) => format!("({}.{})", text, field),
+                Projection::Index(index) => format!("{}[{}]", text, index),
+                Projection::Downcast(variant) => format!("({} as {})", text, variant),
+            };
+        }
+        f.write_str(&text)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Copy(Place),
+    Move(Place),
+    /// 常量, 例如 `const 42_i32`、`const ()`
+    Constant(String),
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Copy(place) => write!(f, "copy {}", place),
+            Operand::Move(place) => write!(f, "move {}", place),
+            Operand::Constant(value) => write!(f, "const {}", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AggregateKind {
+    Tuple,
+    Array,
+    /// 结构体, 记录类型名和字段名
+    Adt(String, Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rvalue {
+    Use(Operand),
+    Ref { mutable: bool, place: Place },
+    BinaryOp(BinOp, Operand, Operand),
+    UnaryOp(UnOp, Operand),
+    Aggregate(AggregateKind, Vec<Operand>),
+    Repeat(Operand, String),
+    Cast(Operand, Type),
+    Discriminant(Place),
+}
+
+impl fmt::Display for Rvalue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |operands: &[Operand]| {
+            operands
+                .iter()
+                .map(|o| o.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            Rvalue::Use(operand) => write!(f, "{}", operand),
+            Rvalue::Ref { mutable, place } => {
+                write!(f, "&{}{}", if *mutable { "mut " } else { "" }, place)
+            }
+            Rvalue::BinaryOp(op, lhs, rhs) => write!(f, "{:?}({}, {})", op, lhs, rhs),
+            Rvalue::UnaryOp(op, operand) => write!(f, "{:?}({})", op, operand),
+            Rvalue::Aggregate(AggregateKind::Tuple, operands) => {
+                write!(f, "({})", list(operands))
+            }
+            Rvalue::Aggregate(AggregateKind::Array, operands) => {
+                write!(f, "[{}]", list(operands))
+            }
+            Rvalue::Aggregate(AggregateKind::Adt(name, fields), operands) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .zip(operands)
+                    .map(|(field, operand)| format!("{}: {}", field, operand))
+                    .collect();
+                write!(f, "{} {{ {} }}", name, fields.join(", "))
+            }
+            Rvalue::Repeat(operand, count) => write!(f, "[{}; {}]", operand, count),
+            Rvalue::Cast(operand, ty) => write!(f, "{} as {}", operand, ty),
+            Rvalue::Discriminant(place) => write!(f, "discriminant({})", place),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Assign(Place, Rvalue),
+    StorageLive(Local),
+    StorageDead(Local),
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Statement::Assign(place, rvalue) => write!(f, "{} = {};", place, rvalue),
+            Statement::StorageLive(local) => write!(f, "StorageLive({});", local),
+            Statement::StorageDead(local) => write!(f, "StorageDead({});", local),
+        }
+    }
+}
+
+/// 基本块的编号 `bbN`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BasicBlock(pub usize);
+
+impl fmt::Display for BasicBlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    Goto(BasicBlock),
+    /// 按整数值跳转, 布尔值 `false` 为 0
+    SwitchInt {
+        discr: Operand,
+        targets: Vec<(u128, BasicBlock)>,
+        otherwise: BasicBlock,
+    },
+    Call {
+        func: String,
+        args: Vec<Operand>,
+        dest: Place,
+        target: BasicBlock,
+    },
+    Return,
+    Unreachable,
+}
+
+impl Terminator {
+    fn retarget<F: Fn(BasicBlock) -> BasicBlock>(&mut self, f: F) {
+        match self {
+            Terminator::Goto(target) | Terminator::Call { target, .. } => *target = f(*target),
+            Terminator::SwitchInt {
+                targets, otherwise, ..
+            } => {
+                for (_, target) in targets {
+                    *target = f(*target);
+                }
+                *otherwise = f(*otherwise);
+            }
+            Terminator::Return | Terminator::Unreachable => {}
+        }
+    }
+
+    /// 后继基本块以及边上的标签
+    pub fn successors(&self) -> Vec<(String, BasicBlock)> {
+        match self {
+            Terminator::Goto(target) => vec![(String::new(), *target)],
+            Terminator::SwitchInt {
+                targets, otherwise, ..
+            } => targets
+                .iter()
+                .map(|(value, target)| (value.to_string(), *target))
+                .chain(Some(("otherwise".to_string(), *otherwise)))
+                .collect(),
+            Terminator::Call { target, .. } => vec![("return".to_string(), *target)],
+            Terminator::Return | Terminator::Unreachable => Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Terminator::Goto(target) => write!(f, "goto -> {};", target),
+            Terminator::SwitchInt {
+                discr,
+                targets,
+                otherwise,
+            } => {
+                write!(f, "switchInt({}) -> [", discr)?;
+                for (value, target) in targets {
+                    write!(f, "{}: {}, ", value, target)?;
+                }
+                write!(f, "otherwise: {}];", otherwise)
+            }
+            Terminator::Call {
+                func,
+                args,
+                dest,
+                target,
+            } => {
+                let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+                write!(f, "{} = {}({}) -> {};", dest, func, args.join(", "), target)
+            }
+            Terminator::Return => f.write_str("return;"),
+            Terminator::Unreachable => f.write_str("unreachable;"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlockData {
+    pub statements: Vec<Statement>,
+    pub terminator: Terminator,
+}
+
+/// 一个函数的中间表示
+#[derive(Debug, Clone, PartialEq)]
+pub struct Body {
+    pub name: String,
+    /// 参数个数, 参数是 `_1` 到 `_{arg_count}`
+    pub arg_count: usize,
+    pub locals: Vec<LocalDecl>,
+    pub blocks: Vec<BasicBlockData>,
+}
+
+impl Body {
+    /// # 输出控制流图
+    ///
+    /// 每个基本块是一个节点, 节点中列出块内的语句
+    ///
+    /// Basic usage:
+    ///
+    /// ```
+    /// use rust_programming_of_zhd::lang::mir::lower;
+    ///
+    /// let bodies = lower("let x = if true { 1 } else { 2 };").unwrap();
+    /// let dot = bodies[0].to_dot();
+    /// assert!(dot.starts_with("digraph main {\n"));
+    /// assert!(dot.contains("    bb0 -> bb2 [label=\"0\"];\n"));
+    /// assert!(dot.contains("    bb0 -> bb1 [label=\"otherwise\"];\n"));
+    /// assert!(dot.contains("    bb1 -> bb3;\n"));
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut out = format!("digraph {} {{\n", self.name);
+        out.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+        for (i, block) in self.blocks.iter().enumerate() {
+            let mut label = format!("bb{}\\l", i);
+            for statement in &block.statements {
+                label.push_str(&escape(&statement.to_string()));
+                label.push_str("\\l");
+            }
+            label.push_str(&escape(&block.terminator.to_string()));
+            label.push_str("\\l");
+            out.push_str(&format!("    bb{} [label=\"{}\"];\n", i, label));
+        }
+        for (i, block) in self.blocks.iter().enumerate() {
+            for (label, target) in block.terminator.successors() {
+                if label.is_empty() {
+                    out.push_str(&format!("    bb{} -> {};\n", i, target));
+                } else {
+                    out.push_str(&format!(
+                        "    bb{} -> {} [label=\"{}\"];\n",
+                        i, target, label
+                    ));
+                }
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+impl fmt::Display for Body {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let params: Vec<String> = (1..=self.arg_count)
+            .map(|i| format!("_{}: {}", i, self.locals[i].ty))
+            .collect();
+        writeln!(
+            f,
+            "fn {}({}) -> {} {{",
+            self.name,
+            params.join(", "),
+            self.locals[0].ty
+        )?;
+        for (i, decl) in self.locals.iter().enumerate() {
+            if let Some(name) = &decl.name {
+                writeln!(f, "    debug {} => _{};", name, i)?;
+            }
+        }
+        for (i, decl) in self.locals.iter().enumerate() {
+            // 参数已经写在函数签名里了
+            if (1..=self.arg_count).contains(&i) {
+                continue;
+            }
+            let mutable = if decl.mutable { "mut " } else { "" };
+            writeln!(f, "    let {}_{}: {};", mutable, i, decl.ty)?;
+        }
+        for (i, block) in self.blocks.iter().enumerate() {
+            writeln!(f)?;
+            writeln!(f, "    bb{}: {{", i)?;
+            for statement in &block.statements {
+                writeln!(f, "        {}", statement)?;
+            }
+            writeln!(f, "        {}", block.terminator)?;
+            writeln!(f, "    }}")?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+struct LoopScope {
+    head: BasicBlock,
+    exit: BasicBlock,
+    /// `break value` 写入的位置
+    dest: Place,
+    /// 进入循环时作用域栈的深度
+    depth: usize,
+}
+
+struct Builder<'a> {
+    locals: Vec<LocalDecl>,
+    blocks: Vec<(Vec<Statement>, Option<Terminator>)>,
+    current: BasicBlock,
+    /// 作用域中可见的用户变量
+    names: Vec<(String, Local)>,
+    /// 每个作用域中需要 `StorageDead` 的局部变量
+    scopes: Vec<Vec<Local>>,
+    /// `let x = &temp();` 中的临时值活到块结束, 这里记录它应当放进哪个作用域
+    extended: Option<usize>,
+    loops: Vec<LoopScope>,
+    /// 片段中定义的函数的返回类型
+    fns: &'a HashMap<String, Type>,
+}
+
+impl<'a> Builder<'a> {
+    fn new(fns: &'a HashMap<String, Type>, ret: Type) -> Self {
+        Builder {
+            locals: vec![LocalDecl {
+                mutable: true,
+                ty: ret,
+                name: None,
+            }],
+            blocks: vec![(Vec::new(), None)],
+            current: BasicBlock(0),
+            names: Vec::new(),
+            scopes: vec![Vec::new()],
+            extended: None,
+            loops: Vec::new(),
+            fns,
+        }
+    }
+
+    /// 结束降级, 删除从 `bb0` 出发不可达的基本块(例如 `return` 之后的代码)并重新编号
+    fn finish(mut self, name: &str, arg_count: usize) -> Body {
+        self.terminate(Terminator::Return);
+        let mut blocks: Vec<BasicBlockData> = self
+            .blocks
+            .into_iter()
+            .map(|(statements, terminator)| BasicBlockData {
+                statements,
+                terminator: terminator.unwrap_or(Terminator::Unreachable),
+            })
+            .collect();
+        let mut reachable = vec![false; blocks.len()];
+        let mut stack = vec![0];
+        while let Some(block) = stack.pop() {
+            if !reachable[block] {
+                reachable[block] = true;
+                let successors = blocks[block].terminator.successors();
+                stack.extend(successors.into_iter().map(|(_, target)| target.0));
+            }
+        }
+        let mut renumbered = Vec::new();
+        let mut next = 0;
+        for &live in &reachable {
+            renumbered.push(BasicBlock(next));
+            next += live as usize;
+        }
+        let mut index = 0;
+        blocks.retain(|_| {
+            index += 1;
+            reachable[index - 1]
+        });
+        for block in &mut blocks {
+            block.terminator.retarget(|target| renumbered[target.0]);
+        }
+        Body {
+            name: name.to_string(),
+            arg_count,
+            locals: self.locals,
+            blocks,
+        }
+    }
+
+    // ---------------------------------------------------------------- 基本块与局部变量
+
+    fn push(&mut self, statement: Statement) {
+        self.blocks[self.current.0].0.push(statement);
+    }
+
+    fn assign(&mut self, place: Place, rvalue: Rvalue) {
+        self.push(Statement::Assign(place, rvalue));
+    }
+
+    fn new_block(&mut self) -> BasicBlock {
+        self.blocks.push((Vec::new(), None));
+        BasicBlock(self.blocks.len() - 1)
+    }
+
+    /// 结束当前基本块; 已经结束的块(例如 `return` 之后)不再改动
+    fn terminate(&mut self, terminator: Terminator) {
+        let block = &mut self.blocks[self.current.0];
+        if block.1.is_none() {
+            block.1 = Some(terminator);
+        }
+    }
+
+    fn goto(&mut self, target: BasicBlock) {
+        self.terminate(Terminator::Goto(target));
+    }
+
+    /// 结束当前基本块, 之后的语句写进一个新的(不可达的)基本块
+    fn diverge(&mut self, terminator: Terminator) {
+        self.terminate(terminator);
+        self.current = self.new_block();
+    }
+
+    fn new_local(&mut self, ty: Type, mutable: bool, name: Option<String>) -> Local {
+        self.locals.push(LocalDecl { mutable, ty, name });
+        Local(self.locals.len() - 1)
+    }
+
+    /// 新的临时值, 在所属作用域结束时 `StorageDead`
+    fn temp(&mut self, ty: Type) -> Local {
+        let local = self.new_local(ty, true, None);
+        self.push(Statement::StorageLive(local));
+        let scope = self.extended.unwrap_or(self.scopes.len() - 1);
+        self.scopes[scope].push(local);
+        local
+    }
+
+    fn var(&mut self, name: &str, ty: Type, mutable: bool) -> Local {
+        let local = self.new_local(ty, mutable, Some(name.to_string()));
+        self.push(Statement::StorageLive(local));
+        self.scopes.last_mut().unwrap().push(local);
+        self.names.push((name.to_string(), local));
+        local
+    }
+
+    fn lookup(&self, name: &str) -> Option<Local> {
+        self.names
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, local)| *local)
+    }
+
+    fn push_scope(&mut self) -> usize {
+        self.scopes.push(Vec::new());
+        self.scopes.len() - 1
+    }
+
+    fn pop_scope(&mut self) {
+        let locals = self.scopes.pop().unwrap_or_default();
+        for local in locals.iter().rev() {
+            self.push(Statement::StorageDead(*local));
+        }
+        self.names.retain(|(_, l)| !locals.contains(l));
+    }
+
+    /// 提前离开作用域(`break`、`return`)时, 为途经的作用域生成 `StorageDead`
+    fn exit_to(&mut self, depth: usize) {
+        let dead: Vec<Local> = self.scopes[depth..]
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev().copied())
+            .collect();
+        for local in dead {
+            self.push(Statement::StorageDead(local));
+        }
+    }
+
+    // ---------------------------------------------------------------- 类型
+
+    fn type_of(&self, expr: &Expr) -> Type {
+        match &expr.kind {
+            ExprKind::Lit(lit) => lit_type(lit),
+            ExprKind::Path(path) if path.len() == 1 => self
+                .lookup(&path[0])
+                .map_or(Type::Infer, |local| self.locals[local.0].ty.clone()),
+            ExprKind::Paren(inner) => self.type_of(inner),
+            ExprKind::Ref { mutable, expr } => Type::Ref {
+                lifetime: None,
+                mutable: *mutable,
+                inner: Box::new(self.type_of(expr)),
+            },
+            ExprKind::Unary(UnOp::Deref, inner) => match self.type_of(inner) {
+                Type::Ref { inner, .. } => *inner,
+                _ => Type::Infer,
+            },
+            ExprKind::Unary(_, inner) => self.type_of(inner),
+            ExprKind::Binary(op, lhs, _) => {
+                if op.is_comparison() || op.is_lazy() {
+                    Type::named("bool")
+                } else {
+                    self.type_of(lhs)
+                }
+            }
+            ExprKind::Cast(_, ty) => ty.clone(),
+            ExprKind::Tuple(items) => Type::Tuple(items.iter().map(|i| self.type_of(i)).collect()),
+            ExprKind::Array(items) => Type::Array(
+                Box::new(items.first().map_or(Type::Infer, |i| self.type_of(i))),
+                Box::new(int(items.len(), expr.span)),
+            ),
+            ExprKind::Repeat(item, len) => {
+                Type::Array(Box::new(self.type_of(item)), Box::new((**len).clone()))
+            }
+            ExprKind::Index(base, _) => match self.type_of(base) {
+                Type::Array(elem, _) | Type::Slice(elem) => *elem,
+                _ => Type::Infer,
+            },
+            ExprKind::Field(base, field) => match self.type_of(base) {
+                Type::Tuple(items) => field
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|i| items.get(i).cloned())
+                    .unwrap_or(Type::Infer),
+                _ => Type::Infer,
+            },
+            ExprKind::Call(callee, _) => match &callee.kind {
+                ExprKind::Path(path) => self
+                    .fns
+                    .get(&path.join("::"))
+                    .cloned()
+                    .unwrap_or(Type::Infer),
+                _ => Type::Infer,
+            },
+            ExprKind::MethodCall { method, .. } if method == "len" => Type::named("usize"),
+            ExprKind::Macro { name, .. } if name == "format" => Type::named("String"),
+            ExprKind::Block(block) => self.block_type(block),
+            ExprKind::If { then, .. } => self.block_type(then),
+            ExprKind::Match { arms, .. } => arms
+                .first()
+                .map_or(Type::Never, |arm| self.type_of(&arm.body)),
+            ExprKind::Assign(..)
+            | ExprKind::AssignOp(..)
+            | ExprKind::While { .. }
+            | ExprKind::For { .. } => Type::unit(),
+            ExprKind::Break(_) | ExprKind::Continue | ExprKind::Return(_) => Type::Never,
+            ExprKind::Loop(body) => self.break_type(body).unwrap_or(Type::Never),
+            ExprKind::Range { start, end, .. } => Type::Path {
+                name: "Range".to_string(),
+                args: vec![start
+                    .as_ref()
+                    .or(end.as_ref())
+                    .map_or(Type::Infer, |bound| self.type_of(bound))],
+            },
+            _ => Type::Infer,
+        }
+    }
+
+    /// `loop` 的类型由其中的 `break value` 决定, 内层循环中的 `break` 不算
+    fn break_type(&self, block: &Block) -> Option<Type> {
+        block.stmts.iter().find_map(|stmt| match &stmt.kind {
+            StmtKind::Expr(expr) | StmtKind::Semi(expr) => self.break_in(expr),
+            StmtKind::Let {
+                init: Some(init), ..
+            } => self.break_in(init),
+            _ => None,
+        })
+    }
+
+    fn break_in(&self, expr: &Expr) -> Option<Type> {
+        match &expr.kind {
+            ExprKind::Break(Some(value)) => Some(self.type_of(value)),
+            ExprKind::Break(None) => Some(Type::unit()),
+            ExprKind::Block(block) => self.break_type(block),
+            ExprKind::If { then, els, .. } => self
+                .break_type(then)
+                .or_else(|| els.as_ref().and_then(|els| self.break_in(els))),
+            ExprKind::Match { arms, .. } => arms.iter().find_map(|arm| self.break_in(&arm.body)),
+            _ => None,
+        }
+    }
+
+    fn block_type(&self, block: &Block) -> Type {
+        block.tail().map_or(Type::unit(), |tail| self.type_of(tail))
+    }
+
+    // ---------------------------------------------------------------- 语句
+
+    fn block_into(&mut self, dest: &Place, block: &Block) -> LResult<()> {
+        self.push_scope();
+        let tail = block.tail().map(|tail| tail as *const Expr);
+        for stmt in &block.stmts {
+            match &stmt.kind {
+                StmtKind::Expr(expr) if Some(expr as *const Expr) == tail => {
+                    // 块中声明的变量此时才可见, `let x = { let y = 1; y };` 中 `x` 的类型由 `y` 决定
+                    if dest.projection.is_empty() && self.locals[dest.local.0].ty == Type::Infer {
+                        self.locals[dest.local.0].ty = self.type_of(expr);
+                    }
+                    self.into(dest, expr)?
+                }
+                _ => self.stmt(stmt)?,
+            }
+        }
+        if tail.is_none() {
+            self.assign(dest.clone(), unit());
+        }
+        self.pop_scope();
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> LResult<()> {
+        match &stmt.kind {
+            StmtKind::Let { pat, ty, init } => {
+                let ty = ty
+                    .clone()
+                    .or_else(|| init.as_ref().map(|init| self.type_of(init)))
+                    .unwrap_or(Type::Infer);
+                match (&pat.kind, init) {
+                    (
+                        PatKind::Binding {
+                            name,
+                            by_ref: false,
+                            mutable,
+                            sub: None,
+                        },
+                        init,
+                    ) => {
+                        let local = self.var(name, ty, *mutable);
+                        if let Some(init) = init {
+                            // `let x = &temp();` 延长临时值的生命周期到块结束
+                            let extend =
+                                matches!(&init.kind, ExprKind::Ref { expr, .. } if !is_place(expr));
+                            let block_scope = self.scopes.len() - 1;
+                            self.push_scope();
+                            if extend {
+                                self.extended = Some(block_scope);
+                            }
+                            let result = self.into(&local.into(), init);
+                            self.extended = None;
+                            result?;
+                            self.pop_scope();
+                        }
+                    }
+                    (_, Some(init)) => {
+                        self.push_scope();
+                        let value = self.as_place(init)?;
+                        let value_ty = self.type_of(init);
+                        self.pop_scope_keep(|builder| builder.bind(pat, &value, &value_ty))?;
+                    }
+                    (_, None) => {
+                        return Err(unsupported(pat.span, "没有初始化表达式的解构模式"));
+                    }
+                }
+            }
+            StmtKind::Expr(expr) | StmtKind::Semi(expr) => {
+                self.push_scope();
+                match &expr.kind {
+                    // 赋值语句不需要保存它的值 `()`
+                    ExprKind::Assign(..) | ExprKind::AssignOp(..) => self.assign_expr(expr)?,
+                    _ => {
+                        let dest = self.temp(self.type_of(expr));
+                        self.into(&dest.into(), expr)?;
+                    }
+                }
+                self.pop_scope();
+            }
+            StmtKind::Use(expr) => {
+                self.push_scope();
+                let dest = self.temp(self.type_of(expr));
+                self.into(&dest.into(), expr)?;
+                self.pop_scope();
+            }
+            // 嵌套的条目在顶层单独降级
+            StmtKind::Item(_) => {}
+        }
+        Ok(())
+    }
+
+    /// 在临时作用域中求值 `f`, 但让 `f` 新声明的变量留在外层作用域
+    fn pop_scope_keep<F: FnOnce(&mut Self) -> LResult<()>>(&mut self, f: F) -> LResult<()> {
+        let temps = self.scopes.pop().unwrap_or_default();
+        f(self)?;
+        for local in temps.iter().rev() {
+            self.push(Statement::StorageDead(*local));
+        }
+        Ok(())
+    }
+
+    /// 把 `value` 按模式 `pat` 解构到新的变量中
+    fn bind(&mut self, pat: &Pat, value: &Place, ty: &Type) -> LResult<()> {
+        match &pat.kind {
+            PatKind::Wild | PatKind::Lit(_) | PatKind::Range { .. } | PatKind::Path(_) => {}
+            PatKind::Binding {
+                name,
+                by_ref,
+                mutable,
+                sub,
+            } => {
+                if *by_ref {
+                    let ref_ty = Type::Ref {
+                        lifetime: None,
+                        mutable: *mutable,
+                        inner: Box::new(ty.clone()),
+                    };
+                    let local = self.var(name, ref_ty, false);
+                    self.assign(
+                        local.into(),
+                        Rvalue::Ref {
+                            mutable: *mutable,
+                            place: value.clone(),
+                        },
+                    );
+                } else {
+                    let local = self.var(name, ty.clone(), *mutable);
+                    let operand = self.operand_of(value.clone(), ty);
+                    self.assign(local.into(), Rvalue::Use(operand));
+                }
+                if let Some(sub) = sub {
+                    self.bind(sub, value, ty)?;
+                }
+            }
+            PatKind::Tuple(pats) => {
+                for (i, pat) in pats.iter().enumerate() {
+                    let field_ty = match ty {
+                        Type::Tuple(items) => items.get(i).cloned().unwrap_or(Type::Infer),
+                        _ => Type::Infer,
+                    };
+                    let field = value.project(Projection::Field(i.to_string()));
+                    self.bind(pat, &field, &field_ty)?;
+                }
+            }
+            PatKind::TupleStruct(path, pats) => {
+                let variant = value.project(Projection::Downcast(path.join("::")));
+                for (i, pat) in pats.iter().enumerate() {
+                    let field = variant.project(Projection::Field(i.to_string()));
+                    self.bind(pat, &field, &Type::Infer)?;
+                }
+            }
+            PatKind::Ref { pat, .. } => {
+                let inner = match ty {
+                    Type::Ref { inner, .. } => (**inner).clone(),
+                    _ => Type::Infer,
+                };
+                self.bind(pat, &value.project(Projection::Deref), &inner)?;
+            }
+            PatKind::Or(_) => {
+                if !pat.bindings().is_empty() {
+                    return Err(unsupported(pat.span, "带绑定的 `|` 模式"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn operand_of(&self, place: Place, ty: &Type) -> Operand {
+        if is_copy(ty) {
+            Operand::Copy(place)
+        } else {
+            Operand::Move(place)
+        }
+    }
+
+    // ---------------------------------------------------------------- 表达式
+
+    /// 把表达式的值写入 `dest`
+    fn into(&mut self, dest: &Place, expr: &Expr) -> LResult<()> {
+        match &expr.kind {
+            ExprKind::Block(block) => self.block_into(dest, block)?,
+            ExprKind::Paren(inner) => self.into(dest, inner)?,
+            ExprKind::If { cond, then, els } => {
+                if let ExprKind::Let(..) = cond.kind {
+                    return Err(unsupported(cond.span, "`if let`"));
+                }
+                let cond = self.as_operand(cond)?;
+                let then_block = self.new_block();
+                let else_block = self.new_block();
+                let join = self.new_block();
+                self.terminate(Terminator::SwitchInt {
+                    discr: cond,
+                    targets: vec![(0, else_block)],
+                    otherwise: then_block,
+                });
+                self.current = then_block;
+                self.block_into(dest, then)?;
+                self.goto(join);
+                self.current = else_block;
+                match els {
+                    Some(els) => self.into(dest, els)?,
+                    None => self.assign(dest.clone(), unit()),
+                }
+                self.goto(join);
+                self.current = join;
+            }
+            ExprKind::Loop(body) => {
+                let head = self.new_block();
+                let exit = self.new_block();
+                self.goto(head);
+                self.current = head;
+                self.loop_body(head, exit, dest, body)?;
+                self.goto(head);
+                self.current = exit;
+            }
+            ExprKind::While { cond, body } => {
+                if let ExprKind::Let(..) = cond.kind {
+                    return Err(unsupported(cond.span, "`while let`"));
+                }
+                let head = self.new_block();
+                let body_block = self.new_block();
+                let exit = self.new_block();
+                self.goto(head);
+                self.current = head;
+                let cond = self.as_operand(cond)?;
+                self.terminate(Terminator::SwitchInt {
+                    discr: cond,
+                    targets: vec![(0, exit)],
+                    otherwise: body_block,
+                });
+                self.current = body_block;
+                self.loop_body(head, exit, dest, body)?;
+                self.goto(head);
+                self.current = exit;
+                self.assign(dest.clone(), unit());
+            }
+            ExprKind::For { pat, iter, body } => self.for_loop(dest, pat, iter, body)?,
+            ExprKind::Match { scrutinee, arms } => {
+                let ty = self.type_of(scrutinee);
+                let scrutinee = self.as_place(scrutinee)?;
+                let join = self.new_block();
+                if let Some(targets) = switch_targets(arms) {
+                    self.switch_match(dest, &scrutinee, &ty, arms, targets, join)?;
+                } else {
+                    for arm in arms {
+                        let matched = self.new_block();
+                        let next = self.new_block();
+                        self.test(&arm.pat, &scrutinee, matched, next)?;
+                        self.current = matched;
+                        self.arm(dest, &scrutinee, &ty, arm, next, join)?;
+                        self.current = next;
+                    }
+                    self.terminate(Terminator::Unreachable);
+                }
+                self.current = join;
+            }
+            ExprKind::Call(callee, args) => {
+                let func = match &callee.kind {
+                    ExprKind::Path(path) => path.join("::"),
+                    _ => return Err(unsupported(callee.span, "调用非路径表达式")),
+                };
+                let args = args
+                    .iter()
+                    .map(|arg| self.as_operand(arg))
+                    .collect::<LResult<Vec<_>>>()?;
+                self.call(func, args, dest);
+            }
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+            } => {
+                let ty = self.type_of(receiver);
+                let mutable = MUTATING_METHODS.contains(&method.as_str());
+                let receiver = match &ty {
+                    // 接收者本身是共享引用时直接传入, 不再自动引用
+                    Type::Ref { mutable: false, .. } => self.as_operand(receiver)?,
+                    _ if is_place(receiver) => {
+                        let mut place = self.as_place(receiver)?;
+                        let mut inner = ty.clone();
+                        // 可变引用先解引用再借用: `&mut (*r)` 或 `&(*r)`
+                        if let Type::Ref { inner: target, .. } = &ty {
+                            place = place.project(Projection::Deref);
+                            inner = (**target).clone();
+                        }
+                        let temp = self.temp(Type::Ref {
+                            lifetime: None,
+                            mutable,
+                            inner: Box::new(inner),
+                        });
+                        self.assign(temp.into(), Rvalue::Ref { mutable, place });
+                        Operand::Move(temp.into())
+                    }
+                    _ => self.as_operand(receiver)?,
+                };
+                let mut operands = vec![receiver];
+                for arg in args {
+                    operands.push(self.as_operand(arg)?);
+                }
+                let owner = match &ty {
+                    Type::Path { name, .. } => name.clone(),
+                    Type::Ref { inner, .. } => inner.to_string(),
+                    _ => "_".to_string(),
+                };
+                self.call(format!("{}::{}", owner, method), operands, dest);
+            }
+            ExprKind::Macro { name, args } => {
+                let mut operands = Vec::new();
+                for (i, arg) in args.iter().enumerate() {
+                    let format_string = i == 0 && matches!(arg.kind, ExprKind::Lit(Lit::Str(_)));
+                    if FORMAT_MACROS.contains(&name.as_str()) && !format_string {
+                        let ty = self.type_of(arg);
+                        let place = self.as_place(arg)?;
+                        let temp = self.temp(Type::Ref {
+                            lifetime: None,
+                            mutable: false,
+                            inner: Box::new(ty),
+                        });
+                        self.assign(
+                            temp.into(),
+                            Rvalue::Ref {
+                                mutable: false,
+                                place,
+                            },
+                        );
+                        operands.push(Operand::Move(temp.into()));
+                    } else {
+                        operands.push(self.as_operand(arg)?);
+                    }
+                }
+                self.call(format!("{}!", name), operands, dest);
+            }
+            ExprKind::Return(value) => {
+                let ret: Place = Local(0).into();
+                match value {
+                    Some(value) => self.into(&ret, value)?,
+                    None => self.assign(ret, unit()),
+                }
+                self.exit_to(0);
+                self.diverge(Terminator::Return);
+            }
+            ExprKind::Break(value) => {
+                let (exit, target, depth) = match self.loops.last() {
+                    Some(scope) => (scope.exit, scope.dest.clone(), scope.depth),
+                    None => return Err(unsupported(expr.span, "循环之外的 `break`")),
+                };
+                match value {
+                    Some(value) => self.into(&target, value)?,
+                    None => self.assign(target, unit()),
+                }
+                self.exit_to(depth);
+                self.diverge(Terminator::Goto(exit));
+            }
+            ExprKind::Continue => {
+                let (head, depth) = match self.loops.last() {
+                    Some(scope) => (scope.head, scope.depth),
+                    None => return Err(unsupported(expr.span, "循环之外的 `continue`")),
+                };
+                self.exit_to(depth);
+                self.diverge(Terminator::Goto(head));
+            }
+            ExprKind::Assign(..) | ExprKind::AssignOp(..) => {
+                self.assign_expr(expr)?;
+                self.assign(dest.clone(), unit());
+            }
+            ExprKind::Binary(op, lhs, rhs) if op.is_lazy() => {
+                // `a && b` 等价于 `if a { b } else { false }`
+                let lhs = self.as_operand(lhs)?;
+                let rhs_block = self.new_block();
+                let short = self.new_block();
+                let join = self.new_block();
+                let (zero, nonzero) = match op {
+                    BinOp::And => (short, rhs_block),
+                    _ => (rhs_block, short),
+                };
+                self.terminate(Terminator::SwitchInt {
+                    discr: lhs,
+                    targets: vec![(0, zero)],
+                    otherwise: nonzero,
+                });
+                self.current = rhs_block;
+                self.into(dest, rhs)?;
+                self.goto(join);
+                self.current = short;
+                let value = Operand::Constant((*op == BinOp::Or).to_string());
+                self.assign(dest.clone(), Rvalue::Use(value));
+                self.goto(join);
+                self.current = join;
+            }
+            ExprKind::Let(..) => return Err(unsupported(expr.span, "`let` 表达式")),
+            _ => {
+                let rvalue = self.as_rvalue(expr)?;
+                self.assign(dest.clone(), rvalue);
+            }
+        }
+        Ok(())
+    }
+
+    fn assign_expr(&mut self, expr: &Expr) -> LResult<()> {
+        match &expr.kind {
+            ExprKind::Assign(lhs, rhs) => {
+                let rvalue = self.as_rvalue(rhs)?;
+                let place = self.as_place(lhs)?;
+                self.assign(place, rvalue);
+            }
+            ExprKind::AssignOp(op, lhs, rhs) => {
+                let rhs = self.as_operand(rhs)?;
+                let place = self.as_place(lhs)?;
+                let rvalue = Rvalue::BinaryOp(*op, Operand::Copy(place.clone()), rhs);
+                self.assign(place, rvalue);
+            }
+            _ => unreachable!("assign_expr 只处理赋值表达式"),
+        }
+        Ok(())
+    }
+
+    fn call(&mut self, func: String, args: Vec<Operand>, dest: &Place) {
+        let target = self.new_block();
+        self.terminate(Terminator::Call {
+            func,
+            args,
+            dest: dest.clone(),
+            target,
+        });
+        self.current = target;
+    }
+
+    fn loop_body(
+        &mut self,
+        head: BasicBlock,
+        exit: BasicBlock,
+        dest: &Place,
+        body: &Block,
+    ) -> LResult<()> {
+        self.loops.push(LoopScope {
+            head,
+            exit,
+            dest: dest.clone(),
+            depth: self.scopes.len(),
+        });
+        self.push_scope();
+        let value = self.temp(Type::unit());
+        let result = self.block_into(&value.into(), body);
+        self.pop_scope();
+        self.loops.pop();
+        result
+    }
+
+    /// `for pat in iter { body }` 按 rustc 的方式展开为 `IntoIterator::into_iter`
+    /// 与反复调用 `Iterator::next`
+    fn for_loop(&mut self, dest: &Place, pat: &Pat, iter: &Expr, body: &Block) -> LResult<()> {
+        let item_ty = match self.type_of(iter) {
+            Type::Path { name, mut args } if name.starts_with("Range") && args.len() == 1 => {
+                args.remove(0)
+            }
+            _ => Type::Infer,
+        };
+        let iter = self.as_operand(iter)?;
+        let iterator = self.temp(Type::Infer);
+        self.call(
+            "IntoIterator::into_iter".to_string(),
+            vec![iter],
+            &iterator.into(),
+        );
+        let head = self.new_block();
+        let exit = self.new_block();
+        self.goto(head);
+        self.current = head;
+        let depth = self.scopes.len();
+        self.push_scope();
+        let next = self.temp(Type::Path {
+            name: "Option".to_string(),
+            args: vec![Type::Infer],
+        });
+        let borrow = self.temp(Type::Ref {
+            lifetime: None,
+            mutable: true,
+            inner: Box::new(Type::Infer),
+        });
+        self.assign(
+            borrow.into(),
+            Rvalue::Ref {
+                mutable: true,
+                place: iterator.into(),
+            },
+        );
+        self.call(
+            "Iterator::next".to_string(),
+            vec![Operand::Move(borrow.into())],
+            &next.into(),
+        );
+        let discr = self.temp(Type::named("isize"));
+        self.assign(discr.into(), Rvalue::Discriminant(next.into()));
+        let body_block = self.new_block();
+        let none = self.new_block();
+        let unreachable = self.new_block();
+        self.terminate(Terminator::SwitchInt {
+            discr: Operand::Move(discr.into()),
+            targets: vec![(0, none), (1, body_block)],
+            otherwise: unreachable,
+        });
+        self.current = unreachable;
+        self.terminate(Terminator::Unreachable);
+
+        self.current = none;
+        self.exit_to(depth);
+        self.goto(exit);
+
+        self.current = body_block;
+        self.loops.push(LoopScope {
+            head,
+            exit,
+            dest: dest.clone(),
+            depth,
+        });
+        self.push_scope();
+        let item = Place::from(next)
+            .project(Projection::Downcast("Some".to_string()))
+            .project(Projection::Field("0".to_string()));
+        self.bind(pat, &item, &item_ty)?;
+        let value = self.temp(Type::unit());
+        let result = self.block_into(&value.into(), body);
+        self.pop_scope();
+        self.loops.pop();
+        result?;
+        self.pop_scope();
+        self.goto(head);
+
+        self.current = exit;
+        self.assign(dest.clone(), unit());
+        Ok(())
+    }
+
+    /// 全部分支都是整数、布尔或字符字面量(以及通配)时, 用一个 `switchInt` 完成匹配
+    fn switch_match(
+        &mut self,
+        dest: &Place,
+        scrutinee: &Place,
+        ty: &Type,
+        arms: &[super::ast::Arm],
+        targets: Vec<Vec<u128>>,
+        join: BasicBlock,
+    ) -> LResult<()> {
+        let blocks: Vec<BasicBlock> = arms.iter().map(|_| self.new_block()).collect();
+        let mut switch = Vec::new();
+        let mut otherwise = None;
+        for ((arm, values), block) in arms.iter().zip(&targets).zip(&blocks) {
+            if values.is_empty() && !matches!(arm.pat.kind, PatKind::Or(_)) {
+                otherwise.get_or_insert(*block);
+            }
+            for value in values {
+                if !switch.iter().any(|(v, _)| v == value) {
+                    switch.push((*value, *block));
+                }
+            }
+        }
+        let otherwise = match otherwise {
+            Some(block) => block,
+            None => {
+                let block = self.new_block();
+                self.blocks[block.0].1 = Some(Terminator::Unreachable);
+                block
+            }
+        };
+        self.terminate(Terminator::SwitchInt {
+            discr: Operand::Copy(scrutinee.clone()),
+            targets: switch,
+            otherwise,
+        });
+        for (arm, block) in arms.iter().zip(blocks) {
+            self.current = block;
+            self.push_scope();
+            self.bind(&arm.pat, scrutinee, ty)?;
+            self.into(dest, &arm.body)?;
+            self.pop_scope();
+            self.goto(join);
+        }
+        Ok(())
+    }
+
+    /// 匹配成功之后: 绑定变量, 检查守卫, 求值分支
+    fn arm(
+        &mut self,
+        dest: &Place,
+        scrutinee: &Place,
+        ty: &Type,
+        arm: &super::ast::Arm,
+        next: BasicBlock,
+        join: BasicBlock,
+    ) -> LResult<()> {
+        self.push_scope();
+        self.bind(&arm.pat, scrutinee, ty)?;
+        if let Some(guard) = &arm.guard {
+            let guard = self.as_operand(guard)?;
+            let body = self.new_block();
+            let failed = self.new_block();
+            self.terminate(Terminator::SwitchInt {
+                discr: guard,
+                targets: vec![(0, failed)],
+                otherwise: body,
+            });
+            // 守卫失败: 离开分支作用域, 继续匹配下一个分支
+            self.current = failed;
+            self.exit_to(self.scopes.len() - 1);
+            self.goto(next);
+            self.current = body;
+        }
+        self.into(dest, &arm.body)?;
+        self.pop_scope();
+        self.goto(join);
+        Ok(())
+    }
+
+    /// 检查 `place` 是否匹配模式 `pat`, 匹配时跳到 `matched`, 否则跳到 `failed`
+    fn test(
+        &mut self,
+        pat: &Pat,
+        place: &Place,
+        matched: BasicBlock,
+        failed: BasicBlock,
+    ) -> LResult<()> {
+        match &pat.kind {
+            PatKind::Wild | PatKind::Binding { sub: None, .. } => self.goto(matched),
+            PatKind::Binding { sub: Some(sub), .. } => self.test(sub, place, matched, failed)?,
+            PatKind::Lit(lit) => {
+                let value = self.as_operand(lit)?;
+                self.compare(BinOp::Eq, place, value, matched, failed);
+            }
+            PatKind::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let low = self.as_operand(start)?;
+                let upper = self.new_block();
+                self.compare(BinOp::Ge, place, low, upper, failed);
+                self.current = upper;
+                let high = self.as_operand(end)?;
+                let op = if *inclusive { BinOp::Le } else { BinOp::Lt };
+                self.compare(op, place, high, matched, failed);
+            }
+            PatKind::Tuple(pats) => {
+                for (i, pat) in pats.iter().enumerate() {
+                    let field = place.project(Projection::Field(i.to_string()));
+                    let next = if i + 1 == pats.len() {
+                        matched
+                    } else {
+                        self.new_block()
+                    };
+                    self.test(pat, &field, next, failed)?;
+                    self.current = next;
+                }
+                if pats.is_empty() {
+                    self.goto(matched);
+                }
+            }
+            PatKind::Ref { pat, .. } => {
+                self.test(pat, &place.project(Projection::Deref), matched, failed)?
+            }
+            PatKind::Or(alternatives) => {
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    let next = if i + 1 == alternatives.len() {
+                        failed
+                    } else {
+                        self.new_block()
+                    };
+                    self.test(alternative, place, matched, next)?;
+                    self.current = next;
+                }
+            }
+            PatKind::Path(_) | PatKind::TupleStruct(..) => {
+                return Err(unsupported(pat.span, "枚举模式"));
+            }
+        }
+        Ok(())
+    }
+
+    fn compare(
+        &mut self,
+        op: BinOp,
+        place: &Place,
+        value: Operand,
+        matched: BasicBlock,
+        failed: BasicBlock,
+    ) {
+        let result = self.temp(Type::named("bool"));
+        self.assign(
+            result.into(),
+            Rvalue::BinaryOp(op, Operand::Copy(place.clone()), value),
+        );
+        self.terminate(Terminator::SwitchInt {
+            discr: Operand::Move(result.into()),
+            targets: vec![(0, failed)],
+            otherwise: matched,
+        });
+    }
+
+    fn as_rvalue(&mut self, expr: &Expr) -> LResult<Rvalue> {
+        Ok(match &expr.kind {
+            ExprKind::Lit(lit) => Rvalue::Use(Operand::Constant(constant(lit))),
+            ExprKind::Paren(inner) => self.as_rvalue(inner)?,
+            ExprKind::Binary(op, lhs, rhs) if !op.is_lazy() => {
+                let lhs = self.as_operand(lhs)?;
+                let rhs = self.as_operand(rhs)?;
+                Rvalue::BinaryOp(*op, lhs, rhs)
+            }
+            ExprKind::Unary(op, inner) if *op != UnOp::Deref => {
+                Rvalue::UnaryOp(*op, self.as_operand(inner)?)
+            }
+            ExprKind::Ref { mutable, expr } => Rvalue::Ref {
+                mutable: *mutable,
+                place: self.as_place(expr)?,
+            },
+            ExprKind::Tuple(items) => Rvalue::Aggregate(
+                AggregateKind::Tuple,
+                items
+                    .iter()
+                    .map(|i| self.as_operand(i))
+                    .collect::<LResult<_>>()?,
+            ),
+            ExprKind::Array(items) => Rvalue::Aggregate(
+                AggregateKind::Array,
+                items
+                    .iter()
+                    .map(|i| self.as_operand(i))
+                    .collect::<LResult<_>>()?,
+            ),
+            ExprKind::Repeat(item, len) => Rvalue::Repeat(self.as_operand(item)?, len.to_string()),
+            ExprKind::Cast(inner, ty) => Rvalue::Cast(self.as_operand(inner)?, ty.clone()),
+            ExprKind::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let mut fields = Vec::new();
+                let mut operands = Vec::new();
+                for (name, bound) in [("start", start), ("end", end)] {
+                    if let Some(bound) = bound {
+                        fields.push(name.to_string());
+                        operands.push(self.as_operand(bound)?);
+                    }
+                }
+                let name = match (start.is_some(), end.is_some(), inclusive) {
+                    (_, _, true) => "RangeInclusive",
+                    (true, true, false) => "Range",
+                    (true, false, false) => "RangeFrom",
+                    (false, true, false) => "RangeTo",
+                    (false, false, false) => "RangeFull",
+                };
+                Rvalue::Aggregate(AggregateKind::Adt(name.to_string(), fields), operands)
+            }
+            ExprKind::Path(path) if self.lookup(&path.join("::")).is_none() => {
+                Rvalue::Use(Operand::Constant(path.join("::")))
+            }
+            _ => Rvalue::Use(self.as_operand(expr)?),
+        })
+    }
+
+    fn as_operand(&mut self, expr: &Expr) -> LResult<Operand> {
+        match &expr.kind {
+            ExprKind::Lit(lit) => Ok(Operand::Constant(constant(lit))),
+            ExprKind::Paren(inner) => self.as_operand(inner),
+            ExprKind::Path(path) if self.lookup(&path.join("::")).is_none() => {
+                Ok(Operand::Constant(path.join("::")))
+            }
+            _ if is_place(expr) => {
+                let ty = self.type_of(expr);
+                let place = self.as_place(expr)?;
+                Ok(self.operand_of(place, &ty))
+            }
+            _ => {
+                let temp = self.temp(self.type_of(expr));
+                self.into(&temp.into(), expr)?;
+                Ok(Operand::Move(temp.into()))
+            }
+        }
+    }
+
+    /// 在位置上下文中求值; 值表达式会先被写进一个临时值
+    fn as_place(&mut self, expr: &Expr) -> LResult<Place> {
+        match &expr.kind {
+            ExprKind::Path(path) if path.len() == 1 => {
+                if let Some(local) = self.lookup(&path[0]) {
+                    return Ok(local.into());
+                }
+            }
+            ExprKind::Paren(inner) => return self.as_place(inner),
+            ExprKind::Unary(UnOp::Deref, inner) => {
+                let place = match self.as_operand(inner)? {
+                    Operand::Copy(place) | Operand::Move(place) => place,
+                    constant => {
+                        let temp = self.temp(self.type_of(inner));
+                        self.assign(temp.into(), Rvalue::Use(constant));
+                        temp.into()
+                    }
+                };
+                return Ok(place.project(Projection::Deref));
+            }
+            ExprKind::Field(base, field) => {
+                let base = self.as_place(base)?;
+                return Ok(base.project(Projection::Field(field.clone())));
+            }
+            ExprKind::Index(base, index) => {
+                let base = self.as_place(base)?;
+                let index = match &index.kind {
+                    // 没有后缀的整数字面量用作索引时推断为 `usize`
+                    ExprKind::Lit(Lit::Int {
+                        value,
+                        suffix: None,
+                    }) => Operand::Constant(format!("{}_usize", value)),
+                    _ => self.as_operand(index)?,
+                };
+                let index = match index {
+                    Operand::Copy(place) | Operand::Move(place) if place.projection.is_empty() => {
+                        place.local
+                    }
+                    operand => {
+                        let temp = self.temp(Type::named("usize"));
+                        self.assign(temp.into(), Rvalue::Use(operand));
+                        temp
+                    }
+                };
+                return Ok(base.project(Projection::Index(index)));
+            }
+            _ => {}
+        }
+        let temp = self.temp(self.type_of(expr));
+        self.into(&temp.into(), expr)?;
+        Ok(temp.into())
+    }
+}
+
+fn unit() -> Rvalue {
+    Rvalue::Use(Operand::Constant("()".to_string()))
+}
+
+fn int(value: usize, span: Span) -> Expr {
+    Expr::new(
+        ExprKind::Lit(Lit::Int {
+            value: value as u128,
+            suffix: None,
+        }),
+        span,
+    )
+}
+
+fn unsupported(span: Span, what: &str) -> LowerError {
+    LowerError {
+        message: format!("尚不支持降级{}", what),
+        span,
+    }
+}
+
+fn lit_type(lit: &Lit) -> Type {
+    match lit {
+        Lit::Int { suffix, .. } => Type::named(suffix.as_deref().unwrap_or("i32")),
+        Lit::Float { suffix, .. } => Type::named(suffix.as_deref().unwrap_or("f64")),
+        Lit::Bool(_) => Type::named("bool"),
+        Lit::Char(_) => Type::named("char"),
+        Lit::Str(_) => Type::Ref {
+            lifetime: Some("'static".to_string()),
+            mutable: false,
+            inner: Box::new(Type::named("str")),
+        },
+    }
+}
+
+/// 常量的写法与 rustc 的 MIR 输出一致, 例如 `42_i32`、`1.5f64`
+fn constant(lit: &Lit) -> String {
+    match lit {
+        Lit::Int { value, .. } => format!("{}_{}", value, lit_type(lit)),
+        Lit::Float { value, .. } => format!("{:?}{}", value, lit_type(lit)),
+        Lit::Bool(value) => value.to_string(),
+        Lit::Str(value) => format!("{:?}", value),
+        Lit::Char(value) => format!("{:?}", value),
+    }
+}
+
+/// 位置表达式: 本地变量、解引用、字段和索引
+fn is_place(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::Path(path) => path.len() == 1,
+        ExprKind::Paren(inner) => is_place(inner),
+        ExprKind::Unary(UnOp::Deref, _) | ExprKind::Field(..) | ExprKind::Index(..) => true,
+        _ => false,
+    }
+}
+
+/// 字面量模式在 `switchInt` 中对应的整数值
+fn switch_value(pat: &Pat) -> Option<Vec<u128>> {
+    match &pat.kind {
+        PatKind::Lit(expr) => match &expr.kind {
+            ExprKind::Lit(Lit::Int { value, .. }) => Some(vec![*value]),
+            ExprKind::Lit(Lit::Bool(value)) => Some(vec![*value as u128]),
+            ExprKind::Lit(Lit::Char(value)) => Some(vec![*value as u128]),
+            _ => None,
+        },
+        PatKind::Or(alternatives) => {
+            let mut values = Vec::new();
+            for alternative in alternatives {
+                values.extend(switch_value(alternative)?);
+            }
+            Some(values)
+        }
+        _ => None,
+    }
+}
+
+/// 能否用一个 `switchInt` 完成匹配: 每个分支是字面量或者通配, 并且没有守卫
+fn switch_targets(arms: &[super::ast::Arm]) -> Option<Vec<Vec<u128>>> {
+    arms.iter()
+        .map(|arm| {
+            if arm.guard.is_some() {
+                return None;
+            }
+            match &arm.pat.kind {
+                PatKind::Wild | PatKind::Binding { sub: None, .. } => Some(Vec::new()),
+                _ => switch_value(&arm.pat),
+            }
+        })
+        .collect()
+}
+
+fn lower_fn(decl: &FnDecl, fns: &HashMap<String, Type>) -> LResult<Body> {
+    let mut builder = Builder::new(fns, decl.ret.clone());
+    let mut params = Vec::new();
+    for param in &decl.params {
+        let local = builder.new_local(param.ty.clone(), false, None);
+        params.push((param, local));
+    }
+    for (param, local) in params {
+        match &param.pat.kind {
+            PatKind::Binding {
+                name,
+                by_ref: false,
+                mutable,
+                sub: None,
+            } => {
+                builder.locals[local.0].name = Some(name.clone());
+                builder.locals[local.0].mutable = *mutable;
+                builder.names.push((name.clone(), local));
+            }
+            _ => {
+                let ty = param.ty.clone();
+                builder.bind(&param.pat, &local.into(), &ty)?;
+            }
+        }
+    }
+    builder.block_into(&Local(0).into(), &decl.body)?;
+    Ok(builder.finish(&decl.name, decl.params.len()))
+}
+
+/// # 降级为 MIR 风格的中间表示
+///
+/// 片段中的每个函数各自降级; 其余的顶层语句组成一个名为 `main` 的函数
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::lang::mir::lower;
+///
+/// // binding::reference2: `&42` 会创建一个临时值, 它的生命周期被延长到块结束
+/// let bodies = lower("let x = &42; let y = *x + 1;").unwrap();
+/// assert_eq!(bodies[0].to_string(), "\
+/// fn main() -> () {
+///     debug x => _1;
+///     debug y => _3;
+///     let mut _0: ();
+///     let _1: &i32;
+///     let mut _2: i32;
+///     let _3: i32;
+///
+///     bb0: {
+///         StorageLive(_1);
+///         StorageLive(_2);
+///         _2 = const 42_i32;
+///         _1 = &_2;
+///         StorageLive(_3);
+///         _3 = Add(copy (*_1), const 1_i32);
+///         StorageDead(_3);
+///         StorageDead(_2);
+///         StorageDead(_1);
+///         return;
+///     }
+/// }
+/// ");
+///
+/// let bodies = lower("fn pick(n: i32) -> i32 { match n { 1 | 2 => 10, _ => 0 } }").unwrap();
+/// assert!(bodies[0].to_string().contains("switchInt(copy _1) -> [1: bb2, 2: bb2, otherwise: bb3];"));
+/// ```
+pub fn lower(src: &str) -> Result<Vec<Body>, LowerError> {
+    let stmts = parse_stmts(src)?;
+    let mut fns = HashMap::new();
+    for stmt in &stmts {
+        if let StmtKind::Item(Item::Fn(decl)) = &stmt.kind {
+            fns.insert(decl.name.clone(), decl.ret.clone());
+        }
+    }
+    let mut bodies = Vec::new();
+    let mut main = Vec::new();
+    for stmt in stmts {
+        match stmt.kind {
+            StmtKind::Item(Item::Fn(decl)) => bodies.push(lower_fn(&decl, &fns)?),
+            StmtKind::Item(_) => {}
+            _ => main.push(stmt),
+        }
+    }
+    if !main.is_empty() {
+        let mut builder = Builder::new(&fns, Type::unit());
+        for stmt in &main {
+            builder.stmt(stmt)?;
+        }
+        builder.pop_scope();
+        bodies.insert(0, builder.finish("main", 0));
+    }
+    Ok(bodies)
+}
@@ -0,0 +1,54 @@
+//! # Rust 子集的前端
+//!
+//! 一组面向教学的小工具共用的词法分析、语法分析等基础设施
+//!
+//! - [`lexer`] 模块: 把源码切分成带位置的记号
+//! - [`ast`] 模块: 教学用 Rust 子集的语法树
+//! - [`parser`] 模块: 把记号解析成语法树
+//! - [`diagnostic`] 模块: 各个分析器共用的诊断信息
+//! - [`drift`] 模块: 检查文档中的示例代码与真实实现之间的偏差
+//! - [`place`] 模块: 标注位置表达式与值表达式, 以及它们所处的求值上下文
+//! - [`ownership`] 模块: 模拟 `let` 绑定之间所有权的移动与复制
+//! - [`borrowck`] 模块: 在词法作用域与 NLL 两种模式下检查共享借用与可变借用的冲突
+//! - [`consteval`] 模块: 模拟编译期求值, 拒绝常量上下文中不允许的操作并报告求值失败
+//! - [`interp`] 模块: 逐步解释执行表达式语言, 说明块、`if`、`loop` 与 `match` 各自求出什么值
+//! - [`mir`] 模块: 降级为带有临时值、`StorageLive`/`StorageDead` 和基本块的 MIR 风格中间表示
+//! - [`typeck`] 模块: 按 rustc 的流分析规则给块、`if`、`while` 与 `loop` 定型, 报告 E0308 与 E0317
+
+pub mod ast;
+pub mod borrowck;
+pub mod consteval;
+pub mod diagnostic;
+pub mod drift;
+pub mod interp;
+pub mod lexer;
+pub mod mir;
+pub mod ownership;
+pub mod parser;
+pub mod place;
+pub mod typeck;
+
+use std::panic;
+use std::thread;
+
+/// 求值线程的栈大小
+///
+/// 解释器与常量求值器都是递归求值的, 调试构建中每一层函数调用要占用上百 KB 的栈,
+/// 主线程默认的 8 MB 栈在达到调用层数的上限之前就会溢出
+const EVAL_STACK_SIZE: usize = 64 << 20;
+
+/// 在栈足够大的线程上运行 `f`, `f` 中的 panic 原样传播给调用者
+pub(crate) fn with_eval_stack<T, F>(f: F) -> T
+where
+    F: FnOnce() -> T + Send,
+    T: Send,
+{
+    thread::scope(|scope| {
+        thread::Builder::new()
+            .stack_size(EVAL_STACK_SIZE)
+            .spawn_scoped(scope, f)
+            .expect("failed to spawn evaluation thread")
+            .join()
+            .unwrap_or_else(|payload| panic::resume_unwind(payload))
+    })
+}
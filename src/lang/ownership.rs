@@ -0,0 +1,1199 @@
+//! # 所有权模拟
+//!
+//! 把 [`ch02::binding::ownership`](crate::ch02::binding::ownership) 中 `place1` 与 `place2` 的例子推广到任意代码片段:
+//! 逐条执行语句, 记录每个绑定拥有的值, 根据类型是否实现了 `Copy` 决定赋值时是复制还是移动,
+//! 并在移动之后再次使用时报告 rustc 风格的错误
+//!
+//! 类型来自 `let` 的类型标注, 或者从初始化表达式粗略推断(字面量、`to_string()`、`String::from`、
+//! `vec!` 等). 无法推断的类型按没有实现 `Copy` 处理
+//!
+//! 除了 `use after move`(E0382)之外, 还会报告使用未初始化的绑定(E0381)、
+//! 对不可变绑定二次赋值(E0384)、可变借用不可变绑定(E0596)以及从引用或索引中移出值(E0507、E0508)
+//!
+//! 元组字段可以单独移出: 移出 `t.0` 之后 `t.1` 仍然可用, 但不能再整体使用 `t`(部分移动)
+//!
+//! `use x;` 是一条伪语句, 表示读取一次 `x` 而不移动它
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::ast::{Block, Expr, ExprKind, Item, Lit, Pat, PatKind, Stmt, StmtKind, Type, UnOp};
+use super::diagnostic::Diagnostic;
+use super::lexer::Span;
+use super::parser::{parse_stmts, ParseError};
+
+const COPY_PRIMITIVES: [&str; 16] = [
+    "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize", "f32",
+    "f64", "bool", "char",
+];
+
+/// 会修改接收者的方法, 调用时需要可变借用
+const MUTATING_METHODS: [&str; 10] = [
+    "push", "push_str", "pop", "insert", "remove", "clear", "truncate", "sort", "extend", "append",
+];
+
+/// 会借用参数的格式化宏
+const FORMAT_MACROS: [&str; 11] = [
+    "println",
+    "print",
+    "eprintln",
+    "eprint",
+    "format",
+    "write",
+    "writeln",
+    "assert",
+    "assert_eq",
+    "assert_ne",
+    "panic",
+];
+
+/// # 类型是否实现了 Copy
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::lang::ownership::is_copy;
+/// use rust_programming_of_zhd::lang::parser::parse_type;
+///
+/// let copy = |ty| is_copy(&parse_type(ty).unwrap());
+/// assert!(copy("&'static str"));
+/// assert!(copy("(i32, [char; 3])"));
+/// assert!(!copy("String"));
+/// assert!(!copy("&mut i32"));
+/// assert!(!copy("(i32, Vec<i32>)"));
+/// ```
+pub fn is_copy(ty: &Type) -> bool {
+    match ty {
+        Type::Path { name, args } => args.is_empty() && COPY_PRIMITIVES.contains(&name.as_str()),
+        Type::Ref { mutable, .. } => !mutable,
+        Type::Tuple(items) => items.iter().all(is_copy),
+        Type::Array(elem, _) => is_copy(elem),
+        Type::Fn(..) | Type::Never => true,
+        Type::Slice(_) | Type::Trait { .. } | Type::Infer => false,
+    }
+}
+
+fn reference(mutable: bool, inner: Type) -> Type {
+    Type::Ref {
+        lifetime: None,
+        mutable,
+        inner: Box::new(inner),
+    }
+}
+
+fn generic(name: &str, arg: Type) -> Type {
+    Type::Path {
+        name: name.to_string(),
+        args: vec![arg],
+    }
+}
+
+/// 解引用之后的类型
+fn deref(ty: &Type) -> Option<Type> {
+    match ty {
+        Type::Ref { inner, .. } => Some((**inner).clone()),
+        Type::Path { name, args } if name == "Box" && args.len() == 1 => Some(args[0].clone()),
+        _ => None,
+    }
+}
+
+/// 字段路径 `fields` 对应的类型, 只能确定元组字段
+fn field_type(ty: &Type, fields: &[String]) -> Type {
+    fields
+        .iter()
+        .fold(ty.clone(), |ty, field| match (ty, field.parse::<usize>()) {
+            (Type::Tuple(items), Ok(i)) => items.get(i).cloned().unwrap_or(Type::Infer),
+            _ => Type::Infer,
+        })
+}
+
+/// 本地变量的名字
+fn local_name(expr: &Expr) -> Option<&str> {
+    match &expr.kind {
+        ExprKind::Path(path) if path.len() == 1 => Some(&path[0]),
+        ExprKind::Paren(inner) => local_name(inner),
+        _ => None,
+    }
+}
+
+/// 以本地变量为根、只经过字段投影的位置, 例如 `t.0.1` 为 `("t", ["0", "1"])`
+fn field_place(expr: &Expr) -> Option<(&str, Vec<String>)> {
+    match &expr.kind {
+        ExprKind::Field(base, field) => {
+            let (root, mut fields) = match local_name(base) {
+                Some(root) => (root, Vec::new()),
+                None => field_place(base)?,
+            };
+            fields.push(field.clone());
+            Some((root, fields))
+        }
+        ExprKind::Paren(inner) => field_place(inner),
+        _ => None,
+    }
+}
+
+fn place_name(root: &str, fields: &[String]) -> String {
+    fields
+        .iter()
+        .fold(root.to_string(), |name, field| name + "." + field)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventKind {
+    /// 引入一个新绑定
+    Bind {
+        name: String,
+        ty: Type,
+        initialized: bool,
+    },
+    /// 值被移动, `to` 为接收它的绑定
+    Move {
+        name: String,
+        to: Option<String>,
+    },
+    /// 值被复制
+    Copy {
+        name: String,
+        to: Option<String>,
+    },
+    Borrow {
+        name: String,
+        mutable: bool,
+    },
+    Assign {
+        name: String,
+    },
+    Use {
+        name: String,
+    },
+}
+
+/// 模拟过程中的一步
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub span: Span,
+    pub kind: EventKind,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:>5}  ", self.span.to_string())?;
+        let arrow = |to: &Option<String>| match to {
+            Some(to) => format!(" -> {}", to),
+            None => String::new(),
+        };
+        match &self.kind {
+            EventKind::Bind {
+                name,
+                ty,
+                initialized,
+            } => {
+                write!(f, "let    {}: {}", name, ty)?;
+                if is_copy(ty) {
+                    f.write_str(" (Copy)")?;
+                }
+                if !initialized {
+                    f.write_str(" (未初始化)")?;
+                }
+                Ok(())
+            }
+            EventKind::Move { name, to } => write!(f, "move   {}{}", name, arrow(to)),
+            EventKind::Copy { name, to } => write!(f, "copy   {}{}", name, arrow(to)),
+            EventKind::Borrow { name, mutable } => {
+                write!(f, "borrow &{}{}", if *mutable { "mut " } else { "" }, name)
+            }
+            EventKind::Assign { name } => write!(f, "assign {}", name),
+            EventKind::Use { name } => write!(f, "use    {}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Uninit,
+    /// 已初始化, 记录最近一次赋值的位置
+    Init(Span),
+    /// 已被移动, 记录移动发生的位置
+    Moved(Span),
+}
+
+#[derive(Debug, Clone)]
+struct Binding {
+    name: String,
+    ty: Type,
+    mutable: bool,
+    declared: Span,
+    /// 第一次初始化的位置, 移动之后仍然保留, 用于 E0384 的提示
+    initialized: Option<Span>,
+    state: State,
+    /// 已被移出的字段路径与移出的位置, 只在整体处于 `Init` 状态时有意义
+    moved_fields: Vec<(Vec<String>, Span)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// 值上下文: 移动或复制
+    Move,
+    Borrow {
+        mutable: bool,
+    },
+    /// 只读取, 不移动也不产生借用
+    Use,
+}
+
+#[derive(Default)]
+struct Simulator {
+    bindings: Vec<Binding>,
+    scopes: Vec<usize>,
+    /// 片段中定义的函数: 参数类型与返回类型
+    fns: HashMap<String, (Vec<Type>, Type)>,
+    events: Vec<Event>,
+    diagnostics: Vec<Diagnostic>,
+    /// 正在模拟循环体的第二次迭代, 此时不再记录事件
+    second_iteration: bool,
+}
+
+impl Simulator {
+    fn lookup(&self, name: &str) -> Option<usize> {
+        self.bindings.iter().rposition(|b| b.name == name)
+    }
+
+    fn event(&mut self, span: Span, kind: EventKind) {
+        if !self.second_iteration {
+            self.events.push(Event { span, kind });
+        }
+    }
+
+    fn report(&mut self, diagnostic: Diagnostic) {
+        // 同一次移动之后对同一个位置的多次使用只报告第一次, 不论是使用还是借用
+        let place = |d: &Diagnostic| {
+            d.message
+                .split_once("value: ")
+                .map(|(_, place)| place.to_string())
+        };
+        let same_move = |d: &Diagnostic| {
+            diagnostic.code == Some("E0382")
+                && place(d) == place(&diagnostic)
+                && d.notes.last() == diagnostic.notes.last()
+        };
+        let duplicate = self
+            .diagnostics
+            .iter()
+            .any(|d| d.code == diagnostic.code && (d.span == diagnostic.span || same_move(d)));
+        if !duplicate {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(self.bindings.len());
+    }
+
+    fn pop_scope(&mut self) {
+        let start = self.scopes.pop().unwrap_or(0);
+        self.bindings.truncate(start);
+    }
+
+    /// 合并两个分支之后的状态: 任何一个分支中被移动的值都视为已被移动
+    fn merge(&mut self, other: Vec<Binding>) {
+        for (binding, other) in self.bindings.iter_mut().zip(other) {
+            binding.state = match (binding.state, other.state) {
+                (State::Moved(at), _) | (_, State::Moved(at)) => State::Moved(at),
+                (State::Uninit, _) | (_, State::Uninit) => State::Uninit,
+                (State::Init(at), _) => State::Init(at),
+            };
+            binding.initialized = binding.initialized.or(other.initialized);
+            for (fields, at) in other.moved_fields {
+                if !binding.moved_fields.iter().any(|(f, _)| *f == fields) {
+                    binding.moved_fields.push((fields, at));
+                }
+            }
+        }
+    }
+
+    // ---------------------------------------------------------------- 类型推断
+
+    fn type_of(&self, expr: &Expr) -> Type {
+        match &expr.kind {
+            ExprKind::Lit(Lit::Int { suffix, .. }) => {
+                Type::named(suffix.as_deref().unwrap_or("i32"))
+            }
+            ExprKind::Lit(Lit::Float { suffix, .. }) => {
+                Type::named(suffix.as_deref().unwrap_or("f64"))
+            }
+            ExprKind::Lit(Lit::Bool(_)) => Type::named("bool"),
+            ExprKind::Lit(Lit::Char(_)) => Type::named("char"),
+            ExprKind::Lit(Lit::Str(_)) => Type::Ref {
+                lifetime: Some("'static".to_string()),
+                mutable: false,
+                inner: Box::new(Type::named("str")),
+            },
+            ExprKind::Path(path) => match path.as_slice() {
+                [name] => match self.lookup(name) {
+                    Some(i) => self.bindings[i].ty.clone(),
+                    None => match self.fns.get(name) {
+                        Some((params, ret)) => Type::Fn(params.clone(), Box::new(ret.clone())),
+                        None => Type::Infer,
+                    },
+                },
+                _ => Type::Infer,
+            },
+            ExprKind::Paren(inner) => self.type_of(inner),
+            ExprKind::Ref { mutable, expr } => reference(*mutable, self.type_of(expr)),
+            ExprKind::Unary(UnOp::Deref, inner) => {
+                deref(&self.type_of(inner)).unwrap_or(Type::Infer)
+            }
+            ExprKind::Unary(_, inner) => self.type_of(inner),
+            ExprKind::Binary(op, lhs, _) => {
+                if op.is_comparison() || op.is_lazy() {
+                    Type::named("bool")
+                } else {
+                    self.type_of(lhs)
+                }
+            }
+            ExprKind::Assign(..) | ExprKind::AssignOp(..) => Type::unit(),
+            ExprKind::Field(base, field) => match self.type_of(base) {
+                Type::Tuple(items) => field
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|i| items.get(i).cloned())
+                    .unwrap_or(Type::Infer),
+                _ => Type::Infer,
+            },
+            ExprKind::Index(base, _) => match self.type_of(base) {
+                Type::Array(elem, _) | Type::Slice(elem) => *elem,
+                Type::Path { name, mut args } if name == "Vec" && args.len() == 1 => args.remove(0),
+                _ => Type::Infer,
+            },
+            ExprKind::Call(callee, args) => match &callee.kind {
+                ExprKind::Path(path) => match path.join("::").as_str() {
+                    "String::from" | "String::new" => Type::named("String"),
+                    "Vec::new" | "Vec::with_capacity" => generic("Vec", Type::Infer),
+                    "Box::new" => {
+                        generic("Box", args.first().map_or(Type::Infer, |a| self.type_of(a)))
+                    }
+                    name => self
+                        .fns
+                        .get(name)
+                        .map_or(Type::Infer, |(_, ret)| ret.clone()),
+                },
+                _ => Type::Infer,
+            },
+            ExprKind::MethodCall {
+                receiver, method, ..
+            } => {
+                let receiver = self.type_of(receiver);
+                let owned = deref(&receiver).unwrap_or_else(|| receiver.clone());
+                match method.as_str() {
+                    "to_string" => Type::named("String"),
+                    "to_owned" if owned == Type::named("str") => Type::named("String"),
+                    "clone" | "to_owned" => owned,
+                    "len" | "count" => Type::named("usize"),
+                    "is_empty" | "contains" | "starts_with" | "ends_with" => Type::named("bool"),
+                    "as_str" => reference(false, Type::named("str")),
+                    "to_vec" => generic("Vec", Type::Infer),
+                    m if MUTATING_METHODS.contains(&m) && m != "pop" && m != "remove" => {
+                        Type::unit()
+                    }
+                    _ => Type::Infer,
+                }
+            }
+            ExprKind::Macro { name, args } => match name.as_str() {
+                "format" => Type::named("String"),
+                "vec" => match args.first().map(|a| self.type_of(a)) {
+                    Some(Type::Array(elem, _)) => generic("Vec", *elem),
+                    _ => generic("Vec", Type::Infer),
+                },
+                _ => Type::unit(),
+            },
+            ExprKind::Tuple(items) => Type::Tuple(items.iter().map(|i| self.type_of(i)).collect()),
+            ExprKind::Array(items) => {
+                let elem = items.first().map_or(Type::Infer, |i| self.type_of(i));
+                let len = Expr::new(
+                    ExprKind::Lit(Lit::Int {
+                        value: items.len() as u128,
+                        suffix: None,
+                    }),
+                    expr.span,
+                );
+                Type::Array(Box::new(elem), Box::new(len))
+            }
+            ExprKind::Repeat(item, len) => {
+                Type::Array(Box::new(self.type_of(item)), Box::new((**len).clone()))
+            }
+            ExprKind::Let(..) => Type::named("bool"),
+            ExprKind::Block(block) => self.block_type(block),
+            ExprKind::If { then, .. } => self.block_type(then),
+            ExprKind::Match { arms, .. } => arms
+                .first()
+                .map_or(Type::Never, |arm| self.type_of(&arm.body)),
+            ExprKind::While { .. } | ExprKind::For { .. } => Type::unit(),
+            ExprKind::Loop(_) => Type::Infer,
+            ExprKind::Break(_) | ExprKind::Continue | ExprKind::Return(_) => Type::Never,
+            ExprKind::Range { start, end, .. } => {
+                let bound = start.as_ref().or(end.as_ref());
+                generic("Range", bound.map_or(Type::Infer, |b| self.type_of(b)))
+            }
+            ExprKind::Cast(_, ty) => ty.clone(),
+        }
+    }
+
+    fn block_type(&self, block: &Block) -> Type {
+        block.tail().map_or(Type::unit(), |tail| self.type_of(tail))
+    }
+
+    // ---------------------------------------------------------------- 语句
+
+    fn block(&mut self, block: &Block) {
+        self.push_scope();
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+        self.pop_scope();
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Let { pat, ty, init } => {
+                let declared = ty.clone();
+                let inferred = init.as_ref().map(|init| self.type_of(init));
+                if let Some(init) = init {
+                    let mode = match &pat.kind {
+                        PatKind::Binding {
+                            by_ref: true,
+                            mutable,
+                            ..
+                        } => Mode::Borrow { mutable: *mutable },
+                        _ => Mode::Move,
+                    };
+                    let dest = match &pat.kind {
+                        PatKind::Binding { name, .. } => Some(name.as_str()),
+                        _ => None,
+                    };
+                    self.expr(init, mode, dest);
+                }
+                let ty = declared.or(inferred).unwrap_or(Type::Infer);
+                self.bind(pat, ty, init.is_some());
+            }
+            StmtKind::Expr(expr) | StmtKind::Semi(expr) => self.expr(expr, Mode::Move, None),
+            StmtKind::Use(expr) => self.expr(expr, Mode::Use, None),
+            StmtKind::Item(Item::Fn(decl)) => {
+                // 函数体不能访问外部的绑定
+                let outer = std::mem::take(&mut self.bindings);
+                let scopes = std::mem::take(&mut self.scopes);
+                for param in &decl.params {
+                    self.bind(&param.pat, param.ty.clone(), true);
+                }
+                self.block(&decl.body);
+                self.bindings = outer;
+                self.scopes = scopes;
+            }
+            StmtKind::Item(_) => {}
+        }
+    }
+
+    /// 为模式中的绑定引入新的变量
+    fn bind(&mut self, pat: &Pat, ty: Type, initialized: bool) {
+        match (&pat.kind, ty) {
+            (
+                PatKind::Binding {
+                    name,
+                    by_ref,
+                    mutable,
+                    sub,
+                },
+                ty,
+            ) => {
+                let ty = if *by_ref { reference(*mutable, ty) } else { ty };
+                self.event(
+                    pat.span,
+                    EventKind::Bind {
+                        name: name.clone(),
+                        ty: ty.clone(),
+                        initialized,
+                    },
+                );
+                self.bindings.push(Binding {
+                    name: name.clone(),
+                    ty: ty.clone(),
+                    mutable: *mutable && !*by_ref,
+                    declared: pat.span,
+                    initialized: if initialized { Some(pat.span) } else { None },
+                    state: if initialized {
+                        State::Init(pat.span)
+                    } else {
+                        State::Uninit
+                    },
+                    moved_fields: Vec::new(),
+                });
+                if let Some(sub) = sub {
+                    self.bind(sub, ty, initialized);
+                }
+            }
+            (PatKind::Tuple(pats), Type::Tuple(types)) if pats.len() == types.len() => {
+                for (pat, ty) in pats.iter().zip(types) {
+                    self.bind(pat, ty, initialized);
+                }
+            }
+            (PatKind::Ref { pat, .. }, Type::Ref { inner, .. }) => {
+                self.bind(pat, *inner, initialized)
+            }
+            _ => {
+                for binding in pat.bindings() {
+                    self.bind(binding, Type::Infer, initialized);
+                }
+            }
+        }
+    }
+
+    // ---------------------------------------------------------------- 表达式
+
+    /// 访问本地变量 `bindings[i]`
+    fn access(&mut self, i: usize, span: Span, mode: Mode, dest: Option<&str>) {
+        let binding = self.bindings[i].clone();
+        let name = binding.name.clone();
+        match binding.state {
+            State::Uninit => self.report(
+                Diagnostic::error(
+                    "E0381",
+                    span,
+                    format!("used binding `{}` isn't initialized", name),
+                )
+                .with_label(&format!("`{}` used here but it isn't initialized", name))
+                .with_note(
+                    binding.declared,
+                    "binding declared here but left uninitialized",
+                ),
+            ),
+            State::Moved(at) => {
+                let (verb, label) = match mode {
+                    Mode::Borrow { .. } => ("borrow", "value borrowed here after move"),
+                    Mode::Move | Mode::Use => ("use", "value used here after move"),
+                };
+                let moved = if self.second_iteration && at >= span {
+                    "value moved here, in previous iteration of loop"
+                } else {
+                    "value moved here"
+                };
+                self.report(
+                    Diagnostic::error(
+                        "E0382",
+                        span,
+                        format!("{} of moved value: `{}`", verb, name),
+                    )
+                    .with_label(label)
+                    .with_note(
+                        binding.declared,
+                        &format!(
+                            "move occurs because `{}` has type `{}`, which does not implement the `Copy` trait",
+                            name, binding.ty
+                        ),
+                    )
+                    .with_note(at, moved),
+                );
+            }
+            State::Init(_) if !binding.moved_fields.is_empty() => {
+                let (fields, at) = binding.moved_fields[0].clone();
+                self.report_partial_move(&name, &binding, &fields, at, span, mode);
+            }
+            State::Init(_) => match mode {
+                Mode::Move if is_copy(&binding.ty) => {
+                    let to = dest.map(str::to_string);
+                    self.event(span, EventKind::Copy { name, to });
+                }
+                Mode::Move => {
+                    self.bindings[i].state = State::Moved(span);
+                    let to = dest.map(str::to_string);
+                    self.event(span, EventKind::Move { name, to });
+                }
+                Mode::Borrow { mutable } => {
+                    if mutable && !binding.mutable && !matches!(binding.ty, Type::Ref { .. }) {
+                        self.report(
+                            Diagnostic::error(
+                                "E0596",
+                                span,
+                                format!(
+                                    "cannot borrow `{}` as mutable, as it is not declared as mutable",
+                                    name
+                                ),
+                            )
+                            .with_label("cannot borrow as mutable")
+                            .with_note(binding.declared, "consider changing this to be mutable"),
+                        );
+                    }
+                    self.event(span, EventKind::Borrow { name, mutable });
+                }
+                Mode::Use => self.event(span, EventKind::Use { name }),
+            },
+        }
+    }
+
+    /// 给本地变量 `bindings[i]` 赋值; `compound` 为复合赋值, 此时需要读取原来的值
+    fn assign(&mut self, i: usize, span: Span, compound: bool) {
+        if compound {
+            self.access(i, span, Mode::Use, None);
+        }
+        let binding = self.bindings[i].clone();
+        match binding.state {
+            State::Init(_) | State::Moved(_) if !binding.mutable => {
+                let first = binding.initialized.unwrap_or(binding.declared);
+                self.report(
+                    Diagnostic::error(
+                        "E0384",
+                        span,
+                        format!(
+                            "cannot assign twice to immutable variable `{}`",
+                            binding.name
+                        ),
+                    )
+                    .with_label("cannot assign twice to immutable variable")
+                    .with_note(first, &format!("first assignment to `{}`", binding.name)),
+                );
+            }
+            _ => {
+                self.bindings[i].state = State::Init(span);
+                self.bindings[i].initialized.get_or_insert(span);
+                self.bindings[i].moved_fields.clear();
+                self.event(span, EventKind::Assign { name: binding.name });
+            }
+        }
+    }
+
+    /// 整体或部分使用了一个已被部分移动的值
+    fn report_partial_move(
+        &mut self,
+        place: &str,
+        root: &Binding,
+        moved: &[String],
+        at: Span,
+        span: Span,
+        mode: Mode,
+    ) {
+        let (verb, label) = match mode {
+            Mode::Borrow { .. } => ("borrow", "value borrowed here after partial move"),
+            Mode::Move | Mode::Use => ("use", "value used here after partial move"),
+        };
+        self.report(
+            Diagnostic::error(
+                "E0382",
+                span,
+                format!("{} of partially moved value: `{}`", verb, place),
+            )
+            .with_label(label)
+            .with_note(
+                root.declared,
+                &format!(
+                    "partial move occurs because `{}` has type `{}`, which does not implement the `Copy` trait",
+                    place_name(&root.name, moved),
+                    field_type(&root.ty, moved)
+                ),
+            )
+            .with_note(at, "value partially moved here"),
+        );
+    }
+
+    /// 访问本地变量 `bindings[i]` 的字段 `fields`
+    fn access_field(
+        &mut self,
+        i: usize,
+        fields: Vec<String>,
+        span: Span,
+        mode: Mode,
+        dest: Option<&str>,
+    ) {
+        let binding = self.bindings[i].clone();
+        if !matches!(binding.state, State::Init(_)) {
+            // 整体未初始化或已被移动, 按整体访问报告
+            let mode = match mode {
+                Mode::Borrow { mutable } => Mode::Borrow { mutable },
+                Mode::Move | Mode::Use => Mode::Use,
+            };
+            return self.access(i, span, mode, None);
+        }
+        let place = place_name(&binding.name, &fields);
+        let overlapping = binding
+            .moved_fields
+            .iter()
+            .find(|(moved, _)| moved.starts_with(&fields) || fields.starts_with(moved));
+        if let Some((moved, at)) = overlapping {
+            if fields.starts_with(moved) {
+                // 这个字段本身或包含它的字段已被移出
+                let (verb, label) = match mode {
+                    Mode::Borrow { .. } => ("borrow", "value borrowed here after move"),
+                    Mode::Move | Mode::Use => ("use", "value used here after move"),
+                };
+                let moved_name = place_name(&binding.name, moved);
+                self.report(
+                    Diagnostic::error(
+                        "E0382",
+                        span,
+                        format!("{} of moved value: `{}`", verb, moved_name),
+                    )
+                    .with_label(label)
+                    .with_note(
+                        binding.declared,
+                        &format!(
+                            "move occurs because `{}` has type `{}`, which does not implement the `Copy` trait",
+                            moved_name,
+                            field_type(&binding.ty, moved)
+                        ),
+                    )
+                    .with_note(*at, "value moved here"),
+                );
+            } else {
+                let (moved, at) = (moved.clone(), *at);
+                self.report_partial_move(&place, &binding, &moved, at, span, mode);
+            }
+            return;
+        }
+        let ty = field_type(&binding.ty, &fields);
+        match mode {
+            Mode::Move if ty == Type::Infer => self.event(span, EventKind::Use { name: place }),
+            Mode::Move if is_copy(&ty) => {
+                let to = dest.map(str::to_string);
+                self.event(span, EventKind::Copy { name: place, to });
+            }
+            Mode::Move => {
+                self.bindings[i].moved_fields.push((fields, span));
+                let to = dest.map(str::to_string);
+                self.event(span, EventKind::Move { name: place, to });
+            }
+            Mode::Borrow { mutable } => {
+                if mutable && !binding.mutable && !matches!(binding.ty, Type::Ref { .. }) {
+                    self.report(
+                        Diagnostic::error(
+                            "E0596",
+                            span,
+                            format!(
+                                "cannot borrow `{}` as mutable, as `{}` is not declared as mutable",
+                                place, binding.name
+                            ),
+                        )
+                        .with_label("cannot borrow as mutable")
+                        .with_note(binding.declared, "consider changing this to be mutable"),
+                    );
+                }
+                self.event(
+                    span,
+                    EventKind::Borrow {
+                        name: place,
+                        mutable,
+                    },
+                );
+            }
+            Mode::Use => self.event(span, EventKind::Use { name: place }),
+        }
+    }
+
+    /// 给本地变量 `bindings[i]` 的字段 `fields` 赋值, 被移出的字段因此重新初始化
+    fn assign_field(&mut self, i: usize, fields: Vec<String>, span: Span, compound: bool) {
+        if compound {
+            self.access_field(i, fields.clone(), span, Mode::Use, None);
+        }
+        let binding = self.bindings[i].clone();
+        let place = place_name(&binding.name, &fields);
+        match binding.state {
+            State::Init(_) if !binding.mutable && !matches!(binding.ty, Type::Ref { .. }) => {
+                self.report(
+                    Diagnostic::error(
+                        "E0594",
+                        span,
+                        format!(
+                            "cannot assign to `{}`, as `{}` is not declared as mutable",
+                            place, binding.name
+                        ),
+                    )
+                    .with_label("cannot assign")
+                    .with_note(binding.declared, "consider changing this to be mutable"),
+                );
+            }
+            State::Init(_) => {
+                let moved_parent = binding
+                    .moved_fields
+                    .iter()
+                    .find(|(moved, _)| fields.starts_with(moved) && *moved != fields);
+                if let Some((moved, at)) = moved_parent {
+                    let moved_name = place_name(&binding.name, moved);
+                    self.report(
+                        Diagnostic::error(
+                            "E0382",
+                            span,
+                            format!("assign to part of moved value: `{}`", moved_name),
+                        )
+                        .with_label("value partially assigned here after move")
+                        .with_note(*at, "value moved here"),
+                    );
+                    return;
+                }
+                self.bindings[i]
+                    .moved_fields
+                    .retain(|(moved, _)| !moved.starts_with(&fields));
+                self.event(span, EventKind::Assign { name: place });
+            }
+            // 整体已被移动或未初始化: 与 rustc 一样报告为对整体的使用
+            _ => self.access(i, span, Mode::Use, None),
+        }
+    }
+
+    /// 从索引中按值移出非 `Copy` 的元素
+    fn index_move(&mut self, expr: &Expr, base: &Expr) {
+        let elem = self.type_of(expr);
+        if elem == Type::Infer || is_copy(&elem) {
+            return;
+        }
+        match self.type_of(base) {
+            ty @ Type::Array(..) => self.report(
+                Diagnostic::error(
+                    "E0508",
+                    expr.span,
+                    format!("cannot move out of type `{}`, a non-copy array", ty),
+                )
+                .with_label(&format!(
+                    "cannot move out of here: move occurs because `{}` has type `{}`, which does not implement the `Copy` trait",
+                    expr, elem
+                )),
+            ),
+            ty => self.report(
+                Diagnostic::error(
+                    "E0507",
+                    expr.span,
+                    format!("cannot move out of index of `{}`", ty),
+                )
+                .with_label(&format!(
+                    "move occurs because value has type `{}`, which does not implement the `Copy` trait",
+                    elem
+                )),
+            ),
+        }
+    }
+
+    fn place_base(&mut self, base: &Expr, mode: Mode) {
+        // 通过字段或索引访问时, 基础操作数只被读取或借用
+        let mode = match mode {
+            Mode::Borrow { mutable } => Mode::Borrow { mutable },
+            Mode::Move | Mode::Use => Mode::Use,
+        };
+        self.expr(base, mode, None);
+    }
+
+    fn expr(&mut self, expr: &Expr, mode: Mode, dest: Option<&str>) {
+        match &expr.kind {
+            ExprKind::Lit(_) | ExprKind::Continue => {}
+            ExprKind::Path(path) => {
+                if let [name] = path.as_slice() {
+                    match self.lookup(name) {
+                        Some(i) => self.access(i, expr.span, mode, dest),
+                        None if !self.fns.contains_key(name)
+                            && name.starts_with(|c: char| c.is_lowercase() || c == '_') =>
+                        {
+                            self.report(
+                                Diagnostic::error(
+                                    "E0425",
+                                    expr.span,
+                                    format!("cannot find value `{}` in this scope", name),
+                                )
+                                .with_label("not found in this scope"),
+                            );
+                        }
+                        None => {}
+                    }
+                }
+            }
+            ExprKind::Paren(inner) => self.expr(inner, mode, dest),
+            ExprKind::Unary(UnOp::Deref, inner) => {
+                if mode == Mode::Move {
+                    if let Type::Ref {
+                        mutable, inner: ty, ..
+                    } = self.type_of(inner)
+                    {
+                        if *ty != Type::Infer && !is_copy(&ty) {
+                            self.report(
+                                Diagnostic::error(
+                                    "E0507",
+                                    expr.span,
+                                    format!(
+                                        "cannot move out of `{}` which is behind a {} reference",
+                                        expr,
+                                        if mutable { "mutable" } else { "shared" }
+                                    ),
+                                )
+                                .with_label(&format!(
+                                    "move occurs because `{}` has type `{}`, which does not implement the `Copy` trait",
+                                    expr, ty
+                                )),
+                            );
+                        }
+                    }
+                }
+                let mode = match mode {
+                    Mode::Borrow { mutable } => Mode::Borrow { mutable },
+                    _ => Mode::Use,
+                };
+                self.expr(inner, mode, None);
+            }
+            ExprKind::Unary(_, inner) | ExprKind::Cast(inner, _) => {
+                self.expr(inner, Mode::Move, None)
+            }
+            ExprKind::Ref {
+                mutable,
+                expr: inner,
+            } => self.expr(inner, Mode::Borrow { mutable: *mutable }, None),
+            ExprKind::Binary(op, lhs, rhs) => {
+                let mode = if op.is_comparison() {
+                    Mode::Borrow { mutable: false }
+                } else {
+                    Mode::Move
+                };
+                self.expr(lhs, mode, None);
+                self.expr(rhs, mode, None);
+            }
+            ExprKind::Assign(lhs, rhs) | ExprKind::AssignOp(_, lhs, rhs) => {
+                let compound = matches!(expr.kind, ExprKind::AssignOp(..));
+                let name = local_name(lhs);
+                self.expr(rhs, Mode::Move, name);
+                if let Some(i) = name.and_then(|name| self.lookup(name)) {
+                    return self.assign(i, lhs.span, compound);
+                }
+                match field_place(lhs).and_then(|(root, fields)| Some((self.lookup(root)?, fields)))
+                {
+                    Some((i, fields)) => self.assign_field(i, fields, lhs.span, compound),
+                    None => self.expr(lhs, Mode::Borrow { mutable: true }, None),
+                }
+            }
+            ExprKind::Field(base, _) => {
+                match field_place(expr)
+                    .and_then(|(root, fields)| Some((self.lookup(root)?, fields)))
+                {
+                    Some((i, fields)) => self.access_field(i, fields, expr.span, mode, dest),
+                    None => self.place_base(base, mode),
+                }
+            }
+            ExprKind::Index(base, index) => {
+                if mode == Mode::Move {
+                    self.index_move(expr, base);
+                }
+                self.place_base(base, mode);
+                self.expr(index, Mode::Move, None);
+            }
+            ExprKind::Call(callee, args) => {
+                if !matches!(callee.kind, ExprKind::Path(_)) || local_name(callee).is_some() {
+                    self.expr(callee, Mode::Use, None);
+                }
+                for arg in args {
+                    self.expr(arg, Mode::Move, None);
+                }
+            }
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+            } => {
+                let mode = if method.starts_with("into_") {
+                    Mode::Move
+                } else {
+                    Mode::Borrow {
+                        mutable: MUTATING_METHODS.contains(&method.as_str()),
+                    }
+                };
+                self.expr(receiver, mode, None);
+                for arg in args {
+                    self.expr(arg, Mode::Move, None);
+                }
+            }
+            ExprKind::Macro { name, args } => {
+                let mode = if FORMAT_MACROS.contains(&name.as_str()) {
+                    Mode::Borrow { mutable: false }
+                } else {
+                    Mode::Move
+                };
+                for arg in args {
+                    self.expr(arg, mode, None);
+                }
+            }
+            ExprKind::Tuple(items) | ExprKind::Array(items) => {
+                for item in items {
+                    self.expr(item, Mode::Move, None);
+                }
+            }
+            ExprKind::Repeat(item, _) => self.expr(item, Mode::Move, None),
+            ExprKind::Let(pat, scrutinee) => {
+                let ty = self.type_of(scrutinee);
+                self.scrutinee(scrutinee, std::slice::from_ref(&**pat));
+                self.bind(pat, ty, true);
+            }
+            ExprKind::Block(block) => self.block(block),
+            ExprKind::If { cond, then, els } => {
+                self.push_scope();
+                self.expr(cond, Mode::Move, None);
+                let before = self.bindings.clone();
+                self.block(then);
+                let after_then = std::mem::replace(&mut self.bindings, before);
+                if let Some(els) = els {
+                    self.expr(els, Mode::Move, None);
+                }
+                self.merge(after_then);
+                self.pop_scope();
+            }
+            ExprKind::While { cond, body } => self.repeat(|sim| {
+                sim.push_scope();
+                sim.expr(cond, Mode::Move, None);
+                sim.block(body);
+                sim.pop_scope();
+            }),
+            ExprKind::Loop(body) => self.repeat(|sim| sim.block(body)),
+            ExprKind::For { pat, iter, body } => {
+                self.expr(iter, Mode::Move, None);
+                self.repeat(|sim| {
+                    sim.push_scope();
+                    sim.bind(pat, Type::Infer, true);
+                    sim.block(body);
+                    sim.pop_scope();
+                });
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                let ty = self.type_of(scrutinee);
+                let pats: Vec<Pat> = arms.iter().map(|arm| arm.pat.clone()).collect();
+                self.scrutinee(scrutinee, &pats);
+                let before = self.bindings.clone();
+                let mut merged: Option<Vec<Binding>> = None;
+                for arm in arms {
+                    self.bindings = before.clone();
+                    self.push_scope();
+                    self.bind(&arm.pat, ty.clone(), true);
+                    if let Some(guard) = &arm.guard {
+                        self.expr(guard, Mode::Move, None);
+                    }
+                    self.expr(&arm.body, Mode::Move, None);
+                    self.pop_scope();
+                    if let Some(previous) = merged.take() {
+                        self.merge(previous);
+                    }
+                    merged = Some(self.bindings.clone());
+                }
+                if let Some(merged) = merged {
+                    self.bindings = merged;
+                }
+            }
+            ExprKind::Break(value) | ExprKind::Return(value) => {
+                if let Some(value) = value {
+                    self.expr(value, Mode::Move, None);
+                }
+            }
+            ExprKind::Range { start, end, .. } => {
+                for bound in start.iter().chain(end) {
+                    self.expr(bound, Mode::Move, None);
+                }
+            }
+        }
+    }
+
+    /// `match` 或 `if let` 的判别式: 只有按值绑定了非 `Copy` 的值时才会移动
+    fn scrutinee(&mut self, scrutinee: &Expr, pats: &[Pat]) {
+        let binds_by_value = pats.iter().any(|pat| {
+            pat.bindings()
+                .iter()
+                .any(|b| matches!(b.kind, PatKind::Binding { by_ref: false, .. }))
+        });
+        let mode = if binds_by_value {
+            Mode::Move
+        } else {
+            Mode::Use
+        };
+        self.expr(scrutinee, mode, None);
+    }
+
+    /// 循环体执行两遍, 第二遍用来发现 "在上一次迭代中被移动" 的错误
+    fn repeat<F: FnMut(&mut Self)>(&mut self, mut body: F) {
+        body(self);
+        let nested = self.second_iteration;
+        self.second_iteration = true;
+        body(self);
+        self.second_iteration = nested;
+    }
+}
+
+/// 一次模拟的结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Simulation {
+    pub events: Vec<Event>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Simulation {
+    /// 是否没有所有权错误
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// 先列出每一步, 再按 rustc 的格式输出诊断信息
+    pub fn render(&self, src: &str) -> String {
+        let mut out: String = self.events.iter().map(|e| format!("{}\n", e)).collect();
+        for diagnostic in &self.diagnostics {
+            out.push('\n');
+            out.push_str(&diagnostic.render(src));
+        }
+        out
+    }
+}
+
+impl fmt::Display for Simulation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+        for diagnostic in &self.diagnostics {
+            writeln!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+/// # 模拟代码片段中的所有权转移
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::lang::ownership::simulate;
+///
+/// let sim = simulate(r#"let a = "hello"; let b = a.to_string(); let c = b; use a; use b;"#).unwrap();
+/// assert_eq!(sim.to_string(), "\
+/// \x20 1:5  let    a: &'static str (Copy)
+/// \x201:26  borrow &a
+/// \x201:22  let    b: String
+/// \x201:49  move   b -> c
+/// \x201:45  let    c: String
+/// \x201:56  use    a
+/// error[E0382]: 1:63: use of moved value: `b`
+///   1:22: move occurs because `b` has type `String`, which does not implement the `Copy` trait
+///   1:49: value moved here
+/// ");
+///
+/// // 在循环中移动, 第二次迭代时报错
+/// let sim = simulate("let s = String::new(); loop { let t = s; }").unwrap();
+/// assert_eq!(
+///     sim.diagnostics[0].notes[1].1,
+///     "value moved here, in previous iteration of loop"
+/// );
+///
+/// // 只在一个分支中移动, 之后的使用同样是错误
+/// assert!(!simulate("let v = vec![1]; if true { let w = v; } use v;").unwrap().is_ok());
+/// assert!(simulate("let v = vec![1]; let n = v.len(); let m = n; use n; use v;").unwrap().is_ok());
+/// ```
+pub fn simulate(src: &str) -> Result<Simulation, ParseError> {
+    let stmts = parse_stmts(src)?;
+    let mut sim = Simulator::default();
+    for stmt in &stmts {
+        if let StmtKind::Item(Item::Fn(decl)) = &stmt.kind {
+            let params = decl.params.iter().map(|p| p.ty.clone()).collect();
+            sim.fns
+                .insert(decl.name.clone(), (params, decl.ret.clone()));
+        }
+    }
+    for stmt in &stmts {
+        sim.stmt(stmt);
+    }
+    Ok(Simulation {
+        events: sim.events,
+        diagnostics: sim.diagnostics,
+    })
+}
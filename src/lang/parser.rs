@@ -0,0 +1,1420 @@
+//! # 语法分析
+//!
+//! 手写的递归下降分析器, 把记号序列解析成 [`ast`](super::ast) 中的语法树.
+//! 二元运算使用优先级爬升, 优先级从低到高依次为:
+//!
+//! 赋值 `=` `+=` ... < 区间 `..` `..=` < `||` < `&&` < 比较 < `|` < `^` < `&` < `<<` `>>`
+//! < `+` `-` < `*` `/` `%` < `as` < 一元运算 < 调用、方法调用、字段与索引
+//!
+//! 不支持的语法(闭包、结构体字面量、`?` 运算符、标签等)会报告错误而不是被静默忽略
+
+use std::error::Error;
+use std::fmt;
+
+use super::ast::{
+    Arm, BinOp, Block, Expr, ExprKind, FnDecl, Item, Lit, Param, Pat, PatKind, Stmt, StmtKind,
+    Type, UnOp,
+};
+use super::lexer::{tokenize, LexError, Span, Token, TokenKind};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+impl From<LexError> for ParseError {
+    fn from(e: LexError) -> Self {
+        ParseError {
+            message: e.message,
+            span: e.span,
+        }
+    }
+}
+
+type PResult<T> = Result<T, ParseError>;
+
+const KEYWORDS: [&str; 23] = [
+    "as", "break", "const", "continue", "else", "false", "fn", "for", "if", "in", "let", "loop",
+    "match", "mut", "pub", "ref", "return", "static", "true", "while", "dyn", "impl", "use",
+];
+
+const COMPOUND_ASSIGN: [(&str, BinOp); 10] = [
+    ("+=", BinOp::Add),
+    ("-=", BinOp::Sub),
+    ("*=", BinOp::Mul),
+    ("/=", BinOp::Div),
+    ("%=", BinOp::Rem),
+    ("&=", BinOp::BitAnd),
+    ("|=", BinOp::BitOr),
+    ("^=", BinOp::BitXor),
+    ("<<=", BinOp::Shl),
+    (">>=", BinOp::Shr),
+];
+
+/// 二元运算符及其优先级, 数字越大结合越紧
+fn binary_op(token: &Token) -> Option<(BinOp, u8)> {
+    if token.kind != TokenKind::Punct {
+        return None;
+    }
+    let op = match token.text.as_str() {
+        "||" => (BinOp::Or, 1),
+        "&&" => (BinOp::And, 2),
+        "==" => (BinOp::Eq, 3),
+        "!=" => (BinOp::Ne, 3),
+        "<" => (BinOp::Lt, 3),
+        "<=" => (BinOp::Le, 3),
+        ">" => (BinOp::Gt, 3),
+        ">=" => (BinOp::Ge, 3),
+        "|" => (BinOp::BitOr, 4),
+        "^" => (BinOp::BitXor, 5),
+        "&" => (BinOp::BitAnd, 6),
+        "<<" => (BinOp::Shl, 7),
+        ">>" => (BinOp::Shr, 7),
+        "+" => (BinOp::Add, 8),
+        "-" => (BinOp::Sub, 8),
+        "*" => (BinOp::Mul, 9),
+        "/" => (BinOp::Div, 9),
+        "%" => (BinOp::Rem, 9),
+        _ => return None,
+    };
+    Some(op)
+}
+
+/// 处理字符串和字符字面量中的转义序列
+fn unescape(body: &str, span: Span) -> PResult<String> {
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        let escaped = match chars.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some('\\') => '\\',
+            Some('\'') => '\'',
+            Some('"') => '"',
+            Some('\n') => {
+                // 行尾的反斜杠: 跳过换行和下一行开头的空白
+                let rest: String = chars.clone().collect();
+                let trimmed = rest.trim_start();
+                chars = body[body.len() - trimmed.len()..].chars();
+                continue;
+            }
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                u8::from_str_radix(&hex, 16)
+                    .ok()
+                    .map(char::from)
+                    .ok_or_else(|| error(span, "invalid `\\x` escape"))?
+            }
+            Some('u') => {
+                let rest = chars.as_str();
+                let end = rest
+                    .find('}')
+                    .ok_or_else(|| error(span, "invalid unicode escape"))?;
+                let code = rest[1..end].replace('_', "");
+                chars = rest[end + 1..].chars();
+                u32::from_str_radix(&code, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| error(span, "invalid unicode escape"))?
+            }
+            _ => return Err(error(span, "unknown character escape")),
+        };
+        out.push(escaped);
+    }
+    Ok(out)
+}
+
+fn error(span: Span, message: &str) -> ParseError {
+    ParseError {
+        message: message.to_string(),
+        span,
+    }
+}
+
+/// 把字面量记号转换成 [`Lit`]
+fn literal(token: &Token) -> PResult<Lit> {
+    let text = token.text.as_str();
+    let span = token.span;
+    match token.kind {
+        TokenKind::Int => {
+            let digits = text.replace('_', "");
+            let (radix, body) = match digits.get(..2) {
+                Some("0x") => (16, &digits[2..]),
+                Some("0o") => (8, &digits[2..]),
+                Some("0b") => (2, &digits[2..]),
+                _ => (10, &digits[..]),
+            };
+            // 十六进制数字里可能有 a-f, 所以后缀只能从 `i` 或 `u` 开始
+            let split = if radix == 16 {
+                body.find(['i', 'u'])
+            } else {
+                body.find(|c: char| c.is_alphabetic())
+            }
+            .unwrap_or(body.len());
+            let (number, suffix) = body.split_at(split);
+            let value = u128::from_str_radix(number, radix)
+                .map_err(|_| error(span, "integer literal is too large"))?;
+            Ok(Lit::Int {
+                value,
+                suffix: Some(suffix.to_string()).filter(|s| !s.is_empty()),
+            })
+        }
+        TokenKind::Float => {
+            let digits = text.replace('_', "");
+            let (number, suffix) = match digits.find('f') {
+                Some(pos) => (&digits[..pos], Some(digits[pos..].to_string())),
+                None => (&digits[..], None),
+            };
+            let value = number
+                .parse()
+                .map_err(|_| error(span, "invalid float literal"))?;
+            Ok(Lit::Float { value, suffix })
+        }
+        TokenKind::Str => {
+            let raw = text.trim_start_matches('b');
+            if let Some(raw) = raw.strip_prefix('r') {
+                let hashes = raw.len() - raw.trim_start_matches('#').len();
+                Ok(Lit::Str(
+                    raw[hashes + 1..raw.len() - hashes - 1].to_string(),
+                ))
+            } else {
+                Ok(Lit::Str(unescape(&raw[1..raw.len() - 1], span)?))
+            }
+        }
+        TokenKind::Char => {
+            let byte = text.starts_with('b');
+            let body = unescape(&text[1 + byte as usize..text.len() - 1], span)?;
+            let mut chars = body.chars();
+            let c = match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => {
+                    return Err(error(
+                        span,
+                        "character literal may only contain one codepoint",
+                    ))
+                }
+            };
+            if byte {
+                Ok(Lit::Int {
+                    value: c as u128,
+                    suffix: Some("u8".to_string()),
+                })
+            } else {
+                Ok(Lit::Char(c))
+            }
+        }
+        _ => Err(error(span, "expected literal")),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// 输入结束的位置, 用于报告 "unexpected end of input"
+    eof: Span,
+}
+
+impl Parser {
+    fn new(src: &str) -> PResult<Self> {
+        let tokens = tokenize(src)?;
+        let lines = src.split('\n').count();
+        let last = src.rsplit('\n').next().unwrap_or("");
+        Ok(Parser {
+            tokens,
+            pos: 0,
+            eof: Span::new(lines, last.chars().count() + 1),
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_at(&self, n: usize) -> Option<&Token> {
+        self.tokens.get(self.pos + n)
+    }
+
+    fn span(&self) -> Span {
+        self.peek().map_or(self.eof, |t| t.span)
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn bump(&mut self) -> PResult<Token> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| error(self.eof, "unexpected end of input"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn is_punct(&self, p: &str) -> bool {
+        self.peek().is_some_and(|t| t.is_punct(p))
+    }
+
+    fn is_ident(&self, kw: &str) -> bool {
+        self.peek().is_some_and(|t| t.is_ident(kw))
+    }
+
+    fn eat_punct(&mut self, p: &str) -> bool {
+        let found = self.is_punct(p);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn eat_ident(&mut self, kw: &str) -> bool {
+        let found = self.is_ident(kw);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn unexpected(&self, expected: &str) -> ParseError {
+        match self.peek() {
+            Some(token) => error(
+                token.span,
+                &format!("expected {}, found `{}`", expected, token.text),
+            ),
+            None => error(
+                self.eof,
+                &format!("expected {}, found end of input", expected),
+            ),
+        }
+    }
+
+    fn expect_punct(&mut self, p: &str) -> PResult<()> {
+        if self.eat_punct(p) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", p)))
+        }
+    }
+
+    /// 期望一个 `>`, 遇到 `>>`、`>=`、`>>=` 时只取走第一个字符
+    fn expect_gt(&mut self) -> PResult<()> {
+        if self.eat_punct(">") {
+            return Ok(());
+        }
+        let split = match self.peek() {
+            Some(t)
+                if t.kind == TokenKind::Punct && t.text.starts_with('>') && t.text.len() > 1 =>
+            {
+                t.text[1..].to_string()
+            }
+            _ => return Err(self.unexpected("`>`")),
+        };
+        let token = &mut self.tokens[self.pos];
+        token.text = split;
+        token.span.col += 1;
+        Ok(())
+    }
+
+    fn expect_name(&mut self) -> PResult<String> {
+        match self.peek() {
+            Some(t) if t.kind == TokenKind::Ident && !KEYWORDS.contains(&t.text.as_str()) => {
+                Ok(self.bump()?.text)
+            }
+            _ => Err(self.unexpected("identifier")),
+        }
+    }
+
+    fn expect_end(&self) -> PResult<()> {
+        if self.at_end() {
+            Ok(())
+        } else {
+            Err(self.unexpected("end of input"))
+        }
+    }
+
+    /// 当前记号能否作为表达式的开头
+    fn can_start_expr(&self) -> bool {
+        match self.peek() {
+            None => false,
+            Some(t) => match t.kind {
+                TokenKind::Ident => !matches!(t.text.as_str(), "as" | "else" | "in"),
+                TokenKind::Punct => matches!(
+                    t.text.as_str(),
+                    "(" | "[" | "{" | "-" | "!" | "*" | "&" | "&&" | ".." | "..="
+                ),
+                TokenKind::Lifetime => false,
+                _ => true,
+            },
+        }
+    }
+
+    // ---------------------------------------------------------------- 表达式
+
+    fn expr(&mut self) -> PResult<Expr> {
+        let lhs = self.range()?;
+        let span = lhs.span;
+        if self.eat_punct("=") {
+            let rhs = self.expr()?;
+            return Ok(Expr::new(
+                ExprKind::Assign(Box::new(lhs), Box::new(rhs)),
+                span,
+            ));
+        }
+        for (text, op) in COMPOUND_ASSIGN.iter() {
+            if self.eat_punct(text) {
+                let rhs = self.expr()?;
+                return Ok(Expr::new(
+                    ExprKind::AssignOp(*op, Box::new(lhs), Box::new(rhs)),
+                    span,
+                ));
+            }
+        }
+        Ok(lhs)
+    }
+
+    /// 区间的结束部分; `{` 不能作为结束, 这样 `for i in 0.. {}` 才能正常解析
+    fn range_end(&mut self) -> PResult<Option<Box<Expr>>> {
+        if self.can_start_expr() && !self.is_punct("{") {
+            Ok(Some(Box::new(self.binary(1)?)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn range(&mut self) -> PResult<Expr> {
+        let span = self.span();
+        if self.is_punct("..") || self.is_punct("..=") {
+            let inclusive = self.bump()?.text == "..=";
+            let end = self.range_end()?;
+            return Ok(Expr::new(
+                ExprKind::Range {
+                    start: None,
+                    end,
+                    inclusive,
+                },
+                span,
+            ));
+        }
+        let start = self.binary(1)?;
+        if self.is_punct("..") || self.is_punct("..=") {
+            let inclusive = self.bump()?.text == "..=";
+            let end = self.range_end()?;
+            return Ok(Expr::new(
+                ExprKind::Range {
+                    start: Some(Box::new(start)),
+                    end,
+                    inclusive,
+                },
+                span,
+            ));
+        }
+        Ok(start)
+    }
+
+    fn binary(&mut self, min_prec: u8) -> PResult<Expr> {
+        let mut lhs = self.cast()?;
+        while let Some((op, prec)) = self.peek().and_then(binary_op) {
+            if prec < min_prec {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.binary(prec + 1)?;
+            let span = lhs.span;
+            lhs = Expr::new(ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)), span);
+        }
+        Ok(lhs)
+    }
+
+    fn cast(&mut self) -> PResult<Expr> {
+        let mut expr = self.unary()?;
+        while self.eat_ident("as") {
+            let ty = self.ty()?;
+            let span = expr.span;
+            expr = Expr::new(ExprKind::Cast(Box::new(expr), ty), span);
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> PResult<Expr> {
+        let span = self.span();
+        let op = match self.peek() {
+            Some(t) if t.is_punct("-") => Some(UnOp::Neg),
+            Some(t) if t.is_punct("!") => Some(UnOp::Not),
+            Some(t) if t.is_punct("*") => Some(UnOp::Deref),
+            _ => None,
+        };
+        if let Some(op) = op {
+            self.pos += 1;
+            let expr = self.unary()?;
+            return Ok(Expr::new(ExprKind::Unary(op, Box::new(expr)), span));
+        }
+        if self.is_punct("&") || self.is_punct("&&") {
+            let double = self.bump()?.text == "&&";
+            let mutable = self.eat_ident("mut");
+            let expr = self.unary()?;
+            let mut inner = Expr::new(
+                ExprKind::Ref {
+                    mutable,
+                    expr: Box::new(expr),
+                },
+                span,
+            );
+            if double {
+                inner = Expr::new(
+                    ExprKind::Ref {
+                        mutable: false,
+                        expr: Box::new(inner),
+                    },
+                    span,
+                );
+            }
+            return Ok(inner);
+        }
+        let primary = self.primary()?;
+        self.postfix(primary)
+    }
+
+    fn call_args(&mut self, close: &str) -> PResult<Vec<Expr>> {
+        let mut args = Vec::new();
+        while !self.eat_punct(close) {
+            args.push(self.expr()?);
+            if !self.eat_punct(",") {
+                self.expect_punct(close)?;
+                break;
+            }
+        }
+        Ok(args)
+    }
+
+    /// 跳过 `::<...>` 形式的泛型参数
+    fn turbofish(&mut self) -> PResult<()> {
+        if self.is_punct("::") && self.peek_at(1).is_some_and(|t| t.is_punct("<")) {
+            self.pos += 2;
+            loop {
+                self.ty()?;
+                if !self.eat_punct(",") {
+                    break;
+                }
+            }
+            self.expect_gt()?;
+        }
+        Ok(())
+    }
+
+    fn postfix(&mut self, mut expr: Expr) -> PResult<Expr> {
+        loop {
+            let span = expr.span;
+            if self.eat_punct("(") {
+                let args = self.call_args(")")?;
+                expr = Expr::new(ExprKind::Call(Box::new(expr), args), span);
+            } else if self.eat_punct("[") {
+                let index = self.expr()?;
+                self.expect_punct("]")?;
+                expr = Expr::new(ExprKind::Index(Box::new(expr), Box::new(index)), span);
+            } else if self.eat_punct(".") {
+                let name = match self.peek() {
+                    Some(t) if t.kind == TokenKind::Int => self.bump()?.text,
+                    _ => self.expect_name()?,
+                };
+                self.turbofish()?;
+                if self.eat_punct("(") {
+                    let args = self.call_args(")")?;
+                    expr = Expr::new(
+                        ExprKind::MethodCall {
+                            receiver: Box::new(expr),
+                            method: name,
+                            args,
+                        },
+                        span,
+                    );
+                } else {
+                    expr = Expr::new(ExprKind::Field(Box::new(expr), name), span);
+                }
+            } else if self.is_punct("?") {
+                return Err(error(self.span(), "the `?` operator is not supported"));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn path(&mut self) -> PResult<Vec<String>> {
+        let mut segments = vec![self.expect_name()?];
+        loop {
+            self.turbofish()?;
+            if self.is_punct("::") && self.peek_at(1).is_some_and(|t| t.kind == TokenKind::Ident) {
+                self.pos += 1;
+                segments.push(self.expect_name()?);
+            } else {
+                return Ok(segments);
+            }
+        }
+    }
+
+    fn macro_call(&mut self, name: String, span: Span) -> PResult<Expr> {
+        self.expect_punct("!")?;
+        let close = match self.bump()?.text.as_str() {
+            "(" => ")",
+            "[" => "]",
+            "{" => "}",
+            _ => return Err(error(span, "expected macro delimiter")),
+        };
+        let args = if name == "vec" {
+            let array = self.array(span, close)?;
+            vec![array]
+        } else {
+            self.call_args(close)?
+        };
+        Ok(Expr::new(ExprKind::Macro { name, args }, span))
+    }
+
+    /// `[` 之后的数组内容, 直到 `close`
+    fn array(&mut self, span: Span, close: &str) -> PResult<Expr> {
+        if self.eat_punct(close) {
+            return Ok(Expr::new(ExprKind::Array(Vec::new()), span));
+        }
+        let first = self.expr()?;
+        if self.eat_punct(";") {
+            let len = self.expr()?;
+            self.expect_punct(close)?;
+            return Ok(Expr::new(
+                ExprKind::Repeat(Box::new(first), Box::new(len)),
+                span,
+            ));
+        }
+        let mut items = vec![first];
+        if self.eat_punct(",") {
+            items.extend(self.call_args(close)?);
+        } else {
+            self.expect_punct(close)?;
+        }
+        Ok(Expr::new(ExprKind::Array(items), span))
+    }
+
+    fn primary(&mut self) -> PResult<Expr> {
+        let span = self.span();
+        let token = match self.peek() {
+            Some(token) => token.clone(),
+            None => return Err(self.unexpected("expression")),
+        };
+        match token.kind {
+            TokenKind::Int | TokenKind::Float | TokenKind::Str | TokenKind::Char => {
+                self.pos += 1;
+                return Ok(Expr::new(ExprKind::Lit(literal(&token)?), span));
+            }
+            TokenKind::Lifetime => {
+                return Err(error(span, "labels are not supported"));
+            }
+            TokenKind::Ident => return self.keyword_or_path(span),
+            TokenKind::Punct => {}
+        }
+        if self.eat_punct("(") {
+            if self.eat_punct(")") {
+                return Ok(Expr::new(ExprKind::Tuple(Vec::new()), span));
+            }
+            let first = self.expr()?;
+            if self.eat_punct(")") {
+                return Ok(Expr::new(ExprKind::Paren(Box::new(first)), span));
+            }
+            self.expect_punct(",")?;
+            let mut items = vec![first];
+            items.extend(self.call_args(")")?);
+            return Ok(Expr::new(ExprKind::Tuple(items), span));
+        }
+        if self.eat_punct("[") {
+            return self.array(span, "]");
+        }
+        if self.is_punct("{") {
+            return Ok(Expr::new(ExprKind::Block(self.block()?), span));
+        }
+        if self.is_punct("|") || self.is_punct("||") {
+            return Err(error(span, "closures are not supported"));
+        }
+        Err(self.unexpected("expression"))
+    }
+
+    fn keyword_or_path(&mut self, span: Span) -> PResult<Expr> {
+        let text = self.peek().map(|t| t.text.clone()).unwrap_or_default();
+        let kind = match text.as_str() {
+            "true" | "false" => {
+                self.pos += 1;
+                ExprKind::Lit(Lit::Bool(text == "true"))
+            }
+            "if" | "while" | "loop" | "for" | "match" => return self.block_like(),
+            "break" => {
+                self.pos += 1;
+                let value = if self.can_start_expr() {
+                    Some(Box::new(self.expr()?))
+                } else {
+                    None
+                };
+                ExprKind::Break(value)
+            }
+            "continue" => {
+                self.pos += 1;
+                ExprKind::Continue
+            }
+            "return" => {
+                self.pos += 1;
+                let value = if self.can_start_expr() {
+                    Some(Box::new(self.expr()?))
+                } else {
+                    None
+                };
+                ExprKind::Return(value)
+            }
+            _ => {
+                let path = self.path()?;
+                if self.is_punct("!") && path.len() == 1 {
+                    let name = path.into_iter().next().unwrap_or_default();
+                    return self.macro_call(name, span);
+                }
+                ExprKind::Path(path)
+            }
+        };
+        Ok(Expr::new(kind, span))
+    }
+
+    /// `if` 和 `while` 的条件, 允许 `let pat = expr`
+    fn condition(&mut self) -> PResult<Expr> {
+        let span = self.span();
+        if self.eat_ident("let") {
+            let pat = self.pat()?;
+            self.expect_punct("=")?;
+            let expr = self.binary(2)?;
+            return Ok(Expr::new(
+                ExprKind::Let(Box::new(pat), Box::new(expr)),
+                span,
+            ));
+        }
+        self.expr()
+    }
+
+    fn block_like(&mut self) -> PResult<Expr> {
+        let span = self.span();
+        let kind = if self.eat_ident("if") {
+            let cond = self.condition()?;
+            let then = self.block()?;
+            let els = if self.eat_ident("else") {
+                let span = self.span();
+                if self.is_ident("if") {
+                    Some(Box::new(self.block_like()?))
+                } else {
+                    Some(Box::new(Expr::new(ExprKind::Block(self.block()?), span)))
+                }
+            } else {
+                None
+            };
+            ExprKind::If {
+                cond: Box::new(cond),
+                then,
+                els,
+            }
+        } else if self.eat_ident("while") {
+            let cond = self.condition()?;
+            ExprKind::While {
+                cond: Box::new(cond),
+                body: self.block()?,
+            }
+        } else if self.eat_ident("loop") {
+            ExprKind::Loop(self.block()?)
+        } else if self.eat_ident("for") {
+            let pat = self.pat()?;
+            if !self.eat_ident("in") {
+                return Err(self.unexpected("`in`"));
+            }
+            let iter = self.expr()?;
+            ExprKind::For {
+                pat: Box::new(pat),
+                iter: Box::new(iter),
+                body: self.block()?,
+            }
+        } else if self.eat_ident("match") {
+            let scrutinee = self.expr()?;
+            self.expect_punct("{")?;
+            let mut arms = Vec::new();
+            while !self.eat_punct("}") {
+                let pat = self.pat()?;
+                let guard = if self.eat_ident("if") {
+                    Some(self.expr()?)
+                } else {
+                    None
+                };
+                self.expect_punct("=>")?;
+                let body = self.expr()?;
+                let block_like = body.is_block_like();
+                arms.push(Arm { pat, guard, body });
+                if !self.eat_punct(",") && !block_like {
+                    self.expect_punct("}")?;
+                    break;
+                }
+            }
+            ExprKind::Match {
+                scrutinee: Box::new(scrutinee),
+                arms,
+            }
+        } else if self.is_punct("{") {
+            ExprKind::Block(self.block()?)
+        } else {
+            return Err(self.unexpected("expression"));
+        };
+        Ok(Expr::new(kind, span))
+    }
+
+    // ---------------------------------------------------------------- 语句
+
+    fn block(&mut self) -> PResult<Block> {
+        let span = self.span();
+        self.expect_punct("{")?;
+        let mut stmts = Vec::new();
+        loop {
+            let end = self.span();
+            if self.eat_punct("}") {
+                return Ok(Block { stmts, span, end });
+            }
+            if self.at_end() {
+                return Err(self.unexpected("`}`"));
+            }
+            if let Some(stmt) = self.stmt()? {
+                stmts.push(stmt);
+            }
+        }
+    }
+
+    /// 是否位于条目(函数、常量、静态变量)的开头
+    fn at_item(&self) -> bool {
+        let mut n = 0;
+        if self.peek_at(n).is_some_and(|t| t.is_ident("pub")) {
+            n += 1;
+        }
+        match self.peek_at(n) {
+            Some(t) if t.is_ident("fn") || t.is_ident("static") => true,
+            // `const fn` 或 `const NAME`
+            Some(t) if t.is_ident("const") => true,
+            Some(t) if t.is_punct("#") => true,
+            _ => false,
+        }
+    }
+
+    fn stmt(&mut self) -> PResult<Option<Stmt>> {
+        let span = self.span();
+        if self.eat_punct(";") {
+            return Ok(None);
+        }
+        if self.eat_ident("let") {
+            let pat = self.pat()?;
+            let ty = if self.eat_punct(":") {
+                Some(self.ty()?)
+            } else {
+                None
+            };
+            let init = if self.eat_punct("=") {
+                Some(self.expr()?)
+            } else {
+                None
+            };
+            self.expect_punct(";")?;
+            return Ok(Some(Stmt {
+                kind: StmtKind::Let { pat, ty, init },
+                span,
+            }));
+        }
+        if self.eat_ident("use") {
+            let expr = self.expr()?;
+            self.expect_punct(";")?;
+            return Ok(Some(Stmt {
+                kind: StmtKind::Use(expr),
+                span,
+            }));
+        }
+        if self.at_item() {
+            let item = self.item()?;
+            return Ok(Some(Stmt {
+                kind: StmtKind::Item(item),
+                span,
+            }));
+        }
+        let block_like = matches!(
+            self.peek(),
+            Some(t) if t.is_punct("{")
+                || ["if", "while", "loop", "for", "match"].iter().any(|k| t.is_ident(k))
+        );
+        let expr = if block_like {
+            let expr = self.block_like()?;
+            // 块状表达式之后的方法调用, 例如 `match x { .. }.to_string()`
+            if self.is_punct(".") {
+                let expr = self.postfix(expr)?;
+                self.finish_expr(expr)?
+            } else {
+                expr
+            }
+        } else {
+            self.expr()?
+        };
+        let kind = if self.eat_punct(";") {
+            StmtKind::Semi(expr)
+        } else if block_like || self.is_punct("}") || self.at_end() {
+            StmtKind::Expr(expr)
+        } else {
+            return Err(self.unexpected("`;`"));
+        };
+        Ok(Some(Stmt { kind, span }))
+    }
+
+    /// 在已经解析出的操作数之后继续解析二元运算和赋值
+    fn finish_expr(&mut self, lhs: Expr) -> PResult<Expr> {
+        let span = lhs.span;
+        if self.eat_punct("=") {
+            let rhs = self.expr()?;
+            return Ok(Expr::new(
+                ExprKind::Assign(Box::new(lhs), Box::new(rhs)),
+                span,
+            ));
+        }
+        let mut lhs = lhs;
+        while let Some((op, _)) = self.peek().and_then(binary_op) {
+            self.pos += 1;
+            let rhs = self.binary(1)?;
+            lhs = Expr::new(ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)), span);
+        }
+        Ok(lhs)
+    }
+
+    // ---------------------------------------------------------------- 条目
+
+    fn skip_attributes(&mut self) -> PResult<()> {
+        while self.eat_punct("#") {
+            self.expect_punct("[")?;
+            let mut depth = 1;
+            while depth > 0 {
+                let token = self.bump()?;
+                if token.is_punct("[") {
+                    depth += 1;
+                } else if token.is_punct("]") {
+                    depth -= 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn item(&mut self) -> PResult<Item> {
+        self.skip_attributes()?;
+        let span = self.span();
+        self.eat_ident("pub");
+        if self.is_ident("const") && self.peek_at(1).is_some_and(|t| t.is_ident("fn")) {
+            self.pos += 1;
+            return self.function(true, span);
+        }
+        if self.is_ident("fn") {
+            return self.function(false, span);
+        }
+        let is_static = self.eat_ident("static");
+        if !is_static && !self.eat_ident("const") {
+            return Err(self.unexpected("item"));
+        }
+        let mutable = is_static && self.eat_ident("mut");
+        let name = self.expect_name()?;
+        self.expect_punct(":")?;
+        let ty = self.ty()?;
+        self.expect_punct("=")?;
+        let expr = self.expr()?;
+        self.expect_punct(";")?;
+        if is_static {
+            Ok(Item::Static {
+                name,
+                mutable,
+                ty,
+                expr,
+                span,
+            })
+        } else {
+            Ok(Item::Const {
+                name,
+                ty,
+                expr,
+                span,
+            })
+        }
+    }
+
+    fn function(&mut self, is_const: bool, span: Span) -> PResult<Item> {
+        if !self.eat_ident("fn") {
+            return Err(self.unexpected("`fn`"));
+        }
+        let name = self.expect_name()?;
+        if self.is_punct("<") {
+            return Err(error(self.span(), "generic functions are not supported"));
+        }
+        self.expect_punct("(")?;
+        let mut params = Vec::new();
+        while !self.eat_punct(")") {
+            if self.is_ident("self")
+                || self.is_punct("&") && self.peek_at(1).is_some_and(|t| t.is_ident("self"))
+            {
+                return Err(error(self.span(), "methods are not supported"));
+            }
+            let pat = self.pat()?;
+            self.expect_punct(":")?;
+            let ty = self.ty()?;
+            params.push(Param { pat, ty });
+            if !self.eat_punct(",") {
+                self.expect_punct(")")?;
+                break;
+            }
+        }
+        let (ret, ret_span) = if self.eat_punct("->") {
+            let ret_span = self.span();
+            (self.ty()?, Some(ret_span))
+        } else {
+            (Type::unit(), None)
+        };
+        let body = self.block()?;
+        Ok(Item::Fn(FnDecl {
+            name,
+            is_const,
+            params,
+            ret,
+            ret_span,
+            body,
+            span,
+        }))
+    }
+
+    // ---------------------------------------------------------------- 模式
+
+    fn pat(&mut self) -> PResult<Pat> {
+        let span = self.span();
+        self.eat_punct("|");
+        let first = self.pat_no_or()?;
+        if !self.is_punct("|") {
+            return Ok(first);
+        }
+        let mut pats = vec![first];
+        while self.eat_punct("|") {
+            pats.push(self.pat_no_or()?);
+        }
+        Ok(Pat {
+            kind: PatKind::Or(pats),
+            span,
+        })
+    }
+
+    /// 模式中的字面量或常量路径, 可以带负号
+    fn pat_bound(&mut self) -> PResult<Expr> {
+        let span = self.span();
+        if self.eat_punct("-") {
+            let lit = self.pat_bound()?;
+            return Ok(Expr::new(ExprKind::Unary(UnOp::Neg, Box::new(lit)), span));
+        }
+        match self.peek() {
+            Some(t)
+                if matches!(
+                    t.kind,
+                    TokenKind::Int | TokenKind::Float | TokenKind::Str | TokenKind::Char
+                ) =>
+            {
+                let token = self.bump()?;
+                Ok(Expr::new(ExprKind::Lit(literal(&token)?), span))
+            }
+            Some(t) if t.is_ident("true") || t.is_ident("false") => {
+                let value = self.bump()?.text == "true";
+                Ok(Expr::new(ExprKind::Lit(Lit::Bool(value)), span))
+            }
+            Some(t) if t.kind == TokenKind::Ident => {
+                Ok(Expr::new(ExprKind::Path(self.path()?), span))
+            }
+            _ => Err(self.unexpected("pattern")),
+        }
+    }
+
+    /// 如果后面是 `..=`、`...` 或 `..`, 把 `start` 扩展成区间模式
+    fn pat_range(&mut self, start: Expr, span: Span) -> PResult<Pat> {
+        let inclusive = if self.eat_punct("..=") || self.eat_punct("...") {
+            true
+        } else if self.eat_punct("..") {
+            false
+        } else {
+            return Ok(Pat {
+                kind: PatKind::Lit(start),
+                span,
+            });
+        };
+        let end = self.pat_bound()?;
+        Ok(Pat {
+            kind: PatKind::Range {
+                start: Box::new(start),
+                end: Box::new(end),
+                inclusive,
+            },
+            span,
+        })
+    }
+
+    fn binding(&mut self, by_ref: bool, mutable: bool, span: Span) -> PResult<Pat> {
+        let name = self.expect_name()?;
+        let sub = if self.eat_punct("@") {
+            Some(Box::new(self.pat_no_or()?))
+        } else {
+            None
+        };
+        Ok(Pat {
+            kind: PatKind::Binding {
+                name,
+                by_ref,
+                mutable,
+                sub,
+            },
+            span,
+        })
+    }
+
+    fn pat_list(&mut self) -> PResult<Vec<Pat>> {
+        let mut pats = Vec::new();
+        while !self.eat_punct(")") {
+            pats.push(self.pat()?);
+            if !self.eat_punct(",") {
+                self.expect_punct(")")?;
+                break;
+            }
+        }
+        Ok(pats)
+    }
+
+    fn pat_no_or(&mut self) -> PResult<Pat> {
+        let span = self.span();
+        if self.eat_ident("_") {
+            return Ok(Pat {
+                kind: PatKind::Wild,
+                span,
+            });
+        }
+        if self.is_punct("&") || self.is_punct("&&") {
+            let double = self.bump()?.text == "&&";
+            let mutable = self.eat_ident("mut");
+            let mut pat = Pat {
+                kind: PatKind::Ref {
+                    mutable,
+                    pat: Box::new(self.pat_no_or()?),
+                },
+                span,
+            };
+            if double {
+                pat = Pat {
+                    kind: PatKind::Ref {
+                        mutable: false,
+                        pat: Box::new(pat),
+                    },
+                    span,
+                };
+            }
+            return Ok(pat);
+        }
+        if self.eat_punct("(") {
+            let mut pats = self.pat_list()?;
+            // `(pat)` 只是加了括号, `(pat,)` 才是一元组
+            let trailing_comma = self.tokens[self.pos - 2].is_punct(",");
+            if pats.len() == 1 && !trailing_comma {
+                return Ok(pats.remove(0));
+            }
+            return Ok(Pat {
+                kind: PatKind::Tuple(pats),
+                span,
+            });
+        }
+        if self.eat_ident("ref") {
+            let mutable = self.eat_ident("mut");
+            return self.binding(true, mutable, span);
+        }
+        if self.eat_ident("mut") {
+            return self.binding(false, true, span);
+        }
+        let bound = self.pat_bound()?;
+        if let ExprKind::Path(path) = &bound.kind {
+            if self.eat_punct("(") {
+                let pats = self.pat_list()?;
+                return Ok(Pat {
+                    kind: PatKind::TupleStruct(path.clone(), pats),
+                    span,
+                });
+            }
+            // 小写开头的单个标识符是绑定, 其余的是常量或枚举成员
+            let binding = path.len() == 1
+                && path[0].starts_with(|c: char| c.is_lowercase() || c == '_')
+                && !self.is_punct("..=")
+                && !self.is_punct("...")
+                && !self.is_punct("..");
+            if binding {
+                self.pos -= 1;
+                return self.binding(false, false, span);
+            }
+            if !self.is_punct("..=") && !self.is_punct("...") && !self.is_punct("..") {
+                return Ok(Pat {
+                    kind: PatKind::Path(path.clone()),
+                    span,
+                });
+            }
+        }
+        self.pat_range(bound, span)
+    }
+
+    // ---------------------------------------------------------------- 类型
+
+    fn type_list(&mut self, close: &str) -> PResult<Vec<Type>> {
+        let mut types = Vec::new();
+        while !self.eat_punct(close) {
+            types.push(self.ty()?);
+            if !self.eat_punct(",") {
+                self.expect_punct(close)?;
+                break;
+            }
+        }
+        Ok(types)
+    }
+
+    /// `Fn(A, B) -> C` 这样的闭包 trait 语法糖
+    fn fn_sugar(&mut self) -> PResult<Type> {
+        self.expect_punct("(")?;
+        let params = self.type_list(")")?;
+        let ret = if self.eat_punct("->") {
+            self.ty()?
+        } else {
+            Type::unit()
+        };
+        Ok(Type::Fn(params, Box::new(ret)))
+    }
+
+    fn trait_bound(&mut self, dynamic: bool) -> PResult<Type> {
+        let bound = self.ty()?;
+        // 忽略 `+ Send`、`+ 'a` 之类的附加约束
+        while self.eat_punct("+") {
+            match self.peek() {
+                Some(t) if t.kind == TokenKind::Lifetime => {
+                    self.pos += 1;
+                }
+                _ => {
+                    self.ty()?;
+                }
+            }
+        }
+        Ok(Type::Trait {
+            dynamic,
+            bound: Box::new(bound),
+        })
+    }
+
+    fn ty(&mut self) -> PResult<Type> {
+        if self.is_punct("&") || self.is_punct("&&") {
+            let double = self.bump()?.text == "&&";
+            let lifetime = match self.peek() {
+                Some(t) if t.kind == TokenKind::Lifetime => Some(self.bump()?.text),
+                _ => None,
+            };
+            let mutable = self.eat_ident("mut");
+            let mut ty = Type::Ref {
+                lifetime,
+                mutable,
+                inner: Box::new(self.ty()?),
+            };
+            if double {
+                ty = Type::Ref {
+                    lifetime: None,
+                    mutable: false,
+                    inner: Box::new(ty),
+                };
+            }
+            return Ok(ty);
+        }
+        if self.eat_punct("(") {
+            let mut types = Vec::new();
+            let mut trailing_comma = false;
+            while !self.eat_punct(")") {
+                types.push(self.ty()?);
+                trailing_comma = self.eat_punct(",");
+                if !trailing_comma {
+                    self.expect_punct(")")?;
+                    break;
+                }
+            }
+            if types.len() == 1 && !trailing_comma {
+                return Ok(types.remove(0));
+            }
+            return Ok(Type::Tuple(types));
+        }
+        if self.eat_punct("[") {
+            let elem = self.ty()?;
+            if self.eat_punct(";") {
+                let len = self.expr()?;
+                self.expect_punct("]")?;
+                return Ok(Type::Array(Box::new(elem), Box::new(len)));
+            }
+            self.expect_punct("]")?;
+            return Ok(Type::Slice(Box::new(elem)));
+        }
+        if self.eat_punct("!") {
+            return Ok(Type::Never);
+        }
+        if self.eat_ident("_") {
+            return Ok(Type::Infer);
+        }
+        if self.eat_ident("fn") {
+            return self.fn_sugar();
+        }
+        if self.eat_ident("dyn") {
+            return self.trait_bound(true);
+        }
+        if self.eat_ident("impl") {
+            return self.trait_bound(false);
+        }
+        let name = self.path_segments_for_type()?;
+        if matches!(name.as_str(), "Fn" | "FnMut" | "FnOnce") && self.is_punct("(") {
+            // 2015 风格的裸 trait 对象, 例如 `Box<Fn(i32) -> i32>`
+            let sugar = self.fn_sugar()?;
+            return Ok(Type::Trait {
+                dynamic: true,
+                bound: Box::new(sugar),
+            });
+        }
+        let mut args = Vec::new();
+        if self.eat_punct("<") {
+            loop {
+                match self.peek() {
+                    Some(t) if t.kind == TokenKind::Lifetime => {
+                        self.pos += 1;
+                    }
+                    _ => args.push(self.ty()?),
+                }
+                if !self.eat_punct(",") || self.is_punct(">") {
+                    break;
+                }
+            }
+            self.expect_gt()?;
+        }
+        Ok(Type::Path { name, args })
+    }
+
+    fn path_segments_for_type(&mut self) -> PResult<String> {
+        let mut name = match self.peek() {
+            Some(t) if t.kind == TokenKind::Ident && !KEYWORDS.contains(&t.text.as_str()) => {
+                self.bump()?.text
+            }
+            _ => return Err(self.unexpected("type")),
+        };
+        while self.is_punct("::") {
+            self.pos += 1;
+            name.push_str("::");
+            name.push_str(&self.expect_name()?);
+        }
+        Ok(name)
+    }
+}
+
+/// # 解析表达式
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::lang::parser::parse_expr;
+///
+/// let expr = parse_expr("temp() = *x").unwrap();
+/// assert_eq!(expr.to_string(), "temp() = *x");
+/// // 乘法比加法结合得更紧, `as` 比乘法更紧
+/// let expr = parse_expr("1 + 2 * n as i64").unwrap();
+/// assert_eq!(format!("{:?}", expr).matches("Binary").count(), 2);
+/// assert_eq!(
+///     parse_expr("a[0].1 += v.iter().sum::<i32>()").unwrap().to_string(),
+///     "a[0].1 += v.iter().sum()"
+/// );
+/// let e = parse_expr("temp() = ").unwrap_err();
+/// assert_eq!(e.to_string(), "1:10: expected expression, found end of input");
+/// ```
+pub fn parse_expr(src: &str) -> Result<Expr, ParseError> {
+    let mut parser = Parser::new(src)?;
+    let expr = parser.expr()?;
+    parser.expect_end()?;
+    Ok(expr)
+}
+
+/// # 解析语句序列
+///
+/// 相当于一个省略了花括号的块, 其中可以出现函数、常量等条目
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::lang::ast::StmtKind;
+/// use rust_programming_of_zhd::lang::parser::parse_stmts;
+///
+/// let stmts = parse_stmts("
+///     let x: Vec<Vec<i32>> = vec![vec![1]; 2];
+///     if x.len() > 1 { 1 } else { 2 }
+///     match x[0][0] { 0 => {} 1..=3 | 5 => println!(\"small\"), n @ _ => {} }
+///     x
+/// ").unwrap();
+/// assert_eq!(stmts.len(), 4);
+/// assert!(matches!(stmts[1].kind, StmtKind::Expr(_)));
+/// assert!(matches!(stmts[3].kind, StmtKind::Expr(_)));
+/// assert_eq!(stmts[0].to_string(), "let x: Vec<Vec<i32>> = vec![vec![1]; 2];");
+/// ```
+pub fn parse_stmts(src: &str) -> Result<Vec<Stmt>, ParseError> {
+    let mut parser = Parser::new(src)?;
+    let mut stmts = Vec::new();
+    while !parser.at_end() {
+        if let Some(stmt) = parser.stmt()? {
+            stmts.push(stmt);
+        }
+    }
+    Ok(stmts)
+}
+
+/// # 解析条目
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::lang::parser::parse_items;
+///
+/// let items = parse_items("const fn init_len() -> usize { return 5; } static N: [u8; 2] = [0; 2];").unwrap();
+/// assert_eq!(items[0].to_string(), "const fn init_len() -> usize { return 5; }");
+/// assert_eq!(items[1].name(), "N");
+/// ```
+pub fn parse_items(src: &str) -> Result<Vec<Item>, ParseError> {
+    let mut parser = Parser::new(src)?;
+    let mut items = Vec::new();
+    while !parser.at_end() {
+        items.push(parser.item()?);
+    }
+    Ok(items)
+}
+
+/// # 解析类型
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::lang::parser::parse_type;
+///
+/// assert_eq!(parse_type("&'a mut [i32; 3]").unwrap().to_string(), "&'a mut [i32; 3]");
+/// assert_eq!(parse_type("Box<Fn(i32) -> i32>").unwrap().to_string(), "Box<dyn Fn(i32) -> i32>");
+/// ```
+pub fn parse_type(src: &str) -> Result<Type, ParseError> {
+    let mut parser = Parser::new(src)?;
+    let ty = parser.ty()?;
+    parser.expect_end()?;
+    Ok(ty)
+}
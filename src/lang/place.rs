@@ -0,0 +1,490 @@
+//! # 位置表达式与值表达式
+//!
+//! 把 [`ch02::binding::temp`](crate::ch02::binding::temp) 文档中的分类做成可以运行的分析器:
+//! 为代码片段中的每个子表达式标注它是位置表达式还是值表达式, 以及它所处的是位置上下文还是值上下文,
+//! 并解释值表达式出现在位置上下文中的后果
+//!
+//! 没有类型信息, 路径按照命名习惯区分: 小写开头的是本地变量, 全大写的是静态变量,
+//! 其余(大写开头的枚举成员、被调用的函数名)是值表达式
+
+use std::fmt;
+
+use super::ast::{Block, Expr, ExprKind, Item, Stmt, StmtKind, UnOp};
+use super::diagnostic::Diagnostic;
+use super::lexer::Span;
+use super::parser::{parse_stmts, ParseError};
+
+/// 位置表达式的六种形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlaceKind {
+    /// 本地变量
+    Local,
+    /// 静态变量
+    Static,
+    /// 解引用 `*expr`
+    Deref,
+    /// 数组索引 `expr[expr]`
+    Index,
+    /// 字段引用 `expr.field`
+    Field,
+    /// 位置表达式组合, 例如 `(*x)`
+    Paren,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    Place(PlaceKind),
+    Value,
+}
+
+impl Category {
+    pub fn is_place(self) -> bool {
+        matches!(self, Category::Place(_))
+    }
+}
+
+/// 位置上下文的种类, 前四种来自 `temp` 的文档, 最后一种来自 Rust 参考手册
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlaceContext {
+    /// 赋值或者复合赋值语句左侧的操作数
+    Assign,
+    /// 一元引用表达式 `&`、`&mut` 的独立操作数
+    Borrow,
+    /// 包含隐式借用的操作数: 方法调用的接收者、比较运算的操作数、格式化宏的参数以及 `use` 伪语句
+    ImplicitBorrow,
+    /// 使用 `ref` 模式时 `let` 的右侧或 `match` 的判别式
+    RefBinding,
+    /// 字段访问或索引的基础操作数
+    Projection,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Context {
+    Place(PlaceContext),
+    Value,
+}
+
+impl Context {
+    pub fn is_place(self) -> bool {
+        matches!(self, Context::Place(_))
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self {
+            Category::Value => return f.write_str("值表达式"),
+            Category::Place(PlaceKind::Local) => "本地变量",
+            Category::Place(PlaceKind::Static) => "静态变量",
+            Category::Place(PlaceKind::Deref) => "解引用",
+            Category::Place(PlaceKind::Index) => "数组索引",
+            Category::Place(PlaceKind::Field) => "字段引用",
+            Category::Place(PlaceKind::Paren) => "位置表达式组合",
+        };
+        write!(f, "位置表达式({})", kind)
+    }
+}
+
+impl fmt::Display for Context {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self {
+            Context::Value => return f.write_str("值上下文"),
+            Context::Place(PlaceContext::Assign) => "赋值左侧",
+            Context::Place(PlaceContext::Borrow) => "借用",
+            Context::Place(PlaceContext::ImplicitBorrow) => "隐式借用",
+            Context::Place(PlaceContext::RefBinding) => "ref 模式",
+            Context::Place(PlaceContext::Projection) => "字段或索引的基础",
+        };
+        write!(f, "位置上下文({})", kind)
+    }
+}
+
+/// 一个子表达式的分析结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub span: Span,
+    /// 规范化后的源码
+    pub expr: String,
+    /// 在语法树中的深度, 顶层表达式为 0
+    pub depth: usize,
+    pub category: Category,
+    pub context: Context,
+}
+
+/// 整个代码片段的分析结果
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Analysis {
+    pub nodes: Vec<Node>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Analysis {
+    /// 是否没有错误, 即编译器会接受这段代码(仅就位置与值的规则而言)
+    pub fn is_accepted(&self) -> bool {
+        !self.diagnostics.iter().any(Diagnostic::is_error)
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter().filter(|d| d.is_error())
+    }
+}
+
+/// 终端中的显示宽度, 中文字符占两列
+fn display_width(s: &str) -> usize {
+    s.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = |node: &Node| node.depth * 2 + node.expr.chars().count();
+        let column = self.nodes.iter().map(width).max().unwrap_or(0);
+        for node in &self.nodes {
+            let category = node.category.to_string();
+            writeln!(
+                f,
+                "{:>5}  {}{}{}  {}{}  {}",
+                node.span.to_string(),
+                "  ".repeat(node.depth),
+                node.expr,
+                " ".repeat(column - width(node)),
+                category,
+                " ".repeat(24usize.saturating_sub(display_width(&category))),
+                node.context
+            )?;
+        }
+        for diagnostic in &self.diagnostics {
+            writeln!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+/// 按命名习惯判断路径表达式的类别
+fn path_category(path: &[String]) -> Category {
+    let last = path.last().map(String::as_str).unwrap_or("");
+    let is_static = last.chars().any(|c| c.is_ascii_uppercase())
+        && last
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+    if is_static {
+        Category::Place(PlaceKind::Static)
+    } else if path.len() == 1 && last.starts_with(|c: char| c.is_lowercase() || c == '_') {
+        Category::Place(PlaceKind::Local)
+    } else {
+        Category::Value
+    }
+}
+
+/// # 表达式的类别
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::lang::parser::parse_expr;
+/// use rust_programming_of_zhd::lang::place::{category, Category, PlaceKind};
+///
+/// let of = |src| category(&parse_expr(src).unwrap());
+/// assert_eq!(of("x"), Category::Place(PlaceKind::Local));
+/// assert_eq!(of("MAX_LEN"), Category::Place(PlaceKind::Static));
+/// assert_eq!(of("*x"), Category::Place(PlaceKind::Deref));
+/// assert_eq!(of("a[0]"), Category::Place(PlaceKind::Index));
+/// assert_eq!(of("p.0"), Category::Place(PlaceKind::Field));
+/// assert_eq!(of("(*x)"), Category::Place(PlaceKind::Paren));
+/// assert_eq!(of("temp()"), Category::Value);
+/// assert_eq!(of("(1 + 2)"), Category::Value);
+/// ```
+pub fn category(expr: &Expr) -> Category {
+    match &expr.kind {
+        ExprKind::Path(path) => path_category(path),
+        ExprKind::Unary(UnOp::Deref, _) => Category::Place(PlaceKind::Deref),
+        ExprKind::Index(..) => Category::Place(PlaceKind::Index),
+        ExprKind::Field(..) => Category::Place(PlaceKind::Field),
+        ExprKind::Paren(inner) if category(inner).is_place() => Category::Place(PlaceKind::Paren),
+        _ => Category::Value,
+    }
+}
+
+/// 会隐式借用参数的格式化宏
+const FORMAT_MACROS: [&str; 9] = [
+    "println",
+    "print",
+    "eprintln",
+    "eprint",
+    "format",
+    "write",
+    "writeln",
+    "assert_eq",
+    "assert_ne",
+];
+
+#[derive(Default)]
+struct Analyzer {
+    analysis: Analysis,
+}
+
+impl Analyzer {
+    fn note(&mut self, span: Span, message: String) {
+        self.analysis
+            .diagnostics
+            .push(Diagnostic::note(span, message));
+    }
+
+    fn error(&mut self, code: &'static str, span: Span, message: String) {
+        self.analysis
+            .diagnostics
+            .push(Diagnostic::error(code, span, message));
+    }
+
+    fn block(&mut self, block: &Block, depth: usize) {
+        for stmt in &block.stmts {
+            self.stmt(stmt, depth);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, depth: usize) {
+        match &stmt.kind {
+            StmtKind::Let { pat, init, .. } => {
+                if let Some(init) = init {
+                    let context = if pat.has_ref_binding() {
+                        Context::Place(PlaceContext::RefBinding)
+                    } else {
+                        Context::Value
+                    };
+                    self.expr(init, context, depth);
+                }
+            }
+            StmtKind::Expr(expr) | StmtKind::Semi(expr) => self.expr(expr, Context::Value, depth),
+            StmtKind::Use(expr) => {
+                self.expr(expr, Context::Place(PlaceContext::ImplicitBorrow), depth)
+            }
+            StmtKind::Item(Item::Fn(decl)) => self.block(&decl.body, depth),
+            StmtKind::Item(Item::Const { expr, .. })
+            | StmtKind::Item(Item::Static { expr, .. }) => self.expr(expr, Context::Value, depth),
+        }
+    }
+
+    fn explain(&mut self, expr: &Expr, category: Category, context: Context) {
+        match (category, context) {
+            (Category::Value, Context::Place(PlaceContext::Assign)) => {
+                self.error(
+                    "E0070",
+                    expr.span,
+                    format!(
+                        "invalid left-hand side of assignment: `{}` 是值表达式, \
+                         不能出现在位置上下文(赋值左侧)中, 它只代表一个临时值, 没有可以写入的内存位置",
+                        expr
+                    ),
+                );
+            }
+            (Category::Value, Context::Place(_)) => {
+                let what = match &expr.kind {
+                    ExprKind::Lit(_) => "字面量",
+                    _ => "值表达式",
+                };
+                self.note(
+                    expr.span,
+                    format!(
+                        "{} `{}` 出现在{}中, 编译器会为它创建一个临时值",
+                        what, expr, context
+                    ),
+                );
+            }
+            (Category::Place(_), Context::Value) => {
+                self.note(
+                    expr.span,
+                    format!(
+                        "位置表达式 `{}` 出现在值上下文中, 其中的值会被移动(Move)或复制(Copy)",
+                        expr
+                    ),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr, context: Context, depth: usize) {
+        let category = category(expr);
+        self.analysis.nodes.push(Node {
+            span: expr.span,
+            expr: expr.to_string(),
+            depth,
+            category,
+            context,
+        });
+        self.explain(expr, category, context);
+        let depth = depth + 1;
+        let value = Context::Value;
+        match &expr.kind {
+            ExprKind::Lit(_) | ExprKind::Path(_) | ExprKind::Continue => {}
+            ExprKind::Paren(inner) => self.expr(inner, context, depth),
+            ExprKind::Unary(_, operand) | ExprKind::Cast(operand, _) => {
+                self.expr(operand, value, depth)
+            }
+            ExprKind::Ref { expr: operand, .. } => {
+                self.expr(operand, Context::Place(PlaceContext::Borrow), depth)
+            }
+            ExprKind::Binary(op, lhs, rhs) => {
+                let operands = if op.is_comparison() {
+                    Context::Place(PlaceContext::ImplicitBorrow)
+                } else {
+                    value
+                };
+                self.expr(lhs, operands, depth);
+                self.expr(rhs, operands, depth);
+            }
+            ExprKind::Assign(lhs, rhs) => {
+                self.expr(lhs, Context::Place(PlaceContext::Assign), depth);
+                self.expr(rhs, value, depth);
+            }
+            ExprKind::AssignOp(_, lhs, rhs) => {
+                let start = self.analysis.diagnostics.len();
+                self.expr(lhs, Context::Place(PlaceContext::Assign), depth);
+                // 复合赋值左侧的错误在 rustc 中是另一个错误码
+                if let Some(error) = self.analysis.diagnostics[start..]
+                    .iter_mut()
+                    .find(|d| d.code == Some("E0070"))
+                {
+                    error.code = Some("E0067");
+                    error.message = error.message.replacen(
+                        "invalid left-hand side of assignment",
+                        "invalid left-hand side of compound assignment",
+                        1,
+                    );
+                }
+                self.expr(rhs, value, depth);
+            }
+            ExprKind::Field(base, _) => {
+                self.expr(base, Context::Place(PlaceContext::Projection), depth)
+            }
+            ExprKind::Index(base, index) => {
+                self.expr(base, Context::Place(PlaceContext::Projection), depth);
+                self.expr(index, value, depth);
+            }
+            ExprKind::Call(callee, args) => {
+                // 被调用的函数名不是变量, 不单独列出
+                if !matches!(callee.kind, ExprKind::Path(_)) {
+                    self.expr(callee, value, depth);
+                }
+                for arg in args {
+                    self.expr(arg, value, depth);
+                }
+            }
+            ExprKind::MethodCall { receiver, args, .. } => {
+                self.expr(
+                    receiver,
+                    Context::Place(PlaceContext::ImplicitBorrow),
+                    depth,
+                );
+                for arg in args {
+                    self.expr(arg, value, depth);
+                }
+            }
+            ExprKind::Macro { name, args } => {
+                let context = if FORMAT_MACROS.contains(&name.as_str()) {
+                    Context::Place(PlaceContext::ImplicitBorrow)
+                } else {
+                    value
+                };
+                for arg in args {
+                    // 格式字符串本身不是被借用的参数
+                    let context = match arg.kind {
+                        ExprKind::Lit(_) => value,
+                        _ => context,
+                    };
+                    self.expr(arg, context, depth);
+                }
+            }
+            ExprKind::Tuple(items) | ExprKind::Array(items) => {
+                for item in items {
+                    self.expr(item, value, depth);
+                }
+            }
+            ExprKind::Repeat(item, len) => {
+                self.expr(item, value, depth);
+                self.expr(len, value, depth);
+            }
+            ExprKind::Let(pat, scrutinee) => {
+                let context = if pat.has_ref_binding() {
+                    Context::Place(PlaceContext::RefBinding)
+                } else {
+                    value
+                };
+                self.expr(scrutinee, context, depth);
+            }
+            ExprKind::Block(block) | ExprKind::Loop(block) => self.block(block, depth),
+            ExprKind::If { cond, then, els } => {
+                self.expr(cond, value, depth);
+                self.block(then, depth);
+                if let Some(els) = els {
+                    self.expr(els, value, depth);
+                }
+            }
+            ExprKind::While { cond, body } => {
+                self.expr(cond, value, depth);
+                self.block(body, depth);
+            }
+            ExprKind::For { iter, body, .. } => {
+                self.expr(iter, value, depth);
+                self.block(body, depth);
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                let context = if arms.iter().any(|arm| arm.pat.has_ref_binding()) {
+                    Context::Place(PlaceContext::RefBinding)
+                } else {
+                    value
+                };
+                self.expr(scrutinee, context, depth);
+                for arm in arms {
+                    if let Some(guard) = &arm.guard {
+                        self.expr(guard, value, depth);
+                    }
+                    self.expr(&arm.body, value, depth);
+                }
+            }
+            ExprKind::Break(operand) | ExprKind::Return(operand) => {
+                if let Some(operand) = operand {
+                    self.expr(operand, value, depth);
+                }
+            }
+            ExprKind::Range { start, end, .. } => {
+                for operand in start.iter().chain(end) {
+                    self.expr(operand, value, depth);
+                }
+            }
+        }
+    }
+}
+
+/// # 分析代码片段
+///
+/// `src` 是一组语句, 最后一条可以省略分号
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::lang::place::{analyze, Category, Context, PlaceContext};
+///
+/// let analysis = analyze("let x = &temp(); temp() = *x;").unwrap();
+/// assert!(!analysis.is_accepted());
+/// let error = analysis.errors().next().unwrap();
+/// assert_eq!(error.code, Some("E0070"));
+/// assert_eq!(error.span.col, 18);
+///
+/// // `temp()` 在借用中是合法的, 只是会创建临时值; 在赋值左侧则是错误
+/// let temps: Vec<_> = analysis.nodes.iter().filter(|n| n.expr == "temp()").collect();
+/// assert_eq!(temps[0].context, Context::Place(PlaceContext::Borrow));
+/// assert_eq!(temps[1].context, Context::Place(PlaceContext::Assign));
+/// assert!(temps.iter().all(|n| n.category == Category::Value));
+///
+/// assert!(analyze("let mut v = [1, 2]; v[0] = v[1] + 1;").unwrap().is_accepted());
+/// let compound = analyze("1 += 2").unwrap();
+/// assert_eq!(compound.errors().next().unwrap().code, Some("E0067"));
+/// ```
+pub fn analyze(src: &str) -> Result<Analysis, ParseError> {
+    let stmts = parse_stmts(src)?;
+    let mut analyzer = Analyzer::default();
+    for stmt in &stmts {
+        analyzer.stmt(stmt, 0);
+    }
+    Ok(analyzer.analysis)
+}
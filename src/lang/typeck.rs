@@ -0,0 +1,844 @@
+//! # 类型检查
+//!
+//! [`control_flow::while_true`](crate::ch02::control_flow::while_true) 与
+//! [`control_flow::if_true`](crate::ch02::control_flow::if_true) 解释了为什么函数体只有
+//! `while true { return x + 1; }` 时会报告 E0308: 类型检查做流分析时不看循环条件的值,
+//! 它只知道 `while` 循环的类型是 `()`, 与函数的返回类型 `i32` 不匹配
+//!
+//! 这里按同样的规则给表达式定型:
+//!
+//! - `while` 与 `for` 循环的类型总是 `()`
+//! - 没有 `break` 的 `loop` 的类型是 `!`, 否则是 `break` 所带的值的类型
+//! - 没有 `else` 分支的 `if` 的类型是 `()`, 期望其他类型时报告 E0317
+//! - 块的类型是尾表达式的类型; 没有尾表达式时是 `()`, 如果其中的语句发散(例如 `return`)则是 `!`
+//! - `!` 可以转换为任何类型
+//!
+//! 整数与浮点数字面量在没有后缀时分别是 `{integer}` 与 `{float}`, 可以匹配任何整数或浮点类型;
+//! 无法推断的类型记为 `_`, 与任何类型都兼容
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::ast::{BinOp, Block, Expr, ExprKind, FnDecl, Item, Lit, Pat, PatKind, Stmt, StmtKind};
+use super::ast::{Type, UnOp};
+use super::diagnostic::Diagnostic;
+use super::lexer::Span;
+use super::parser::{parse_stmts, ParseError};
+
+const INTEGERS: [&str; 12] = [
+    "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize",
+];
+
+/// 块状表达式的类型, 以及得到这个类型的原因
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub span: Span,
+    /// 表达式的开头, 例如 `while true`
+    pub head: String,
+    pub ty: Type,
+    pub reason: String,
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:>5}  {}: {}  ({})",
+            self.span.to_string(),
+            self.head,
+            self.ty,
+            self.reason
+        )
+    }
+}
+
+/// 一次类型检查的结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeCheck {
+    pub annotations: Vec<Annotation>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl TypeCheck {
+    /// 是否通过了类型检查
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// 先列出块状表达式的类型, 再按 rustc 的格式输出诊断信息
+    pub fn render(&self, src: &str) -> String {
+        let mut out: String = self
+            .annotations
+            .iter()
+            .map(|a| format!("{}\n", a))
+            .collect();
+        for diagnostic in &self.diagnostics {
+            out.push('\n');
+            out.push_str(&diagnostic.render(src));
+        }
+        out
+    }
+}
+
+impl fmt::Display for TypeCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for annotation in &self.annotations {
+            writeln!(f, "{}", annotation)?;
+        }
+        for diagnostic in &self.diagnostics {
+            writeln!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+/// 期望的类型, 以及期望的来源, 例如函数的返回类型
+#[derive(Debug, Clone)]
+struct Expectation {
+    ty: Type,
+    origin: Option<(Span, String)>,
+}
+
+impl Expectation {
+    fn new(ty: Type) -> Self {
+        Expectation { ty, origin: None }
+    }
+
+    fn because(ty: Type, span: Span, why: &str) -> Self {
+        let origin = Some((span, format!("expected `{}` {}", ty, why)));
+        Expectation { ty, origin }
+    }
+
+    fn is_known(&self) -> bool {
+        self.ty != Type::Infer
+    }
+}
+
+struct LoopContext {
+    /// `loop` 可以用 `break` 带出值, `while` 和 `for` 不行
+    is_loop: bool,
+    expected: Option<Expectation>,
+    breaks: Vec<Type>,
+}
+
+struct Checker {
+    scopes: Vec<Vec<(String, Type)>>,
+    fns: HashMap<String, (Vec<Type>, Type)>,
+    /// 当前函数返回类型的期望, 供 `return` 使用
+    returns: Vec<Expectation>,
+    loops: Vec<LoopContext>,
+    annotations: Vec<Annotation>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    fn new() -> Self {
+        Checker {
+            scopes: vec![Vec::new()],
+            fns: HashMap::new(),
+            returns: Vec::new(),
+            loops: Vec::new(),
+            annotations: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn annotate(&mut self, span: Span, head: String, ty: &Type, reason: &str) {
+        self.annotations.push(Annotation {
+            span,
+            head,
+            ty: ty.clone(),
+            reason: reason.to_string(),
+        });
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Type> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|(n, _)| n == name)
+            .map(|(_, ty)| ty)
+    }
+
+    /// 检查 `found` 能否转换为期望的类型, 不能时报告 E0308
+    fn demand(&mut self, span: Span, found: &Type, expected: Option<&Expectation>, note: &str) {
+        let expected = match expected {
+            Some(expected) if !coerces(found, &expected.ty) => expected,
+            _ => return,
+        };
+        let label = format!("expected `{}`, found `{}`", expected.ty, found);
+        let mut diagnostic =
+            Diagnostic::error("E0308", span, "mismatched types".to_string()).with_label(&label);
+        if let Some((origin, why)) = &expected.origin {
+            diagnostic = diagnostic.with_note(*origin, why);
+        }
+        if !note.is_empty() {
+            diagnostic = diagnostic.with_note(span, note);
+        }
+        self.diagnostics.push(diagnostic);
+    }
+
+    // ---------------------------------------------------------------- 语句
+
+    fn declare_fns(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            if let StmtKind::Item(Item::Fn(decl)) = &stmt.kind {
+                let params = decl.params.iter().map(|p| p.ty.clone()).collect();
+                self.fns
+                    .insert(decl.name.clone(), (params, decl.ret.clone()));
+            }
+        }
+    }
+
+    fn function(&mut self, decl: &FnDecl) {
+        let mut scope = Vec::new();
+        for param in &decl.params {
+            bind(&param.pat, &param.ty, &mut scope);
+        }
+        // 函数体看不到外层的局部变量
+        let outer = std::mem::replace(&mut self.scopes, vec![scope]);
+        let outer_loops = std::mem::take(&mut self.loops);
+        // 省略返回类型时 rustc 指向函数体之前的位置
+        let expected = match decl.ret_span {
+            Some(span) => Expectation::because(decl.ret.clone(), span, "because of return type"),
+            None => Expectation::because(
+                decl.ret.clone(),
+                decl.body.span,
+                "because of default return type",
+            ),
+        };
+        self.returns.push(expected.clone());
+        let ty = self.block(&decl.body, decl.span, Some(&expected));
+        self.returns.pop();
+        self.loops = outer_loops;
+        self.scopes = outer;
+        self.annotate(
+            decl.span,
+            format!("fn {}", decl.name),
+            &ty,
+            &format!("函数体的类型, 需要转换为返回类型 `{}`", decl.ret),
+        );
+    }
+
+    fn block(&mut self, block: &Block, span: Span, expected: Option<&Expectation>) -> Type {
+        self.scopes.push(Vec::new());
+        self.declare_fns(&block.stmts);
+        let (ty, reason) = self.stmts(&block.stmts, span, expected);
+        self.scopes.pop();
+        self.annotate(block.span, "{ .. }".to_string(), &ty, reason);
+        ty
+    }
+
+    fn stmts(
+        &mut self,
+        stmts: &[Stmt],
+        span: Span,
+        expected: Option<&Expectation>,
+    ) -> (Type, &'static str) {
+        let mut diverges = false;
+        for (i, stmt) in stmts.iter().enumerate() {
+            let last = i + 1 == stmts.len();
+            match &stmt.kind {
+                StmtKind::Let { pat, ty, init } => {
+                    let declared = ty.clone().unwrap_or(Type::Infer);
+                    let found = match init {
+                        Some(init) => {
+                            let expected =
+                                Expectation::because(declared.clone(), pat.span, "due to this");
+                            self.expr(init, Some(&expected).filter(|e| e.is_known()))
+                        }
+                        None => Type::Infer,
+                    };
+                    diverges |= found == Type::Never;
+                    let ty = if declared == Type::Infer {
+                        found
+                    } else {
+                        declared
+                    };
+                    bind(pat, &ty, self.scopes.last_mut().unwrap());
+                }
+                StmtKind::Expr(expr) if last => {
+                    let ty = self.expr(expr, expected);
+                    return (ty, "块的类型来自尾表达式");
+                }
+                StmtKind::Expr(expr) => {
+                    // 不带分号的块状表达式作为语句时, 类型必须是 ()
+                    let ty = self.expr(expr, Some(&Expectation::new(Type::unit())));
+                    diverges |= ty == Type::Never;
+                }
+                StmtKind::Semi(expr) | StmtKind::Use(expr) => {
+                    diverges |= self.expr(expr, None) == Type::Never;
+                }
+                StmtKind::Item(Item::Fn(decl)) => self.function(decl),
+                StmtKind::Item(Item::Const { name, ty, expr, .. })
+                | StmtKind::Item(Item::Static { name, ty, expr, .. }) => {
+                    let expected = Expectation::new(ty.clone());
+                    self.expr(expr, Some(&expected));
+                    self.scopes
+                        .last_mut()
+                        .unwrap()
+                        .push((name.clone(), ty.clone()));
+                }
+            }
+        }
+        if diverges {
+            return (Type::Never, "块中的语句发散, 块的类型是 `!`");
+        }
+        self.demand(
+            span,
+            &Type::unit(),
+            expected,
+            "块没有尾表达式, 也没有发散的语句, 隐式地求值为 `()`",
+        );
+        (Type::unit(), "块没有尾表达式, 类型是 `()`")
+    }
+
+    // ---------------------------------------------------------------- 表达式
+
+    fn expr(&mut self, expr: &Expr, expected: Option<&Expectation>) -> Type {
+        let span = expr.span;
+        let ty = match &expr.kind {
+            ExprKind::Paren(inner) => return self.expr(inner, expected),
+            ExprKind::Block(block) => return self.block(block, span, expected),
+            ExprKind::If { cond, then, els } => {
+                return self.if_expr(expr, cond, then, els, expected)
+            }
+            ExprKind::Match { scrutinee, arms } => {
+                let scrutinee_ty = self.expr(scrutinee, None);
+                let mut ty = Type::Never;
+                for arm in arms {
+                    self.scopes.push(Vec::new());
+                    bind(&arm.pat, &scrutinee_ty, self.scopes.last_mut().unwrap());
+                    if let Some(guard) = &arm.guard {
+                        self.expr(guard, Some(&Expectation::new(Type::named("bool"))));
+                    }
+                    let arm_ty = self.expr(&arm.body, expected);
+                    self.scopes.pop();
+                    if expected.is_none_or(|e| !e.is_known())
+                        && !coerces(&arm_ty, &ty)
+                        && !coerces(&ty, &arm_ty)
+                    {
+                        let label = format!("expected `{}`, found `{}`", ty, arm_ty);
+                        self.diagnostics.push(
+                            Diagnostic::error(
+                                "E0308",
+                                arm.body.span,
+                                "`match` arms have incompatible types".to_string(),
+                            )
+                            .with_label(&label),
+                        );
+                    }
+                    ty = join(ty, arm_ty);
+                }
+                self.annotate(
+                    span,
+                    format!("match {}", scrutinee),
+                    &ty,
+                    "各分支类型一致, 发散的分支不影响类型",
+                );
+                return ty;
+            }
+            ExprKind::While { cond, body } => {
+                self.expr(cond, Some(&Expectation::new(Type::named("bool"))));
+                self.loop_body(body, false, None);
+                let ty = Type::unit();
+                self.annotate(
+                    span,
+                    format!("while {}", cond),
+                    &ty,
+                    "`while` 循环的类型总是 `()`, 不会检查条件是否恒为真",
+                );
+                self.demand(
+                    span,
+                    &ty,
+                    expected,
+                    "`while` loops evaluate to unit type `()`",
+                );
+                return ty;
+            }
+            ExprKind::For { pat, iter, body } => {
+                let iter_ty = self.expr(iter, None);
+                self.scopes.push(Vec::new());
+                bind(pat, &element(&iter_ty), self.scopes.last_mut().unwrap());
+                self.loop_body(body, false, None);
+                self.scopes.pop();
+                let ty = Type::unit();
+                self.annotate(
+                    span,
+                    format!("for {} in {}", pat, iter),
+                    &ty,
+                    "`for` 循环的类型总是 `()`",
+                );
+                self.demand(
+                    span,
+                    &ty,
+                    expected,
+                    "`for` loops evaluate to unit type `()`",
+                );
+                return ty;
+            }
+            ExprKind::Loop(body) => {
+                let breaks = self.loop_body(body, true, expected);
+                let (ty, reason) = match breaks.into_iter().reduce(join) {
+                    None => (
+                        Type::Never,
+                        "`loop` 中没有 `break`, 永远不会正常结束, 类型是 `!`",
+                    ),
+                    Some(ty) => (ty, "`loop` 的类型来自 `break` 带出的值"),
+                };
+                self.annotate(span, "loop".to_string(), &ty, reason);
+                return ty;
+            }
+            ExprKind::Break(value) => {
+                let expected = self.loops.last().and_then(|l| l.expected.clone());
+                let ty = match value {
+                    Some(value) => self.expr(value, expected.as_ref()),
+                    None => {
+                        self.demand(span, &Type::unit(), expected.as_ref(), "");
+                        Type::unit()
+                    }
+                };
+                match self.loops.last_mut() {
+                    Some(context) if !context.is_loop && value.is_some() => {
+                        self.diagnostics.push(
+                            Diagnostic::error(
+                                "E0571",
+                                span,
+                                "`break` with value from a `while` loop".to_string(),
+                            )
+                            .with_label(
+                                "can only break with a value inside `loop` or breakable block",
+                            ),
+                        );
+                    }
+                    Some(context) => context.breaks.push(ty),
+                    None => {}
+                }
+                return Type::Never;
+            }
+            ExprKind::Continue => return Type::Never,
+            ExprKind::Return(value) => {
+                let expected = self.returns.last().cloned();
+                match value {
+                    Some(value) => {
+                        self.expr(value, expected.as_ref());
+                    }
+                    None => self.demand(span, &Type::unit(), expected.as_ref(), ""),
+                }
+                return Type::Never;
+            }
+            ExprKind::Lit(lit) => literal(lit),
+            ExprKind::Path(path) => {
+                let name = path.join("::");
+                match (self.lookup(&name), self.fns.get(&name)) {
+                    (Some(ty), _) => ty.clone(),
+                    (None, Some((params, ret))) => Type::Fn(params.clone(), Box::new(ret.clone())),
+                    (None, None) => Type::Infer,
+                }
+            }
+            ExprKind::Unary(op, inner) => {
+                let ty = self.expr(inner, None);
+                match (op, ty) {
+                    (UnOp::Deref, Type::Ref { inner, .. }) => *inner,
+                    (UnOp::Deref, _) => Type::Infer,
+                    (_, ty) => ty,
+                }
+            }
+            ExprKind::Ref { mutable, expr } => Type::Ref {
+                lifetime: None,
+                mutable: *mutable,
+                inner: Box::new(self.expr(expr, None)),
+            },
+            ExprKind::Binary(op, lhs, rhs) if op.is_lazy() => {
+                let bool_ty = Expectation::new(Type::named("bool"));
+                self.expr(lhs, Some(&bool_ty));
+                self.expr(rhs, Some(&bool_ty));
+                Type::named("bool")
+            }
+            ExprKind::Binary(op, lhs, rhs) => {
+                let lhs_ty = self.expr(lhs, None);
+                let rhs_expected = match op {
+                    BinOp::Shl | BinOp::Shr => None,
+                    _ => Some(Expectation::new(lhs_ty.clone())),
+                };
+                let rhs_ty = self.expr(rhs, rhs_expected.as_ref());
+                if op.is_comparison() {
+                    Type::named("bool")
+                } else {
+                    join(lhs_ty, rhs_ty)
+                }
+            }
+            ExprKind::Assign(lhs, rhs) | ExprKind::AssignOp(_, lhs, rhs) => {
+                let lhs_ty = self.expr(lhs, None);
+                self.expr(rhs, Some(&Expectation::new(lhs_ty)));
+                Type::unit()
+            }
+            ExprKind::Field(base, field) => match (self.expr(base, None), field.parse::<usize>()) {
+                (Type::Tuple(items), Ok(i)) => items.get(i).cloned().unwrap_or(Type::Infer),
+                _ => Type::Infer,
+            },
+            ExprKind::Index(base, index) => {
+                self.expr(index, None);
+                match (self.expr(base, None), &index.kind) {
+                    (_, ExprKind::Range { .. }) => Type::Infer,
+                    (ty, _) => element(&ty),
+                }
+            }
+            ExprKind::Call(callee, args) => {
+                let callee_ty = self.expr(callee, None);
+                let (params, ret) = match callee_ty {
+                    Type::Fn(params, ret) => (params, *ret),
+                    _ => (Vec::new(), Type::Infer),
+                };
+                for (i, arg) in args.iter().enumerate() {
+                    let expected = params.get(i).cloned().map(Expectation::new);
+                    self.expr(arg, expected.as_ref());
+                }
+                ret
+            }
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+            } => {
+                let receiver_ty = self.expr(receiver, None);
+                for arg in args {
+                    self.expr(arg, None);
+                }
+                match method.as_str() {
+                    "to_string" | "to_owned" | "to_uppercase" | "to_lowercase" => {
+                        Type::named("String")
+                    }
+                    "len" | "count" => Type::named("usize"),
+                    "is_empty" | "contains" | "starts_with" | "ends_with" => Type::named("bool"),
+                    "clone" => match receiver_ty {
+                        Type::Ref { inner, .. } => *inner,
+                        ty => ty,
+                    },
+                    "abs" | "pow" | "min" | "max" => receiver_ty,
+                    "push" | "push_str" | "clear" | "insert" | "sort" => Type::unit(),
+                    _ => Type::Infer,
+                }
+            }
+            ExprKind::Macro { name, args } => {
+                let tys: Vec<Type> = args.iter().map(|arg| self.expr(arg, None)).collect();
+                match name.as_str() {
+                    "format" => Type::named("String"),
+                    "vec" => match tys.into_iter().next() {
+                        Some(Type::Array(elem, _)) => Type::Path {
+                            name: "Vec".to_string(),
+                            args: vec![*elem],
+                        },
+                        _ => Type::Infer,
+                    },
+                    "panic" | "unreachable" | "todo" | "unimplemented" => Type::Never,
+                    _ => Type::unit(),
+                }
+            }
+            ExprKind::Tuple(items) => {
+                Type::Tuple(items.iter().map(|item| self.expr(item, None)).collect())
+            }
+            ExprKind::Array(items) => {
+                let mut elem = Type::Never;
+                for item in items {
+                    let expected =
+                        Some(Expectation::new(elem.clone())).filter(|_| elem != Type::Never);
+                    let ty = self.expr(item, expected.as_ref());
+                    elem = join(elem, ty);
+                }
+                if items.is_empty() {
+                    elem = Type::Infer;
+                }
+                let len = Expr::new(
+                    ExprKind::Lit(Lit::Int {
+                        value: items.len() as u128,
+                        suffix: None,
+                    }),
+                    span,
+                );
+                Type::Array(Box::new(elem), Box::new(len))
+            }
+            ExprKind::Repeat(item, len) => {
+                let elem = self.expr(item, None);
+                self.expr(len, Some(&Expectation::new(Type::named("usize"))));
+                Type::Array(Box::new(elem), len.clone())
+            }
+            ExprKind::Let(pat, scrutinee) => {
+                let ty = self.expr(scrutinee, None);
+                bind(pat, &ty, self.scopes.last_mut().unwrap());
+                Type::named("bool")
+            }
+            ExprKind::Range { start, end, .. } => {
+                let start_ty = start.as_ref().map(|s| self.expr(s, None));
+                let end_ty = end.as_ref().map(|e| self.expr(e, None));
+                let elem = match (start_ty, end_ty) {
+                    (Some(a), Some(b)) => join(a, b),
+                    (Some(ty), None) | (None, Some(ty)) => ty,
+                    (None, None) => Type::Infer,
+                };
+                Type::Path {
+                    name: "Range".to_string(),
+                    args: vec![elem],
+                }
+            }
+            ExprKind::Cast(inner, ty) => {
+                self.expr(inner, None);
+                ty.clone()
+            }
+        };
+        self.demand(span, &ty, expected, "");
+        ty
+    }
+
+    fn if_expr(
+        &mut self,
+        expr: &Expr,
+        cond: &Expr,
+        then: &Block,
+        els: &Option<Box<Expr>>,
+        expected: Option<&Expectation>,
+    ) -> Type {
+        let span = expr.span;
+        self.scopes.push(Vec::new());
+        self.expr(cond, Some(&Expectation::new(Type::named("bool"))));
+        let head = format!("if {}", cond);
+        let els = match els {
+            Some(els) => els,
+            None => {
+                // 没有 else 分支时, if 分支的类型必须是 ()
+                let unit = Expectation::new(Type::unit());
+                self.block(then, then.span, Some(&unit));
+                self.scopes.pop();
+                let ty = Type::unit();
+                self.annotate(span, head, &ty, "没有 `else` 分支的 `if` 的类型是 `()`");
+                if let Some(expected) = expected.filter(|e| !coerces(&ty, &e.ty)) {
+                    let label = format!("expected `{}`, found `()`", expected.ty);
+                    let mut diagnostic = Diagnostic::error(
+                        "E0317",
+                        span,
+                        "`if` may be missing an `else` clause".to_string(),
+                    )
+                    .with_label(&label);
+                    if let Some((origin, why)) = &expected.origin {
+                        diagnostic = diagnostic.with_note(*origin, why);
+                    }
+                    self.diagnostics.push(
+                        diagnostic
+                            .with_note(span, "`if` expressions without `else` evaluate to `()`"),
+                    );
+                }
+                return ty;
+            }
+        };
+        let then_ty = self.block(then, then.span, expected);
+        self.scopes.pop();
+        // 没有外部期望时, else 分支要与 if 分支的类型一致
+        let branch = Expectation::new(then_ty.clone());
+        let els_expected = match expected {
+            Some(expected) if expected.is_known() => expected.clone(),
+            _ => branch,
+        };
+        let mismatches = self.diagnostics.len();
+        let els_ty = self.expr(els, Some(&els_expected));
+        if expected.is_none_or(|e| !e.is_known()) {
+            for diagnostic in &mut self.diagnostics[mismatches..] {
+                if diagnostic.code == Some("E0308") && diagnostic.message == "mismatched types" {
+                    diagnostic.message = "`if` and `else` have incompatible types".to_string();
+                    diagnostic
+                        .notes
+                        .push((then.span, "expected because of this".to_string()));
+                }
+            }
+        }
+        let ty = join(then_ty, els_ty);
+        self.annotate(span, head, &ty, "两个分支类型一致, 发散的分支不影响类型");
+        ty
+    }
+
+    /// 检查循环体, 返回其中 `break` 带出的值的类型
+    fn loop_body(
+        &mut self,
+        body: &Block,
+        is_loop: bool,
+        expected: Option<&Expectation>,
+    ) -> Vec<Type> {
+        self.loops.push(LoopContext {
+            is_loop,
+            expected: expected.cloned(),
+            breaks: Vec::new(),
+        });
+        self.block(body, body.span, Some(&Expectation::new(Type::unit())));
+        self.loops.pop().unwrap().breaks
+    }
+}
+
+fn literal(lit: &Lit) -> Type {
+    match lit {
+        Lit::Int { suffix, .. } => Type::named(suffix.as_deref().unwrap_or("{integer}")),
+        Lit::Float { suffix, .. } => Type::named(suffix.as_deref().unwrap_or("{float}")),
+        Lit::Bool(_) => Type::named("bool"),
+        Lit::Char(_) => Type::named("char"),
+        Lit::Str(_) => Type::Ref {
+            lifetime: None,
+            mutable: false,
+            inner: Box::new(Type::named("str")),
+        },
+    }
+}
+
+/// 数组、切片、`Vec` 与区间的元素类型
+fn element(ty: &Type) -> Type {
+    match ty {
+        Type::Array(elem, _) | Type::Slice(elem) => (**elem).clone(),
+        Type::Ref { inner, .. } => element(inner),
+        Type::Path { name, args } if (name == "Vec" || name == "Range") && args.len() == 1 => {
+            args[0].clone()
+        }
+        _ => Type::Infer,
+    }
+}
+
+/// 把模式中的绑定加入作用域, 能从类型中分解出来时记录具体的类型
+fn bind(pat: &Pat, ty: &Type, scope: &mut Vec<(String, Type)>) {
+    match (&pat.kind, ty) {
+        (PatKind::Binding { name, by_ref, .. }, _) => {
+            let ty = if *by_ref {
+                Type::Ref {
+                    lifetime: None,
+                    mutable: false,
+                    inner: Box::new(ty.clone()),
+                }
+            } else {
+                ty.clone()
+            };
+            scope.push((name.clone(), ty));
+        }
+        (PatKind::Tuple(pats), Type::Tuple(items)) if pats.len() == items.len() => {
+            for (pat, ty) in pats.iter().zip(items) {
+                bind(pat, ty, scope);
+            }
+        }
+        (PatKind::Ref { pat, .. }, Type::Ref { inner, .. }) => bind(pat, inner, scope),
+        _ => {
+            for binding in pat.bindings() {
+                if let PatKind::Binding { name, .. } = &binding.kind {
+                    scope.push((name.clone(), Type::Infer));
+                }
+            }
+        }
+    }
+}
+
+fn is_integer(ty: &Type) -> bool {
+    matches!(ty, Type::Path { name, args } if args.is_empty() && INTEGERS.contains(&name.as_str()))
+}
+
+fn is_float(ty: &Type) -> bool {
+    matches!(ty, Type::Path { name, args } if args.is_empty() && (name == "f32" || name == "f64"))
+}
+
+/// `found` 能否转换为 `expected`
+fn coerces(found: &Type, expected: &Type) -> bool {
+    let integer = Type::named("{integer}");
+    let float = Type::named("{float}");
+    match (found, expected) {
+        (Type::Never, _) | (Type::Infer, _) | (_, Type::Infer) => true,
+        (a, b) if *a == integer || *b == integer => {
+            a == b || (*a == integer && is_integer(b)) || (*b == integer && is_integer(a))
+        }
+        (a, b) if *a == float || *b == float => {
+            a == b || (*a == float && is_float(b)) || (*b == float && is_float(a))
+        }
+        (Type::Path { name: a, args: x }, Type::Path { name: b, args: y }) => {
+            a == b && x.len() == y.len() && x.iter().zip(y).all(|(x, y)| coerces(x, y))
+        }
+        (
+            Type::Ref {
+                mutable: m1,
+                inner: a,
+                ..
+            },
+            Type::Ref {
+                mutable: m2,
+                inner: b,
+                ..
+            },
+        ) => {
+            // `&mut T` 可以转换为 `&T`, `&String` 可以解引用转换为 `&str`
+            let deref = matches!((&**a, &**b), (Type::Path { name: s, .. }, Type::Path { name: t, .. }) if s == "String" && t == "str");
+            (*m1 || !*m2) && (coerces(a, b) || deref)
+        }
+        (Type::Tuple(x), Type::Tuple(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(x, y)| coerces(x, y))
+        }
+        (Type::Array(a, _), Type::Array(b, _))
+        | (Type::Array(a, _), Type::Slice(b))
+        | (Type::Slice(a), Type::Slice(b)) => coerces(a, b),
+        (Type::Fn(p1, r1), Type::Fn(p2, r2)) => {
+            p1.len() == p2.len() && p1.iter().zip(p2).all(|(x, y)| coerces(y, x)) && coerces(r1, r2)
+        }
+        (a, b) => a == b,
+    }
+}
+
+/// 两个分支的共同类型: `!` 与 `_` 让位于另一方, 字面量类型让位于具体类型
+fn join(a: Type, b: Type) -> Type {
+    let vague = |ty: &Type| match ty {
+        Type::Never => 3,
+        Type::Infer => 2,
+        Type::Path { name, .. } if name == "{integer}" || name == "{float}" => 1,
+        _ => 0,
+    };
+    if vague(&a) > vague(&b) {
+        b
+    } else {
+        a
+    }
+}
+
+/// # 类型检查
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::lang::typeck::check;
+///
+/// // control_flow::while_true: while 循环的类型总是 (), 即使条件是 true
+/// let src = "fn while_true(x: i32) -> i32 {\n    while true {\n        return x + 1;\n    }\n}";
+/// let result = check(src).unwrap();
+/// assert_eq!(result.to_string(), "\
+/// \x202:16  { .. }: !  (块中的语句发散, 块的类型是 `!`)
+/// \x20 2:5  while true: ()  (`while` 循环的类型总是 `()`, 不会检查条件是否恒为真)
+/// \x201:30  { .. }: ()  (块的类型来自尾表达式)
+/// \x20 1:1  fn while_true: ()  (函数体的类型, 需要转换为返回类型 `i32`)
+/// error[E0308]: 2:5: mismatched types
+///   1:26: expected `i32` because of return type
+///   2:5: `while` loops evaluate to unit type `()`
+/// ");
+///
+/// // 换成 loop 之后, 没有 break 的 loop 的类型是 !, 可以转换为 i32
+/// let src = "fn while_true(x: i32) -> i32 { loop { return x + 1; } }";
+/// let result = check(src).unwrap();
+/// assert!(result.is_ok());
+/// assert_eq!(result.annotations[1].to_string(), " 1:32  loop: !  (`loop` 中没有 `break`, 永远不会正常结束, 类型是 `!`)");
+///
+/// // control_flow::if_true: 没有 else 分支的 if 的类型是 ()
+/// let result = check("fn if_true(x: i32) -> i32 { if true { return x + 1; } }").unwrap();
+/// assert_eq!(result.diagnostics[0].code, Some("E0317"));
+///
+/// let result = check("let x: i32 = if true { 1 } else { \"one\" };").unwrap();
+/// assert_eq!(result.diagnostics[0].to_string(), "error[E0308]: 1:35: mismatched types\n  1:5: expected `i32` due to this");
+/// let result = check("let v = loop { break 1u8; }; let w: u8 = v;").unwrap();
+/// assert!(result.is_ok());
+/// ```
+pub fn check(src: &str) -> Result<TypeCheck, ParseError> {
+    let stmts = parse_stmts(src)?;
+    let mut checker = Checker::new();
+    checker.declare_fns(&stmts);
+    checker.stmts(&stmts, Span::new(1, 1), None);
+    Ok(TypeCheck {
+        annotations: checker.annotations,
+        diagnostics: checker.diagnostics,
+    })
+}
@@ -1,163 +1,435 @@
-fn main1() {
-    use rust_programming_of_zhd::ch01::{fly_dyn, fly_static, title, Duck, Fly, Pig};
-    title();
-    let d = Duck;
-    let p = Pig;
-    println!("d fly: {}", d.fly());
-    println!("p fly: {}", p.fly());
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::Path;
+use std::process::{self, Command};
 
-    println!("d static: {}", fly_static::<Duck>(d));
-    println!("p dyn: {}", fly_dyn(&p));
-}
+use rust_programming_of_zhd::ch01::dispatch::Animal;
+use rust_programming_of_zhd::ch01::registry::FlyRegistry;
+use rust_programming_of_zhd::ch01::vtable::inspect;
+use rust_programming_of_zhd::ch01::zoo::Eagle;
+use rust_programming_of_zhd::ch01::{Duck, Pig};
+use rust_programming_of_zhd::ch02::fizzbuzz::render::{render, Format};
+use rust_programming_of_zhd::example::{registry, Example, Selector};
+use rust_programming_of_zhd::lang::borrowck::{self, Mode};
+use rust_programming_of_zhd::lang::interp::{self, Session};
+use rust_programming_of_zhd::lang::{consteval, drift, mir, ownership, place, typeck};
+use rust_programming_of_zhd::snapshot;
 
-fn main2_2() {
-    use rust_programming_of_zhd::ch02::answer;
-    answer();
-}
+const USAGE: &str = "\
+用法:
+    rust-programming-of-zhd              运行全部示例
+    rust-programming-of-zhd list         列出全部示例
+    rust-programming-of-zhd run <选择>   运行示例, <选择> 可以是 --all、ch02 或 2.17
+    rust-programming-of-zhd snapshot [--bless] [<选择>]
+                                         与 snapshots/*.golden 比较示例输出, --bless 更新快照
+    rust-programming-of-zhd fizzbuzz [--format <格式>] [<范围>]
+                                         输出 FizzBuzz 序列, <格式> 为 plain/csv/json/jsonl/markdown,
+                                         <范围> 形如 100、1..=100 或 1..101, 默认 1..=100
+    rust-programming-of-zhd fly <动物>... | fly --config <文件>
+                                         根据名字构造 Fly 的实现者并报告 fly() 的结果
+    rust-programming-of-zhd vtable       查看 &dyn Fly 的胖指针与虚表布局
+    rust-programming-of-zhd drift [<路径>...]
+                                         比较文档示例与函数实现, 默认检查 src/ch01 和 src/ch02
+    rust-programming-of-zhd place [<代码>]
+                                         标注位置表达式与值表达式, 省略 <代码> 时从标准输入读取
+    rust-programming-of-zhd ownership [<代码>]
+                                         模拟绑定之间的移动与复制, 报告 use after move 等错误
+    rust-programming-of-zhd borrowck [--lexical | --nll] [<代码>]
+                                         检查借用冲突, 默认同时输出词法作用域与 NLL 两种模式的结果
+    rust-programming-of-zhd mir [--dot] [<代码>]
+                                         降级为 MIR 风格的中间表示, --dot 输出 Graphviz 控制流图
+    rust-programming-of-zhd typeck [<代码>]
+                                         给块、if、while 与 loop 定型, 报告 E0308 等类型不匹配
+    rust-programming-of-zhd consteval [<代码>]
+                                         在编译期求值常量与数组长度, 拒绝常量上下文中不允许的操作
+    rust-programming-of-zhd repl [--steps]
+                                         交互式求值表达式, --steps 显示每一步的解释,
+                                         输入 :steps 切换显示, :quit 退出
+";
 
-fn main2_3() {
-    use rust_programming_of_zhd::ch02::binding::temp;
-    let x = &temp();
-    println!("{}", x);
-    // temp() = *x;
-    // ^ error[E0070]: invalid left- hand side expression
+fn list() {
+    for example in registry() {
+        println!("{:>5}  {}", example.id(), example.title);
+    }
 }
 
-fn main2_4() {
-    use rust_programming_of_zhd::ch02::binding::immutable_and_mutable;
-    immutable_and_mutable();
+fn run(examples: &[&Example]) {
+    // 只运行单个清单时不打印分隔线, 保持输出干净
+    let separated = examples.len() > 1;
+    let mut chapter = None;
+    for example in examples {
+        if separated && chapter != Some(example.chapter) {
+            println!("------------------------");
+            chapter = Some(example.chapter);
+        }
+        (example.run)();
+    }
+    if separated {
+        println!("------------------------");
+    }
 }
 
-fn main2_5() {
-    use rust_programming_of_zhd::ch02::binding::ownership;
-    ownership();
+/// 在子进程中运行每个清单并比较输出, 返回是否全部通过
+fn snapshot(examples: &[&Example], dir: &Path, bless: bool) -> bool {
+    let exe = env::current_exe().expect("cannot locate current executable");
+    let mut passed = true;
+    for example in examples {
+        let output = Command::new(&exe)
+            .args(["run", &example.id()])
+            .output()
+            .expect("failed to spawn example");
+        if !output.status.success() {
+            passed = false;
+            println!(
+                "{:>5}  FAILED: example exited with {}",
+                example.id(),
+                output.status
+            );
+            print!("{}", String::from_utf8_lossy(&output.stderr));
+            continue;
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        match snapshot::check(dir, example, &stdout, bless) {
+            Ok(outcome) => {
+                passed &= outcome.is_ok();
+                println!("{:>5}  {}", example.id(), outcome);
+            }
+            Err(e) => {
+                passed = false;
+                println!("{:>5}  FAILED: {}", example.id(), e);
+            }
+        }
+    }
+    passed
 }
 
-fn main2_6() {
-    use rust_programming_of_zhd::ch02::binding::reference;
-    reference();
-    use rust_programming_of_zhd::ch02::binding::reference2;
-    reference2();
+/// 解析 `100`、`1..=100`、`1..101` 形式的闭区间
+fn parse_range(range: &str) -> Option<(i64, i64)> {
+    if let Some(pos) = range.find("..") {
+        let start = range[..pos].parse().ok()?;
+        let end = &range[pos + 2..];
+        if let Some(end) = end.strip_prefix('=') {
+            Some((start, end.parse().ok()?))
+        } else {
+            Some((start, end.parse::<i64>().ok()?.checked_sub(1)?))
+        }
+    } else {
+        Some((1, range.parse().ok()?))
+    }
 }
 
-fn main2_8() {
-    use rust_programming_of_zhd::ch02::function::fizz_buzz;
-    assert_eq!(fizz_buzz(15), "fizzbuzz".to_string());
-    assert_eq!(fizz_buzz(3), "fizz".to_string());
-    assert_eq!(fizz_buzz(5), "buzz".to_string());
-    assert_eq!(fizz_buzz(13), "13".to_string());
+fn fizzbuzz(args: &[&str]) {
+    let mut format = Format::Plain;
+    let mut range = (1, 100);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match *arg {
+            "--format" | "-f" => {
+                let name = args
+                    .next()
+                    .unwrap_or_else(|| usage_error("--format requires a value"));
+                format = name
+                    .parse()
+                    .unwrap_or_else(|e| usage_error(&format!("{}", e)));
+            }
+            other => {
+                range = parse_range(other)
+                    .unwrap_or_else(|| usage_error(&format!("invalid range `{}`", other)));
+            }
+        }
+    }
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    let result = render(range.0..=range.1, format.renderer(), &mut out).and_then(|_| out.flush());
+    if let Err(e) = result {
+        if e.kind() != io::ErrorKind::BrokenPipe {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
 }
 
-fn main2_9() {
-    use rust_programming_of_zhd::ch02::function::lexical_scope;
-    lexical_scope();
+fn fly(args: &[&str]) {
+    let registry = FlyRegistry::with_defaults();
+    let animals = match args {
+        [] => usage_error(&format!(
+            "fly requires animal names: {}",
+            registry.names().join(", ")
+        )),
+        ["--config", path] => {
+            let config = fs::read_to_string(path)
+                .unwrap_or_else(|e| usage_error(&format!("cannot read `{}`: {}", path, e)));
+            registry.from_config(&config)
+        }
+        names => registry.from_config(&names.join(" ")),
+    };
+    match animals {
+        Ok(animals) => {
+            for (name, animal) in animals {
+                println!("{}: {}", name, animal.fly());
+            }
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
 }
 
-fn main2_10() {
-    use rust_programming_of_zhd::ch02::function::{math, product, sum};
-    let a = 2;
-    let b = 3;
-    println!("{} == {}", math(sum, a, b), 5);
-    println!("{} == {}", math(product, a, b), 6);
+/// 报告文档示例与实现之间的偏差, 存在偏差时以状态码 1 退出
+fn drift(paths: &[&str]) {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let paths = if paths.is_empty() {
+        &drift::DEFAULT_DIRS[..]
+    } else {
+        paths
+    };
+    match drift::check_paths(root, paths) {
+        Ok(drifts) if drifts.is_empty() => println!("no drift found"),
+        Ok(drifts) => {
+            print!("{}", drift::report(&drifts));
+            println!(
+                "{} function(s) drifted from their doc examples",
+                drifts.len()
+            );
+            process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
 }
 
-fn main2_11() {
-    use rust_programming_of_zhd::ch02::function::true_maker;
-    println!("true_maker: {}", true_maker()());
+/// 代码片段来自命令行参数, 没有参数时读取标准输入
+fn snippet(args: &[&str]) -> String {
+    if !args.is_empty() {
+        return args.join(" ");
+    }
+    let mut src = String::new();
+    if let Err(e) = io::stdin().read_to_string(&mut src) {
+        eprintln!("error: cannot read stdin: {}", e);
+        process::exit(1);
+    }
+    src
 }
 
-fn main2_12() {
-    use rust_programming_of_zhd::ch02::function::init_len;
-    // 通过［O; N] 这种形式来初始化初始值为0、长度为N的数组
-    // Rust 中固定长度的数组必须在编译期就知道长度, 否则会编译出错
-    // 以函数 init_len 必须在编译期求值. 这就是 CTFE 的能力
-    let arr = [0; init_len()];
-    println!("{:?}", arr);
+fn place(args: &[&str]) {
+    match place::analyze(&snippet(args)) {
+        Ok(analysis) => {
+            print!("{}", analysis);
+            if !analysis.is_accepted() {
+                process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
 }
 
-fn main2_13() {
-    use rust_programming_of_zhd::ch02::function::closure;
-    closure();
+fn ownership(args: &[&str]) {
+    let src = snippet(args);
+    match ownership::simulate(&src) {
+        Ok(simulation) => {
+            print!("{}", simulation.render(&src));
+            if !simulation.is_ok() {
+                process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
 }
 
-fn main2_14() {
-    use rust_programming_of_zhd::ch02::function::closure_math;
-    let a = 2;
-    let b = 3;
-    println!("{} == {}", closure_math(|| a + b), 5);
-    println!("{} == {}", closure_math(|| a * b), 6);
+fn borrowck(modes: &[Mode], args: &[&str]) {
+    let src = snippet(args);
+    let mut rejected = false;
+    for &mode in modes {
+        match borrowck::check(&src, mode) {
+            Ok(result) => {
+                if modes.len() > 1 {
+                    println!("== {} ==", mode);
+                }
+                print!("{}", result.render(&src));
+                rejected |= !result.is_ok();
+            }
+            Err(e) => {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+    if rejected {
+        process::exit(1);
+    }
 }
 
-fn main2_15() {
-    use rust_programming_of_zhd::ch02::function::{two_times, two_times_dyn, two_times_impl};
-    println!("{} == {}", two_times()(2), 4);
-    println!("{} == {}", two_times_dyn()(2), 4);
-    println!("{} == {}", two_times_impl()(2), 4);
+fn mir(dot: bool, args: &[&str]) {
+    match mir::lower(&snippet(args)) {
+        Ok(bodies) => {
+            for (i, body) in bodies.iter().enumerate() {
+                if i > 0 {
+                    println!();
+                }
+                if dot {
+                    print!("{}", body.to_dot());
+                } else {
+                    print!("{}", body);
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
 }
 
-fn main2_16() {
-    use rust_programming_of_zhd::ch02::control_flow::if_expr;
-    if_expr()
+fn typeck(args: &[&str]) {
+    let src = snippet(args);
+    match typeck::check(&src) {
+        Ok(result) => {
+            print!("{}", result.render(&src));
+            if !result.is_ok() {
+                process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
 }
 
-fn main2_17() {
-    use rust_programming_of_zhd::ch02::control_flow::for_fizzbuzz;
-    use rust_programming_of_zhd::ch02::control_flow::loop_fizzbuzz;
-    use rust_programming_of_zhd::ch02::control_flow::while_fizzbuzz;
-    while_fizzbuzz();
-    println!("------------------");
-    loop_fizzbuzz();
-    println!("------------------");
-    for_fizzbuzz();
+fn consteval(args: &[&str]) {
+    let src = snippet(args);
+    match consteval::evaluate(&src) {
+        Ok(result) => {
+            print!("{}", result.render(&src));
+            if !result.is_ok() {
+                process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
 }
 
-fn main2_19() {
-    use rust_programming_of_zhd::ch02::control_flow::if_true;
-    use rust_programming_of_zhd::ch02::control_flow::while_true;
-    println!("while_true {}", while_true(12));
-    println!("if_true {}", if_true(12));
+/// 逐行读取标准输入, 括号闭合后求值; 绑定与函数在多次输入之间保留
+fn repl(mut steps: bool) {
+    let mut session = Session::new();
+    let mut buffer = String::new();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("{}", if buffer.is_empty() { ">> " } else { ".. " });
+        let _ = io::stdout().flush();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            Some(Err(e)) => {
+                eprintln!("error: cannot read stdin: {}", e);
+                process::exit(1);
+            }
+            None => break,
+        };
+        if buffer.is_empty() {
+            match line.trim() {
+                "" => continue,
+                ":quit" | ":q" => break,
+                ":steps" => {
+                    steps = !steps;
+                    println!("steps: {}", if steps { "on" } else { "off" });
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        buffer.push_str(&line);
+        buffer.push('\n');
+        if !interp::is_complete(&buffer) {
+            continue;
+        }
+        match session.eval(&buffer) {
+            Ok(evaluation) => {
+                if steps {
+                    for step in &evaluation.steps {
+                        println!("{}", step);
+                    }
+                }
+                print!("{}", evaluation.output);
+                println!("=> {}", evaluation.value);
+            }
+            Err(e) => println!("error: {}", e),
+        }
+        buffer.clear();
+    }
+    println!();
 }
 
-fn main2_20() {
-    use rust_programming_of_zhd::ch02::control_flow::match_expr;
-    match_expr(43);
+fn parse_selector(selector: &str) -> Vec<&'static Example> {
+    let parsed: Selector = selector
+        .parse()
+        .unwrap_or_else(|e| usage_error(&format!("{}", e)));
+    let examples = parsed.select();
+    if examples.is_empty() {
+        usage_error(&format!("no example matches `{}`", selector));
+    }
+    examples
 }
 
-fn main2_21() {
-    use rust_programming_of_zhd::ch02::control_flow::match_bool;
-    use rust_programming_of_zhd::ch02::control_flow::if_let_bool;
-    use rust_programming_of_zhd::ch02::control_flow::while_let_pop;
-    use rust_programming_of_zhd::ch02::control_flow::loop_match_pop;
-
-    match_bool();
-    if_let_bool();
-    while_let_pop();
-    loop_match_pop();
+fn usage_error(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    eprint!("{}", USAGE);
+    process::exit(2)
 }
 
 fn main() {
-    println!("------------------------");
-    main1();
-
-    println!("------------------------");
-    main2_2();
-    main2_3();
-    main2_4();
-    main2_5();
-    main2_6();
-    main2_8();
-    main2_9();
-    main2_10();
-    main2_11();
-    main2_12();
-    main2_13();
-    main2_14();
-    main2_15();
-    main2_16();
-    main2_17();
-    main2_19();
-    main2_20();
-    main2_21();
-
-    println!("------------------------");
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        [] => run(&registry()),
+        ["list"] => list(),
+        ["run", selector] => run(&parse_selector(selector)),
+        ["snapshot", rest @ ..] => {
+            let bless = rest.contains(&"--bless");
+            let selectors: Vec<&str> = rest.iter().copied().filter(|a| *a != "--bless").collect();
+            let examples = match selectors.as_slice() {
+                [] => registry(),
+                [selector] => parse_selector(selector),
+                _ => usage_error("snapshot accepts at most one selector"),
+            };
+            if !snapshot(&examples, Path::new(snapshot::DEFAULT_DIR), bless) {
+                process::exit(1);
+            }
+        }
+        ["fizzbuzz", rest @ ..] => fizzbuzz(rest),
+        ["fly", rest @ ..] => fly(rest),
+        ["vtable"] => {
+            print!("{}", inspect(&Duck));
+            print!("{}", inspect(&Pig));
+            print!("{}", inspect(&Eagle));
+            print!("{}", inspect(&Animal::from(Duck)));
+        }
+        ["drift", rest @ ..] => drift(rest),
+        ["place", rest @ ..] => place(rest),
+        ["ownership", rest @ ..] => ownership(rest),
+        ["borrowck", "--lexical", rest @ ..] => borrowck(&[Mode::Lexical], rest),
+        ["borrowck", "--nll", rest @ ..] => borrowck(&[Mode::NonLexical], rest),
+        ["borrowck", rest @ ..] => borrowck(&[Mode::Lexical, Mode::NonLexical], rest),
+        ["mir", "--dot", rest @ ..] => mir(true, rest),
+        ["mir", rest @ ..] => mir(false, rest),
+        ["typeck", rest @ ..] => typeck(rest),
+        ["consteval", rest @ ..] => consteval(rest),
+        ["repl"] => repl(false),
+        ["repl", "--steps"] => repl(true),
+        ["help"] | ["-h"] | ["--help"] => print!("{}", USAGE),
+        _ => usage_error(&format!("unrecognized arguments: {}", args.join(" "))),
+    }
 }
@@ -0,0 +1,138 @@
+//! 示例输出的快照(Golden)测试
+//!
+//! 每个代码清单的标准输出都保存在 `snapshots/<清单编号>.golden` 中,
+//! 比较之前会先把不确定的部分(例如 `{:p}` 打印的内存地址)规范化
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::example::Example;
+
+/// 快照文件默认所在的目录
+pub const DEFAULT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/snapshots");
+
+/// # 规范化输出
+///
+/// 把形如 `0x7ffcbc067704` 的内存地址替换成 `0x<addr>`
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::snapshot::normalize;
+/// assert_eq!(normalize("0x7ffcbc067704 0x7FF0\n"), "0x<addr> 0x<addr>\n");
+/// assert_eq!(normalize("0 x 0x"), "0 x 0x");
+/// ```
+pub fn normalize(output: &str) -> String {
+    let mut normalized = String::with_capacity(output.len());
+    let mut rest = output;
+    while let Some(pos) = rest.find("0x") {
+        let (before, after) = rest.split_at(pos);
+        normalized.push_str(before);
+        let digits = after[2..]
+            .find(|c: char| !c.is_ascii_hexdigit())
+            .unwrap_or(after.len() - 2);
+        if digits == 0 {
+            normalized.push_str("0x");
+        } else {
+            normalized.push_str("0x<addr>");
+        }
+        rest = &after[2 + digits..];
+    }
+    normalized.push_str(rest);
+    normalized
+}
+
+/// 某个清单对应的快照文件路径
+pub fn golden_path(dir: &Path, example: &Example) -> PathBuf {
+    dir.join(format!("{}.golden", example.id()))
+}
+
+/// 一次快照比较的结果
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// 输出与快照一致
+    Match,
+    /// 输出与快照不一致
+    Mismatch { expected: String, actual: String },
+    /// 快照文件不存在
+    Missing,
+    /// 使用 `--bless` 更新了快照
+    Blessed,
+}
+
+impl Outcome {
+    pub fn is_ok(&self) -> bool {
+        match self {
+            Outcome::Match | Outcome::Blessed => true,
+            Outcome::Mismatch { .. } | Outcome::Missing => false,
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Match => write!(f, "ok"),
+            Outcome::Blessed => write!(f, "blessed"),
+            Outcome::Missing => write!(f, "missing golden file (run with --bless)"),
+            Outcome::Mismatch { expected, actual } => {
+                writeln!(f, "output differs from golden file")?;
+                write!(f, "{}", diff(expected, actual))
+            }
+        }
+    }
+}
+
+/// # 比较(或更新)快照
+///
+/// `actual` 会先经过 [`normalize`] 再与快照比较; `bless` 为真时直接覆盖快照
+pub fn check(dir: &Path, example: &Example, actual: &str, bless: bool) -> io::Result<Outcome> {
+    let path = golden_path(dir, example);
+    let actual = normalize(actual);
+    if bless {
+        fs::create_dir_all(dir)?;
+        fs::write(&path, actual)?;
+        return Ok(Outcome::Blessed);
+    }
+    let expected = match fs::read_to_string(&path) {
+        Ok(expected) => expected,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Outcome::Missing),
+        Err(e) => return Err(e),
+    };
+    if expected == actual {
+        Ok(Outcome::Match)
+    } else {
+        Ok(Outcome::Mismatch { expected, actual })
+    }
+}
+
+/// # 逐行差异
+///
+/// 只列出不相同的行, `-` 为快照中的内容, `+` 为实际输出
+///
+/// Basic usage:
+///
+/// ```
+/// use rust_programming_of_zhd::snapshot::diff;
+/// assert_eq!(diff("a\nb\n", "a\nc\n"), "  line 2:\n  - b\n  + c\n");
+/// assert_eq!(diff("a\n", "a\nb\n"), "  line 2:\n  - <none>\n  + b\n");
+/// ```
+pub fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let mut report = String::new();
+    for i in 0..expected.len().max(actual.len()) {
+        let (e, a) = (expected.get(i), actual.get(i));
+        if e != a {
+            report.push_str(&format!(
+                "  line {}:\n  - {}\n  + {}\n",
+                i + 1,
+                e.unwrap_or(&"<none>"),
+                a.unwrap_or(&"<none>")
+            ));
+        }
+    }
+    report
+}
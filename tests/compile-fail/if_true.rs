@@ -0,0 +1,7 @@
+// ch02::control_flow::if_true: 没有 else 分支的 if 表达式类型为单元值
+
+pub fn if_true(x: i32) -> i32 {
+    if true {
+        return x + 1;
+    }
+}
@@ -0,0 +1,7 @@
+// ch02::binding::immutable_and_mutable: 不可变绑定不能被再次赋值
+
+pub fn main() {
+    let a = 1;
+    a = 2;
+    println!("{}", a);
+}
@@ -0,0 +1,10 @@
+// ch02::binding::ownership: 所有权转移之后不能再使用原来的绑定
+
+pub fn main() {
+    let place1 = "hello";
+    let place2 = "hello".to_string();
+    let other = place1; // Copy
+    println!("{:?} {:?}", other, place1);
+    let other = place2; // Move
+    println!("{:?} {:?}", other, place2);
+}
@@ -0,0 +1,8 @@
+// ch02::binding::reference: 可变借用存续期间, 不能再次借用被借用的值
+
+pub fn main() {
+    let mut c = vec![1, 2, 3];
+    let d = &mut c;
+    c.push(4);
+    d.push(5);
+}
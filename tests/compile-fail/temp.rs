@@ -0,0 +1,10 @@
+// ch02::binding::temp: 值表达式不能出现在位置上下文中
+
+pub fn temp() -> i32 {
+    1
+}
+
+pub fn main() {
+    let x = &temp();
+    temp() = *x;
+}
@@ -0,0 +1,7 @@
+// ch02::control_flow::while_true: while 循环的类型总是单元值
+
+pub fn while_true(x: i32) -> i32 {
+    while true {
+        return x + 1;
+    }
+}
@@ -0,0 +1,25 @@
+//! 文档中标注为编译错误的清单, 必须产生 `tests/compile-fail/*.expected` 中记录的错误
+//!
+//! 更新预期: `BLESS=1 cargo test --test compile_fail`
+
+use std::path::Path;
+
+use rust_programming_of_zhd::compile_fail::Runner;
+
+#[test]
+fn documented_error_listings_fail_to_compile() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/compile-fail");
+    let results = Runner::from_env()
+        .run_dir(&dir)
+        .expect("failed to run rustc");
+    assert!(!results.is_empty());
+    let mut report = String::new();
+    for (name, outcome) in &results {
+        report.push_str(&format!("{}: {}\n", name, outcome));
+    }
+    assert!(
+        results.iter().all(|(_, outcome)| outcome.is_ok()),
+        "\n{}",
+        report
+    );
+}
@@ -0,0 +1,66 @@
+//! 文档示例与实现之间的偏差检查
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use rust_programming_of_zhd::lang::drift::{
+    check_paths, check_source, report, Drift, DEFAULT_DIRS,
+};
+
+/// 已知偏差的基线(不含行号), 设置环境变量 `BLESS=1` 重新生成
+const EXPECTED: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/drift.expected");
+
+#[test]
+fn doc_examples_do_not_drift_from_implementations() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let drifts = check_paths(root, &DEFAULT_DIRS).unwrap();
+    let actual: String = drifts.iter().map(Drift::fingerprint).collect();
+    if env::var_os("BLESS").is_some() {
+        fs::write(EXPECTED, &actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(EXPECTED).unwrap_or_default();
+    assert!(
+        expected == actual,
+        "doc examples drifted from their implementations \
+         (run with BLESS=1 to accept):\n{}",
+        report(&drifts)
+    );
+}
+
+#[test]
+fn stdout_wrappers_are_compared_against_their_writer_bodies() {
+    let src = r#"
+/// ```
+/// fn count() {
+///     let mut n = 1;
+///     loop {
+///         if n >= 4 { break; }
+///         println!("{}", n);
+///         n += 1;
+///     }
+/// }
+/// ```
+pub fn count() {
+    count_to(&mut io::stdout()).expect("failed to write to stdout")
+}
+
+pub fn count_to<W: Write>(out: &mut W) -> io::Result<()> {
+    let mut n = 1;
+    loop {
+        if n > 4 { break; }
+        writeln!(out, "{}", n)?;
+        n += 1;
+    }
+    Ok(())
+}
+"#;
+    let drifts = check_source("lib.rs", src).unwrap();
+    assert_eq!(drifts.len(), 1);
+    assert_eq!(drifts[0].fingerprint(), "lib.rs: fn count\n  - >=\n  + >\n");
+
+    let fixed = src.replace("n > 4", "n >= 4");
+    let drifts = check_source("lib.rs", &fixed).unwrap();
+    assert_eq!(drifts, []);
+}
@@ -0,0 +1,257 @@
+//! FizzBuzz 工具与 `fizz_buzz` 之间的一致性测试
+
+use std::io::{self, Write};
+use std::ops::Bound;
+
+use rust_programming_of_zhd::ch02::control_flow::{
+    for_fizzbuzz_to, loop_fizzbuzz_to, while_fizzbuzz_to,
+};
+use rust_programming_of_zhd::ch02::fizzbuzz::strategy;
+use rust_programming_of_zhd::ch02::fizzbuzz::table;
+use rust_programming_of_zhd::ch02::fizzbuzz::wheel::Wheel;
+use rust_programming_of_zhd::ch02::fizzbuzz::{
+    fizzbuzz, FizzBuzz, FizzBuzzToken, ParallelFizzBuzz,
+};
+use rust_programming_of_zhd::ch02::function::fizz_buzz;
+
+/// 每次写入都失败的 `Write`, 模拟磁盘已满
+struct Full;
+
+impl Write for Full {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::other("disk full"))
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn iterator_matches_fizz_buzz_over_full_i16_range() {
+    let tokens: Vec<String> = fizzbuzz(i16::MIN..=i16::MAX)
+        .map(|t| t.to_string())
+        .collect();
+    assert_eq!(tokens.len(), 1 << 16);
+    for (n, token) in (i16::MIN..=i16::MAX).zip(&tokens) {
+        assert_eq!(*token, fizz_buzz(i32::from(n)));
+    }
+}
+
+#[test]
+fn iterator_handles_range_edges() {
+    assert_eq!(fizzbuzz::<u8, _>(..).len(), 256);
+    assert_eq!(fizzbuzz(5u8..5).next(), None);
+    let (high, low) = (7i32, 3);
+    assert_eq!(fizzbuzz(high..=low).next(), None);
+    assert_eq!(fizzbuzz(..i8::MIN).next(), None);
+    assert_eq!(
+        fizzbuzz((Bound::Excluded(u8::MAX), Bound::Unbounded)).next(),
+        None
+    );
+    assert_eq!(
+        fizzbuzz((Bound::Excluded(0u64), Bound::Included(1))).collect::<Vec<_>>(),
+        [FizzBuzzToken::Number(1)]
+    );
+}
+
+#[test]
+fn iterator_counts_ranges_up_to_usize_max() {
+    assert_eq!(fizzbuzz(0u64..u64::MAX).count(), usize::MAX);
+    assert_eq!(fizzbuzz(i128::MAX - 9..).count(), 10);
+}
+
+#[test]
+#[should_panic(expected = "overflow")]
+fn iterator_count_panics_when_longer_than_usize() {
+    fizzbuzz::<u64, _>(..).count();
+}
+
+#[test]
+fn iterator_meets_in_the_middle() {
+    let mut iter = fizzbuzz(1i64..=4);
+    assert_eq!(iter.next(), Some(FizzBuzzToken::Number(1)));
+    assert_eq!(iter.next_back(), Some(FizzBuzzToken::Number(4)));
+    assert_eq!(iter.size_hint(), (2, Some(2)));
+    assert_eq!(iter.next_back(), Some(FizzBuzzToken::Fizz));
+    assert_eq!(iter.next(), Some(FizzBuzzToken::Number(2)));
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.next_back(), None);
+}
+
+#[test]
+fn step_by_and_rev_agree_with_materialised_ranges() {
+    let lazy: Vec<_> = fizzbuzz(-100i128..100).rev().step_by(7).collect();
+    let eager: Vec<_> = (-100i128..100)
+        .rev()
+        .step_by(7)
+        .map(FizzBuzzToken::of)
+        .collect();
+    assert_eq!(lazy, eager);
+}
+
+#[test]
+fn parallel_generator_matches_fizz_buzz_for_any_chunking() {
+    let expected: String = (990..=10_010).map(|n| fizz_buzz(n) + "\n").collect();
+    for &threads in &[1, 2, 3, 8] {
+        for &chunk_size in &[1, 7, 1000, 1 << 20] {
+            let mut out = Vec::new();
+            let written = ParallelFizzBuzz::new()
+                .threads(threads)
+                .chunk_size(chunk_size)
+                .write_to(990..=10_010, &mut out)
+                .unwrap();
+            assert_eq!(written as usize, out.len());
+            assert_eq!(
+                out,
+                expected.as_bytes(),
+                "threads={} chunk={}",
+                threads,
+                chunk_size
+            );
+        }
+    }
+}
+
+#[test]
+fn parallel_generator_stops_on_write_error() {
+    let result = ParallelFizzBuzz::new()
+        .threads(4)
+        .chunk_size(10)
+        .write_to(1..=1_000_000, &mut Full);
+    assert!(result.is_err());
+}
+
+#[test]
+fn parallel_generator_handles_full_u64_range_with_unit_chunks() {
+    for &threads in &[1, 3] {
+        let generator = ParallelFizzBuzz::new().threads(threads).chunk_size(1);
+        assert!(generator.write_to(0..=u64::MAX, &mut Full).is_err());
+
+        let mut out = Vec::new();
+        generator
+            .write_to(u64::MAX - 20..=u64::MAX, &mut out)
+            .unwrap();
+        let expected: String = (u64::MAX - 20..=u64::MAX)
+            .map(|n| format!("{}\n", FizzBuzzToken::of(n)))
+            .collect();
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+}
+
+#[test]
+fn wheel_matches_fizz_buzz_over_large_i32_sample() {
+    // 连续的 400 万个数
+    for (n, word) in (-2_000_000..2_000_000).zip(Wheel::classic(-2_000_000)) {
+        assert_eq!(word, fizz_buzz(n), "n = {}", n);
+    }
+    // 覆盖整个 i32 范围的抽样, 每个起点连续比较一段
+    let starts = (i32::MIN..=i32::MAX - 1000).step_by(1_000_003);
+    for start in starts.chain(vec![i32::MIN, i32::MAX - 1000]) {
+        let wheel = Wheel::classic(i64::from(start)).take(1001);
+        for (n, word) in (start..=start + 1000).zip(wheel) {
+            assert_eq!(word, fizz_buzz(n), "n = {}", n);
+        }
+    }
+}
+
+#[test]
+fn every_loop_strategy_has_identical_semantics() {
+    let (high, low) = (7, 3);
+    let ranges = vec![
+        0..0,
+        5..5,
+        high..low,
+        -100..100,
+        1..101,
+        i32::MAX - 20..i32::MAX,
+        i32::MIN..i32::MIN + 20,
+    ];
+    for range in ranges {
+        let timings = strategy::compare(range.clone());
+        let expected: String = range.clone().map(|n| fizz_buzz(n) + "\n").collect();
+        for timing in &timings {
+            assert_eq!(
+                timing.output,
+                expected.as_bytes(),
+                "{} over {:?}",
+                timing.name,
+                range
+            );
+        }
+    }
+}
+
+#[test]
+fn control_flow_listings_match_every_loop_strategy() {
+    type Listing = fn(&mut Vec<u8>) -> io::Result<()>;
+    let listings: [(&str, Listing); 3] = [
+        ("while_fizzbuzz_to", while_fizzbuzz_to),
+        ("loop_fizzbuzz_to", loop_fizzbuzz_to),
+        ("for_fizzbuzz_to", for_fizzbuzz_to),
+    ];
+    for strategy in strategy::strategies().iter() {
+        let mut expected = Vec::new();
+        strategy::run(*strategy, 1..101, &mut expected).unwrap();
+        for (name, listing) in &listings {
+            let mut out = Vec::new();
+            listing(&mut out).unwrap();
+            assert_eq!(out, expected, "{} vs {}", name, strategy.name());
+        }
+    }
+}
+
+#[test]
+fn loop_strategies_stop_at_first_error() {
+    for strategy in strategy::strategies().iter() {
+        let mut seen = Vec::new();
+        let result = strategy.drive(1..100, &mut |n| {
+            seen.push(n);
+            if n == 10 {
+                Err(io::Error::other("stop"))
+            } else {
+                Ok(())
+            }
+        });
+        assert!(result.is_err());
+        assert_eq!(seen, (1..=10).collect::<Vec<_>>(), "{}", strategy.name());
+    }
+}
+
+#[test]
+fn classic_rules_match_fizz_buzz() {
+    let classic = FizzBuzz::classic();
+    for n in (-10_000..=10_000).chain(vec![i32::MIN, i32::MIN + 1, i32::MAX - 1, i32::MAX]) {
+        assert_eq!(classic.apply(i64::from(n)), fizz_buzz(n));
+    }
+}
+
+#[test]
+fn rule_engine_handles_extreme_divisors() {
+    let engine = FizzBuzz::new().divisor(-1, "one").divisor(i64::MIN, "min");
+    assert_eq!(engine.apply(i64::MIN), "onemin");
+    assert_eq!(engine.apply(i64::MAX), "one");
+    assert_eq!(FizzBuzz::classic().apply(i64::MIN), "-9223372036854775808");
+}
+
+#[test]
+fn const_table_matches_fizz_buzz() {
+    static TOKENS: [FizzBuzzToken<u64>; 3000] = table::table();
+    for (n, token) in (1..).zip(&TOKENS) {
+        assert_eq!(token.to_string(), fizz_buzz(n));
+    }
+    for (token, n) in table::TOKENS_100.iter().zip(1u64..) {
+        assert_eq!(*token, FizzBuzzToken::of(n));
+    }
+}
+
+#[test]
+fn pre_rendered_buffer_matches_every_runtime_loop() {
+    let mut expected = Vec::new();
+    for_fizzbuzz_to(&mut expected).unwrap();
+    assert_eq!(&table::FIZZBUZZ_100[..], &expected[..]);
+    assert_eq!(table::FIZZBUZZ_100_TEXT.as_bytes(), &expected[..]);
+    assert_eq!(table::rendered_len(100), expected.len());
+
+    let lines: Vec<String> = (1..=100).map(fizz_buzz).collect();
+    assert_eq!(table::FIZZBUZZ_100_TEXT, lines.join("\n") + "\n");
+}
@@ -0,0 +1,569 @@
+//! 教学用分析器与 rustc 的一致性测试
+//!
+//! `tests/compile-fail/` 中的清单由 rustc 检查, 这里用对应的分析器重新检查一遍,
+//! 要求报告的错误码和错误标题与 `.expected` 中记录的 rustc 输出一致
+
+use std::fs;
+use std::path::PathBuf;
+
+use rust_programming_of_zhd::ch02::ctfe::{FACTORIALS, FIZZBUZZ_WORDS};
+use rust_programming_of_zhd::ch02::function::fizz_buzz;
+use rust_programming_of_zhd::lang::borrowck::{self, Mode};
+use rust_programming_of_zhd::lang::consteval::{self, Value as ConstValue};
+use rust_programming_of_zhd::lang::interp::{Session, Value};
+use rust_programming_of_zhd::lang::{mir, ownership, place, typeck};
+
+fn listing(name: &str) -> (String, String) {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/compile-fail");
+    let src = fs::read_to_string(dir.join(format!("{}.rs", name))).unwrap();
+    let expected = fs::read_to_string(dir.join(format!("{}.expected", name))).unwrap();
+    (src, expected.trim_end().to_string())
+}
+
+/// 把诊断信息转换成 rustc 的错误标题, 例如 `error[E0070]: invalid left-hand side of assignment`
+///
+/// 标题之后用 ": " 隔开的中文解释会被去掉, 而 "use of moved value: `b`" 中的名字会保留
+fn headline(code: Option<&str>, message: &str) -> String {
+    let end = message
+        .match_indices(": ")
+        .map(|(i, _)| i)
+        .find(|&i| !message[i..].is_ascii())
+        .unwrap_or(message.len());
+    let title = &message[..end];
+    format!("error[{}]: {}", code.unwrap_or("?"), title)
+}
+
+#[test]
+fn place_analyzer_rejects_temp_listing_like_rustc() {
+    let (src, expected) = listing("temp");
+    let analysis = place::analyze(&src).unwrap();
+    let errors: Vec<String> = analysis
+        .errors()
+        .map(|d| headline(d.code, &d.message))
+        .collect();
+    assert_eq!(errors, [expected]);
+}
+
+#[test]
+fn ownership_simulator_rejects_moved_listing_like_rustc() {
+    let (src, expected) = listing("ownership");
+    let simulation = ownership::simulate(&src).unwrap();
+    let errors: Vec<String> = simulation
+        .diagnostics
+        .iter()
+        .map(|d| headline(d.code, &d.message))
+        .collect();
+    assert_eq!(errors, [expected]);
+}
+
+#[test]
+fn ownership_simulator_rejects_immutable_listing_like_rustc() {
+    let (src, expected) = listing("immutable_and_mutable");
+    let simulation = ownership::simulate(&src).unwrap();
+    let errors: Vec<String> = simulation
+        .diagnostics
+        .iter()
+        .map(|d| headline(d.code, &d.message))
+        .collect();
+    assert_eq!(errors, [expected]);
+}
+
+fn ownership_errors(src: &str) -> Vec<String> {
+    ownership::simulate(src)
+        .unwrap()
+        .diagnostics
+        .iter()
+        .map(|d| headline(d.code, &d.message))
+        .collect()
+}
+
+#[test]
+fn ownership_simulator_points_reassignment_note_at_the_initialization() {
+    let src = "let a = String::new(); let b = a; a = String::new();";
+    let result = ownership::simulate(src).unwrap();
+    let errors: Vec<String> = result
+        .diagnostics
+        .iter()
+        .map(|d| headline(d.code, &d.message))
+        .collect();
+    assert_eq!(
+        errors,
+        ["error[E0384]: cannot assign twice to immutable variable `a`"]
+    );
+    let (span, note) = &result.diagnostics[0].notes[0];
+    assert_eq!(note, "first assignment to `a`");
+    // `let a` 中的 `a`, 而不是移动发生的 `let b = a`
+    assert_eq!((span.line, span.col), (1, 5));
+}
+
+#[test]
+fn ownership_simulator_tracks_partial_moves_per_field() {
+    let src = "let t = (String::new(), 1); let w = t.0; let n = t.1; let u = t; let x = t.1;";
+    assert_eq!(
+        ownership_errors(src),
+        ["error[E0382]: use of partially moved value: `t`"]
+    );
+    let src = "let mut t = (String::new(), 1); let w = t.0; t.0 = String::new(); let u = t;";
+    assert!(ownership_errors(src).is_empty());
+}
+
+#[test]
+fn ownership_simulator_rejects_moves_out_of_indexes() {
+    let src = "let v: Vec<String> = Vec::new(); let w = v[0]; let n = v[0].len();";
+    assert_eq!(
+        ownership_errors(src),
+        ["error[E0507]: cannot move out of index of `Vec<String>`"]
+    );
+}
+
+#[test]
+fn ownership_simulator_reports_each_moved_place_once() {
+    let src = "let s = String::new(); let a = s; let b = s; let c = &s; let d = s;";
+    assert_eq!(
+        ownership_errors(src),
+        ["error[E0382]: use of moved value: `s`"]
+    );
+}
+
+#[test]
+fn borrow_checker_rejects_reference_listing_like_rustc_in_both_modes() {
+    let (src, expected) = listing("reference");
+    for mode in [Mode::Lexical, Mode::NonLexical] {
+        let result = borrowck::check(&src, mode).unwrap();
+        let errors: Vec<String> = result
+            .diagnostics
+            .iter()
+            .map(|d| headline(d.code, &d.message))
+            .collect();
+        assert_eq!(errors, std::slice::from_ref(&expected), "{}", mode);
+    }
+}
+
+#[test]
+fn borrow_checker_accepts_reference_example_only_under_nll() {
+    // binding::reference 中 `d` 最后一次使用之后, `c` 可以再次被借用
+    let src = "let mut c = vec![1, 2, 3]; let d = &mut c; d.push(4); println!(\"{:?}\", c);";
+    assert!(!borrowck::check(src, Mode::Lexical).unwrap().is_ok());
+    assert!(borrowck::check(src, Mode::NonLexical).unwrap().is_ok());
+}
+
+#[test]
+fn borrow_checker_keeps_reborrow_alive_after_reference_goes_out_of_scope() {
+    // `s` 再借用的是 `*r` 指向的 `x`, `r` 离开作用域之后对 `x` 的借用仍然有效
+    let src = "let mut x = vec![1]; let s; { let r = &mut x; s = &mut *r; } x.push(3); s.push(1);";
+    let errors: Vec<String> = borrowck::check(src, Mode::NonLexical)
+        .unwrap()
+        .diagnostics
+        .iter()
+        .map(|d| headline(d.code, &d.message))
+        .collect();
+    assert_eq!(
+        errors,
+        ["error[E0499]: cannot borrow `x` as mutable more than once at a time"]
+    );
+    let src = "let mut x = vec![1]; let s; { let r = &mut x; s = &mut *r; } s.push(1); x.push(3);";
+    assert!(borrowck::check(src, Mode::NonLexical).unwrap().is_ok());
+}
+
+#[test]
+fn borrow_checker_ends_loan_when_holder_is_overwritten_before_next_use() {
+    // `use r` 读到的是重新赋值之后的 `&a`, 旧值持有的借用在 `&mut a` 处已经结束
+    let src = "let mut a = 1; let mut r = &a; let s = &mut a; r = &a; use r;";
+    assert!(borrowck::check(src, Mode::NonLexical).unwrap().is_ok());
+    // 右侧仍然读取旧值; 只在一个分支中的覆盖, 或者可能被 `break` 跳过的覆盖, 不能结束借用
+    let rejected = [
+        "let mut a = 1; let mut r = &a; let s = &mut a; r = id(r); use r;",
+        "let mut a = 1; let b = 2; let mut r = &a; let s = &mut a; if c { r = &b; } use r;",
+        "let mut a = 1; let b = 2; let mut r = &a; loop { let s = &mut a; if c { break; } r = &b; } use r;",
+    ];
+    for src in rejected {
+        let errors: Vec<String> = borrowck::check(src, Mode::NonLexical)
+            .unwrap()
+            .diagnostics
+            .iter()
+            .map(|d| headline(d.code, &d.message))
+            .collect();
+        assert_eq!(
+            errors,
+            ["error[E0502]: cannot borrow `a` as mutable because it is also borrowed as immutable"],
+            "{}",
+            src
+        );
+    }
+    // 下一轮迭代在使用之前会先覆盖它
+    let src = "let mut a = 1; let mut r = &a; loop { use r; let s = &mut a; use s; r = &a; }";
+    assert!(borrowck::check(src, Mode::NonLexical).unwrap().is_ok());
+}
+
+#[test]
+fn type_checker_rejects_while_true_and_if_true_listings_like_rustc() {
+    for name in ["while_true", "if_true"] {
+        let (src, expected) = listing(name);
+        let result = typeck::check(&src).unwrap();
+        let errors: Vec<String> = result
+            .diagnostics
+            .iter()
+            .map(|d| headline(d.code, &d.message))
+            .collect();
+        assert_eq!(errors, std::slice::from_ref(&expected), "{}", name);
+    }
+}
+
+#[test]
+fn type_checker_points_return_type_note_at_the_return_type() {
+    let (src, _) = listing("while_true");
+    let result = typeck::check(&src).unwrap();
+    let (span, note) = &result.diagnostics[0].notes[0];
+    assert_eq!(note, "expected `i32` because of return type");
+    // `pub fn while_true(x: i32) -> i32 {` 中的返回类型 `i32`
+    assert_eq!((span.line, span.col), (3, 30));
+}
+
+#[test]
+fn type_checker_accepts_loop_in_place_of_while_true() {
+    // control_flow::while_true 的实现: 没有 break 的 loop 的类型是 `!`
+    let src = "pub fn while_true(x: i32) -> i32 { loop { return x + 1; } }";
+    assert!(typeck::check(src).unwrap().is_ok());
+    let src = "pub fn if_true(x: i32) -> i32 { if true { return x + 1; } x }";
+    assert!(typeck::check(src).unwrap().is_ok());
+}
+
+#[test]
+fn interpreter_agrees_with_fizz_buzz_doc_example() {
+    // 书中 fizz_buzz 清单使用 `return` 的版本
+    let mut session = Session::new();
+    session
+        .eval(
+            "fn fizz_buzz(num: i32) -> String {
+                if num % 15 == 0 {
+                    return \"fizzbuzz\".to_string();
+                } else if num % 3 == 0 {
+                    return \"fizz\".to_string();
+                } else if num % 5 == 0 {
+                    return \"buzz\".to_string();
+                } else {
+                    return num.to_string();
+                }
+            }",
+        )
+        .unwrap();
+    for num in 1..=100 {
+        let result = session.eval(&format!("fizz_buzz({})", num)).unwrap();
+        assert_eq!(result.value, Value::String(fizz_buzz(num)), "{}", num);
+    }
+}
+
+#[test]
+fn interpreter_checks_overflow_against_the_integer_type() {
+    let message = |src: &str| Session::new().eval(src).unwrap_err().message;
+    assert_eq!(
+        message("-(-9223372036854775807 - 1)"),
+        "attempt to negate with overflow"
+    );
+    assert_eq!(
+        message("let x: i32 = -2147483647 - 1; x % -1"),
+        "attempt to calculate the remainder with overflow"
+    );
+    assert_eq!(
+        message("let x: u8 = 255; x + 1"),
+        "attempt to add with overflow"
+    );
+    let result = Session::new()
+        .eval("let x: i64 = 2147483647; x + 1")
+        .unwrap();
+    assert_eq!(result.value, Value::Int(2147483648, Some("i64")));
+}
+
+#[test]
+fn interpreter_follows_rustc_for_negative_literals_and_string_concatenation() {
+    let message = |src: &str| Session::new().eval(src).unwrap_err().message;
+    let value = |src: &str| Session::new().eval(src).unwrap().value;
+    // `-128i8` 本身是合法的字面量, 再取负才溢出
+    assert_eq!(value("-128i8"), Value::Int(-128, Some("i8")));
+    assert_eq!(message("-(-128i8)"), "attempt to negate with overflow");
+    assert_eq!(message("-129i8"), "literal out of range for `i8`");
+    // 只有 `String + &str`
+    assert_eq!(
+        message("let s = \"a\"; s + s"),
+        "cannot add `&str` to `&str`"
+    );
+    assert_eq!(
+        message("let s = String::from(\"a\"); s.clone() + s"),
+        "mismatched types: expected `&str`, found `String`"
+    );
+    assert_eq!(
+        value("let s = String::from(\"a\"); s + \"a\""),
+        Value::String("aa".to_string())
+    );
+}
+
+#[test]
+fn interpreter_bounds_empty_loops_and_huge_arrays() {
+    let message = |src: &str| Session::new().eval(src).unwrap_err().message;
+    assert!(message("loop {}").contains("可能是死循环"));
+    assert!(message("while true {}").contains("可能是死循环"));
+    assert!(message("[0; 99999999999999]").contains("超过了解释器的上限"));
+}
+
+#[test]
+fn interpreter_reports_call_depth_limit_instead_of_overflowing_the_stack() {
+    let mut session = Session::new();
+    session
+        .eval("fn f(n: u64) -> u64 { if n == 0 { 1 } else { 1 + f(n - 1) } }")
+        .unwrap();
+    // f(199) 一共调用 200 层, 正好达到上限
+    assert_eq!(
+        session.eval("f(199)").unwrap().value,
+        Value::Int(200, Some("u64"))
+    );
+    assert_eq!(
+        session.eval("f(200)").unwrap_err().message,
+        "函数调用超过了 200 层"
+    );
+}
+
+#[test]
+fn const_evaluator_agrees_with_ctfe_tables() {
+    // ch02::ctfe 中的 const fn 用 while 循环填表, 这里用同样的写法在沙盒中求值
+    let src = "
+        const fn factorials() -> [u64; 21] {
+            let mut table = [1; 21];
+            let mut i = 1;
+            while i < 21 {
+                table[i] = table[i - 1] * i as u64;
+                i += 1;
+            }
+            table
+        }
+        const fn fizzbuzz_words() -> [u8; 15] {
+            let mut table = [0; 15];
+            let mut i = 0;
+            while i < 15 {
+                table[i] = match (i % 3, i % 5) { (0, 0) => 3, (0, _) => 1, (_, 0) => 2, _ => 0 };
+                i += 1;
+            }
+            table
+        }
+        const FACTORIALS: [u64; 21] = factorials();
+        const WORDS: [u8; 15] = fizzbuzz_words();
+    ";
+    let result = consteval::evaluate(src).unwrap();
+    assert!(result.is_ok(), "{}", result);
+    let factorials: Vec<ConstValue> = FACTORIALS
+        .iter()
+        .map(|&n| ConstValue::Int(n as i128, Some("u64")))
+        .collect();
+    assert_eq!(
+        result.get("FACTORIALS"),
+        Some(&ConstValue::Array(factorials))
+    );
+    let words: Vec<ConstValue> = FIZZBUZZ_WORDS
+        .iter()
+        .map(|word| {
+            let code = match *word {
+                Some("fizz") => 1,
+                Some("buzz") => 2,
+                Some(_) => 3,
+                None => 0,
+            };
+            ConstValue::Int(code, Some("u8"))
+        })
+        .collect();
+    assert_eq!(result.get("WORDS"), Some(&ConstValue::Array(words)));
+
+    // 多算一项就会溢出
+    let result = consteval::evaluate(&src.replace("21", "22")).unwrap();
+    assert_eq!(result.diagnostics[0].code, Some("E0080"));
+}
+
+fn mir(src: &str) -> String {
+    mir::lower(src).unwrap()[0].to_string()
+}
+
+#[test]
+fn mir_lowers_index_with_usize_temporary() {
+    // 没有后缀的索引字面量推断为 `usize`
+    assert_eq!(
+        mir("let a = [1, 2, 3]; let b = a[2];"),
+        "\
+fn main() -> () {
+    debug a => _1;
+    debug b => _2;
+    let mut _0: ();
+    let _1: [i32; 3];
+    let _2: i32;
+    let mut _3: usize;
+
+    bb0: {
+        StorageLive(_1);
+        _1 = [const 1_i32, const 2_i32, const 3_i32];
+        StorageLive(_2);
+        StorageLive(_3);
+        _3 = const 2_usize;
+        _2 = copy _1[_3];
+        StorageDead(_3);
+        StorageDead(_2);
+        StorageDead(_1);
+        return;
+    }
+}
+"
+    );
+}
+
+#[test]
+fn mir_lowers_block_value_with_type_of_tail() {
+    // 块的类型由块中声明的 `y` 决定
+    assert_eq!(
+        mir("let x = { let y = 1; y };"),
+        "\
+fn main() -> () {
+    debug x => _1;
+    debug y => _2;
+    let mut _0: ();
+    let _1: i32;
+    let _2: i32;
+
+    bb0: {
+        StorageLive(_1);
+        StorageLive(_2);
+        _2 = const 1_i32;
+        _1 = copy _2;
+        StorageDead(_2);
+        StorageDead(_1);
+        return;
+    }
+}
+"
+    );
+}
+
+#[test]
+fn mir_auto_refs_method_receivers_only_when_needed() {
+    // `&str` 直接传入, `Vec` 才需要自动引用
+    assert_eq!(
+        mir("let s = \"hi\"; let n = s.len(); let v: Vec<i32> = Vec::new(); let m = v.len();"),
+        "\
+fn main() -> () {
+    debug s => _1;
+    debug n => _2;
+    debug v => _3;
+    debug m => _4;
+    let mut _0: ();
+    let _1: &'static str;
+    let _2: usize;
+    let _3: Vec<i32>;
+    let _4: usize;
+    let mut _5: &Vec<i32>;
+
+    bb0: {
+        StorageLive(_1);
+        _1 = const \"hi\";
+        StorageLive(_2);
+        _2 = str::len(copy _1) -> bb1;
+    }
+
+    bb1: {
+        StorageLive(_3);
+        _3 = Vec::new() -> bb2;
+    }
+
+    bb2: {
+        StorageLive(_4);
+        StorageLive(_5);
+        _5 = &_3;
+        _4 = Vec::len(move _5) -> bb3;
+    }
+
+    bb3: {
+        StorageDead(_5);
+        StorageDead(_4);
+        StorageDead(_3);
+        StorageDead(_2);
+        StorageDead(_1);
+        return;
+    }
+}
+"
+    );
+}
+
+#[test]
+fn mir_stores_intermediate_values_in_temporaries() {
+    // 中间结果写进临时值, 用完之后立即 `move`
+    assert_eq!(
+        mir("let x = (1 + 2) * 3;"),
+        "\
+fn main() -> () {
+    debug x => _1;
+    let mut _0: ();
+    let _1: i32;
+    let mut _2: i32;
+
+    bb0: {
+        StorageLive(_1);
+        StorageLive(_2);
+        _2 = Add(const 1_i32, const 2_i32);
+        _1 = Mul(move _2, const 3_i32);
+        StorageDead(_2);
+        StorageDead(_1);
+        return;
+    }
+}
+"
+    );
+}
+
+#[test]
+fn const_evaluator_computes_in_the_declared_width() {
+    let src = "const A: u128 = 1 << 127; const B: u128 = 340282366920938463463374607431768211455;";
+    let result = consteval::evaluate(src).unwrap();
+    assert!(result.is_ok(), "{}", result);
+    assert_eq!(
+        result.get("A").unwrap().to_string(),
+        "170141183460469231731687303715884105728"
+    );
+    assert_eq!(result.get("B").unwrap().to_string(), u128::MAX.to_string());
+
+    let result = consteval::evaluate("const C: i32 = i32::MIN % -1;").unwrap();
+    let errors: Vec<String> = result
+        .diagnostics
+        .iter()
+        .map(|d| headline(d.code, &d.message))
+        .collect();
+    assert_eq!(
+        errors,
+        ["error[E0080]: attempt to compute the remainder of `i32::MIN % -1_i32`, which would overflow"]
+    );
+}
+
+#[test]
+fn const_evaluator_bounds_empty_loops_and_huge_arrays() {
+    for src in [
+        "const fn f() -> u32 { loop {} } const E: u32 = f();",
+        "const F: [u8; 3] = [0; 99999999999999];",
+    ] {
+        let result = consteval::evaluate(src).unwrap();
+        let codes: Vec<_> = result.diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(codes, [Some("E0080")], "{}", src);
+    }
+}
+
+#[test]
+fn const_evaluator_reports_call_depth_limit_instead_of_overflowing_the_stack() {
+    let src = "const fn f(n: u32) -> u32 { if n == 0 { 0 } else { 1 + f(n - 1) } }";
+    let result = consteval::evaluate(&format!("{} const X: u32 = f(127);", src)).unwrap();
+    assert!(result.is_ok(), "{}", result);
+    assert_eq!(result.get("X"), Some(&ConstValue::Int(127, Some("u32"))));
+
+    let result = consteval::evaluate(&format!("{} const Y: u32 = f(128);", src)).unwrap();
+    let errors: Vec<String> = result
+        .diagnostics
+        .iter()
+        .map(|d| headline(d.code, &d.message))
+        .collect();
+    assert_eq!(
+        errors,
+        ["error[E0080]: reached the configured maximum number of stack frames (128)"]
+    );
+}
@@ -0,0 +1,131 @@
+//! `ch02::ops` 与更宽类型上的精确结果之间的一致性测试
+
+use std::convert::TryFrom;
+
+use rust_programming_of_zhd::ch02::function::{math, product, sum};
+use rust_programming_of_zhd::ch02::ops::{
+    checked_product, checked_sum, wrapping_sum, Arithmetic, ArithmeticError, Number, Op, OpFn,
+    Overflow,
+};
+
+/// 在 `i32` 上计算的精确结果, 除以零时为 `None`
+fn exact(op: Op, a: i32, b: i32) -> Option<i32> {
+    match op {
+        Op::Add => Some(a + b),
+        Op::Sub => Some(a - b),
+        Op::Mul => Some(a * b),
+        Op::Div => a.checked_div(b),
+        Op::Rem => a.checked_rem(b),
+    }
+}
+
+fn check_exhaustively<T>(values: impl Iterator<Item = T> + Clone)
+where
+    T: Number + Into<i32> + TryFrom<i32>,
+{
+    let (min, max) = (T::MIN.into(), T::MAX.into());
+    let modulus = max - min + 1;
+    for a in values.clone() {
+        for b in values.clone() {
+            let (x, y) = (a.into(), b.into());
+            for &op in &Op::ALL {
+                let exact = match exact(op, x, y) {
+                    Some(exact) => exact,
+                    None => {
+                        for &overflow in
+                            &[Overflow::Checked, Overflow::Wrapping, Overflow::Saturating]
+                        {
+                            assert_eq!(
+                                a.compute(op, overflow, b).map(Into::into),
+                                Err(ArithmeticError::DivideByZero(op))
+                            );
+                        }
+                        continue;
+                    }
+                };
+                let checked = a.checked(op, b).map(Into::into);
+                // 与标准库一致, `MIN % -1` 的商溢出, 因此取余也视为溢出
+                let in_range = |n: i32| (min..=max).contains(&n);
+                if in_range(exact) && (op != Op::Rem || in_range(x / y)) {
+                    assert_eq!(checked, Ok(exact), "{} {} {}", x, op, y);
+                } else {
+                    assert_eq!(
+                        checked,
+                        Err(ArithmeticError::Overflow(op)),
+                        "{} {} {}",
+                        x,
+                        op,
+                        y
+                    );
+                }
+                let wrapped = (exact - min).rem_euclid(modulus) + min;
+                assert_eq!(
+                    a.wrapping(op, b).map(Into::into),
+                    Ok(wrapped),
+                    "{} {} {}",
+                    x,
+                    op,
+                    y
+                );
+                let saturated = exact.clamp(min, max);
+                assert_eq!(
+                    a.saturating(op, b).map(Into::into),
+                    Ok(saturated),
+                    "{} {} {}",
+                    x,
+                    op,
+                    y
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn eight_bit_integers_match_exact_arithmetic() {
+    check_exhaustively(u8::MIN..=u8::MAX);
+    check_exhaustively(i8::MIN..=i8::MAX);
+}
+
+#[test]
+fn floats_report_overflow_and_nan() {
+    assert_eq!(checked_sum(1.5f32, 2.25), Ok(3.75));
+    assert_eq!(
+        checked_product(f32::MAX, 2.0),
+        Err(ArithmeticError::Overflow(Op::Mul))
+    );
+    assert_eq!(
+        math(Arithmetic::checked(Op::Sub), f64::INFINITY, f64::INFINITY),
+        Err(ArithmeticError::NotANumber(Op::Sub))
+    );
+    // 已经是无穷大的操作数不算溢出
+    assert_eq!(checked_sum(f64::INFINITY, 1.0), Ok(f64::INFINITY));
+    assert_eq!(f64::MIN.saturating(Op::Sub, f64::MAX), Ok(f64::MIN));
+    assert!(f64::NAN.saturating(Op::Add, 1.0).unwrap().is_nan());
+    assert_eq!(
+        5.5f64.checked(Op::Rem, 0.0),
+        Err(ArithmeticError::DivideByZero(Op::Rem))
+    );
+}
+
+#[test]
+fn operations_can_be_passed_as_values() {
+    assert_eq!(math(sum, 2, 3), 5);
+    assert_eq!(math(product, 2, 3), 6);
+
+    let table: [OpFn<u16>; 3] = [checked_sum, wrapping_sum, checked_product];
+    let results: Vec<_> = table.iter().map(|op| math(op, u16::MAX, 2)).collect();
+    assert_eq!(
+        results,
+        [
+            Err(ArithmeticError::Overflow(Op::Add)),
+            Ok(1),
+            Err(ArithmeticError::Overflow(Op::Mul))
+        ]
+    );
+
+    let offset = 10i64;
+    assert_eq!(math(|a: i64, b: i64| a * b + offset, 4, 5), 30);
+    assert_eq!(math(Arithmetic::saturating(Op::Sub), 0u128, 1), Ok(0));
+    assert_eq!(math(Arithmetic::wrapping(Op::Add), usize::MAX, 1), Ok(0));
+}
@@ -0,0 +1,19 @@
+//! 所有代码清单的输出都必须与 `snapshots/*.golden` 保持一致
+//!
+//! 修改了示例的输出后, 使用 `cargo run -- snapshot --bless` 更新快照
+
+use std::process::Command;
+
+#[test]
+fn listings_match_golden_files() {
+    let output = Command::new(env!("CARGO_BIN_EXE_rust-programming-of-zhd"))
+        .arg("snapshot")
+        .output()
+        .expect("failed to run snapshot");
+    assert!(
+        output.status.success(),
+        "snapshot mismatch:\n{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}